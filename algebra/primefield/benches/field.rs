@@ -1,7 +1,9 @@
 #![warn(clippy::all)]
 use criterion::{black_box, Criterion};
 use rand::prelude::*;
-use zkp_primefield::{FieldElement, Inv, SquareInline, SquareRoot};
+use zkp_primefield::{
+    BabyBear, FieldElement, Goldilocks, Inv, Mersenne31, SquareInline, SquareRoot,
+};
 
 fn field_add(crit: &mut Criterion) {
     crit.bench_function("Field add", move |bench| {
@@ -64,6 +66,30 @@ fn field_sqrt(crit: &mut Criterion) {
     });
 }
 
+fn small_field_mul(crit: &mut Criterion) {
+    crit.bench_function("Goldilocks mul", move |bench| {
+        let a: &Goldilocks = &random();
+        let b: &Goldilocks = &random();
+        bench.iter(|| {
+            black_box(black_box(a) * black_box(b));
+        })
+    });
+    crit.bench_function("BabyBear mul", move |bench| {
+        let a: &BabyBear = &random();
+        let b: &BabyBear = &random();
+        bench.iter(|| {
+            black_box(black_box(a) * black_box(b));
+        })
+    });
+    crit.bench_function("Mersenne31 mul", move |bench| {
+        let a: &Mersenne31 = &random();
+        let b: &Mersenne31 = &random();
+        bench.iter(|| {
+            black_box(black_box(a) * black_box(b));
+        })
+    });
+}
+
 pub fn group(crit: &mut Criterion) {
     field_add(crit);
     field_sub(crit);
@@ -72,4 +98,5 @@ pub fn group(crit: &mut Criterion) {
     field_square(crit);
     field_inv(crit);
     field_sqrt(crit);
+    small_field_mul(crit);
 }
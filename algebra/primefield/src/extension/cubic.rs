@@ -0,0 +1,251 @@
+use super::CubicNonResidue;
+use crate::{AddInline, Inv, MulInline, NegInline, One, Pow, Root, SquareInline, SubInline, Zero};
+#[cfg(any(test, feature = "proptest"))]
+use proptest::prelude::*;
+use std::{
+    iter::{Product, Sum},
+    ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign},
+};
+
+// False positives on the Latex math.
+#[allow(clippy::doc_markdown)]
+/// The cubic extension $\mathbb{F}[u] / (u^3 - w)$ of a base field.
+///
+/// Elements are $c_0 + c_1 u + c_2 u^2$ with coefficients in the base field
+/// and $w$ is [`CubicNonResidue::cubic_non_residue`].
+#[derive(Clone, PartialEq, Eq, Hash, Default, Debug)]
+pub struct CubicExtension<F>([F; 3]);
+
+// False positives on the Latex math.
+#[allow(clippy::doc_markdown)]
+impl<F> CubicExtension<F>
+where
+    F: CubicNonResidue,
+{
+    /// Degree of the extension.
+    pub const DEGREE: usize = 3;
+
+    /// Construct $c_0 + c_1 u + c_2 u^2$.
+    pub fn new(c0: F, c1: F, c2: F) -> Self {
+        Self([c0, c1, c2])
+    }
+
+    /// Embed an element of the base field.
+    pub fn from_base(base: F) -> Self {
+        Self([base, F::zero(), F::zero()])
+    }
+
+    /// Construct from the coefficients $[c_0, c_1, c_2]$.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are not exactly three coefficients.
+    pub fn from_coefficients(coefficients: &[F]) -> Self {
+        assert_eq!(coefficients.len(), Self::DEGREE);
+        Self([
+            coefficients[0].clone(),
+            coefficients[1].clone(),
+            coefficients[2].clone(),
+        ])
+    }
+
+    /// The coefficients $[c_0, c_1, c_2]$.
+    pub fn coefficients(&self) -> &[F] {
+        &self.0
+    }
+}
+
+impl<F> AddInline<&Self> for CubicExtension<F>
+where
+    F: CubicNonResidue,
+{
+    #[inline(always)]
+    fn add_inline(&self, rhs: &Self) -> Self {
+        Self([
+            self.0[0].add_inline(&rhs.0[0]),
+            self.0[1].add_inline(&rhs.0[1]),
+            self.0[2].add_inline(&rhs.0[2]),
+        ])
+    }
+}
+
+impl<F> SubInline<&Self> for CubicExtension<F>
+where
+    F: CubicNonResidue,
+{
+    #[inline(always)]
+    fn sub_inline(&self, rhs: &Self) -> Self {
+        Self([
+            self.0[0].sub_inline(&rhs.0[0]),
+            self.0[1].sub_inline(&rhs.0[1]),
+            self.0[2].sub_inline(&rhs.0[2]),
+        ])
+    }
+}
+
+impl<F> NegInline for CubicExtension<F>
+where
+    F: CubicNonResidue,
+{
+    #[inline(always)]
+    fn neg_inline(&self) -> Self {
+        Self([
+            self.0[0].neg_inline(),
+            self.0[1].neg_inline(),
+            self.0[2].neg_inline(),
+        ])
+    }
+}
+
+impl<F> MulInline<&Self> for CubicExtension<F>
+where
+    F: CubicNonResidue,
+{
+    // Schoolbook multiplication, reducing with $u^3 = w$
+    #[inline(always)]
+    fn mul_inline(&self, rhs: &Self) -> Self {
+        let [a0, a1, a2] = &self.0;
+        let [b0, b1, b2] = &rhs.0;
+        let w = F::cubic_non_residue();
+        let c0 = a1.mul_inline(b2).add_inline(&a2.mul_inline(b1));
+        let c1 = a2.mul_inline(b2);
+        Self([
+            a0.mul_inline(b0).add_inline(&w.mul_inline(&c0)),
+            a0.mul_inline(b1)
+                .add_inline(&a1.mul_inline(b0))
+                .add_inline(&w.mul_inline(&c1)),
+            a0.mul_inline(b2)
+                .add_inline(&a1.mul_inline(b1))
+                .add_inline(&a2.mul_inline(b0)),
+        ])
+    }
+}
+
+impl<F> SquareInline for CubicExtension<F>
+where
+    F: CubicNonResidue,
+{
+    #[inline(always)]
+    fn square_inline(&self) -> Self {
+        let [c0, c1, c2] = &self.0;
+        let w = F::cubic_non_residue();
+        let c0c1 = c0.mul_inline(c1);
+        let c0c2 = c0.mul_inline(c2);
+        let c1c2 = c1.mul_inline(c2);
+        Self([
+            c0.square_inline()
+                .add_inline(&w.mul_inline(&c1c2.add_inline(&c1c2))),
+            c0c1.add_inline(&c0c1)
+                .add_inline(&w.mul_inline(&c2.square_inline())),
+            c0c2.add_inline(&c0c2).add_inline(&c1.square_inline()),
+        ])
+    }
+}
+
+impl<F> Inv for &CubicExtension<F>
+where
+    F: CubicNonResidue,
+{
+    type Output = Option<CubicExtension<F>>;
+
+    fn inv(self) -> Self::Output {
+        // The adjugate of the multiplication matrix divided by its
+        // determinant, the norm. Non-zero elements have a non-zero norm
+        // because $u^3 - w$ is irreducible.
+        let [c0, c1, c2] = &self.0;
+        let w = F::cubic_non_residue();
+        let t0 = c0
+            .square_inline()
+            .sub_inline(&w.mul_inline(&c1.mul_inline(c2)));
+        let t1 = w
+            .mul_inline(&c2.square_inline())
+            .sub_inline(&c0.mul_inline(c1));
+        let t2 = c1.square_inline().sub_inline(&c0.mul_inline(c2));
+        let norm = c0
+            .mul_inline(&t0)
+            .add_inline(&w.mul_inline(&c2.mul_inline(&t1).add_inline(&c1.mul_inline(&t2))));
+        let norm_inv = norm.inverse()?;
+        Some(CubicExtension([
+            t0.mul_inline(&norm_inv),
+            t1.mul_inline(&norm_inv),
+            t2.mul_inline(&norm_inv),
+        ]))
+    }
+}
+
+extension_ops!(CubicExtension, CubicNonResidue);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{invert_batch, BabyBear, Goldilocks, Mersenne31};
+
+    type BabyBear3 = CubicExtension<BabyBear>;
+
+    #[test]
+    fn test_irreducible() {
+        // The non-residue is not a cube, so $u^3 - w$ has no roots and being
+        // of degree three it is irreducible.
+        assert_eq!(Goldilocks::order() % 3, 0);
+        assert_eq!(BabyBear::order() % 3, 0);
+        assert_eq!(Mersenne31::order() % 3, 0);
+        assert!(!Goldilocks::cubic_non_residue()
+            .pow((Goldilocks::order() / 3) as usize)
+            .is_one());
+        assert!(!BabyBear::cubic_non_residue()
+            .pow((BabyBear::order() / 3) as usize)
+            .is_one());
+        assert!(!Mersenne31::cubic_non_residue()
+            .pow((Mersenne31::order() / 3) as usize)
+            .is_one());
+    }
+
+    #[test]
+    fn test_u_cubed() {
+        let u = BabyBear3::new(BabyBear::zero(), BabyBear::one(), BabyBear::zero());
+        assert_eq!(
+            u.pow(3),
+            BabyBear3::from_base(BabyBear::cubic_non_residue())
+        );
+    }
+
+    proptest!(
+        #[test]
+        fn associative_mul(a: BabyBear3, b: BabyBear3, c: BabyBear3) {
+            prop_assert_eq!(&a * (&b * &c), (a * b) * c);
+        }
+
+        #[test]
+        fn distributivity(a: BabyBear3, b: BabyBear3, c: BabyBear3) {
+            prop_assert_eq!(&a * (&b + &c), (&a * b) + (a * c));
+        }
+
+        #[test]
+        fn square(a: BabyBear3) {
+            prop_assert_eq!(a.square(), &a * &a);
+        }
+
+        #[test]
+        fn inverse_mul(a: BabyBear3) {
+            match a.inv() {
+                None => prop_assert!(a.is_zero()),
+                Some(inverse) => prop_assert!((a * inverse).is_one()),
+            }
+        }
+
+        #[test]
+        fn fermats_little_theorem(a: CubicExtension<Mersenne31>) {
+            // The multiplicative group has order p^3 - 1
+            let p = Mersenne31::MODULUS as usize;
+            prop_assert_eq!(a.pow(p).pow(p).pow(p), a);
+        }
+
+        #[test]
+        fn batch_inverse(values: Vec<BabyBear3>) {
+            prop_assume!(!values.iter().any(BabyBear3::is_zero));
+            for (inverse, value) in invert_batch(&values).iter().zip(values.iter()) {
+                prop_assert!((inverse * value).is_one());
+            }
+        }
+    );
+}
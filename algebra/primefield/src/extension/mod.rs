@@ -0,0 +1,379 @@
+//! Extension fields over a base field.
+//!
+//! The extensions are constructed as $\mathbb{F}[u] / (u^k - w)$ for a fixed
+//! non-residue $w$ of the base field, which makes the polynomial irreducible.
+
+// Many false positives from trait bounds
+#![allow(single_use_lifetimes)]
+
+use crate::{BabyBear, FieldLike, Goldilocks, Inv, Mersenne31, Parameters, PrimeField};
+
+/// Base fields for the extensions.
+///
+/// The extensions can not require `for<'a> &'a F: RefFieldLike<F>` on the
+/// base field, as trait resolution would then recurse into nested extensions
+/// whenever the field type is not yet inferred. This trait provides the
+/// inversion instead, the remaining arithmetic is available from
+/// [`FieldLike`].
+pub trait ExtensionBase: FieldLike {
+    fn inverse(&self) -> Option<Self>;
+}
+
+/// Base fields with an irreducible polynomial $u^2 - w$.
+pub trait QuadraticNonResidue: ExtensionBase {
+    /// The quadratic non-residue $w$.
+    fn quadratic_non_residue() -> Self;
+}
+
+/// Base fields with an irreducible polynomial $u^3 - w$.
+///
+/// This requires three to divide the multiplicative order of the field, as
+/// otherwise all elements are cubes.
+pub trait CubicNonResidue: ExtensionBase {
+    /// The cubic non-residue $w$.
+    fn cubic_non_residue() -> Self;
+}
+
+impl<P: Parameters> ExtensionBase for PrimeField<P>
+where
+    Self: FieldLike,
+{
+    fn inverse(&self) -> Option<Self> {
+        self.inv()
+    }
+}
+
+/// The generator of the multiplicative group is never a square.
+impl<P: Parameters> QuadraticNonResidue for PrimeField<P>
+where
+    Self: FieldLike,
+{
+    fn quadratic_non_residue() -> Self {
+        Self::generator()
+    }
+}
+
+/// For the word sized fields the order is a multiple of six, so the generator
+/// is neither a square nor a cube.
+macro_rules! small_field_non_residue {
+    ($($field:ident),*) => {
+        $(
+            impl ExtensionBase for $field {
+                fn inverse(&self) -> Option<Self> {
+                    self.inv()
+                }
+            }
+
+            impl QuadraticNonResidue for $field {
+                fn quadratic_non_residue() -> Self {
+                    Self::generator()
+                }
+            }
+
+            impl CubicNonResidue for $field {
+                fn cubic_non_residue() -> Self {
+                    Self::generator()
+                }
+            }
+        )*
+    };
+}
+
+small_field_non_residue!(Goldilocks, BabyBear, Mersenne31);
+
+/// Implements the operator traits using the inline traits and inversion.
+macro_rules! extension_ops {
+    ($name:ident, $bound:ident) => {
+        extension_ops!(@op $name, $bound, Add, add, AddAssign, add_assign, add_inline);
+        extension_ops!(@op $name, $bound, Sub, sub, SubAssign, sub_assign, sub_inline);
+        extension_ops!(@op $name, $bound, Mul, mul, MulAssign, mul_assign, mul_inline);
+
+        impl<F> Div<&$name<F>> for &$name<F>
+        where
+            F: $bound,
+        {
+            type Output = $name<F>;
+
+            // Division suspiciously requires multiplication
+            #[allow(clippy::suspicious_arithmetic_impl)]
+            #[inline(always)]
+            fn div(self, rhs: &$name<F>) -> Self::Output {
+                self.mul_inline(&rhs.inv().expect("Division by zero"))
+            }
+        }
+
+        impl<F> Div<$name<F>> for &$name<F>
+        where
+            F: $bound,
+        {
+            type Output = $name<F>;
+
+            #[inline(always)] // Simple wrapper
+            fn div(self, rhs: $name<F>) -> Self::Output {
+                self / &rhs
+            }
+        }
+
+        impl<F> Div<&$name<F>> for $name<F>
+        where
+            F: $bound,
+        {
+            type Output = $name<F>;
+
+            #[inline(always)] // Simple wrapper
+            fn div(self, rhs: &$name<F>) -> Self::Output {
+                &self / rhs
+            }
+        }
+
+        impl<F> Div<$name<F>> for $name<F>
+        where
+            F: $bound,
+        {
+            type Output = $name<F>;
+
+            #[inline(always)] // Simple wrapper
+            fn div(self, rhs: $name<F>) -> Self::Output {
+                &self / &rhs
+            }
+        }
+
+        impl<F> DivAssign<&$name<F>> for $name<F>
+        where
+            F: $bound,
+        {
+            #[inline(always)] // Simple wrapper
+            fn div_assign(&mut self, rhs: &$name<F>) {
+                *self = &*self / rhs
+            }
+        }
+
+        impl<F> DivAssign<$name<F>> for $name<F>
+        where
+            F: $bound,
+        {
+            #[inline(always)] // Simple wrapper
+            fn div_assign(&mut self, rhs: $name<F>) {
+                *self = &*self / &rhs
+            }
+        }
+
+        impl<F> Neg for $name<F>
+        where
+            F: $bound,
+        {
+            type Output = $name<F>;
+
+            #[inline(always)] // Simple wrapper
+            fn neg(self) -> Self::Output {
+                self.neg_inline()
+            }
+        }
+
+        impl<F> Neg for &$name<F>
+        where
+            F: $bound,
+        {
+            type Output = $name<F>;
+
+            #[inline(always)] // Simple wrapper
+            fn neg(self) -> Self::Output {
+                self.neg_inline()
+            }
+        }
+
+        impl<F> Zero for $name<F>
+        where
+            F: $bound,
+        {
+            fn zero() -> Self {
+                Self::from_base(F::zero())
+            }
+
+            fn is_zero(&self) -> bool {
+                self.coefficients().iter().all(F::is_zero)
+            }
+        }
+
+        impl<F> One for $name<F>
+        where
+            F: $bound,
+        {
+            fn one() -> Self {
+                Self::from_base(F::one())
+            }
+
+            fn is_one(&self) -> bool {
+                let (first, rest) = self.coefficients().split_first().unwrap();
+                first.is_one() && rest.iter().all(F::is_zero)
+            }
+        }
+
+        impl<F> Pow<usize> for &$name<F>
+        where
+            F: $bound,
+        {
+            type Output = $name<F>;
+
+            fn pow(self, exponent: usize) -> Self::Output {
+                let mut result = <$name<F>>::one();
+                let mut square = self.clone();
+                let mut exponent = exponent;
+                while exponent > 0 {
+                    if exponent & 1 == 1 {
+                        result *= &square;
+                    }
+                    exponent >>= 1;
+                    if exponent > 0 {
+                        square.square_assign();
+                    }
+                }
+                result
+            }
+        }
+
+        /// Only the roots of unity of the base field are provided.
+        impl<F> Root<usize> for $name<F>
+        where
+            F: $bound,
+        {
+            fn root(order: usize) -> Option<Self> {
+                F::root(order).map(Self::from_base)
+            }
+        }
+
+        impl<F> From<F> for $name<F>
+        where
+            F: $bound,
+        {
+            fn from(base: F) -> Self {
+                Self::from_base(base)
+            }
+        }
+
+        extension_ops!(@from $name, $bound, u8, u16, u32, u64, u128, usize);
+        extension_ops!(@from $name, $bound, i8, i16, i32, i64, i128, isize);
+
+        impl<F> Sum<Self> for $name<F>
+        where
+            F: $bound,
+        {
+            fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+                iter.fold(Self::zero(), Add::add)
+            }
+        }
+
+        impl<F> Product<Self> for $name<F>
+        where
+            F: $bound,
+        {
+            fn product<I: Iterator<Item = Self>>(iter: I) -> Self {
+                iter.fold(Self::one(), Mul::mul)
+            }
+        }
+
+        #[cfg(any(test, feature = "proptest"))]
+        impl<F> Arbitrary for $name<F>
+        where
+            F: $bound + Arbitrary + 'static,
+            F::Strategy: 'static,
+        {
+            type Parameters = ();
+            type Strategy = BoxedStrategy<Self>;
+
+            fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+                proptest::collection::vec(F::arbitrary(), Self::DEGREE)
+                    .prop_map(|coefficients| Self::from_coefficients(&coefficients))
+                    .boxed()
+            }
+        }
+    };
+    (@op $name:ident, $bound:ident, $op_trait:ident, $op_fn:ident, $assign_trait:ident,
+        $assign_fn:ident, $inline_fn:ident) => {
+        impl<F> $op_trait<&$name<F>> for &$name<F>
+        where
+            F: $bound,
+        {
+            type Output = $name<F>;
+
+            #[inline(always)] // Simple wrapper in hot path
+            fn $op_fn(self, rhs: &$name<F>) -> Self::Output {
+                self.$inline_fn(rhs)
+            }
+        }
+
+        impl<F> $op_trait<$name<F>> for &$name<F>
+        where
+            F: $bound,
+        {
+            type Output = $name<F>;
+
+            #[inline(always)] // Simple wrapper in hot path
+            fn $op_fn(self, rhs: $name<F>) -> Self::Output {
+                self.$inline_fn(&rhs)
+            }
+        }
+
+        impl<F> $op_trait<&$name<F>> for $name<F>
+        where
+            F: $bound,
+        {
+            type Output = $name<F>;
+
+            #[inline(always)] // Simple wrapper in hot path
+            fn $op_fn(self, rhs: &$name<F>) -> Self::Output {
+                self.$inline_fn(rhs)
+            }
+        }
+
+        impl<F> $op_trait<$name<F>> for $name<F>
+        where
+            F: $bound,
+        {
+            type Output = $name<F>;
+
+            #[inline(always)] // Simple wrapper in hot path
+            fn $op_fn(self, rhs: $name<F>) -> Self::Output {
+                self.$inline_fn(&rhs)
+            }
+        }
+
+        impl<F> $assign_trait<&$name<F>> for $name<F>
+        where
+            F: $bound,
+        {
+            #[inline(always)] // Simple wrapper in hot path
+            fn $assign_fn(&mut self, rhs: &$name<F>) {
+                *self = self.$inline_fn(rhs)
+            }
+        }
+
+        impl<F> $assign_trait<$name<F>> for $name<F>
+        where
+            F: $bound,
+        {
+            #[inline(always)] // Simple wrapper in hot path
+            fn $assign_fn(&mut self, rhs: $name<F>) {
+                *self = self.$inline_fn(&rhs)
+            }
+        }
+    };
+    (@from $name:ident, $bound:ident, $($type:ident),*) => {
+        $(
+            impl<F> From<$type> for $name<F>
+            where
+                F: $bound + From<$type>,
+            {
+                fn from(value: $type) -> Self {
+                    Self::from_base(F::from(value))
+                }
+            }
+        )*
+    };
+}
+
+mod cubic;
+mod quadratic;
+
+pub use cubic::CubicExtension;
+pub use quadratic::QuadraticExtension;
@@ -0,0 +1,248 @@
+use super::QuadraticNonResidue;
+use crate::{AddInline, Inv, MulInline, NegInline, One, Pow, Root, SquareInline, SubInline, Zero};
+#[cfg(any(test, feature = "proptest"))]
+use proptest::prelude::*;
+use std::{
+    iter::{Product, Sum},
+    ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign},
+};
+
+// False positives on the Latex math.
+#[allow(clippy::doc_markdown)]
+/// The quadratic extension $\mathbb{F}[u] / (u^2 - w)$ of a base field.
+///
+/// Elements are $c_0 + c_1 u$ with coefficients in the base field and $w$ is
+/// [`QuadraticNonResidue::quadratic_non_residue`].
+#[derive(Clone, PartialEq, Eq, Hash, Default, Debug)]
+pub struct QuadraticExtension<F>([F; 2]);
+
+// False positives on the Latex math.
+#[allow(clippy::doc_markdown)]
+impl<F> QuadraticExtension<F>
+where
+    F: QuadraticNonResidue,
+{
+    /// Degree of the extension.
+    pub const DEGREE: usize = 2;
+
+    /// Construct $c_0 + c_1 u$.
+    pub fn new(c0: F, c1: F) -> Self {
+        Self([c0, c1])
+    }
+
+    /// Embed an element of the base field.
+    pub fn from_base(base: F) -> Self {
+        Self([base, F::zero()])
+    }
+
+    /// Construct from the coefficients $[c_0, c_1]$.
+    ///
+    /// # Panics
+    ///
+    /// Panics if there are not exactly two coefficients.
+    pub fn from_coefficients(coefficients: &[F]) -> Self {
+        assert_eq!(coefficients.len(), Self::DEGREE);
+        Self([coefficients[0].clone(), coefficients[1].clone()])
+    }
+
+    /// The coefficients $[c_0, c_1]$.
+    pub fn coefficients(&self) -> &[F] {
+        &self.0
+    }
+
+    /// The conjugate $c_0 - c_1 u$.
+    pub fn conjugate(&self) -> Self {
+        Self([self.0[0].clone(), self.0[1].neg_inline()])
+    }
+
+    /// The norm $c_0^2 - w c_1^2$, the product with the conjugate.
+    pub fn norm(&self) -> F {
+        let [c0, c1] = &self.0;
+        c0.square_inline()
+            .sub_inline(&F::quadratic_non_residue().mul_inline(&c1.square_inline()))
+    }
+}
+
+impl<F> AddInline<&Self> for QuadraticExtension<F>
+where
+    F: QuadraticNonResidue,
+{
+    #[inline(always)]
+    fn add_inline(&self, rhs: &Self) -> Self {
+        Self([
+            self.0[0].add_inline(&rhs.0[0]),
+            self.0[1].add_inline(&rhs.0[1]),
+        ])
+    }
+}
+
+impl<F> SubInline<&Self> for QuadraticExtension<F>
+where
+    F: QuadraticNonResidue,
+{
+    #[inline(always)]
+    fn sub_inline(&self, rhs: &Self) -> Self {
+        Self([
+            self.0[0].sub_inline(&rhs.0[0]),
+            self.0[1].sub_inline(&rhs.0[1]),
+        ])
+    }
+}
+
+impl<F> NegInline for QuadraticExtension<F>
+where
+    F: QuadraticNonResidue,
+{
+    #[inline(always)]
+    fn neg_inline(&self) -> Self {
+        Self([self.0[0].neg_inline(), self.0[1].neg_inline()])
+    }
+}
+
+impl<F> MulInline<&Self> for QuadraticExtension<F>
+where
+    F: QuadraticNonResidue,
+{
+    // Karatsuba multiplication
+    #[inline(always)]
+    fn mul_inline(&self, rhs: &Self) -> Self {
+        let [a0, a1] = &self.0;
+        let [b0, b1] = &rhs.0;
+        let v0 = a0.mul_inline(b0);
+        let v1 = a1.mul_inline(b1);
+        let c1 = a0
+            .add_inline(a1)
+            .mul_inline(&b0.add_inline(b1))
+            .sub_inline(&v0)
+            .sub_inline(&v1);
+        Self([
+            v0.add_inline(&F::quadratic_non_residue().mul_inline(&v1)),
+            c1,
+        ])
+    }
+}
+
+impl<F> SquareInline for QuadraticExtension<F>
+where
+    F: QuadraticNonResidue,
+{
+    #[inline(always)]
+    fn square_inline(&self) -> Self {
+        let [c0, c1] = &self.0;
+        let product = c0.mul_inline(c1);
+        Self([
+            c0.square_inline()
+                .add_inline(&F::quadratic_non_residue().mul_inline(&c1.square_inline())),
+            product.add_inline(&product),
+        ])
+    }
+}
+
+impl<F> Inv for &QuadraticExtension<F>
+where
+    F: QuadraticNonResidue,
+{
+    type Output = Option<QuadraticExtension<F>>;
+
+    fn inv(self) -> Self::Output {
+        // Non-zero elements have a non-zero norm because $u^2 - w$ is
+        // irreducible.
+        let norm_inv = self.norm().inverse()?;
+        let [c0, c1] = &self.0;
+        Some(QuadraticExtension([
+            c0.mul_inline(&norm_inv),
+            c1.mul_inline(&norm_inv).neg_inline(),
+        ]))
+    }
+}
+
+extension_ops!(QuadraticExtension, QuadraticNonResidue);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        geometric_series::geometric_series, invert_batch, FieldElement, Goldilocks, SquareRoot,
+    };
+
+    type Goldilocks2 = QuadraticExtension<Goldilocks>;
+    type Stark2 = QuadraticExtension<FieldElement>;
+
+    #[test]
+    fn test_irreducible() {
+        assert!(!Goldilocks::quadratic_non_residue().is_quadratic_residue());
+        assert!(!FieldElement::quadratic_non_residue().is_quadratic_residue());
+    }
+
+    #[test]
+    fn test_u_squared() {
+        let u = Goldilocks2::new(Goldilocks::zero(), Goldilocks::one());
+        assert_eq!(
+            u.square(),
+            Goldilocks2::from_base(Goldilocks::quadratic_non_residue())
+        );
+    }
+
+    #[test]
+    fn test_root() {
+        let root = Goldilocks2::root(1 << 20).unwrap();
+        assert_eq!(root.pow(1 << 20), Goldilocks2::one());
+        assert_ne!(root.pow(1 << 19), Goldilocks2::one());
+    }
+
+    proptest!(
+        #[test]
+        fn associative_mul(a: Goldilocks2, b: Goldilocks2, c: Goldilocks2) {
+            prop_assert_eq!(&a * (&b * &c), (a * b) * c);
+        }
+
+        #[test]
+        fn distributivity(a: Goldilocks2, b: Goldilocks2, c: Goldilocks2) {
+            prop_assert_eq!(&a * (&b + &c), (&a * b) + (a * c));
+        }
+
+        #[test]
+        fn square(a: Stark2) {
+            prop_assert_eq!(a.square(), &a * &a);
+        }
+
+        #[test]
+        fn inverse_mul(a: Stark2) {
+            match a.inv() {
+                None => prop_assert!(a.is_zero()),
+                Some(inverse) => prop_assert!((a * inverse).is_one()),
+            }
+        }
+
+        #[test]
+        fn norm_is_product_with_conjugate(a: Goldilocks2) {
+            prop_assert_eq!(&a * a.conjugate(), Goldilocks2::from_base(a.norm()));
+        }
+
+        #[test]
+        fn pow_n(a: Goldilocks2, n: usize) {
+            let exponent = n % 64;
+            prop_assert_eq!(
+                a.pow(exponent),
+                std::iter::repeat(a).take(exponent).product()
+            );
+        }
+
+        #[test]
+        fn batch_inverse(values: Vec<Goldilocks2>) {
+            prop_assume!(!values.iter().any(Goldilocks2::is_zero));
+            for (inverse, value) in invert_batch(&values).iter().zip(values.iter()) {
+                prop_assert!((inverse * value).is_one());
+            }
+        }
+
+        #[test]
+        fn geometric(base: Goldilocks2, step: Goldilocks2) {
+            let mut expected = base.clone();
+            for value in geometric_series(&base, &step).take(8) {
+                prop_assert_eq!(&value, &expected);
+                expected *= &step;
+            }
+        }
+    );
+}
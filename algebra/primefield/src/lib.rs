@@ -46,6 +46,7 @@
 #![allow(clippy::inline_always)]
 
 mod convert;
+mod extension;
 pub mod fft;
 pub mod geometric_series;
 mod invert_batch;
@@ -60,6 +61,7 @@ mod proth_field;
 mod rand;
 #[cfg(feature = "serde")]
 mod serde;
+mod small_fields;
 mod traits;
 mod uint;
 
@@ -70,6 +72,14 @@ pub use zkp_u256::MontgomeryParameters;
 // The smallest 252-bit Proth field
 pub use proth_field::FieldElement;
 
+// Word sized fields with native arithmetic
+pub use small_fields::{BabyBear, Goldilocks, Mersenne31};
+
+// Extension fields
+pub use extension::{
+    CubicExtension, CubicNonResidue, ExtensionBase, QuadraticExtension, QuadraticNonResidue,
+};
+
 pub use invert_batch::{invert_batch, invert_batch_src_dst};
 
 // Re-exports dependencies that are part of the public interface
@@ -0,0 +1,56 @@
+/// The `BabyBear` field of order $p = 15 \cdot 2^{27} + 1$.
+///
+/// Elements fit in 31 bits, so sums of two elements do not overflow a `u32`
+/// and products are reduced from a `u64` using a Barrett reduction. There are
+/// roots of unity for FFTs up to size $2^{27}$. The generator is $31$.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default, Debug)]
+pub struct BabyBear(u32);
+
+impl BabyBear {
+    /// $\lfloor 2^{62} / p \rfloor$
+    const BARRETT: u64 = (1 << 62) / 0x7800_0001;
+
+    // The result is less than the modulus
+    #[allow(clippy::cast_possible_truncation)]
+    #[inline(always)]
+    fn reduce(wide: u64) -> u32 {
+        // Products are less than 2^62, so the quotient estimate is at most one
+        // too small and the remainder is less than 2p
+        let quotient = ((u128::from(wide) * u128::from(Self::BARRETT)) >> 62) as u64;
+        let remainder = (wide - quotient * u64::from(Self::MODULUS)) as u32;
+        if remainder >= Self::MODULUS {
+            remainder - Self::MODULUS
+        } else {
+            remainder
+        }
+    }
+}
+
+small_field!(BabyBear, u32, u64, 0x7800_0001, 31);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reduce() {
+        let p = u64::from(BabyBear::MODULUS);
+        for wide in &[0, 1, p - 1, p, p + 1, 2 * p, (p - 1) * (p - 1), p * p - 1] {
+            assert_eq!(u64::from(BabyBear::reduce(*wide)), wide % p);
+        }
+    }
+
+    #[test]
+    fn test_two_adicity() {
+        let root = BabyBear::root(1 << 27).unwrap();
+        assert_eq!(root.pow(1_usize << 27), BabyBear::one());
+        assert_ne!(root.pow(1_usize << 26), BabyBear::one());
+        assert_eq!(BabyBear::root(1 << 28), None);
+    }
+
+    #[test]
+    fn test_negative() {
+        assert_eq!(BabyBear::from(-1), BabyBear::from(BabyBear::MODULUS - 1));
+        assert_eq!(BabyBear::from(-1) + BabyBear::one(), BabyBear::zero());
+    }
+}
@@ -0,0 +1,79 @@
+/// The Goldilocks field of order $p = 2^{64} - 2^{32} + 1$.
+///
+/// Products are reduced using $2^{64} \equiv 2^{32} - 1$ and
+/// $2^{96} \equiv -1 \pmod p$, which only takes a few word operations. The
+/// multiplicative group has order $2^{32} \cdot 3 \cdot 5 \cdot 17 \cdot 257
+/// \cdot 65537$, so there are roots of unity for FFTs up to size $2^{32}$.
+/// The generator is $7$.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default, Debug)]
+pub struct Goldilocks(u64);
+
+/// $2^{64} \bmod p$
+const EPSILON: u64 = 0xffff_ffff;
+
+impl Goldilocks {
+    // Truncations are intended
+    #[allow(clippy::cast_possible_truncation)]
+    #[inline(always)]
+    fn reduce(wide: u128) -> u64 {
+        let low = wide as u64;
+        let high = (wide >> 64) as u64;
+        let high_high = high >> 32;
+        let high_low = high & EPSILON;
+
+        // Subtract `high_high` using 2^96 = -1
+        let (mut result, borrow) = low.overflowing_sub(high_high);
+        if borrow {
+            result = result.wrapping_sub(EPSILON);
+        }
+        // Add `high_low` using 2^64 = EPSILON, the product can not overflow
+        let (mut result, carry) = result.overflowing_add(high_low * EPSILON);
+        if carry {
+            result += EPSILON;
+        }
+        if result >= Self::MODULUS {
+            result - Self::MODULUS
+        } else {
+            result
+        }
+    }
+}
+
+small_field!(Goldilocks, u64, u128, 0xffff_ffff_0000_0001, 7);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reduce() {
+        let p = u128::from(Goldilocks::MODULUS);
+        for wide in &[
+            0,
+            p - 1,
+            p,
+            p + 1,
+            u128::from(u64::MAX),
+            (p - 1) * (p - 1),
+            1 << 96,
+            (1 << 96) - 1,
+        ] {
+            assert_eq!(u128::from(Goldilocks::reduce(*wide)), wide % p);
+        }
+    }
+
+    #[test]
+    fn test_two_adicity() {
+        let root = Goldilocks::root(1 << 32).unwrap();
+        assert_eq!(root.pow(1_usize << 32), Goldilocks::one());
+        assert_ne!(root.pow(1_usize << 31), Goldilocks::one());
+        assert_eq!(Goldilocks::root(1 << 33), None);
+    }
+
+    #[test]
+    fn test_add_overflow() {
+        let a = -Goldilocks::one();
+        assert_eq!(a + a, Goldilocks::from(-2));
+        assert_eq!(Goldilocks::from(u64::MAX), Goldilocks::from(EPSILON - 1));
+    }
+}
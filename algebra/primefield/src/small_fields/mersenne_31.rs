@@ -0,0 +1,49 @@
+/// The Mersenne-31 field of order $p = 2^{31} - 1$.
+///
+/// Products are reduced using $2^{31} \equiv 1 \pmod p$, a shift and an
+/// addition. Note that $p - 1 = 2 \cdot 3^2 \cdot 7 \cdot 11 \cdot 31 \cdot
+/// 151 \cdot 331$ has a single factor two, so [`Root`] only returns roots of
+/// unity of order dividing $p - 1$ and power of two FFTs are limited to size
+/// two. The generator is $7$.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default, Debug)]
+pub struct Mersenne31(u32);
+
+impl Mersenne31 {
+    // The result is less than the modulus
+    #[allow(clippy::cast_possible_truncation)]
+    #[inline(always)]
+    fn reduce(wide: u64) -> u32 {
+        // Products are less than 2^62, so this is less than 2p
+        let folded = (wide & u64::from(Self::MODULUS)) + (wide >> 31);
+        let folded = folded as u32;
+        if folded >= Self::MODULUS {
+            folded - Self::MODULUS
+        } else {
+            folded
+        }
+    }
+}
+
+small_field!(Mersenne31, u32, u64, 0x7fff_ffff, 7);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reduce() {
+        let p = u64::from(Mersenne31::MODULUS);
+        for wide in &[0, 1, p - 1, p, p + 1, 2 * p, (p - 1) * (p - 1)] {
+            assert_eq!(u64::from(Mersenne31::reduce(*wide)), wide % p);
+        }
+    }
+
+    #[test]
+    fn test_roots() {
+        assert_eq!(Mersenne31::root(2), Some(-Mersenne31::one()));
+        assert_eq!(Mersenne31::root(4), None);
+        let root = Mersenne31::root(31).unwrap();
+        assert_eq!(root.pow(31_usize), Mersenne31::one());
+        assert_ne!(root, Mersenne31::one());
+    }
+}
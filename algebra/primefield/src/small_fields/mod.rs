@@ -0,0 +1,637 @@
+//! Prime fields that fit in a single machine word.
+//!
+//! Unlike [`PrimeField`](crate::PrimeField) these do not use Montgomery
+//! arithmetic on [`U256`](zkp_u256::U256). Values are stored in canonical
+//! form in a `u32` or `u64` and products are reduced without a division,
+//! using the special form of the modulus or a Barrett reduction.
+
+// Using `Self` makes things less readable here.
+#![allow(clippy::use_self)]
+
+/// Implements the field traits for a word sized prime field.
+///
+/// The type must be a tuple struct wrapping a canonical `$uint` and provide
+/// an associated function `reduce(wide: $wide) -> $uint` that reduces any
+/// product of two canonical values.
+macro_rules! small_field {
+    ($name:ident, $uint:ty, $wide:ty, $modulus:expr, $generator:expr) => {
+        use crate::{convert::MaybeSigned, Root, SquareRoot};
+        #[cfg(any(test, feature = "proptest"))]
+        use proptest::prelude::*;
+        #[cfg(feature = "rand")]
+        use rand::{
+            distributions::{Distribution, Standard, Uniform},
+            Rng,
+        };
+        #[cfg(feature = "serde")]
+        use serde::{
+            de::{Deserialize, Deserializer, Error as _},
+            ser::{Serialize, Serializer},
+        };
+        use std::{
+            fmt,
+            iter::{Product, Sum},
+            ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign},
+        };
+        use zkp_u256::{
+            AddInline, Inv, MulInline, NegInline, One, Pow, SquareInline, SubInline, Zero,
+        };
+
+        impl $name {
+            pub const MODULUS: $uint = $modulus;
+
+            #[inline(always)]
+            pub fn modulus() -> $uint {
+                Self::MODULUS
+            }
+
+            /// The multiplicative order of the field.
+            ///
+            /// Equal to `modulus() - 1` for prime fields.
+            #[inline(always)]
+            pub fn order() -> $uint {
+                Self::MODULUS - 1
+            }
+
+            #[inline(always)]
+            pub fn generator() -> Self {
+                Self($generator)
+            }
+
+            /// Construct from a canonical value, which must be less than the
+            /// modulus.
+            #[inline(always)]
+            pub const fn from_uint_const(uint: $uint) -> Self {
+                Self(uint)
+            }
+
+            /// Convert to the canonical value.
+            #[inline(always)]
+            pub fn to_uint(&self) -> $uint {
+                debug_assert!(self.0 < Self::MODULUS);
+                self.0
+            }
+
+            /// Construct from a canonical value.
+            #[inline(always)]
+            pub fn from_uint(uint: &$uint) -> Self {
+                debug_assert!(*uint < Self::MODULUS);
+                Self(*uint)
+            }
+
+            /// Reduce and construct from a value.
+            #[inline(always)]
+            pub fn from_uint_reduce(uint: &$uint) -> Self {
+                Self(uint % Self::MODULUS)
+            }
+
+            #[inline(always)]
+            pub fn double(&self) -> Self {
+                self.add_inline(self)
+            }
+
+            #[inline(always)]
+            pub fn triple(&self) -> Self {
+                self.double().add_inline(self)
+            }
+
+            fn pow_u64(&self, exponent: u64) -> Self {
+                let mut result = Self::one();
+                let mut square = *self;
+                let mut exponent = exponent;
+                while exponent > 0 {
+                    if exponent & 1 == 1 {
+                        result *= &square;
+                    }
+                    exponent >>= 1;
+                    if exponent > 0 {
+                        square.square_assign();
+                    }
+                }
+                result
+            }
+
+            // Reduce an arbitrary integer. Only used for conversions, so
+            // the division is fine.
+            #[allow(clippy::cast_possible_truncation)]
+            fn from_u128_reduce(value: u128) -> Self {
+                Self((value % u128::from(Self::MODULUS)) as $uint)
+            }
+        }
+
+        impl Zero for $name {
+            #[inline(always)]
+            fn zero() -> Self {
+                Self(0)
+            }
+
+            #[inline(always)]
+            fn is_zero(&self) -> bool {
+                self.0 == 0
+            }
+        }
+
+        impl One for $name {
+            #[inline(always)]
+            fn one() -> Self {
+                Self(1)
+            }
+
+            #[inline(always)]
+            fn is_one(&self) -> bool {
+                self.0 == 1
+            }
+        }
+
+        impl AddInline<&Self> for $name {
+            #[inline(always)]
+            fn add_inline(&self, rhs: &Self) -> Self {
+                // On overflow the wrapping subtraction gives the right result
+                let (sum, carry) = self.0.overflowing_add(rhs.0);
+                if carry || sum >= Self::MODULUS {
+                    Self(sum.wrapping_sub(Self::MODULUS))
+                } else {
+                    Self(sum)
+                }
+            }
+        }
+
+        impl SubInline<&Self> for $name {
+            #[inline(always)]
+            fn sub_inline(&self, rhs: &Self) -> Self {
+                let (difference, borrow) = self.0.overflowing_sub(rhs.0);
+                if borrow {
+                    Self(difference.wrapping_add(Self::MODULUS))
+                } else {
+                    Self(difference)
+                }
+            }
+        }
+
+        impl NegInline for $name {
+            #[inline(always)]
+            fn neg_inline(&self) -> Self {
+                if self.is_zero() {
+                    Self::zero()
+                } else {
+                    Self(Self::MODULUS - self.0)
+                }
+            }
+        }
+
+        impl SquareInline for $name {
+            #[inline(always)]
+            fn square_inline(&self) -> Self {
+                Self(Self::reduce(<$wide>::from(self.0) * <$wide>::from(self.0)))
+            }
+        }
+
+        impl MulInline<&Self> for $name {
+            #[inline(always)]
+            fn mul_inline(&self, rhs: &Self) -> Self {
+                Self(Self::reduce(<$wide>::from(self.0) * <$wide>::from(rhs.0)))
+            }
+        }
+
+        impl Inv for &$name {
+            type Output = Option<$name>;
+
+            #[inline(always)] // Simple wrapper
+            fn inv(self) -> Self::Output {
+                if self.is_zero() {
+                    None
+                } else {
+                    // Fermat's little theorem
+                    Some(self.pow_u64(u64::from($name::MODULUS - 2)))
+                }
+            }
+        }
+
+        impl Pow<usize> for &$name {
+            type Output = $name;
+
+            fn pow(self, exponent: usize) -> Self::Output {
+                self.pow_u64(exponent as u64)
+            }
+        }
+
+        impl Pow<isize> for &$name {
+            type Output = Option<$name>;
+
+            fn pow(self, exponent: isize) -> Self::Output {
+                let negative = exponent < 0;
+                let abs = exponent.unsigned_abs();
+                if negative {
+                    self.inv().map(|n| n.pow(abs))
+                } else {
+                    Some(self.pow(abs))
+                }
+            }
+        }
+
+        impl Root<usize> for $name {
+            // OPT: replace this with a constant array of roots of unity.
+            fn root(order: usize) -> Option<Self> {
+                let order = order as u64;
+                let group = u64::from(Self::order());
+                if order == 0 {
+                    Some(Self::one())
+                } else if group % order == 0 {
+                    Some(Self::generator().pow_u64(group / order))
+                } else {
+                    None
+                }
+            }
+        }
+
+        impl SquareRoot for $name {
+            fn is_quadratic_residue(&self) -> bool {
+                self.pow_u64(u64::from(Self::MODULUS >> 1)) != -Self::one()
+            }
+
+            // Tonelli-Shanks square root algorithm for prime fields
+            // See 'Handbook of Applied Cryptography' algorithm 3.34
+            fn square_root(&self) -> Option<Self> {
+                if self.is_zero() {
+                    return Some(Self::zero());
+                }
+                if !self.is_quadratic_residue() {
+                    return None;
+                }
+                let order = u64::from(Self::order());
+                let trailing_zeros = order.trailing_zeros();
+                let signifcant = order >> trailing_zeros;
+                // The generator is a quadratic non-residue
+                let mut c = Self::generator().pow_u64(signifcant);
+                let mut root = self.pow_u64((signifcant + 1) >> 1);
+                let inverse = self.inv().unwrap(); // Zero case is handled above
+                for i in 1..trailing_zeros {
+                    if (root.square() * &inverse).pow_u64(1 << (trailing_zeros - i - 1))
+                        == -Self::one()
+                    {
+                        root *= &c;
+                    }
+                    c.square_assign();
+                }
+                Some(root)
+            }
+        }
+
+        impl Add<&$name> for &$name {
+            type Output = $name;
+
+            #[inline(always)] // Simple wrapper in hot path
+            fn add(self, rhs: &$name) -> Self::Output {
+                self.add_inline(rhs)
+            }
+        }
+
+        impl Sub<&$name> for &$name {
+            type Output = $name;
+
+            #[inline(always)] // Simple wrapper in hot path
+            fn sub(self, rhs: &$name) -> Self::Output {
+                self.sub_inline(rhs)
+            }
+        }
+
+        impl Mul<&$name> for &$name {
+            type Output = $name;
+
+            #[inline(always)] // Simple wrapper in hot path
+            fn mul(self, rhs: &$name) -> Self::Output {
+                self.mul_inline(rhs)
+            }
+        }
+
+        impl Div<&$name> for &$name {
+            type Output = $name;
+
+            // Division suspiciously requires multiplication
+            #[allow(clippy::suspicious_arithmetic_impl)]
+            #[inline(always)]
+            fn div(self, rhs: &$name) -> Self::Output {
+                self * &rhs.inv().expect("Division by zero")
+            }
+        }
+
+        small_field!(@ops $name, Add, add, AddAssign, add_assign);
+        small_field!(@ops $name, Sub, sub, SubAssign, sub_assign);
+        small_field!(@ops $name, Mul, mul, MulAssign, mul_assign);
+        small_field!(@ops $name, Div, div, DivAssign, div_assign);
+
+        impl Neg for $name {
+            type Output = $name;
+
+            #[inline(always)]
+            fn neg(self) -> Self::Output {
+                self.neg_inline()
+            }
+        }
+
+        impl Neg for &$name {
+            type Output = $name;
+
+            #[inline(always)]
+            fn neg(self) -> Self::Output {
+                self.neg_inline()
+            }
+        }
+
+        impl Sum<Self> for $name {
+            fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+                iter.fold(Self::zero(), Add::add)
+            }
+        }
+
+        impl<'a> Sum<&'a Self> for $name {
+            fn sum<I: Iterator<Item = &'a Self>>(iter: I) -> Self {
+                iter.fold(Self::zero(), |a, b| a + b)
+            }
+        }
+
+        impl Product<Self> for $name {
+            fn product<I: Iterator<Item = Self>>(iter: I) -> Self {
+                iter.fold(Self::one(), Mul::mul)
+            }
+        }
+
+        impl<'a> Product<&'a Self> for $name {
+            fn product<I: Iterator<Item = &'a Self>>(iter: I) -> Self {
+                iter.fold(Self::one(), |a, b| a * b)
+            }
+        }
+
+        small_field!(@from_unsigned $name, u8, u16, u32, u64, usize);
+        small_field!(@from_signed $name, i8, i16, i32, i64, i128, isize);
+
+        impl From<u128> for $name {
+            #[inline(always)]
+            fn from(other: u128) -> Self {
+                Self::from_u128_reduce(other)
+            }
+        }
+
+        impl From<$name> for $uint {
+            #[inline(always)]
+            fn from(other: $name) -> Self {
+                other.to_uint()
+            }
+        }
+
+        impl From<&$name> for $uint {
+            #[inline(always)]
+            fn from(other: &$name) -> Self {
+                other.to_uint()
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                write!(f, "{}", self.0)
+            }
+        }
+
+        #[cfg(feature = "serde")]
+        impl Serialize for $name {
+            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+            where
+                S: Serializer,
+            {
+                self.0.serialize(serializer)
+            }
+        }
+
+        #[cfg(feature = "serde")]
+        impl<'de> Deserialize<'de> for $name {
+            fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+            where
+                D: Deserializer<'de>,
+            {
+                let uint = <$uint>::deserialize(deserializer)?;
+                if uint < Self::MODULUS {
+                    Ok(Self(uint))
+                } else {
+                    Err(D::Error::custom("value is not less than the modulus"))
+                }
+            }
+        }
+
+        #[cfg(feature = "rand")]
+        impl Distribution<$name> for Standard {
+            fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> $name {
+                $name(Uniform::new(0, $name::MODULUS).sample(rng))
+            }
+        }
+
+        #[cfg(any(test, feature = "proptest"))]
+        impl Arbitrary for $name {
+            type Parameters = ();
+            type Strategy = BoxedStrategy<Self>;
+
+            fn arbitrary_with(_: Self::Parameters) -> Self::Strategy {
+                (0..$name::MODULUS).prop_map($name).boxed()
+            }
+        }
+    };
+    (@ops $name:ident, $op_trait:ident, $op_fn:ident, $assign_trait:ident, $assign_fn:ident) => {
+        impl $op_trait<$name> for $name {
+            type Output = $name;
+
+            #[inline(always)] // Simple wrapper in hot path
+            fn $op_fn(self, rhs: $name) -> Self::Output {
+                $op_trait::$op_fn(&self, &rhs)
+            }
+        }
+
+        impl $op_trait<&$name> for $name {
+            type Output = $name;
+
+            #[inline(always)] // Simple wrapper in hot path
+            fn $op_fn(self, rhs: &$name) -> Self::Output {
+                $op_trait::$op_fn(&self, rhs)
+            }
+        }
+
+        impl $op_trait<$name> for &$name {
+            type Output = $name;
+
+            #[inline(always)] // Simple wrapper in hot path
+            fn $op_fn(self, rhs: $name) -> Self::Output {
+                $op_trait::$op_fn(self, &rhs)
+            }
+        }
+
+        impl $assign_trait<$name> for $name {
+            #[inline(always)] // Simple wrapper in hot path
+            fn $assign_fn(&mut self, rhs: $name) {
+                *self = $op_trait::$op_fn(&*self, &rhs)
+            }
+        }
+
+        impl $assign_trait<&$name> for $name {
+            #[inline(always)] // Simple wrapper in hot path
+            fn $assign_fn(&mut self, rhs: &$name) {
+                *self = $op_trait::$op_fn(&*self, rhs)
+            }
+        }
+    };
+    (@from_unsigned $name:ident, $($type:ident),*) => {
+        $(
+            impl From<$type> for $name {
+                // All unsigned types fit in `u128`
+                #[allow(clippy::cast_lossless)]
+                #[inline(always)]
+                fn from(other: $type) -> Self {
+                    Self::from_u128_reduce(other as u128)
+                }
+            }
+        )*
+    };
+    (@from_signed $name:ident, $($type:ident),*) => {
+        $(
+            impl From<$type> for $name {
+                #[inline(always)]
+                fn from(other: $type) -> Self {
+                    let (sign, abs) = other.as_sign_abs();
+                    // Non-negative after `as_sign_abs`
+                    #[allow(clippy::cast_sign_loss)]
+                    let result = Self::from_u128_reduce(abs as u128);
+                    if sign {
+                        -result
+                    } else {
+                        result
+                    }
+                }
+            }
+        )*
+    };
+}
+
+mod baby_bear;
+mod goldilocks;
+mod mersenne_31;
+
+pub use baby_bear::BabyBear;
+pub use goldilocks::Goldilocks;
+pub use mersenne_31::Mersenne31;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Fft, Inv, One, Pow, Root, SquareInline, SquareRoot, Zero};
+    use proptest::prelude::*;
+
+    macro_rules! field_tests {
+        ($module:ident, $field:ident) => {
+            mod $module {
+                use super::*;
+
+                proptest!(
+                    #[test]
+                    fn from_i64(n: i64) {
+                        let expected = if n < 0 {
+                            -$field::from(n.unsigned_abs())
+                        } else {
+                            $field::from(n.unsigned_abs())
+                        };
+                        prop_assert_eq!($field::from(n), expected);
+                    }
+
+                    #[test]
+                    fn commutative_mul(a: $field, b: $field) {
+                        prop_assert_eq!(a * b, b * a);
+                    }
+
+                    #[test]
+                    fn associative_mul(a: $field, b: $field, c: $field) {
+                        prop_assert_eq!(a * (b * c), (a * b) * c);
+                    }
+
+                    #[test]
+                    fn distributivity(a: $field, b: $field, c: $field) {
+                        prop_assert_eq!(a * (b + c), a * b + a * c);
+                    }
+
+                    #[test]
+                    fn inverse_add(a: $field, b: $field) {
+                        prop_assert!((a + -a).is_zero());
+                        prop_assert_eq!(a - b + b, a);
+                    }
+
+                    #[test]
+                    fn inverse_mul(a: $field) {
+                        match a.inv() {
+                            None => prop_assert!(a.is_zero()),
+                            Some(ai) => prop_assert!((a * ai).is_one()),
+                        }
+                    }
+
+                    #[test]
+                    fn square(a: $field) {
+                        prop_assert_eq!(a.square(), a * a);
+                    }
+
+                    #[test]
+                    fn fermats_little_theorem(a: $field) {
+                        prop_assert_eq!(a.pow($field::MODULUS as usize), a);
+                    }
+
+                    #[test]
+                    fn square_root(a: $field) {
+                        let r = a.square().square_root().unwrap();
+                        prop_assert!(r == a || r == -a);
+                    }
+
+                    #[test]
+                    fn mul_matches_u128(a: $field, b: $field) {
+                        let expected = u128::from(a.to_uint()) * u128::from(b.to_uint())
+                            % u128::from($field::MODULUS);
+                        prop_assert_eq!(u128::from((a * b).to_uint()), expected);
+                    }
+
+                    #[test]
+                    fn serde(a: $field) {
+                        let serialized = serde_json::to_string(&a)?;
+                        let deserialized: $field = serde_json::from_str(&serialized)?;
+                        prop_assert_eq!(deserialized, a);
+                    }
+                );
+
+                #[test]
+                fn serde_out_of_range() {
+                    let serialized = serde_json::to_string(&$field::MODULUS).unwrap();
+                    assert!(serde_json::from_str::<$field>(&serialized).is_err());
+                }
+
+                #[test]
+                fn generator() {
+                    assert!(!$field::generator().is_quadratic_residue());
+                    assert_eq!($field::root(1), Some($field::one()));
+                    assert_eq!($field::root(0), Some($field::one()));
+                }
+
+                #[test]
+                fn fft_roundtrip() {
+                    // Largest power of two supported, capped for speed
+                    let size = (1..=8)
+                        .map(|log| 1_usize << log)
+                        .take_while(|size| $field::root(*size).is_some())
+                        .last()
+                        .unwrap();
+                    let original = (0..size).map($field::from).collect::<Vec<_>>();
+                    let mut values = original.clone();
+                    values.fft();
+                    crate::fft::permute(&mut values);
+                    values.ifft();
+                    crate::fft::permute(&mut values);
+                    assert_eq!(values, original);
+                }
+            }
+        };
+    }
+
+    field_tests!(goldilocks, Goldilocks);
+    field_tests!(baby_bear, BabyBear);
+    field_tests!(mersenne_31, Mersenne31);
+}
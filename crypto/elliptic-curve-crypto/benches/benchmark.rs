@@ -1,8 +1,9 @@
 #![warn(clippy::all)]
 use criterion::{black_box, Criterion};
 use zkp_elliptic_curve::ScalarFieldElement;
-use zkp_elliptic_curve_crypto::{PrivateKey, PublicKey};
-use zkp_macros_decl::u256h;
+use zkp_elliptic_curve_crypto::{pedersen, PrivateKey, PublicKey};
+use zkp_macros_decl::{field_element, u256h};
+use zkp_primefield::FieldElement;
 use zkp_u256::U256;
 
 fn ecdsa_sign(crit: &mut Criterion) {
@@ -31,9 +32,18 @@ fn ecdsa_verify(crit: &mut Criterion) {
     });
 }
 
+fn pedersen_hash(crit: &mut Criterion) {
+    let a = field_element!("03d937c035c878245caf64531a5756109c53068da139362728feb561405371cb");
+    let b = field_element!("0208a0a10250e382e1e4bbe2880906c2791bf6275695e02fbbc6aeff9cd8b31a");
+    crit.bench_function("Pedersen hash", move |bench| {
+        bench.iter(|| black_box(pedersen::hash(&a, &b)))
+    });
+}
+
 fn main() {
     let crit = &mut Criterion::default().configure_from_args();
     ecdsa_sign(crit);
     ecdsa_verify(crit);
+    pedersen_hash(crit);
     crit.final_summary();
 }
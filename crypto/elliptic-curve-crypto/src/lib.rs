@@ -45,6 +45,9 @@
 // TODO: False positives <https://github.com/rust-lang/rust-clippy/issues/5917>
 #![allow(clippy::wildcard_imports)]
 
+// False positives on the Latex math and StarkWare names.
+#[allow(clippy::doc_markdown)]
+pub mod pedersen;
 mod private_key;
 mod public_key;
 mod signature;
@@ -0,0 +1,222 @@
+//! StarkWare's Pedersen hash over the Stark curve.
+//!
+//! The hash of two field elements $a$ and $b$ is the $x$-coordinate of
+//!
+//! $$
+//! P_0 + a_\mathrm{low} \cdot P_1 + a_\mathrm{high} \cdot P_2
+//!     + b_\mathrm{low} \cdot P_3 + b_\mathrm{high} \cdot P_4
+//! $$
+//!
+//! where the low parts are the least significant 248 bits, the high parts the
+//! remaining four bits, $P_0$ is the [`SHIFT_POINT`] and $P_1, \dots, P_4$ are
+//! the [`PEDERSEN_POINTS`]. This is the hash StarkWare uses for its Pedersen
+//! Merkle trees and in StarkEx.
+//!
+//! The points are fixed, so we precompute, for every four consecutive bits,
+//! all sixteen multiples of the point they scale. A hash then takes at most
+//! 126 additions.
+use lazy_static::lazy_static;
+use std::prelude::v1::*;
+use zkp_elliptic_curve::{Affine, Jacobian};
+use zkp_macros_decl::field_element;
+use zkp_primefield::{FieldElement, Zero};
+use zkp_u256::U256;
+
+#[cfg(feature = "std")]
+use rayon::prelude::*;
+
+/// Number of bits hashed per element.
+const ELEMENT_BITS: usize = 252;
+
+/// Number of bits in the low part of an element.
+const LOW_BITS: usize = 248;
+
+/// Number of bits per window.
+const WINDOW_BITS: usize = 4;
+
+lazy_static! {
+    /// For each window the multiples of its point.
+    ///
+    /// Entry `v` of a table is `v` times the point scaled by the window.
+    static ref PEDERSEN_TABLES: Vec<[Affine; 1 << WINDOW_BITS]> = PEDERSEN_POINTS
+        .chunks_exact(2)
+        .flat_map(|points| {
+            let low = (0..LOW_BITS / WINDOW_BITS).scan(points[0].clone(), |base, _| {
+                let table = multiples(base);
+                *base = &table[(1 << WINDOW_BITS) - 1] + &*base;
+                Some(table)
+            });
+            low.chain(std::iter::once(multiples(&points[1])))
+        })
+        .collect();
+}
+
+/// The first sixteen multiples of `point`, starting at zero.
+// We don't use `zkp_elliptic_curve::window_table_affine` here. It computes
+// the odd multiples `P, 3P, .., 15P` for a wNAF recoding, where the digit
+// positions depend on the scalar. The fixed four bit windows used here need
+// every multiple, so each window is a single table lookup and addition.
+fn multiples(point: &Affine) -> [Affine; 1 << WINDOW_BITS] {
+    let mut table = <[Affine; 1 << WINDOW_BITS]>::default();
+    for multiple in 1..table.len() {
+        table[multiple] = &table[multiple - 1] + point;
+    }
+    table
+}
+
+/// The window values of an element, least significant first.
+fn windows(element: &FieldElement) -> impl Iterator<Item = usize> {
+    let value: U256 = element.to_uint();
+    (0..ELEMENT_BITS / WINDOW_BITS).map(move |window| {
+        let bit = window * WINDOW_BITS;
+        ((value.limb(bit / 64) >> (bit % 64)) & ((1 << WINDOW_BITS) - 1)) as usize
+    })
+}
+
+/// Pedersen hash of two field elements.
+pub fn hash(a: &FieldElement, b: &FieldElement) -> FieldElement {
+    let mut point = Jacobian::from(&SHIFT_POINT);
+    for (table, window) in PEDERSEN_TABLES.iter().zip(windows(a).chain(windows(b))) {
+        point += &table[window];
+    }
+    match Affine::from(&point) {
+        Affine::Zero => panic!("Hashed to zero point!"),
+        Affine::Point { x, .. } => x,
+    }
+}
+
+/// Hash a sequence of elements by chaining two-element hashes.
+///
+/// Computes `hash(...hash(hash(0, e_0), e_1)..., n)` where `n` is the number of
+/// elements. This is StarkWare's `compute_hash_on_elements`.
+pub fn compute_hash_on_elements(elements: &[FieldElement]) -> FieldElement {
+    let result = elements
+        .iter()
+        .fold(FieldElement::zero(), |accumulator, element| {
+            hash(&accumulator, element)
+        });
+    hash(&result, &FieldElement::from(elements.len()))
+}
+
+/// Hash many pairs of elements.
+///
+/// The pairs are hashed in parallel when `std` is available.
+pub fn hash_batch(pairs: &[(FieldElement, FieldElement)]) -> Vec<FieldElement> {
+    #[cfg(feature = "std")]
+    let iter = pairs.par_iter();
+
+    #[cfg(not(feature = "std"))]
+    let iter = pairs.iter();
+
+    iter.map(|(a, b)| hash(a, b)).collect()
+}
+
+/// The point $P_0$ that starts the sum.
+pub const SHIFT_POINT: Affine = Affine::Point {
+    x: field_element!("049ee3eba8c1600700ee1b87eb599f16716b0b1022947733551fde4050ca6804"),
+    y: field_element!("03ca0cfe4b3bc6ddf346d49d06ea0ed34e621062c0e056c1d0405d266e10268a"),
+};
+
+/// The points $P_1, \dots, P_4$ for the low and high parts of the first and
+/// second element.
+///
+/// These are entries two to five of StarkWare's constant points, entry one is
+/// the curve generator.
+pub const PEDERSEN_POINTS: [Affine; 4] = [
+    Affine::Point {
+        x: field_element!("0234287dcbaffe7f969c748655fca9e58fa8120b6d56eb0c1080d17957ebe47b"),
+        y: field_element!("03b056f100f96fb21e889527d41f4e39940135dd7a6c94cc6ed0268ee89e5615"),
+    },
+    Affine::Point {
+        x: field_element!("04fa56f376c83db33f9dab2656558f3399099ec1de5e3018b7a6932dba8aa378"),
+        y: field_element!("03fa0984c931c9e38113e0c0e47e4401562761f92a7a23b45168f4e80ff5b54d"),
+    },
+    Affine::Point {
+        x: field_element!("04ba4cc166be8dec764910f75b45f74b40c690c74709e90f3aa372f0bd2d6997"),
+        y: field_element!("0040301cf5c1751f4b971e46c4ede85fcac5c59a5ce5ae7c48151f27b24b219c"),
+    },
+    Affine::Point {
+        x: field_element!("054302dcb0e6cc1c6e44cca8f61a63bb2ca65048d53fb325d36ff12c49a58202"),
+        y: field_element!("01b77b3e37d13504b348046268d8ae25ce98ad783c25561a879dcc77e99c2426"),
+    },
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use zkp_u256::Binary;
+
+    /// Reference implementation using double-and-add.
+    fn hash_bits(a: &FieldElement, b: &FieldElement) -> FieldElement {
+        let mut point = Jacobian::from(&SHIFT_POINT);
+        let parts = [a, b]
+            .iter()
+            .flat_map(|element| {
+                let value = element.to_uint();
+                let high = value.clone() >> LOW_BITS;
+                vec![value - (high.clone() << LOW_BITS), high]
+            })
+            .collect::<Vec<_>>();
+        for (mut scalar, base) in parts.into_iter().zip(PEDERSEN_POINTS.iter()) {
+            let mut base = base.clone();
+            while !scalar.is_zero() {
+                if scalar.bit(0) {
+                    point += &base;
+                }
+                base.double_assign();
+                scalar >>= 1;
+            }
+        }
+        Affine::from(&point).x().unwrap().clone()
+    }
+
+    #[test]
+    fn test_hash() {
+        let zero = FieldElement::zero();
+        assert_eq!(
+            hash(&zero, &zero),
+            field_element!("049ee3eba8c1600700ee1b87eb599f16716b0b1022947733551fde4050ca6804")
+        );
+        // Test vectors from StarkWare's `starkex-resources`
+        let a = field_element!("03d937c035c878245caf64531a5756109c53068da139362728feb561405371cb");
+        let b = field_element!("0208a0a10250e382e1e4bbe2880906c2791bf6275695e02fbbc6aeff9cd8b31a");
+        assert_eq!(
+            hash(&a, &b),
+            field_element!("030e480bed5fe53fa909cc0f8c4d99b8f9f2c016be4c41e13a4848797979c662")
+        );
+        let a = field_element!("058f580910a6ca59b28927c08fe6c43e2e303ca384badc365795fc645d479d45");
+        let b = field_element!("078734f65a067be9bdb39de18434d71e79f7b6466a4b66bbd979ab9e7515fe0b");
+        assert_eq!(
+            hash(&a, &b),
+            field_element!("068cc0b76cddd1dd4ed2301ada9b7c872b23875d5ff837b3a87993e0d9996b87")
+        );
+    }
+
+    #[test]
+    fn test_hash_on_elements() {
+        let zero = FieldElement::zero();
+        assert_eq!(compute_hash_on_elements(&[]), hash(&zero, &zero));
+        let elements = [FieldElement::from(1), FieldElement::from(2)];
+        assert_eq!(
+            compute_hash_on_elements(&elements),
+            hash(
+                &hash(&hash(&zero, &elements[0]), &elements[1]),
+                &FieldElement::from(2)
+            )
+        );
+    }
+
+    proptest!(
+        #[test]
+        fn test_hash_windows(a: FieldElement, b: FieldElement) {
+            prop_assert_eq!(hash(&a, &b), hash_bits(&a, &b));
+        }
+
+        #[test]
+        fn test_hash_batch(a: FieldElement, b: FieldElement, c: FieldElement) {
+            let pairs = vec![(a.clone(), b.clone()), (b.clone(), c.clone())];
+            prop_assert_eq!(hash_batch(&pairs), vec![hash(&a, &b), hash(&b, &c)]);
+        }
+    );
+}
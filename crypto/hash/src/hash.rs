@@ -1,3 +1,5 @@
+#[cfg(all(feature = "serde", feature = "std"))]
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 #[cfg(feature = "std")]
 use std::fmt;
 
@@ -22,3 +24,52 @@ impl fmt::Debug for Hash {
         write!(f, "Hash(0x{:})", hex::encode(self.0))
     }
 }
+
+#[cfg(all(feature = "serde", feature = "std"))]
+impl Serialize for Hash {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            format!("0x{}", hex::encode(self.0)).serialize(serializer)
+        } else {
+            self.0.serialize(serializer)
+        }
+    }
+}
+
+#[cfg(all(feature = "serde", feature = "std"))]
+impl<'a> Deserialize<'a> for Hash {
+    fn deserialize<D: Deserializer<'a>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            let string = String::deserialize(deserializer)?;
+            let digits = string.trim_start_matches("0x");
+            let mut bytes = [0_u8; 32];
+            hex::decode_to_slice(digits, &mut bytes).map_err(de::Error::custom)?;
+            Ok(Self(bytes))
+        } else {
+            <[u8; 32]>::deserialize(deserializer).map(Self)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[cfg(all(feature = "serde", feature = "std"))]
+    mod serde {
+        use super::super::*;
+        use zkp_macros_decl::hex;
+
+        #[test]
+        fn test_json() {
+            let hash = Hash::new(hex!(
+                "fd112f44bc944f33e2567f86eea202350913b11c000000000000000000000000"
+            ));
+            let serialized = serde_json::to_string(&hash).unwrap();
+            assert_eq!(
+                serialized,
+                "\"0xfd112f44bc944f33e2567f86eea202350913b11c000000000000000000000000\""
+            );
+            let deserialized: Hash = serde_json::from_str(&serialized).unwrap();
+            assert_eq!(deserialized, hash);
+        }
+    }
+}
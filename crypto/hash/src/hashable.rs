@@ -4,16 +4,22 @@
 #[allow(clippy::wildcard_imports)]
 use std::prelude::v1::*;
 
-use crate::{hash::Hash, masked_keccak::MaskedKeccak};
-use zkp_primefield::FieldElement;
+use crate::{hash::Hash, hasher::Hasher, masked_keccak::MaskedKeccak};
+use zkp_primefield::{BabyBear, Goldilocks, Mersenne31, Parameters, PrimeField};
 use zkp_u256::U256;
 
 pub trait Hashable {
-    fn hash(&self) -> Hash;
+    /// Hash using the given hash function.
+    fn hash_with<H: Hasher>(&self) -> Hash;
+
+    /// Hash using the default hash function, [`MaskedKeccak`].
+    fn hash(&self) -> Hash {
+        self.hash_with::<MaskedKeccak>()
+    }
 }
 
 impl Hashable for Hash {
-    fn hash(&self) -> Hash {
+    fn hash_with<H: Hasher>(&self) -> Hash {
         // Hashing here is idempotent. Defining this in combination
         // with `Hashable for [T]` makes it do the right thing for
         // `[Hash]::hash().`
@@ -22,36 +28,51 @@ impl Hashable for Hash {
 }
 
 impl Hashable for U256 {
-    fn hash(&self) -> Hash {
+    fn hash_with<H: Hasher>(&self) -> Hash {
         // U256 values are passed as-is
         // OPT: Figure out a way to get in-place access.
         Hash::new(self.to_bytes_be())
     }
 }
 
-impl Hashable for FieldElement {
-    fn hash(&self) -> Hash {
+impl<P: Parameters<UInt = U256>> Hashable for PrimeField<P> {
+    fn hash_with<H: Hasher>(&self) -> Hash {
         // We hash as U256 in Montgomery form (which is identity-hashed)
-        self.as_montgomery().hash()
+        self.as_montgomery().hash_with::<H>()
     }
 }
 
+macro_rules! hashable_small_field {
+    ($($field:ident),*) => {
+        $(
+            impl Hashable for $field {
+                fn hash_with<H: Hasher>(&self) -> Hash {
+                    // We hash as U256 of the canonical value
+                    U256::from(u64::from(self.to_uint())).hash_with::<H>()
+                }
+            }
+        )*
+    };
+}
+
+hashable_small_field!(Goldilocks, BabyBear, Mersenne31);
+
 impl<T: Hashable> Hashable for &T {
-    fn hash(&self) -> Hash {
-        (*self).hash()
+    fn hash_with<H: Hasher>(&self) -> Hash {
+        (*self).hash_with::<H>()
     }
 }
 
 impl<T: Hashable> Hashable for &[T] {
-    fn hash(&self) -> Hash {
+    fn hash_with<H: Hasher>(&self) -> Hash {
         if self.len() == 1 {
             // For a single element, return its hash.
-            self[0].hash()
+            self[0].hash_with::<H>()
         } else {
             // Concatenate the element hashes and hash the result.
-            let mut hasher = MaskedKeccak::new();
+            let mut hasher = H::default();
             for value in self.iter() {
-                hasher.update(value.hash().as_bytes());
+                hasher.update(value.hash_with::<H>().as_bytes());
             }
             hasher.hash()
         }
@@ -59,7 +80,7 @@ impl<T: Hashable> Hashable for &[T] {
 }
 
 impl<T: Hashable> Hashable for Vec<T> {
-    fn hash(&self) -> Hash {
-        self.as_slice().hash()
+    fn hash_with<H: Hasher>(&self) -> Hash {
+        self.as_slice().hash_with::<H>()
     }
 }
@@ -0,0 +1,131 @@
+use crate::hash::Hash;
+use blake2::Blake2s as Blake2sImpl;
+use sha2::{Digest, Sha256 as Sha256Impl};
+use tiny_keccak::{Hasher as _, Keccak as KeccakImpl};
+
+/// Incremental 256 bit hash function.
+///
+/// This is used for Merkle tree commitments and to derive Fiat-Shamir
+/// randomness. Implementations can truncate the [`Hash`] used in commitments,
+/// but [`Hasher::finalize`] always returns the full digest.
+pub trait Hasher: Default + Clone + Send + Sync {
+    fn update(&mut self, input: &[u8]);
+
+    /// The full 256 bit digest.
+    fn finalize(self) -> [u8; 32];
+
+    /// The hash used in commitments.
+    fn hash(self) -> Hash {
+        Hash::new(self.finalize())
+    }
+
+    /// Hash a single message.
+    fn digest(input: &[u8]) -> Hash {
+        let mut hasher = Self::default();
+        hasher.update(input);
+        hasher.hash()
+    }
+}
+
+/// Keccak-256 with the full 256 bit output.
+#[derive(Clone)]
+pub struct Keccak(KeccakImpl);
+
+impl Default for Keccak {
+    fn default() -> Self {
+        Self(KeccakImpl::v256())
+    }
+}
+
+impl Hasher for Keccak {
+    fn update(&mut self, input: &[u8]) {
+        self.0.update(input)
+    }
+
+    fn finalize(self) -> [u8; 32] {
+        let mut result = [0; 32];
+        self.0.finalize(&mut result);
+        result
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::fmt::Debug for Keccak {
+    fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(fmt, "Keccak(...)")
+    }
+}
+
+/// SHA-256
+#[derive(Clone, Default)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct Sha256(Sha256Impl);
+
+impl Hasher for Sha256 {
+    fn update(&mut self, input: &[u8]) {
+        self.0.update(input)
+    }
+
+    fn finalize(self) -> [u8; 32] {
+        self.0.finalize().into()
+    }
+}
+
+/// Blake2s with 256 bit output.
+#[derive(Clone, Default)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct Blake2s(Blake2sImpl);
+
+impl Hasher for Blake2s {
+    fn update(&mut self, input: &[u8]) {
+        self.0.update(input)
+    }
+
+    fn finalize(self) -> [u8; 32] {
+        self.0.finalize().into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::MaskedKeccak;
+    use zkp_macros_decl::hex;
+
+    #[test]
+    fn test_empty() {
+        assert_eq!(
+            Keccak::digest(b"").as_bytes(),
+            hex!("c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470")
+        );
+        assert_eq!(
+            MaskedKeccak::digest(b"").as_bytes(),
+            hex!("c5d2460186f7233c927e7db2dcc703c0e500b653000000000000000000000000")
+        );
+        assert_eq!(
+            Sha256::digest(b"").as_bytes(),
+            hex!("e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855")
+        );
+        assert_eq!(
+            Blake2s::digest(b"").as_bytes(),
+            hex!("69217a3079908094e11121d042354a7c1f55b6482ca1a51e1b250dfd1ed0eef9")
+        );
+    }
+
+    #[test]
+    fn test_masked_finalize() {
+        let mut masked = MaskedKeccak::default();
+        let mut keccak = Keccak::default();
+        Hasher::update(&mut masked, b"abc");
+        keccak.update(b"abc");
+        assert_eq!(masked.finalize(), keccak.finalize());
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn test_masked_deprecated() {
+        let mut masked = MaskedKeccak::new();
+        masked.update(b"abc");
+        assert_eq!(masked.hash(), MaskedKeccak::digest(b"abc"));
+    }
+}
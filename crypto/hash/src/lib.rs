@@ -46,6 +46,19 @@ extern crate no_std_compat as std;
 
 mod hash;
 mod hashable;
+mod hasher;
 mod masked_keccak;
+mod parameters;
+mod poseidon;
+mod rescue;
+mod sponge;
 
-pub use crate::{hash::Hash, hashable::Hashable, masked_keccak::MaskedKeccak};
+pub use crate::{
+    hash::Hash,
+    hashable::Hashable,
+    hasher::{Blake2s, Hasher, Keccak, Sha256},
+    masked_keccak::MaskedKeccak,
+    poseidon::Poseidon,
+    rescue::RescuePrime,
+    sponge::Permutation,
+};
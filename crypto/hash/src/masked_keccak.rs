@@ -1,6 +1,10 @@
-use crate::hash::Hash;
-use tiny_keccak::{Hasher, Keccak};
+use crate::{hash::Hash, hasher::Hasher};
+use tiny_keccak::{Hasher as _, Keccak};
 
+/// Keccak-256 with the last 12 bytes of the hash set to zero.
+///
+/// The full digest is still available through [`Hasher::finalize`].
+#[derive(Clone)]
 pub struct MaskedKeccak(Keccak);
 
 impl MaskedKeccak {
@@ -11,14 +15,31 @@ impl MaskedKeccak {
         Self(Keccak::v256())
     }
 
+    #[deprecated(note = "Use `Hasher::update` instead")]
     pub fn update(&mut self, input: &[u8]) {
-        self.0.update(input)
+        Hasher::update(self, input)
     }
 
+    #[deprecated(note = "Use `Hasher::hash` instead")]
     #[must_use]
     pub fn hash(self) -> Hash {
+        Hasher::hash(self)
+    }
+}
+
+impl Hasher for MaskedKeccak {
+    fn update(&mut self, input: &[u8]) {
+        self.0.update(input)
+    }
+
+    fn finalize(self) -> [u8; 32] {
         let mut result: [u8; 32] = [0; 32];
         self.0.finalize(&mut result);
+        result
+    }
+
+    fn hash(self) -> Hash {
+        let mut result = self.finalize();
         for byte in result[Self::MASK_LENGTH..].iter_mut() {
             *byte = 0;
         }
@@ -0,0 +1,103 @@
+use crate::hasher::{Hasher, Keccak};
+use std::prelude::v1::*;
+use zkp_primefield::{FieldElement, Inv, Zero};
+use zkp_u256::U256;
+
+/// Generate `count` pseudo-random field elements from a seed.
+///
+/// Element candidates are the big-endian interpretation of
+///
+/// $$
+/// \mathtt{Keccak256}(\mathtt{seed} \mathbin\Vert \mathtt{counter})
+/// $$
+///
+/// with `counter` an eight byte big-endian integer starting at zero. The top
+/// four bits of each candidate are cleared and candidates not less than the
+/// modulus are rejected, so the result is uniformly distributed.
+pub(crate) fn field_elements(seed: &[u8], count: usize) -> Vec<FieldElement> {
+    let mut result = Vec::with_capacity(count);
+    let mut counter = 0_u64;
+    while result.len() < count {
+        let mut hasher = Keccak::default();
+        hasher.update(seed);
+        hasher.update(&counter.to_be_bytes());
+        let mut bytes = hasher.finalize();
+        bytes[0] &= 0x0f;
+        let candidate = U256::from_bytes_be(&bytes);
+        if candidate < FieldElement::MODULUS {
+            result.push(FieldElement::from(candidate));
+        }
+        counter += 1;
+    }
+    result
+}
+
+/// The seed used for parameter generation.
+///
+/// The ASCII name followed by each of the parameters as an eight byte
+/// big-endian integer.
+pub(crate) fn seed(name: &str, parameters: &[usize]) -> Vec<u8> {
+    let mut seed = name.as_bytes().to_vec();
+    for parameter in parameters {
+        seed.extend_from_slice(&(*parameter as u64).to_be_bytes());
+    }
+    seed
+}
+
+// False positives on the Latex math.
+#[allow(clippy::doc_markdown)]
+/// The `width` by `width` Cauchy matrix in row-major order.
+///
+/// Entries are $M_{ij} = (x_i + y_j)^{-1}$ with $x_i = i$ and
+/// $y_j = \mathtt{width} + j$. The $x_i$ and $y_j$ are all distinct and their
+/// sums are non-zero, which makes every square sub-matrix invertible. The
+/// matrix is therefore MDS.
+pub(crate) fn cauchy_mds(width: usize) -> Vec<FieldElement> {
+    let mut result = Vec::with_capacity(width * width);
+    for i in 0..width {
+        for j in 0..width {
+            result.push(
+                FieldElement::from(i + width + j)
+                    .inv()
+                    .expect("Cauchy matrix entries are non-zero."),
+            );
+        }
+    }
+    result
+}
+
+/// Compute `matrix * state` for a square row-major `matrix`.
+pub(crate) fn mix(matrix: &[FieldElement], state: &mut [FieldElement]) {
+    let result = matrix
+        .chunks_exact(state.len())
+        .map(|row| {
+            row.iter()
+                .zip(state.iter())
+                .fold(FieldElement::zero(), |sum, (a, b)| sum + a * b)
+        })
+        .collect::<Vec<_>>();
+    state.clone_from_slice(&result);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zkp_primefield::One;
+
+    #[test]
+    fn test_field_elements_deterministic() {
+        let a = field_elements(b"test", 10);
+        let b = field_elements(b"test", 20);
+        assert_eq!(a[..], b[..10]);
+        assert_ne!(a, field_elements(b"other", 10));
+    }
+
+    #[test]
+    fn test_cauchy_mds() {
+        let matrix = cauchy_mds(2);
+        // Determinant of [[1/2, 1/3], [1/3, 1/4]]
+        let determinant = &matrix[0] * &matrix[3] - &matrix[1] * &matrix[2];
+        assert_ne!(determinant, FieldElement::zero());
+        assert_eq!(&matrix[0] * FieldElement::from(2), FieldElement::one());
+    }
+}
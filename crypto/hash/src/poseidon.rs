@@ -0,0 +1,189 @@
+use crate::{
+    parameters::{cauchy_mds, field_elements, mix, seed},
+    sponge::Permutation,
+};
+use std::prelude::v1::*;
+use zkp_primefield::{FieldElement, Pow};
+
+/// The Poseidon permutation over the Stark field.
+///
+/// Each round adds `width` round constants, applies the S-box
+/// $x \mapsto x^3$ and multiplies by an MDS matrix. In full rounds the S-box
+/// is applied to the entire state, in partial rounds only to the first
+/// element. The first and last `full_rounds / 2` rounds are full, the
+/// `partial_rounds` in between are partial.
+///
+/// The exponent three is the smallest $\alpha$ with
+/// $\gcd(\alpha, p - 1) = 1$ for the Stark field, which makes the S-box a
+/// permutation.
+///
+/// # Parameter generation
+///
+/// The round constants are the first `width * (full_rounds + partial_rounds)`
+/// field elements generated from Keccak-256 in counter mode, seeded with
+/// `"Poseidon"` followed by `width`, `full_rounds` and `partial_rounds` as
+/// eight byte big-endian integers. Candidates are reduced to 252 bits and
+/// rejection sampled. The MDS matrix is the Cauchy matrix
+/// $M_{ij} = (i + j + \mathtt{width})^{-1}$.
+///
+/// The default instance has width three (rate two, capacity one), eight full
+/// rounds and 83 partial rounds, the round numbers recommended for 128 bit
+/// security at this field size. Note that the constants differ from other
+/// published instances, so hashes are not interoperable with them.
+#[derive(Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct Poseidon {
+    width:           usize,
+    full_rounds:     usize,
+    partial_rounds:  usize,
+    round_constants: Vec<FieldElement>,
+    mds:             Vec<FieldElement>,
+}
+
+impl Poseidon {
+    /// Generate parameters for the given width and round numbers.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `width` is zero or `full_rounds` is odd.
+    pub fn new(width: usize, full_rounds: usize, partial_rounds: usize) -> Self {
+        assert!(width > 0, "Width must be non-zero.");
+        assert_eq!(full_rounds % 2, 0, "Number of full rounds must be even.");
+        let rounds = full_rounds + partial_rounds;
+        let seed = seed("Poseidon", &[width, full_rounds, partial_rounds]);
+        Self {
+            width,
+            full_rounds,
+            partial_rounds,
+            round_constants: field_elements(&seed, width * rounds),
+            mds: cauchy_mds(width),
+        }
+    }
+
+    pub fn full_rounds(&self) -> usize {
+        self.full_rounds
+    }
+
+    pub fn partial_rounds(&self) -> usize {
+        self.partial_rounds
+    }
+
+    /// Total number of rounds.
+    pub fn rounds(&self) -> usize {
+        self.full_rounds + self.partial_rounds
+    }
+
+    /// Whether the S-box is applied to the full state in the given round.
+    pub fn is_full_round(&self, round: usize) -> bool {
+        let half = self.full_rounds / 2;
+        round < half || round >= half + self.partial_rounds
+    }
+
+    /// The round constants added at the start of the given round.
+    pub fn round_constants(&self, round: usize) -> &[FieldElement] {
+        &self.round_constants[round * self.width..(round + 1) * self.width]
+    }
+
+    /// The MDS matrix in row-major order.
+    pub fn mds(&self) -> &[FieldElement] {
+        &self.mds
+    }
+
+    /// Apply a single round in place.
+    pub fn round(&self, round: usize, state: &mut [FieldElement]) {
+        assert_eq!(state.len(), self.width);
+        let full = self.is_full_round(round);
+        for (i, (element, constant)) in state
+            .iter_mut()
+            .zip(self.round_constants(round))
+            .enumerate()
+        {
+            *element += constant;
+            if full || i == 0 {
+                *element = element.pow(3_usize);
+            }
+        }
+        mix(&self.mds, state);
+    }
+}
+
+impl Default for Poseidon {
+    fn default() -> Self {
+        Self::new(3, 8, 83)
+    }
+}
+
+impl Permutation for Poseidon {
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn permute(&self, state: &mut [FieldElement]) {
+        for round in 0..self.rounds() {
+            self.round(round, state);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zkp_macros_decl::field_element;
+    use zkp_primefield::Zero;
+    use zkp_u256::U256;
+
+    #[test]
+    fn test_rounds() {
+        let poseidon = Poseidon::default();
+        assert_eq!(poseidon.rounds(), 91);
+        assert!(poseidon.is_full_round(3));
+        assert!(!poseidon.is_full_round(4));
+        assert!(!poseidon.is_full_round(86));
+        assert!(poseidon.is_full_round(87));
+    }
+
+    #[test]
+    fn test_hash() {
+        let poseidon = Poseidon::default();
+        let a = poseidon.hash(&[FieldElement::from(1), FieldElement::from(2)]);
+        let b = poseidon.hash(&[FieldElement::from(2), FieldElement::from(1)]);
+        let c = poseidon.hash(&[
+            FieldElement::from(1),
+            FieldElement::from(2),
+            FieldElement::zero(),
+        ]);
+        assert_ne!(a, b);
+        assert_ne!(a, c);
+        assert_eq!(
+            a,
+            poseidon.hash(&[FieldElement::from(1), FieldElement::from(2)])
+        );
+    }
+
+    // Reference values computed with an independent implementation of the
+    // parameter generation documented on the type.
+    #[test]
+    fn test_known_answer() {
+        let poseidon = Poseidon::default();
+        assert_eq!(poseidon.round_constants(0), &[
+            field_element!("077e3c1fa40dc716160853b4c0b41097acbe2fbbda93f8f5a4b0561c6f0e4bde"),
+            field_element!("00447fa69c0a4a5e5d5d158389dc7a005e952f1ac6376d2cd68450b36dbc14af"),
+            field_element!("006ba1ce09ba56ff8206264047a70e907123c291c991cfcc5eb53d9af068a52c"),
+        ]);
+        assert_eq!(
+            poseidon.round_constants(90)[2],
+            field_element!("072b738baa7f421951a5d33be94081be181335cf97f73a8ef6044494893449b5")
+        );
+        let mut state = vec![
+            FieldElement::from(0),
+            FieldElement::from(1),
+            FieldElement::from(2),
+        ];
+        poseidon.permute(&mut state);
+        assert_eq!(state, vec![
+            field_element!("03a85f43a364e739d065bf140f9f6af0af41de074e89016351eaf45f841ba73b"),
+            field_element!("03d02b0c7a07d53a9cd415278232ae2257f3d70837e92e79b4d2c04b6913d7a2"),
+            field_element!("0070b0a819cd379e1903d7042848c37ad0d423fb202983881ac424b7fd34cf95"),
+        ]);
+    }
+}
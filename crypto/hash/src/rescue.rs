@@ -0,0 +1,191 @@
+use crate::{
+    parameters::{cauchy_mds, field_elements, mix, seed},
+    sponge::Permutation,
+};
+use std::prelude::v1::*;
+use zkp_primefield::{FieldElement, Pow};
+use zkp_u256::{InvMod, U256};
+
+/// The Rescue-Prime permutation over the Stark field.
+///
+/// Each round consists of two steps. The first applies the S-box
+/// $x \mapsto x^3$ to every element, multiplies by an MDS matrix and adds
+/// `width` round constants. The second does the same with the inverse S-box
+/// $x \mapsto x^{1/3}$, where $1/3$ is the inverse of three modulo $p - 1$.
+///
+/// # Parameter generation
+///
+/// The `2 * width * rounds` round constants are generated from Keccak-256 in
+/// counter mode, seeded with `"Rescue-Prime"` followed by `width` and
+/// `rounds` as eight byte big-endian integers. Candidates are reduced to 252
+/// bits and rejection sampled. The MDS matrix is the Cauchy matrix
+/// $M_{ij} = (i + j + \mathtt{width})^{-1}$.
+///
+/// The default instance has width three (rate two, capacity one) and 18
+/// rounds. This is the Rescue-Prime round formula for 128 bit security: the
+/// Gröbner basis attack needs $\binom{v + d}{v}^2 > 2^{128}$ with
+/// $v = d = 3(N - 1) + 2$, which first holds for $N = 12$, and adding the 50%
+/// security margin gives 18. Note that the constants differ from other
+/// published instances, so hashes are not interoperable with them.
+#[derive(Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct RescuePrime {
+    width:           usize,
+    rounds:          usize,
+    alpha_inv:       U256,
+    round_constants: Vec<FieldElement>,
+    mds:             Vec<FieldElement>,
+}
+
+impl RescuePrime {
+    /// Generate parameters for the given width and number of rounds.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `width` is zero.
+    pub fn new(width: usize, rounds: usize) -> Self {
+        assert!(width > 0, "Width must be non-zero.");
+        let alpha_inv = U256::from(3_u64)
+            .inv_mod(&FieldElement::order())
+            .expect("Three is coprime with the field order.");
+        let seed = seed("Rescue-Prime", &[width, rounds]);
+        Self {
+            width,
+            rounds,
+            alpha_inv,
+            round_constants: field_elements(&seed, 2 * width * rounds),
+            mds: cauchy_mds(width),
+        }
+    }
+
+    pub fn rounds(&self) -> usize {
+        self.rounds
+    }
+
+    /// The round constants added after the forward S-box in the given round.
+    pub fn first_constants(&self, round: usize) -> &[FieldElement] {
+        let start = 2 * round * self.width;
+        &self.round_constants[start..start + self.width]
+    }
+
+    /// The round constants added after the inverse S-box in the given round.
+    pub fn second_constants(&self, round: usize) -> &[FieldElement] {
+        let start = (2 * round + 1) * self.width;
+        &self.round_constants[start..start + self.width]
+    }
+
+    /// The MDS matrix in row-major order.
+    pub fn mds(&self) -> &[FieldElement] {
+        &self.mds
+    }
+
+    /// Apply the first half of a round in place.
+    ///
+    /// The result is the input to the inverse S-box, its cube roots are the
+    /// intermediate state.
+    pub fn first_half(&self, round: usize, state: &mut [FieldElement]) {
+        assert_eq!(state.len(), self.width);
+        for element in state.iter_mut() {
+            *element = element.pow(3_usize);
+        }
+        mix(&self.mds, state);
+        for (element, constant) in state.iter_mut().zip(self.first_constants(round)) {
+            *element += constant;
+        }
+    }
+
+    /// Apply the inverse S-box in place.
+    pub fn inverse_sbox(&self, state: &mut [FieldElement]) {
+        for element in state.iter_mut() {
+            *element = element.pow(&self.alpha_inv);
+        }
+    }
+
+    /// Apply the second half of a round to the intermediate state in place.
+    pub fn second_half(&self, round: usize, state: &mut [FieldElement]) {
+        assert_eq!(state.len(), self.width);
+        mix(&self.mds, state);
+        for (element, constant) in state.iter_mut().zip(self.second_constants(round)) {
+            *element += constant;
+        }
+    }
+
+    /// Apply a single round in place.
+    pub fn round(&self, round: usize, state: &mut [FieldElement]) {
+        self.first_half(round, state);
+        self.inverse_sbox(state);
+        self.second_half(round, state);
+    }
+}
+
+impl Default for RescuePrime {
+    fn default() -> Self {
+        Self::new(3, 18)
+    }
+}
+
+impl Permutation for RescuePrime {
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn permute(&self, state: &mut [FieldElement]) {
+        for round in 0..self.rounds {
+            self.round(round, state);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zkp_macros_decl::field_element;
+
+    #[test]
+    fn test_inverse_sbox() {
+        let rescue = RescuePrime::default();
+        let x = field_element!("0123456789abcdef0123456789abcdef");
+        let mut state = vec![x.pow(3_usize)];
+        rescue.inverse_sbox(&mut state);
+        assert_eq!(state[0], x);
+    }
+
+    #[test]
+    fn test_hash() {
+        let rescue = RescuePrime::default();
+        let a = rescue.hash(&[FieldElement::from(1), FieldElement::from(2)]);
+        let b = rescue.hash(&[FieldElement::from(1)]);
+        assert_ne!(a, b);
+        assert_eq!(
+            a,
+            rescue.hash(&[FieldElement::from(1), FieldElement::from(2)])
+        );
+    }
+
+    // Reference values computed with an independent implementation of the
+    // parameter generation documented on the type.
+    #[test]
+    fn test_known_answer() {
+        let rescue = RescuePrime::default();
+        assert_eq!(rescue.first_constants(0), &[
+            field_element!("028add707df82c566564020e93df1170eacdb4790d5b71bcb16ea4b78207659c"),
+            field_element!("019f9883e10d9c2ba7447fe8b731dc3b9d0572199e54f7ceed04bffd73c8f122"),
+            field_element!("06df23389466416c578ba6a49cca376541bff54545bcf355c2aa1e678863d4d1"),
+        ]);
+        assert_eq!(
+            rescue.second_constants(17)[2],
+            field_element!("0641a7013c58912a3c8388bd22180056e10264ad951a05f1a1dfb74f126aa230")
+        );
+        let mut state = vec![
+            FieldElement::from(0),
+            FieldElement::from(1),
+            FieldElement::from(2),
+        ];
+        rescue.permute(&mut state);
+        assert_eq!(state, vec![
+            field_element!("05d729293c15bcba61386ea08e572234530e9d607a8df6e55ec946619b197255"),
+            field_element!("068f81a327f7dfde7ff91705dd45708bbb14e976187cf1e245a9bb13f3c8ac5d"),
+            field_element!("028f75411e80a113c2189dba640a401ff62a8ca9a5bc4d3fa8013232c6a9ae88"),
+        ]);
+    }
+}
@@ -0,0 +1,46 @@
+use std::prelude::v1::*;
+use zkp_primefield::{FieldElement, Zero};
+
+/// Permutation of a fixed width vector of field elements.
+///
+/// Any permutation can be turned into a hash function using the sponge
+/// construction in [`Permutation::hash`].
+pub trait Permutation {
+    /// Number of field elements in the state.
+    fn width(&self) -> usize;
+
+    /// Apply the permutation in place.
+    fn permute(&self, state: &mut [FieldElement]);
+
+    /// Number of field elements absorbed per permutation.
+    ///
+    /// The remaining single element of the state is the capacity.
+    fn rate(&self) -> usize {
+        self.width() - 1
+    }
+
+    /// Sponge hash of a sequence of field elements.
+    ///
+    /// The capacity element (the last element of the state) is initialized
+    /// with the length of the input, which makes zero padding unambiguous. The
+    /// input is added to the rate portion of the state in chunks of
+    /// [`Permutation::rate`] elements, applying the permutation after each
+    /// chunk. The result is the first element of the final state. An empty
+    /// input still applies the permutation once.
+    fn hash(&self, input: &[FieldElement]) -> FieldElement {
+        let rate = self.rate();
+        assert!(rate > 0, "Sponge requires a width of at least two.");
+        let mut state = vec![FieldElement::zero(); self.width()];
+        state[rate] = FieldElement::from(input.len());
+        if input.is_empty() {
+            self.permute(&mut state);
+        }
+        for chunk in input.chunks(rate) {
+            for (element, value) in state.iter_mut().zip(chunk.iter()) {
+                *element += value;
+            }
+            self.permute(&mut state);
+        }
+        state[0].clone()
+    }
+}
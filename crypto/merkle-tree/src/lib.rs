@@ -52,7 +52,6 @@ mod commitment;
 /// <https://eprint.iacr.org/2011/495.pdf>
 // TODO: Spin of to it's own crate.
 // TODO: Implement sparse Merkle trees.
-// TODO: Generalize over hash implementations.
 mod index;
 mod node;
 mod proof;
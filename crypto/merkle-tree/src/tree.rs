@@ -1,8 +1,8 @@
 use crate::{Commitment, Error, Index, Node, Proof, Result, VectorCommitment};
 use log::{info, trace};
-use std::collections::VecDeque;
+use std::{collections::VecDeque, marker::PhantomData, path::Path};
 use zkp_error_utils::require;
-use zkp_hash::{Hash, Hashable};
+use zkp_hash::{Hash, Hashable, Hasher, MaskedKeccak};
 use zkp_mmap_vec::MmapVec;
 
 #[cfg(feature = "std")]
@@ -21,17 +21,17 @@ where
 }
 
 // Utility function to compute the first layer of the tree from the leaves
-fn compute<C: VectorCommitment>(leaves: &C, index: Index) -> Hash {
+fn compute<C: VectorCommitment, H: Hasher>(leaves: &C, index: Index) -> Hash {
     let leaf_depth = Index::depth_for_size(leaves.len());
     assert!(index.depth() <= leaf_depth);
     if index.depth() == leaf_depth {
-        leaves.leaf_hash(index.offset())
+        leaves.leaf_hash::<H>(index.offset())
     } else {
         Node(
-            &compute(leaves, index.left_child()),
-            &compute(leaves, index.right_child()),
+            &compute::<C, H>(leaves, index.left_child()),
+            &compute::<C, H>(leaves, index.right_child()),
         )
-        .hash()
+        .hash_with::<H>()
     }
 }
 
@@ -40,22 +40,54 @@ fn compute<C: VectorCommitment>(leaves: &C, index: Index) -> Hash {
 /// The tree will become the owner of the `Container`. This is necessary because
 /// when low layer-omission is implemented we need immutable access to the
 /// leaves. If shared ownership is required the `Container` can be an `Rc<_>`.
+///
+/// Leaves and nodes are hashed using `H`, which defaults to [`MaskedKeccak`].
 // OPT: Do not store leaf hashes but re-create.
 // OPT: Allow up to `n` lower layers to be skipped.
-// TODO: Make hash depend on type.
 #[cfg_attr(feature = "std", derive(Debug))]
-pub struct Tree<Container: VectorCommitment> {
+pub struct Tree<Container: VectorCommitment, H: Hasher = MaskedKeccak> {
     commitment: Commitment,
     nodes:      MmapVec<Hash>,
     leaves:     Container,
+    hasher:     PhantomData<H>,
 }
 
+// The default hasher constructors are not generic over `H`, as default type
+// parameters do not drive type inference.
 impl<Container: VectorCommitment> Tree<Container> {
     pub fn from_leaves(leaves: Container) -> Result<Self> {
-        Self::from_leaves_skip_layers(leaves, 1)
+        Self::from_leaves_with(leaves)
     }
 
     pub fn from_leaves_skip_layers(leaves: Container, skip_layers: usize) -> Result<Self> {
+        Self::from_leaves_skip_layers_with(leaves, skip_layers)
+    }
+}
+
+impl<Container: VectorCommitment, H: Hasher> Tree<Container, H> {
+    /// Like [`from_leaves`](Tree::from_leaves), with hash function `H`.
+    pub fn from_leaves_with(leaves: Container) -> Result<Self> {
+        Self::from_leaves_skip_layers_with(leaves, 1)
+    }
+
+    /// Like [`from_leaves_with`](Self::from_leaves_with), with the nodes in a
+    /// file in `scratch` if they are large, see
+    /// [`MmapVec::with_capacity_scratch`].
+    pub fn from_leaves_in(leaves: Container, scratch: Option<&Path>) -> Result<Self> {
+        Self::from_leaves_skip_layers_in(leaves, 1, scratch)
+    }
+
+    /// Like [`from_leaves_skip_layers`](Tree::from_leaves_skip_layers), with
+    /// hash function `H`.
+    pub fn from_leaves_skip_layers_with(leaves: Container, skip_layers: usize) -> Result<Self> {
+        Self::from_leaves_skip_layers_in(leaves, skip_layers, None)
+    }
+
+    pub fn from_leaves_skip_layers_in(
+        leaves: Container,
+        skip_layers: usize,
+        scratch: Option<&Path>,
+    ) -> Result<Self> {
         info!(
             "Computing Merkle tree of size {} ({} skip layer)",
             leaves.len(),
@@ -70,6 +102,7 @@ impl<Container: VectorCommitment> Tree<Container> {
                 commitment: Commitment::from_size_hash(size, &Hash::default()).unwrap(),
                 nodes: MmapVec::with_capacity(0),
                 leaves,
+                hasher: PhantomData,
             });
         }
         // TODO: Support non power of two sizes
@@ -84,7 +117,7 @@ impl<Container: VectorCommitment> Tree<Container> {
             let max_index = Index::from_depth_offset(depth, Index::size_at_depth(depth) - 1)
                 .unwrap()
                 .as_index();
-            let mut nodes = MmapVec::with_capacity(max_index + 1);
+            let mut nodes = MmapVec::with_capacity_scratch(max_index + 1, scratch);
             for _ in 0..=max_index {
                 nodes.push(Hash::default());
             }
@@ -100,7 +133,7 @@ impl<Container: VectorCommitment> Tree<Container> {
             let leaf_layer = &mut nodes[Index::layer_range(depth)];
             // First layer
             for_each(leaf_layer, |(i, hash)| {
-                *hash = compute(&leaves, Index::from_depth_offset(depth, i).unwrap())
+                *hash = compute::<_, H>(&leaves, Index::from_depth_offset(depth, i).unwrap())
             });
             // Upper layers
             for depth in (0..depth).rev() {
@@ -109,13 +142,13 @@ impl<Container: VectorCommitment> Tree<Container> {
                     nodes.split_at_mut(Index::from_depth_offset(depth + 1, 0).unwrap().as_index());
                 let current = &mut tree[Index::layer_range(depth)];
                 for_each(current, |(i, hash)| {
-                    *hash = Node(&previous[i << 1], &previous[i << 1 | 1]).hash()
+                    *hash = Node(&previous[i << 1], &previous[i << 1 | 1]).hash_with::<H>()
                 });
             }
         }
 
         let root_hash = if nodes.is_empty() {
-            compute(&leaves, Index::root())
+            compute::<_, H>(&leaves, Index::root())
         } else {
             nodes[0].clone()
         };
@@ -125,6 +158,34 @@ impl<Container: VectorCommitment> Tree<Container> {
             commitment,
             nodes,
             leaves,
+            hasher: PhantomData,
+        })
+    }
+
+    /// Reassemble a tree from its leaves and the node hashes returned by
+    /// [`Tree::nodes`].
+    ///
+    /// This avoids hashing the tree again, for example when a tree is stored
+    /// and loaded. The node hashes are trusted, only their number is checked.
+    pub fn from_parts(leaves: Container, nodes: MmapVec<Hash>) -> Result<Self> {
+        let size = leaves.len();
+        require!(size.is_power_of_two(), Error::NumLeavesNotPowerOfTwo);
+        require!(size <= Index::max_size(), Error::TreeToLarge);
+        // The nodes are complete layers from the root down.
+        require!(
+            (nodes.len() + 1).is_power_of_two() && nodes.len() < 2 * size,
+            Error::NotEnoughHashes
+        );
+        let root_hash = if nodes.is_empty() {
+            compute::<_, H>(&leaves, Index::root())
+        } else {
+            nodes[0].clone()
+        };
+        Ok(Self {
+            commitment: Commitment::from_size_hash(size, &root_hash)?,
+            nodes,
+            leaves,
+            hasher: PhantomData,
         })
     }
 
@@ -132,6 +193,14 @@ impl<Container: VectorCommitment> Tree<Container> {
         &self.commitment
     }
 
+    /// The stored node hashes, in [`Index`] order.
+    ///
+    /// The lowest layers are not stored when they are skipped, see
+    /// [`Tree::from_leaves_skip_layers`].
+    pub fn nodes(&self) -> &[Hash] {
+        &self.nodes
+    }
+
     pub fn leaf_depth(&self) -> usize {
         Index::depth_for_size(self.leaves().len())
     }
@@ -150,18 +219,18 @@ impl<Container: VectorCommitment> Tree<Container> {
         } else {
             assert!(index.depth() <= self.leaf_depth());
             if index.depth() == self.leaf_depth() {
-                self.leaves.leaf_hash(index.offset())
+                self.leaves.leaf_hash::<H>(index.offset())
             } else {
                 Node(
                     &self.node_hash(index.left_child()),
                     &self.node_hash(index.right_child()),
                 )
-                .hash()
+                .hash_with::<H>()
             }
         }
     }
 
-    pub fn open(&self, indices: &[usize]) -> Result<Proof> {
+    pub fn open(&self, indices: &[usize]) -> Result<Proof<H>> {
         let indices = self.commitment().sort_indices(indices)?;
         let proof_indices: Vec<usize> = indices.iter().map(|i| i.offset()).collect();
         let mut indices: VecDeque<Index> = indices.into_iter().collect();
@@ -189,7 +258,7 @@ impl<Container: VectorCommitment> Tree<Container> {
                 hashes.push(self.node_hash(sibling));
             }
         }
-        Proof::from_hashes(self.commitment(), &proof_indices, &hashes)
+        Proof::from_hashes_with(self.commitment(), &proof_indices, &hashes)
     }
 }
 
@@ -271,6 +340,58 @@ mod tests {
         proof.verify(&select_leaves).unwrap();
     }
 
+    #[test]
+    fn test_hasher() {
+        use zkp_hash::Sha256;
+
+        let leaves: Vec<_> = (0..64_u64).map(U256::from).collect();
+        let indices = vec![1, 11, 14];
+        let select_leaves: Vec<_> = indices.iter().map(|&i| (i, leaves[i].clone())).collect();
+
+        let default = Tree::from_leaves(leaves.clone()).unwrap();
+        let tree = Tree::<_, Sha256>::from_leaves_with(leaves).unwrap();
+        assert_ne!(tree.commitment().hash(), default.commitment().hash());
+
+        let proof = tree.open(&indices).unwrap();
+        proof.verify(&select_leaves).unwrap();
+
+        // The proof does not verify with a different hasher
+        let proof = Proof::from_hashes(tree.commitment(), &indices, proof.hashes()).unwrap();
+        assert_eq!(proof.verify(&select_leaves), Err(Error::RootHashMismatch));
+    }
+
+    #[test]
+    fn test_scratch() {
+        let leaves: Vec<_> = (0..1_u64 << 16).map(U256::from).collect();
+        let directory = std::env::temp_dir();
+        let tree = Tree::from_leaves(leaves.clone()).unwrap();
+        let scratch: Tree<_> = Tree::from_leaves_in(leaves, Some(&directory)).unwrap();
+        assert_eq!(scratch.commitment().hash(), tree.commitment().hash());
+        assert_eq!(scratch.nodes(), tree.nodes());
+    }
+
+    #[test]
+    fn test_from_parts() {
+        let leaves: Vec<_> = (0..64_u64).map(U256::from).collect();
+        for skip in 0..3 {
+            let tree = Tree::from_leaves_skip_layers(leaves.clone(), skip).unwrap();
+            let mut nodes = MmapVec::with_capacity(tree.nodes().len());
+            nodes.extend_from_slice(tree.nodes());
+            let parts: Tree<_> = Tree::from_parts(leaves.clone(), nodes).unwrap();
+            assert_eq!(parts.commitment().hash(), tree.commitment().hash());
+            assert_eq!(
+                parts.open(&[3, 40]).unwrap().hashes(),
+                tree.open(&[3, 40]).unwrap().hashes()
+            );
+        }
+        let mut nodes = MmapVec::with_capacity(2);
+        nodes.extend_from_slice(&[Hash::default(), Hash::default()]);
+        assert_eq!(
+            Tree::<_>::from_parts(leaves, nodes).err(),
+            Some(Error::NotEnoughHashes)
+        );
+    }
+
     proptest!(
         #[test]
         fn test_merkle_tree(depth: usize, skip: usize, indices: Vec<usize>, seed: usize) {
@@ -56,7 +56,9 @@ fn main() {
 
     println!("claim: 0x{}", claim.0.as_montgomery());
 
-    let concrete_system = claim.concrete_system();
+    let mut concrete_system = claim.concrete_system();
+    // The generated Solidity verifier only hashes the channel seed
+    concrete_system.strong_fiat_shamir = false;
     let trace = claim.trace(&witness);
     let proof = prove(&concrete_system, &trace).unwrap();
 
@@ -64,7 +66,8 @@ fn main() {
     proof_serialize(&concrete_system, &proof, &mut proof_string).unwrap();
     println!("{}", proof_string);
 
-    let system = claim.constraints();
+    let mut system = claim.constraints();
+    system.strong_fiat_shamir = false;
     let _ = generate(
         &system,
         "../stark-verifier-ethereum/contracts/claim_polynomial",
@@ -38,6 +38,8 @@ fn main() {
     let mut constraints = claim.constraints();
     constraints.num_queries = 2;
     constraints.pow_bits = 10;
+    // The generated Solidity verifier only hashes the channel seed
+    constraints.strong_fiat_shamir = false;
 
     let trace = claim.trace(&witness);
 
@@ -52,7 +54,8 @@ fn main() {
     .unwrap();
     println!("{}", proof_string);
 
-    let system = claim.constraints();
+    let mut system = claim.constraints();
+    system.strong_fiat_shamir = false;
 
     let _ = generate(
         &system,
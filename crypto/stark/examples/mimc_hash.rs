@@ -1,9 +1,10 @@
 #![allow(clippy::possible_missing_comma)]
 use std::time::Instant;
 use zkp_macros_decl::field_element;
-use zkp_primefield::{fft::permute, Fft, FieldElement, Pow, Root, SquareInline, Zero};
+use zkp_primefield::{fft::permute, Fft, FieldElement, One, Pow, SquareInline, Zero};
 use zkp_stark::{
-    Constraints, DensePolynomial, Provable, RationalExpression, TraceTable, Verifiable,
+    ConstraintBuilder, Constraints, DensePolynomial, Domain, Provable, RationalExpression,
+    TraceTable, Verifiable,
 };
 use zkp_u256::U256;
 
@@ -155,54 +156,48 @@ impl Verifiable for Claim {
         seed.extend_from_slice(&self.before_y.as_montgomery().to_bytes_be());
         seed.extend_from_slice(&self.after.as_montgomery().to_bytes_be());
 
-        // Constraint repetitions
         let trace_length = 256;
-        let trace_generator = FieldElement::root(trace_length).unwrap();
-        let g = Constant(trace_generator.clone());
-        let on_row = |index| (X - g.pow(index)).inv();
-        let every_row = || (X - g.pow(trace_length - 1)) / (X.pow(trace_length) - 1);
-
-        let periodic = |coefficients| {
+        let periodic = |values: &[FieldElement]| {
+            let mut coefficients = values.to_vec();
+            coefficients.ifft();
+            permute(&mut coefficients);
             Polynomial(
-                DensePolynomial::new(coefficients),
+                DensePolynomial::new(&coefficients),
                 Box::new(X.pow(trace_length / coefficients.len())),
             )
         };
-        let mut k_coef = K_COEF.to_vec();
-        k_coef.ifft();
-        permute(&mut k_coef);
-        let k_coef = periodic(&k_coef);
-
-        let on_loop_rows = |length: usize| {
-            (X.pow(trace_length / length)
-                - Constant(trace_generator.pow((trace_length / length) * (trace_length - 1))))
-                / (X.pow(trace_length) - 1)
-        };
-
-        let const_before_x = Constant(self.before_x.clone());
-        let const_before_y = Constant(self.before_y.clone());
-        let const_after = Constant(self.after.clone());
+        let k_coef = periodic(&K_COEF);
 
-        let expressions = vec![
-            ((Exp(Trace(0, 0).into(), 3)
-                + Constant(3.into()) * Constant(Q) * Trace(0, 0) * Exp(Trace(1, 0).into(), 2)
-                + k_coef)
-                - Trace(0, 1))
-                * on_loop_rows(128),
-            (Constant(3.into()) * Exp(Trace(0, 0).into(), 2)
-                + Constant(Q) * Exp(Trace(1, 0).into(), 3)
-                - Trace(1, 1))
-                * every_row(),
-            // Boundary constraints
-            (Trace(0, 0) - const_before_x.clone()) * on_row(0),
-            Trace(1, 0) * on_row(0),
-            (Trace(0, 0) - const_before_y.clone()) * on_row(128),
-            (Trace(0, 0) - const_after.clone()) * on_row(255),
-        ];
+        // The last row of each hash has no round, the next row starts the
+        // second hash or wraps around.
+        let mut in_round = vec![FieldElement::one(); 128];
+        in_round[127] = FieldElement::zero();
+        let in_round = periodic(&in_round);
 
-        let _public = vec![&const_before_x, &const_before_y, &const_after];
-
-        Constraints::from_expressions((trace_length, 2), seed, expressions).unwrap()
+        let mut builder = ConstraintBuilder::new(trace_length);
+        let left = builder.column("left");
+        let right = builder.column("right");
+        let round_left = Exp(left.current().into(), 3)
+            + Constant(3.into()) * Constant(Q) * left.current() * Exp(right.current().into(), 2)
+            + k_coef;
+        let round_right = Constant(3.into()) * Exp(left.current().into(), 2)
+            + Constant(Q) * Exp(right.current().into(), 3);
+        let _ = builder
+            .constrain(
+                "round left",
+                (round_left - left.next()) * in_round,
+                Domain::every_row(),
+            )
+            .constrain(
+                "round right",
+                round_right - right.next(),
+                Domain::every_row().except_last(),
+            )
+            .constrain("start right", right.current(), Domain::on_row(0))
+            .boundary("before x", left, 0, self.before_x.clone())
+            .boundary("before y", left, 128, self.before_y.clone())
+            .boundary("after", left, 255, self.after.clone());
+        builder.build(seed).unwrap().0
     }
 }
 
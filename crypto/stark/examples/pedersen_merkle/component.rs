@@ -373,6 +373,10 @@ mod test {
         constraints.pow_bits = 0;
         constraints.num_queries = 13;
         constraints.fri_layout = vec![3, 2];
+        // The reference values only hash the channel seed
+        constraints.strong_fiat_shamir = false;
+        // Counting the powers of X in the periodic columns keeps the degree
+        assert_eq!(constraints.degree(), 2);
         let proof = prove(&constraints, &trace).unwrap();
 
         assert_eq!(
@@ -2,8 +2,8 @@
 use log::info;
 use std::time::Instant;
 use zkp_macros_decl::field_element;
-use zkp_primefield::{FieldElement, Root};
-use zkp_stark::{Constraints, Provable, RationalExpression, TraceTable, Verifiable};
+use zkp_primefield::FieldElement;
+use zkp_stark::{ConstraintBuilder, Constraints, Domain, Provable, TraceTable, Verifiable};
 use zkp_u256::U256;
 
 #[derive(Clone, Debug)]
@@ -19,26 +19,28 @@ struct Witness {
 
 impl Verifiable for Claim {
     fn constraints(&self) -> Constraints {
-        use RationalExpression::*;
-
         // Seed
         let mut seed = self.index.to_be_bytes().to_vec();
         seed.extend_from_slice(&self.value.as_montgomery().to_bytes_be());
 
-        // Constraint repetitions
         let trace_length = self.index.next_power_of_two();
-        let trace_generator = FieldElement::root(trace_length).unwrap();
-        let g = Constant(trace_generator);
-        let on_row = |index| (X - g.pow(index)).inv();
-        let every_row = || (X - g.pow(trace_length - 1)) / (X.pow(trace_length) - 1);
-
-        Constraints::from_expressions((trace_length, 2), seed, vec![
-            (Trace(0, 1) - Trace(1, 0)) * every_row(),
-            (Trace(1, 1) - Trace(0, 0) - Trace(1, 0)) * every_row(),
-            (Trace(0, 0) - 1) * on_row(0),
-            (Trace(0, 0) - &self.value) * on_row(self.index),
-        ])
-        .unwrap()
+        let mut builder = ConstraintBuilder::new(trace_length);
+        let a = builder.column("a");
+        let b = builder.column("b");
+        let _ = builder
+            .constrain(
+                "copy",
+                a.next() - b.current(),
+                Domain::every_row().except_last(),
+            )
+            .constrain(
+                "sum",
+                b.next() - a.current() - b.current(),
+                Domain::every_row().except_last(),
+            )
+            .boundary("start", a, 0, 1.into())
+            .boundary("value", a, self.index, self.value.clone());
+        builder.build(seed).unwrap().0
     }
 }
 
@@ -1,39 +1,19 @@
 use crate::{
-    polynomial::DensePolynomial, rational_expression::RationalExpression, trace_table::TraceTable,
+    field::{from_entropy, StarkField},
+    polynomial::DensePolynomial,
+    rational_expression::RationalExpression,
+    trace_table::TraceTable,
 };
 use log::info;
-use std::{cmp::min, ops::Neg, prelude::v1::*};
+use std::{cmp::min, prelude::v1::*};
 use tiny_keccak::{Hasher, Keccak};
-use zkp_macros_decl::field_element;
-use zkp_primefield::{invert_batch_src_dst, FieldElement, Inv, One, Pow, Root, Zero};
-use zkp_u256::U256;
+use zkp_primefield::{invert_batch_src_dst, Inv, Pow, RefFieldLike};
 
 /// Number of values to calculate at once.
 ///
 /// A larger value means larger chunks for batch inversion and fewer iterations
 /// of the dag. Larger values also mean less cache locality.
-const CHUNK_SIZE: usize = 16;
-// HACK: FieldElement does not implement Copy, so we need to explicitly
-// instantiate
-const CHUNK_INIT: [FieldElement; CHUNK_SIZE] = [
-    field_element!("00"),
-    field_element!("00"),
-    field_element!("00"),
-    field_element!("00"),
-    field_element!("00"),
-    field_element!("00"),
-    field_element!("00"),
-    field_element!("00"),
-    field_element!("00"),
-    field_element!("00"),
-    field_element!("00"),
-    field_element!("00"),
-    field_element!("00"),
-    field_element!("00"),
-    field_element!("00"),
-    field_element!("00"),
-];
-
+pub(crate) const CHUNK_SIZE: usize = 16;
 /// Maximum size of a periodic lookup table.
 ///
 /// Sub-expressions that are discovered to be periodic get evaluated into a
@@ -42,9 +22,9 @@ const LOOKUP_SIZE: usize = 1024;
 
 /// Evaluation graph for algebraic expressions over a coset.
 #[derive(Clone, PartialEq)]
-pub(crate) struct AlgebraicGraph {
+pub(crate) struct AlgebraicGraph<Field: StarkField> {
     /// The cofactor of the evaluation domain.
-    cofactor: FieldElement,
+    cofactor: Field,
 
     /// The size of the evaluation domain.
     coset_size: usize,
@@ -53,10 +33,10 @@ pub(crate) struct AlgebraicGraph {
     trace_blowup: usize,
 
     /// Seed value for random evaluation.
-    seed: FieldElement,
+    seed: Field,
 
     /// Evaluation nodes in causal order.
-    nodes: Vec<Node>,
+    nodes: Vec<Node<Field>>,
 
     /// Current row
     row: usize,
@@ -64,38 +44,38 @@ pub(crate) struct AlgebraicGraph {
 
 /// Node in the evaluation graph.
 #[derive(Clone, Debug, PartialEq)]
-pub(crate) struct Node {
+pub(crate) struct Node<Field: StarkField> {
     /// The operation represented by the node
-    op: Operation,
+    op: Operation<Field>,
 
     /// Node evaluated on a random value.
     ///
     /// It acts as an 'algebraic' hash allowing
     /// us to identify algebraically equivalent nodes.
-    hash: FieldElement,
+    hash: Field,
 
     /// Period after which node values repeat
     period: usize,
 
     /// Scratch space for the evaluators
     // TODO: Something cleaner
-    note:   FieldElement,
-    values: [FieldElement; CHUNK_SIZE],
+    note: Field,
+    values: Vec<Field>,
 }
 
 /// Algebraic operations supported by the graph.
 #[derive(Clone, Debug, PartialEq)]
-enum Operation {
-    Constant(FieldElement),
-    Coset(FieldElement, usize),
+enum Operation<Field: StarkField> {
+    Constant(Field),
+    Coset(Field, usize),
     Trace(usize, isize),
     Add(Index, Index),
     Neg(Index),
     Mul(Index, Index),
     Inv(Index),
     Exp(Index, usize),
-    Poly(DensePolynomial, Index),
-    Lookup(Table),
+    Poly(DensePolynomial<Field>, Index),
+    Lookup(Table<Field>),
 }
 
 /// Reference to a node in the graph.
@@ -103,7 +83,7 @@ enum Operation {
 pub(crate) struct Index(usize);
 
 #[derive(Clone, PartialEq)]
-struct Table(Vec<FieldElement>);
+struct Table<Field: StarkField>(Vec<Field>);
 
 impl std::fmt::Debug for Index {
     fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
@@ -111,21 +91,30 @@ impl std::fmt::Debug for Index {
     }
 }
 
-impl std::fmt::Debug for Table {
+impl<Field: StarkField> std::fmt::Debug for Table<Field> {
     fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(fmt, "Table(len = {:>3})", self.0.len())
     }
 }
 
-impl std::fmt::Debug for AlgebraicGraph {
+impl<Field: StarkField> std::fmt::Debug for AlgebraicGraph<Field> {
     fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         writeln!(fmt, "AlgebraicGraph:")?;
         for (i, n) in self.nodes.iter().enumerate() {
+            // Show the last eight bytes of the hash
+            let mut bytes = Vec::with_capacity(Field::BYTES);
+            n.hash.write_bytes(&mut bytes);
+            let hash = bytes
+                .iter()
+                .rev()
+                .take(8)
+                .rev()
+                .fold(0_u64, |hash, byte| hash << 8 | u64::from(*byte));
             writeln!(
                 fmt,
                 "{:?}: {:016x} {:>8} {:?}",
                 Index(i),
-                n.hash.as_montgomery().limb(0),
+                hash,
                 n.period,
                 n.op
             )?
@@ -135,32 +124,26 @@ impl std::fmt::Debug for AlgebraicGraph {
 }
 
 // TODO: This leaks the Node type outside of this module.
-impl std::ops::Index<Index> for AlgebraicGraph {
-    type Output = Node;
+impl<Field: StarkField> std::ops::Index<Index> for AlgebraicGraph<Field> {
+    type Output = Node<Field>;
 
     fn index(&self, index: Index) -> &Self::Output {
         &self.nodes[index.0]
     }
 }
 
-fn from_entropy(keccak: Keccak) -> FieldElement {
-    let mut result = [0; 32];
-    keccak.finalize(&mut result);
-    result[0] &= 0xF;
-    let mut u256 = U256::from_bytes_be(&result);
-    if u256 >= FieldElement::MODULUS {
-        u256 -= FieldElement::MODULUS;
-    }
-    assert!(u256 < FieldElement::MODULUS);
-    FieldElement::from_montgomery(u256)
-}
-
-impl AlgebraicGraph {
-    pub(crate) fn new(cofactor: &FieldElement, coset_size: usize, trace_blowup: usize) -> Self {
+impl<Field> AlgebraicGraph<Field>
+where
+    Field: StarkField,
+    for<'a> &'a Field: RefFieldLike<Field>,
+{
+    pub(crate) fn new(cofactor: &Field, coset_size: usize, trace_blowup: usize) -> Self {
         assert!(coset_size.is_power_of_two());
         // Create seed out of parameters
+        let mut bytes = Vec::with_capacity(Field::BYTES);
+        cofactor.write_bytes(&mut bytes);
         let mut keccak = Keccak::v256();
-        keccak.update(&cofactor.as_montgomery().to_bytes_be());
+        keccak.update(&bytes);
         keccak.update(&coset_size.to_be_bytes());
         Self {
             cofactor: cofactor.clone(),
@@ -177,20 +160,22 @@ impl AlgebraicGraph {
     /// The node is evaluated on a random set up inputs derived from the seed.
     /// If two nodes have the same random evaluation, it can be safely assumed
     /// that they are algebraically identical.
-    fn hash(&self, operation: &Operation) -> FieldElement {
+    fn hash(&self, operation: &Operation<Field>) -> Field {
         use Operation::*;
         match operation {
             Constant(a) => a.clone(),
             Trace(i, o) => {
                 // Value = hash(seed, i, o)
+                let mut bytes = Vec::with_capacity(Field::BYTES);
+                self.seed.write_bytes(&mut bytes);
                 let mut keccak = Keccak::v256();
-                keccak.update(&self.seed.as_montgomery().to_bytes_be());
+                keccak.update(&bytes);
                 keccak.update(&i.to_be_bytes());
                 keccak.update(&o.to_be_bytes());
                 from_entropy(keccak)
             }
             Add(a, b) => &self[*a].hash + &self[*b].hash,
-            Neg(a) => -&self[*a].hash,
+            Neg(a) => self[*a].hash.neg(),
             Mul(a, b) => &self[*a].hash * &self[*b].hash,
             Inv(a) => self[*a].hash.inv().expect("Division by zero"),
             Exp(a, i) => self[*a].hash.pow(*i),
@@ -214,7 +199,7 @@ impl AlgebraicGraph {
     // NOTE: for evaluation purposes Coset(c, 1) == Constant(c), but this
     // is not covered by the hash, and can not be easily incorprated while
     // maintaining the above identities.
-    fn simplify(&self, operation: Operation) -> Operation {
+    fn simplify(&self, operation: Operation<Field>) -> Operation<Field> {
         use Operation::*;
         match operation {
             Add(a, b) => {
@@ -275,7 +260,7 @@ impl AlgebraicGraph {
         }
     }
 
-    fn period(&self, operation: &Operation) -> usize {
+    fn period(&self, operation: &Operation<Field>) -> usize {
         use Operation::*;
         fn lcm(a: usize, b: usize) -> usize {
             // TODO: Compute it for real. For powers of two this works.
@@ -295,7 +280,7 @@ impl AlgebraicGraph {
     ///
     /// If an algebraically identical node already exits, that index will be
     /// returned instead.
-    fn op(&mut self, operation: Operation) -> Index {
+    fn op(&mut self, operation: Operation<Field>) -> Index {
         let hash = self.hash(&operation);
         self.nodes.iter().position(|n| n.hash == hash).map_or_else(
             || {
@@ -304,8 +289,8 @@ impl AlgebraicGraph {
                 // OPT: Add more constants? Maybe evaluate in two points to detect all
                 // constants?
                 let operation = match &hash {
-                    h if h.is_zero() => Operation::Constant(FieldElement::zero()),
-                    h if h.is_one() => Operation::Constant(FieldElement::one()),
+                    h if h.is_zero() => Operation::Constant(Field::zero()),
+                    h if h.is_one() => Operation::Constant(Field::one()),
                     _ => self.simplify(operation),
                 };
                 // Create new node
@@ -315,8 +300,8 @@ impl AlgebraicGraph {
                     op: operation,
                     hash,
                     period,
-                    values: CHUNK_INIT,
-                    note: FieldElement::zero(),
+                    values: vec![Field::zero(); CHUNK_SIZE],
+                    note: Field::zero(),
                 });
                 Index(index)
             },
@@ -329,7 +314,7 @@ impl AlgebraicGraph {
 
     /// Adds a rational expression to the graph and return the result node
     /// index.
-    pub(crate) fn expression(&mut self, expr: RationalExpression) -> Index {
+    pub(crate) fn expression(&mut self, expr: RationalExpression<Field>) -> Index {
         use Operation as Op;
         use RationalExpression as RE;
         match expr {
@@ -341,6 +326,8 @@ impl AlgebraicGraph {
                 self.op(Op::Poly(p, a))
             }
             RE::ClaimPolynomial(..) => panic!("ClaimPolynomial not supported"),
+            RE::Challenge(_) => panic!("Challenge not supported"),
+            RE::PublicInput(_) => panic!("PublicInput not supported"),
             RE::Add(a, b) => {
                 let a = self.expression(*a);
                 let b = self.expression(*b);
@@ -366,7 +353,7 @@ impl AlgebraicGraph {
         }
     }
 
-    fn make_lookup(&self, index: Index) -> Vec<FieldElement> {
+    fn make_lookup(&self, index: Index) -> Vec<Field> {
         let node = &self[index];
         assert!(node.period <= 1024);
         let mut result = Vec::with_capacity(node.period);
@@ -399,10 +386,14 @@ impl AlgebraicGraph {
         }
     }
 
+    pub(crate) fn num_nodes(&self) -> usize {
+        self.nodes.len()
+    }
+
     /// Remove unnecessary nodes
     pub(crate) fn tree_shake(&mut self, tip: Index) -> Index {
         use Operation::*;
-        fn recurse(nodes: &[Node], used: &mut [bool], i: usize) {
+        fn recurse<Field: StarkField>(nodes: &[Node<Field>], used: &mut [bool], i: usize) {
             used[i] = true;
             match &nodes[i].op {
                 Add(a, b) | Mul(a, b) => {
@@ -464,7 +455,7 @@ impl AlgebraicGraph {
                     }
                 }
                 Coset(c, s) => {
-                    let root = FieldElement::root(*s).unwrap();
+                    let root = Field::root(*s).unwrap();
                     let mut acc = c.clone();
                     acc *= root.pow(self.row);
                     for i in 0..CHUNK_SIZE {
@@ -495,7 +486,7 @@ impl AlgebraicGraph {
     // We want to use `for i in 0..CHUNK_SIZE` for consistency
     #[allow(clippy::needless_range_loop)]
     #[inline(never)]
-    pub(crate) fn next(&mut self, trace_table: &TraceTable) -> FieldElement {
+    pub(crate) fn next(&mut self, trace_table: &TraceTable<Field>) -> Field {
         use Operation::*;
         if self.row % CHUNK_SIZE > 0 {
             let result = self.nodes.last().unwrap().values[self.row % CHUNK_SIZE].clone();
@@ -537,7 +528,7 @@ impl AlgebraicGraph {
                 Neg(a) => {
                     let a = &previous[a.0].values;
                     for i in 0..CHUNK_SIZE {
-                        values[i] = -&a[i]
+                        values[i] = a[i].neg()
                     }
                 }
                 Mul(a, b) => {
@@ -586,6 +577,8 @@ impl AlgebraicGraph {
 mod tests {
     use super::*;
     use zkp_macros_decl::field_element;
+    use zkp_primefield::{FieldElement, One, Zero};
+    use zkp_u256::U256;
     use Operation as Op;
     use RationalExpression as RE;
 
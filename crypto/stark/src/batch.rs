@@ -0,0 +1,597 @@
+//! Proving several statements in a single proof.
+//!
+//! The trace tables of all statements are committed in one Merkle tree and so
+//! are their constraint polynomials. Each statement gets its own constraint
+//! coefficients and out of domain point, after which the DEEP compositions of
+//! all statements are combined with channel randomness into a single
+//! polynomial that is proven low degree by a single FRI instance.
+//!
+//! All statements must use the same blowup, number of queries and proof of
+//! work bits, so the batch has the security of each of them. The statement with
+//! the largest trace, the first one on ties, determines the evaluation domain
+//! and with it the FRI layout.
+#[cfg(feature = "prover")]
+use crate::{
+    channel::{ProverChannel, Writable},
+    fri::FriProver,
+    polynomial::DensePolynomial,
+    prover::{
+        get_coefficients, get_constraint_polynomials, oods_combine, PolyLDE, DEFAULT_CHUNK_SIZE,
+    },
+    ProverError, TraceTable,
+};
+use crate::{
+    channel::{RandomGenerator, Replayable, VerifierChannel},
+    constraints::Constraints,
+    field::StarkField,
+    fri::FriVerifier,
+    proof_of_work,
+    verifier::{
+        get_indices, oods_value_from_constraint_values, oods_value_from_trace_values,
+        out_of_domain_element, Error,
+    },
+    Proof,
+};
+use log::trace;
+use std::{collections::BTreeMap, convert::TryFrom, prelude::v1::*};
+use zkp_hash::{Hash, Hasher, MaskedKeccak};
+#[cfg(feature = "prover")]
+use zkp_merkle_tree::VectorCommitment;
+use zkp_merkle_tree::{Commitment, Proof as MerkleProof};
+#[cfg(feature = "prover")]
+use zkp_mmap_vec::MmapVec;
+use zkp_primefield::{fft, geometric_series::root_series, Pow, RefFieldLike};
+
+/// Produce a single Stark proof for a batch of statements.
+///
+/// Each statement consists of its constraints, its claim and its trace table.
+/// The claim is absorbed in the channel together with the constraints'
+/// transcript seed, so the proof is bound to the claims and their order.
+///
+/// Statements may have traces of different sizes, but must agree on the
+/// blowup, number of queries and proof of work bits. Multi-stage and
+/// zero-knowledge constraints are not supported in a batch. See the
+/// [module documentation](self) for how the statements are combined.
+#[cfg(feature = "prover")]
+pub fn prove_batch<Field>(
+    statements: &[(&Constraints<Field>, &[u8], &TraceTable<Field>)],
+) -> Result<Proof, ProverError>
+where
+    Field: StarkField,
+    for<'a> &'a Field: RefFieldLike<Field>,
+{
+    prove_batch_with_hasher::<MaskedKeccak, _>(statements)
+}
+
+/// Produce a batch proof using the hash function `H`.
+///
+/// See [`prove_batch`] for details.
+// TODO: Split up
+#[allow(clippy::too_many_lines)]
+#[cfg(feature = "prover")]
+pub fn prove_batch_with_hasher<H, Field>(
+    statements: &[(&Constraints<Field>, &[u8], &TraceTable<Field>)],
+) -> Result<Proof, ProverError>
+where
+    H: Hasher,
+    Field: StarkField,
+    for<'a> &'a Field: RefFieldLike<Field>,
+{
+    let claims = statements
+        .iter()
+        .map(|(constraints, claim, _)| (*constraints, *claim))
+        .collect::<Vec<_>>();
+    let main = main_statement(&claims).ok_or(ProverError::InvalidBatch)?;
+    if statements.iter().any(|(constraints, _, trace)| {
+        trace.num_rows() != constraints.trace_nrows()
+            || trace.num_columns() != constraints.trace_ncolumns()
+    }) {
+        return Err(ProverError::InvalidBatch);
+    }
+    let eval_domain_size = main.eval_domain_size();
+    let mut constraints = statements
+        .iter()
+        .map(|(constraints, ..)| {
+            let mut constraints = (*constraints).clone();
+            constraints.substitute_public_inputs();
+            constraints
+        })
+        .collect::<Vec<_>>();
+
+    trace!("BEGIN Batch proof");
+    let mut proof = ProverChannel::<H>::default();
+    proof.initialize(&batch_seed(&claims));
+
+    // 1. Trace commitment
+    trace!("BEGIN Trace commitment");
+    let trace_polynomials = statements
+        .iter()
+        .map(|(_, _, trace)| trace.interpolate())
+        .collect::<Vec<_>>();
+    let trace_lde = PolyLDE(
+        trace_polynomials
+            .iter()
+            .flatten()
+            .map(|p| p.low_degree_extension(eval_domain_size / p.len()))
+            .collect::<Vec<_>>(),
+    );
+    let (commitment, trace_tree) = trace_lde.commit::<H>()?;
+    proof.write(&commitment);
+    trace!("END Trace commitment");
+
+    // 2. Constraint commitment
+    trace!("BEGIN Constraint commitment");
+    let mut constraint_polynomials = Vec::with_capacity(statements.len());
+    let mut columns = trace_tree.leaves().0.iter();
+    for constraints in &mut constraints {
+        let constraint_coefficients = get_coefficients(&mut proof, 2 * constraints.len());
+        let trace_nrows = constraints.trace_nrows();
+        let trace_lde = columns
+            .by_ref()
+            .take(constraints.trace_ncolumns())
+            .collect::<Vec<_>>();
+        let (polynomials, _) = get_constraint_polynomials(
+            &trace_lde,
+            constraints,
+            &constraint_coefficients,
+            trace_nrows,
+            DEFAULT_CHUNK_SIZE,
+            None,
+            &|_| Ok(()),
+        )?;
+        constraint_polynomials.push(polynomials);
+    }
+    let constraint_lde = PolyLDE(
+        constraint_polynomials
+            .iter()
+            .flatten()
+            .map(|p| p.low_degree_extension(eval_domain_size / p.len()))
+            .collect::<Vec<_>>(),
+    );
+    let (commitment, constraint_tree) = constraint_lde.commit::<H>()?;
+    proof.write(&commitment);
+    trace!("END Constraint commitment");
+
+    // 3. Out of domain sampling for each statement
+    trace!("BEGIN Out of domain sampling");
+    let mut oods_polynomials = Vec::with_capacity(statements.len());
+    for ((constraints, trace_polynomials), constraint_polynomials) in constraints
+        .iter()
+        .zip(&trace_polynomials)
+        .zip(&constraint_polynomials)
+    {
+        oods_polynomials.push(oods_combine(
+            &mut proof,
+            trace_polynomials,
+            &constraints.trace_arguments(),
+            constraint_polynomials,
+            constraints.trace_nrows(),
+            constraint_polynomials.len(),
+            None,
+        ));
+    }
+    trace!("END Out of domain sampling");
+
+    // Combine the statements, aligning both the lowest and the highest
+    // coefficients to the degree bound.
+    let degree_bound = main.trace_polynomial_len();
+    let batch_coefficients: Vec<Field> = get_coefficients(&mut proof, 2 * statements.len());
+    let mut combined: MmapVec<Field> = MmapVec::with_capacity(degree_bound);
+    combined.resize(degree_bound, Field::zero());
+    for (polynomial, coefficients) in oods_polynomials
+        .iter()
+        .zip(batch_coefficients.chunks_exact(2))
+    {
+        let shift = degree_bound - polynomial.len();
+        for (i, coefficient) in polynomial.coefficients().iter().enumerate() {
+            combined[i] += &coefficients[0] * coefficient;
+            combined[i + shift] += &coefficients[1] * coefficient;
+        }
+    }
+
+    // 4. FRI layers
+    trace!("BEGIN FRI commitment");
+    let first_fri_layer =
+        DensePolynomial::from_mmap_vec(combined).low_degree_extension(main.blowup);
+    let fri_prover =
+        FriProver::<_, H>::commit(&main.fri_parameters(), first_fri_layer, &mut proof)?;
+    trace!("END FRI commitment");
+
+    // 5. Proof of work
+    let pow_seed: proof_of_work::ChallengeSeed = proof.get_random();
+    let pow_challenge = pow_seed.with_difficulty::<H>(main.pow_bits);
+    let pow_response = pow_challenge.solve();
+    debug_assert!(pow_challenge.verify(pow_response));
+    proof.write(pow_response);
+
+    // 6. Query decommitments
+    let query_indices = crate::prover::get_indices(
+        main.num_queries,
+        eval_domain_size.trailing_zeros(),
+        &mut proof,
+    );
+    for &index in &query_indices {
+        proof.write(trace_tree.leaf(index));
+    }
+    proof.write(&trace_tree.open(&query_indices)?);
+    for &index in &query_indices {
+        proof.write(constraint_tree.leaf(index));
+    }
+    proof.write(&constraint_tree.open(&query_indices)?);
+    fri_prover.decommit(&query_indices, &mut proof)?;
+
+    let proof = Proof::from_bytes(proof.proof);
+    verify_batch_with_hasher::<H, _>(&claims, &proof)?;
+    trace!("END Batch proof");
+    Ok(proof)
+}
+
+/// Verify a batch proof.
+///
+/// The statements are the constraints and claims of all statements in the
+/// order they were proven, see [`prove_batch`](crate::prove_batch).
+pub fn verify_batch<Field>(
+    statements: &[(&Constraints<Field>, &[u8])],
+    proof: &Proof,
+) -> Result<(), Error>
+where
+    Field: StarkField,
+    for<'a> &'a Field: RefFieldLike<Field>,
+{
+    verify_batch_with_hasher::<MaskedKeccak, _>(statements, proof)
+}
+
+/// Verify a batch proof created with hash function `H`.
+///
+/// See [`verify_batch`] for details.
+// TODO: Split up
+#[allow(clippy::too_many_lines)]
+pub fn verify_batch_with_hasher<H, Field>(
+    statements: &[(&Constraints<Field>, &[u8])],
+    proof: &Proof,
+) -> Result<(), Error>
+where
+    H: Hasher,
+    Field: StarkField,
+    for<'a> &'a Field: RefFieldLike<Field>,
+{
+    /// Out of domain values and coefficients of a single statement.
+    struct Opening<Field: StarkField> {
+        constraints:             Constraints<Field>,
+        constraint_coefficients: Vec<Field>,
+        oods_point:              Field,
+        trace_values:            BTreeMap<(usize, isize), Field>,
+        constraint_values:       Vec<Field>,
+        oods_coefficients:       Vec<Field>,
+    }
+
+    trace!("BEGIN Verify batch");
+    let main = main_statement(statements).ok_or(Error::InvalidBatch)?;
+    let eval_domain_size = main.eval_domain_size();
+    let degree_bound = main.trace_polynomial_len();
+    let eval_x = root_series::<Field>(eval_domain_size).collect::<Vec<_>>();
+
+    let mut channel = VerifierChannel::<H>::new(proof.as_bytes().to_vec());
+    channel.initialize(&batch_seed(statements));
+
+    let trace_root: Hash = channel.replay();
+    let trace_commitment = Commitment::from_size_hash(eval_domain_size, &trace_root)?;
+    let constraint_coefficients = statements
+        .iter()
+        .map(|(constraints, _)| channel.get_coefficients(2 * constraints.len()))
+        .collect::<Vec<Vec<Field>>>();
+    let constraint_root: Hash = channel.replay();
+    let constraint_commitment = Commitment::from_size_hash(eval_domain_size, &constraint_root)?;
+
+    let mut openings = Vec::with_capacity(statements.len());
+    for ((constraints, _), constraint_coefficients) in
+        statements.iter().zip(constraint_coefficients)
+    {
+        let oods_point: Field = channel.get_random();
+        let mut parseable_constraints = (*constraints).clone();
+        parseable_constraints.substitute();
+        parseable_constraints.substitute_public_inputs();
+        let trace_arguments = parseable_constraints.trace_arguments();
+        let trace_values: Vec<Field> = channel.replay_many(trace_arguments.len());
+        let constraint_values: Vec<Field> =
+            channel.replay_many(constraints.degree().next_power_of_two());
+        let oods_coefficients =
+            channel.get_coefficients(trace_values.len() + constraint_values.len());
+        openings.push(Opening {
+            constraints: parseable_constraints,
+            constraint_coefficients,
+            oods_point,
+            trace_values: trace_arguments.into_iter().zip(trace_values).collect(),
+            constraint_values,
+            oods_coefficients,
+        });
+    }
+    let batch_coefficients: Vec<Field> = channel.get_coefficients(2 * statements.len());
+
+    let fri_verifier = FriVerifier::read_commitments(main.fri_parameters(), &mut channel)?;
+
+    let pow_seed: proof_of_work::ChallengeSeed = channel.get_random();
+    let pow_challenge = pow_seed.with_difficulty::<H>(main.pow_bits);
+    let pow_response: proof_of_work::Response = channel.replay();
+    if !pow_challenge.verify(pow_response) {
+        return Err(Error::InvalidPoW);
+    }
+
+    let queries = get_indices(
+        main.num_queries,
+        eval_domain_size.trailing_zeros(),
+        &mut channel,
+    );
+
+    // Get the values and check the decommitment of the shared trace tree
+    let trace_columns = statements
+        .iter()
+        .map(|(constraints, _)| constraints.trace_ncolumns())
+        .sum();
+    let trace_values: Vec<(usize, Vec<Field>)> = queries
+        .iter()
+        .map(|&index| (index, channel.replay_fri_layer(trace_columns)))
+        .collect();
+    let trace_hashes: Vec<Hash> = channel.replay_many(trace_commitment.proof_size(&queries)?);
+    let trace_proof = MerkleProof::<H>::from_hashes_with(&trace_commitment, &queries, &trace_hashes)?;
+    if trace_proof.verify(&trace_values).is_err() {
+        return Err(Error::InvalidLDECommitment);
+    }
+
+    // Get the values and check the decommitment of the shared constraint tree
+    let constraint_columns = openings
+        .iter()
+        .map(|opening| opening.constraint_values.len())
+        .sum();
+    let constraint_values: Vec<(usize, Vec<Field>)> = queries
+        .iter()
+        .map(|&index| (index, channel.replay_fri_layer(constraint_columns)))
+        .collect();
+    let constraint_hashes: Vec<Hash> =
+        channel.replay_many(constraint_commitment.proof_size(&queries)?);
+    let constraint_proof =
+        MerkleProof::<H>::from_hashes_with(&constraint_commitment, &queries, &constraint_hashes)?;
+    if constraint_proof.verify(&constraint_values).is_err() {
+        return Err(Error::InvalidConstraintCommitment);
+    }
+
+    // The first FRI layer is the combination of the DEEP compositions
+    let mut first_layer = Vec::with_capacity(queries.len());
+    for ((index, trace_values), (_, constraint_values)) in
+        trace_values.iter().zip(&constraint_values)
+    {
+        let x = &eval_x[fft::permute_index(eval_domain_size, *index)];
+        let shifted_x = x * Field::generator();
+        let mut trace_values = &trace_values[..];
+        let mut constraint_values = &constraint_values[..];
+        let mut value = Field::zero();
+        for ((constraints, _), (opening, coefficients)) in statements
+            .iter()
+            .zip(openings.iter().zip(batch_coefficients.chunks_exact(2)))
+        {
+            let (statement_trace, rest) = trace_values.split_at(constraints.trace_ncolumns());
+            trace_values = rest;
+            let (statement_constraint, rest) =
+                constraint_values.split_at(opening.constraint_values.len());
+            constraint_values = rest;
+            let element = out_of_domain_element(
+                x,
+                statement_trace,
+                statement_constraint,
+                &opening.oods_point,
+                &opening.trace_values,
+                &opening.constraint_values,
+                &opening.oods_coefficients,
+                constraints.trace_nrows(),
+                opening.constraint_values.len(),
+            )?;
+            let shift = degree_bound - constraints.trace_polynomial_len();
+            let weight = &coefficients[0] + &coefficients[1] * &shifted_x.pow(shift);
+            value += &weight * &element;
+        }
+        first_layer.push((*index, value));
+    }
+    fri_verifier.verify::<H, _>(&first_layer, &mut channel)?;
+    if !channel.at_end() {
+        return Err(Error::ProofTooLong);
+    }
+    if channel.has_invalid_encoding() {
+        return Err(Error::InvalidEncoding);
+    }
+
+    for opening in &openings {
+        if oods_value_from_trace_values(
+            &opening.constraints,
+            &opening.constraint_coefficients,
+            &opening.trace_values,
+            &opening.oods_point,
+        ) != oods_value_from_constraint_values(&opening.constraint_values, &opening.oods_point)
+        {
+            return Err(Error::OodsMismatch);
+        }
+    }
+    trace!("END Verify batch");
+    Ok(())
+}
+
+/// Returns the statement that determines the proof parameters, or `None` if
+/// the statements can not be batched.
+fn main_statement<'a, Field>(
+    statements: &[(&'a Constraints<Field>, &[u8])],
+) -> Option<&'a Constraints<Field>>
+where
+    Field: StarkField,
+    for<'b> &'b Field: RefFieldLike<Field>,
+{
+    // `max_by_key` returns the last maximum, so search in reverse.
+    let (main, _) = statements
+        .iter()
+        .rev()
+        .max_by_key(|(constraints, _)| constraints.trace_nrows())?;
+    let eval_domain_size = main.eval_domain_size();
+    let valid = statements.iter().all(|(constraints, _)| {
+        constraints.blowup == main.blowup
+            && constraints.num_queries == main.num_queries
+            && constraints.pow_bits == main.pow_bits
+            && constraints.num_stages() == 1
+            && !constraints.zero_knowledge
+            && constraints.trace_nrows() * constraints.degree().next_power_of_two()
+                <= eval_domain_size
+    });
+    if valid {
+        Some(main)
+    } else {
+        None
+    }
+}
+
+/// Channel seed binding the claims and transcript seeds of all statements.
+fn batch_seed<Field>(statements: &[(&Constraints<Field>, &[u8])]) -> Vec<u8>
+where
+    Field: StarkField,
+    for<'a> &'a Field: RefFieldLike<Field>,
+{
+    fn write_usize(bytes: &mut Vec<u8>, value: usize) {
+        let value = u64::try_from(value).expect("Value does not fit 64 bits");
+        bytes.extend_from_slice(&value.to_be_bytes());
+    }
+    let mut seed = Vec::new();
+    write_usize(&mut seed, statements.len());
+    for (constraints, claim) in statements {
+        for bytes in &[*claim, &constraints.transcript_seed()] {
+            write_usize(&mut seed, bytes.len());
+            seed.extend_from_slice(bytes);
+        }
+    }
+    seed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{traits::tests::Recurrance, Provable, Verifiable};
+    use zkp_macros_decl::field_element;
+    use zkp_primefield::FieldElement;
+    use zkp_u256::U256;
+
+    fn statement(index: usize, initial_value: FieldElement) -> (Constraints, Vec<u8>, TraceTable) {
+        let recurrance = Recurrance {
+            index,
+            initial_value,
+            exponent: 1,
+        };
+        let claim = recurrance.claim();
+        let constraints = claim.constraints();
+        let trace = claim.trace(&recurrance.witness());
+        (constraints, claim.seed(), trace)
+    }
+
+    fn statements() -> Vec<(Constraints, Vec<u8>, TraceTable)> {
+        vec![
+            statement(100, field_element!("cafebabe")),
+            statement(500, field_element!("deadbeef")),
+            statement(30, field_element!("01")),
+            statement(300, field_element!("1234")),
+        ]
+    }
+
+    fn prove_statements(statements: &[(Constraints, Vec<u8>, TraceTable)]) -> Proof {
+        let statements = statements
+            .iter()
+            .map(|(constraints, claim, trace)| (constraints, &claim[..], trace))
+            .collect::<Vec<_>>();
+        prove_batch(&statements).unwrap()
+    }
+
+    fn verify_statements(
+        statements: &[(Constraints, Vec<u8>, TraceTable)],
+        proof: &Proof,
+    ) -> Result<(), Error> {
+        let statements = statements
+            .iter()
+            .map(|(constraints, claim, _)| (constraints, &claim[..]))
+            .collect::<Vec<_>>();
+        verify_batch(&statements, proof)
+    }
+
+    #[test]
+    fn test_batch() {
+        let statements = statements();
+        let proof = prove_statements(&statements);
+        assert!(verify_statements(&statements, &proof).is_ok());
+
+        // A single statement is a batch too
+        let single = vec![statements[2].clone()];
+        let single_proof = prove_statements(&single);
+        assert!(verify_statements(&single, &single_proof).is_ok());
+        assert!(verify_statements(&single, &proof).is_err());
+    }
+
+    #[test]
+    fn test_batch_wrong_statements() {
+        let statements = statements();
+        let proof = prove_statements(&statements);
+
+        let mut swapped = statements.clone();
+        swapped.swap(0, 2);
+        assert!(verify_statements(&swapped, &proof).is_err());
+
+        let mut altered = statements.clone();
+        altered[3].1[0] ^= 1;
+        assert!(verify_statements(&altered, &proof).is_err());
+
+        let mut other = statements.clone();
+        other[1] = statement(500, field_element!("deadbeee"));
+        assert!(verify_statements(&other, &proof).is_err());
+
+        assert!(verify_statements(&statements[..3], &proof).is_err());
+    }
+
+    #[test]
+    fn test_invalid_batch() {
+        let proof = Proof::from_bytes(vec![]);
+        assert!(matches!(
+            prove_batch::<FieldElement>(&[]),
+            Err(ProverError::InvalidBatch)
+        ));
+        assert!(matches!(
+            verify_batch::<FieldElement>(&[], &proof),
+            Err(Error::InvalidBatch)
+        ));
+
+        let (mut constraints, claim, trace) = statement(30, field_element!("01"));
+        constraints.zero_knowledge = true;
+        assert!(matches!(
+            prove_batch(&[(&constraints, &claim[..], &trace)]),
+            Err(ProverError::InvalidBatch)
+        ));
+
+        // The security parameters must match
+        let (constraints, claim, trace) = statement(30, field_element!("01"));
+        let (mut other, other_claim, other_trace) = statement(100, field_element!("01"));
+        other.num_queries -= 1;
+        assert!(matches!(
+            prove_batch(&[
+                (&constraints, &claim[..], &trace),
+                (&other, &other_claim[..], &other_trace)
+            ]),
+            Err(ProverError::InvalidBatch)
+        ));
+        other.num_queries += 1;
+        other.pow_bits += 1;
+        assert!(matches!(
+            verify_batch(
+                &[(&constraints, &claim[..]), (&other, &other_claim[..])],
+                &proof
+            ),
+            Err(Error::InvalidBatch)
+        ));
+
+        let (constraints, claim, _) = statement(30, field_element!("01"));
+        let (_, _, trace) = statement(100, field_element!("01"));
+        assert!(matches!(
+            prove_batch(&[(&constraints, &claim[..], &trace)]),
+            Err(ProverError::InvalidBatch)
+        ));
+    }
+}
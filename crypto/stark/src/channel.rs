@@ -1,22 +1,22 @@
 // TODO: Naming?
 #![allow(clippy::module_name_repetitions)]
-use crate::proof_of_work;
-use std::{convert::TryInto, prelude::v1::*};
-use tiny_keccak::{Hasher, Keccak};
-use zkp_hash::Hash;
-use zkp_macros_decl::u256h;
-use zkp_primefield::FieldElement;
+use crate::{field::StarkField, proof_of_work};
+use std::{marker::PhantomData, prelude::v1::*};
+use zkp_hash::{Hash, Hasher, MaskedKeccak};
 use zkp_u256::U256;
 
-pub(crate) trait RandomGenerator<T> {
+/// Draws values from the public coin of a transcript
+pub trait RandomGenerator<T> {
     fn get_random(&mut self) -> T;
 }
 
-pub(crate) trait Writable<T> {
+/// Appends values to a proof transcript
+pub trait Writable<T> {
     fn write(&mut self, data: T);
 }
 
-pub(crate) trait Replayable<T> {
+/// Reads values from a proof transcript
+pub trait Replayable<T> {
     fn replay(&mut self) -> T;
 
     fn replay_many(&mut self, count: usize) -> Vec<T> {
@@ -24,39 +24,48 @@ pub(crate) trait Replayable<T> {
     }
 }
 
+/// Reads a slice that was written at once, see [`Writable`].
+pub trait ReplayableSlice<T> {
+    fn replay_slice(&mut self, count: usize) -> Vec<T>;
+}
+
 // TODO: Limit to crate
 #[derive(PartialEq, Eq, Clone, Default)]
 #[cfg_attr(feature = "std", derive(Debug))]
-pub(crate) struct PublicCoin {
-    pub(crate) digest: [u8; 32],
-    counter:           u64,
+pub(crate) struct PublicCoin<H: Hasher = MaskedKeccak> {
+    pub(crate) digest:  [u8; 32],
+    pub(crate) counter: u64,
+    hasher:             PhantomData<H>,
 }
 
 #[derive(PartialEq, Eq, Clone, Default)]
 #[cfg_attr(feature = "std", derive(Debug))]
-pub(crate) struct ProverChannel {
-    pub(crate) coin:  PublicCoin,
+pub(crate) struct ProverChannel<H: Hasher = MaskedKeccak> {
+    pub(crate) coin:  PublicCoin<H>,
     pub(crate) proof: Vec<u8>,
 }
 
 #[derive(PartialEq, Eq, Clone, Default)]
 #[cfg_attr(feature = "std", derive(Debug))]
-pub(crate) struct VerifierChannel {
-    pub(crate) coin:  PublicCoin,
+pub(crate) struct VerifierChannel<H: Hasher = MaskedKeccak> {
+    pub(crate) coin:  PublicCoin<H>,
     pub(crate) proof: Vec<u8>,
     proof_index:      usize,
+    invalid_encoding: bool,
 }
 
-impl PublicCoin {
+// The coin uses the full digest of `H`, so for `MaskedKeccak` it is
+// identical to plain Keccak.
+impl<H: Hasher> PublicCoin<H> {
     pub(crate) fn seed(&mut self, seed: &[u8]) {
-        let mut keccak = Keccak::v256();
-        keccak.update(seed);
-        keccak.finalize(&mut self.digest);
+        let mut hasher = H::default();
+        hasher.update(seed);
+        self.digest = hasher.finalize();
         self.counter = 0;
     }
 }
 
-impl From<Vec<u8>> for ProverChannel {
+impl<H: Hasher> From<Vec<u8>> for ProverChannel<H> {
     fn from(proof_data: Vec<u8>) -> Self {
         Self {
             coin:  PublicCoin::default(),
@@ -66,18 +75,19 @@ impl From<Vec<u8>> for ProverChannel {
 }
 
 #[cfg(feature = "prover")]
-impl ProverChannel {
+impl<H: Hasher> ProverChannel<H> {
     pub(crate) fn initialize(&mut self, seed: &[u8]) {
         self.coin.seed(seed);
     }
 }
 
-impl VerifierChannel {
+impl<H: Hasher> VerifierChannel<H> {
     pub(crate) fn new(proof: Vec<u8>) -> Self {
         Self {
             coin: PublicCoin::default(),
             proof,
             proof_index: 0,
+            invalid_encoding: false,
         }
     }
 
@@ -85,29 +95,39 @@ impl VerifierChannel {
         self.coin.seed(seed);
     }
 
-    pub(crate) fn at_end(self) -> bool {
+    pub(crate) fn at_end(&self) -> bool {
         self.proof_index == self.proof.len()
     }
 
-    pub(crate) fn get_coefficients(&mut self, n: usize) -> Vec<FieldElement> {
+    /// Whether a replayed field element was out of range. These are replayed
+    /// as zero and the proof must be rejected.
+    pub(crate) fn has_invalid_encoding(&self) -> bool {
+        self.invalid_encoding
+    }
+
+    /// Number of transcript bytes that have not been replayed yet.
+    pub(crate) fn remaining(&self) -> usize {
+        self.proof.len().saturating_sub(self.proof_index)
+    }
+
+    pub(crate) fn get_coefficients<Field: StarkField>(&mut self, n: usize) -> Vec<Field> {
         (0..n).map(|_| self.get_random()).collect()
     }
 
-    // This differs from Replayable::<FieldElement>::replay_many in that it only
+    // This differs from Replayable::<Field>::replay_many in that it only
     // updates the public coin once, with the contents of the entire layer, instead
-    // of onces for each FieldElement in the layer.
-    pub(crate) fn replay_fri_layer(&mut self, size: usize) -> Vec<FieldElement> {
+    // of onces for each element in the layer.
+    pub(crate) fn replay_fri_layer<Field: StarkField>(&mut self, size: usize) -> Vec<Field> {
         let start_index = self.proof_index;
-        self.proof_index += 32 * size;
+        self.proof_index += Field::BYTES * size;
         let layer_contents = &self.proof[start_index..self.proof_index];
 
         self.coin.write(layer_contents);
 
+        let invalid_encoding = &mut self.invalid_encoding;
         layer_contents
-            .chunks_exact(32)
-            .map(|bytes| {
-                FieldElement::from_montgomery(U256::from_bytes_be(bytes.try_into().unwrap()))
-            })
+            .chunks_exact(Field::BYTES)
+            .map(|bytes| decode_element(bytes, invalid_encoding))
             .collect()
     }
 
@@ -123,7 +143,7 @@ impl VerifierChannel {
     }
 }
 
-impl RandomGenerator<proof_of_work::ChallengeSeed> for PublicCoin {
+impl<H: Hasher> RandomGenerator<proof_of_work::ChallengeSeed> for PublicCoin<H> {
     fn get_random(&mut self) -> proof_of_work::ChallengeSeed {
         self.counter += 1;
         // FIX: Use get_random::<[u8;32]>();
@@ -131,13 +151,13 @@ impl RandomGenerator<proof_of_work::ChallengeSeed> for PublicCoin {
     }
 }
 
-impl Writable<proof_of_work::Response> for ProverChannel {
+impl<H: Hasher> Writable<proof_of_work::Response> for ProverChannel<H> {
     fn write(&mut self, data: proof_of_work::Response) {
         self.write(&data.nonce().to_be_bytes()[..]);
     }
 }
 
-impl Replayable<proof_of_work::Response> for VerifierChannel {
+impl<H: Hasher> Replayable<proof_of_work::Response> for VerifierChannel<H> {
     fn replay(&mut self) -> proof_of_work::Response {
         let mut holder = [0_u8; 8];
         let from = self.proof_index;
@@ -150,66 +170,59 @@ impl Replayable<proof_of_work::Response> for VerifierChannel {
     }
 }
 
-impl RandomGenerator<FieldElement> for PublicCoin {
-    fn get_random(&mut self) -> FieldElement {
-        const MASK: U256 =
-            u256h!("0FFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFFF");
+impl<Field: StarkField, H: Hasher> RandomGenerator<Field> for PublicCoin<H> {
+    fn get_random(&mut self) -> Field {
         loop {
-            let number: U256 = self.get_random();
-            let seed = number & MASK;
-            if seed < FieldElement::MODULUS {
-                break FieldElement::from_montgomery(seed);
+            let bytes: [u8; 32] = self.get_random();
+            if let Some(element) = Field::from_random_bytes(&bytes) {
+                break element;
             }
         }
     }
 }
 
-impl RandomGenerator<U256> for PublicCoin {
+impl<H: Hasher> RandomGenerator<U256> for PublicCoin<H> {
     fn get_random(&mut self) -> U256 {
         U256::from_bytes_be(&self.get_random())
     }
 }
 
-impl RandomGenerator<[u8; 32]> for PublicCoin {
+impl<H: Hasher> RandomGenerator<[u8; 32]> for PublicCoin<H> {
     fn get_random(&mut self) -> [u8; 32] {
-        let mut result = [0; 32];
-        let mut keccak = Keccak::v256();
-        keccak.update(&self.digest);
-        keccak.update(&[0_u8; 24]);
-        keccak.update(&self.counter.to_be_bytes());
-        keccak.finalize(&mut result);
+        let mut hasher = H::default();
+        hasher.update(&self.digest);
+        hasher.update(&[0_u8; 24]);
+        hasher.update(&self.counter.to_be_bytes());
         self.counter += 1;
-        result
+        hasher.finalize()
     }
 }
 
-impl<T> RandomGenerator<T> for ProverChannel
+impl<T, H: Hasher> RandomGenerator<T> for ProverChannel<H>
 where
-    PublicCoin: RandomGenerator<T>,
+    PublicCoin<H>: RandomGenerator<T>,
 {
     fn get_random(&mut self) -> T {
         self.coin.get_random()
     }
 }
 
-impl<T> RandomGenerator<T> for VerifierChannel
+impl<T, H: Hasher> RandomGenerator<T> for VerifierChannel<H>
 where
-    PublicCoin: RandomGenerator<T>,
+    PublicCoin<H>: RandomGenerator<T>,
 {
     fn get_random(&mut self) -> T {
         self.coin.get_random()
     }
 }
 
-impl Writable<&[u8]> for PublicCoin {
+impl<H: Hasher> Writable<&[u8]> for PublicCoin<H> {
     fn write(&mut self, data: &[u8]) {
-        let mut result: [u8; 32] = [0; 32];
-        let mut keccak = Keccak::v256();
-        keccak.update(&self.digest);
-        keccak.update(data);
-        keccak.finalize(&mut result);
+        let mut hasher = H::default();
+        hasher.update(&self.digest);
+        hasher.update(data);
         // FIX: Hash counter into digest.
-        self.digest = result;
+        self.digest = hasher.finalize();
         self.counter = 0;
     }
 }
@@ -218,27 +231,27 @@ impl Writable<&[u8]> for PublicCoin {
 // the proof with the same encoding for the writing and the non writing. However
 // by writing directly to the coin, other writes for the channel could separate
 // encoding from random perturbation.
-impl Writable<&[u8]> for ProverChannel {
+impl<H: Hasher> Writable<&[u8]> for ProverChannel<H> {
     fn write(&mut self, data: &[u8]) {
         self.proof.extend_from_slice(data);
         self.coin.write(data);
     }
 }
 
-impl Writable<&Hash> for ProverChannel {
+impl<H: Hasher> Writable<&Hash> for ProverChannel<H> {
     fn write(&mut self, data: &Hash) {
         self.write(data.as_bytes());
     }
 }
 
-impl Writable<&zkp_merkle_tree::Commitment> for ProverChannel {
+impl<H: Hasher> Writable<&zkp_merkle_tree::Commitment> for ProverChannel<H> {
     fn write(&mut self, data: &zkp_merkle_tree::Commitment) {
         self.write(data.hash())
     }
 }
 
-impl Writable<&zkp_merkle_tree::Proof> for ProverChannel {
-    fn write(&mut self, data: &zkp_merkle_tree::Proof) {
+impl<H: Hasher> Writable<&zkp_merkle_tree::Proof<H>> for ProverChannel<H> {
+    fn write(&mut self, data: &zkp_merkle_tree::Proof<H>) {
         for hash in data.hashes() {
             self.write(hash)
         }
@@ -246,27 +259,35 @@ impl Writable<&zkp_merkle_tree::Proof> for ProverChannel {
 }
 
 // OPT - Remove allocation of vectors
-impl Writable<&[FieldElement]> for ProverChannel {
-    fn write(&mut self, data: &[FieldElement]) {
-        let mut container = Vec::with_capacity(32 * data.len());
+impl<Field: StarkField, H: Hasher> Writable<&[Field]> for ProverChannel<H> {
+    fn write(&mut self, data: &[Field]) {
+        let mut container = Vec::with_capacity(Field::BYTES * data.len());
         for element in data {
-            for byte in &element.as_montgomery().to_bytes_be() {
-                container.push(*byte);
-            }
+            element.write_bytes(&mut container);
         }
         self.write(container.as_slice());
     }
 }
 
-impl Writable<&FieldElement> for ProverChannel {
-    fn write(&mut self, data: &FieldElement) {
-        self.write(&data.as_montgomery().to_bytes_be()[..]);
+impl<Field: StarkField, H: Hasher> Writable<&Field> for ProverChannel<H> {
+    fn write(&mut self, data: &Field) {
+        let mut container = Vec::with_capacity(Field::BYTES);
+        data.write_bytes(&mut container);
+        self.write(container.as_slice());
+    }
+}
+
+// Note -- This method of writing is distinct from the field element slice, and
+// is used in the decommitment when groups are decommited from the rows
+impl<Field: StarkField, H: Hasher> Writable<Vec<Field>> for ProverChannel<H> {
+    fn write(&mut self, data: Vec<Field>) {
+        for element in &data {
+            self.write(element)
+        }
     }
 }
 
-// Note -- This method of writing is distinct from the field element, and is
-// used in the decommitment when groups are decommited from the rows
-impl Writable<Vec<U256>> for ProverChannel {
+impl<H: Hasher> Writable<Vec<U256>> for ProverChannel<H> {
     fn write(&mut self, data: Vec<U256>) {
         for element in data {
             self.write(element)
@@ -274,43 +295,63 @@ impl Writable<Vec<U256>> for ProverChannel {
     }
 }
 
-impl Writable<U256> for ProverChannel {
+impl<H: Hasher> Writable<U256> for ProverChannel<H> {
     fn write(&mut self, data: U256) {
         self.write(&data.to_bytes_be()[..]);
     }
 }
 
-impl Replayable<Hash> for VerifierChannel {
+impl<H: Hasher> Replayable<Hash> for VerifierChannel<H> {
     fn replay(&mut self) -> Hash {
         let hash: [u8; 32] = self.read_32_bytes();
         Hash::new(hash)
     }
 }
 
-impl Replayable<U256> for VerifierChannel {
+impl<H: Hasher> Replayable<U256> for VerifierChannel<H> {
     fn replay(&mut self) -> U256 {
         let big_endian_bytes: [u8; 32] = self.read_32_bytes();
         U256::from_bytes_be(&big_endian_bytes)
     }
 }
 
-impl Replayable<FieldElement> for VerifierChannel {
-    fn replay(&mut self) -> FieldElement {
-        let montgomery_modulus: U256 = self.replay();
-        FieldElement::from_montgomery(montgomery_modulus)
+impl<Field: StarkField, H: Hasher> ReplayableSlice<Field> for VerifierChannel<H> {
+    fn replay_slice(&mut self, count: usize) -> Vec<Field> {
+        self.replay_fri_layer(count)
+    }
+}
+
+impl<Field: StarkField, H: Hasher> Replayable<Field> for VerifierChannel<H> {
+    fn replay(&mut self) -> Field {
+        let from = self.proof_index;
+        self.proof_index += Field::BYTES;
+        let bytes = &self.proof[from..self.proof_index];
+        self.coin.write(bytes);
+        decode_element(bytes, &mut self.invalid_encoding)
     }
 }
 
+// Out of range values are decoded as zero and flagged in `invalid_encoding`.
+// The verifier rejects the proof when the flag is set, otherwise a prover could
+// use the alternative encodings to influence the random values.
+fn decode_element<Field: StarkField>(bytes: &[u8], invalid_encoding: &mut bool) -> Field {
+    Field::from_bytes(bytes).unwrap_or_else(|| {
+        *invalid_encoding = true;
+        Field::zero()
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use zkp_macros_decl::{hex, u256h};
+    use zkp_primefield::{FieldElement, One, Zero};
 
     // Note - This test depends on the specific ordering of the subtests because of
     // the nature of the channel
     #[test]
     fn test_channel_get_random() {
-        let mut source = ProverChannel::default();
+        let mut source: ProverChannel = ProverChannel::default();
         source.initialize(hex!("0123456789abcded").to_vec().as_slice());
         let rand_bytes: [u8; 32] = source.get_random();
         assert_eq!(
@@ -335,7 +376,7 @@ mod tests {
     // the nature of the channel
     #[test]
     fn test_channel_write() {
-        let mut source = ProverChannel::default();
+        let mut source: ProverChannel = ProverChannel::default();
         source.initialize(&hex!("0123456789abcded"));
         let rand_bytes: [u8; 32] = source.get_random();
         source.write(&rand_bytes[..]);
@@ -374,7 +415,7 @@ mod tests {
 
     #[test]
     fn verifier_channel_test() {
-        let mut source = ProverChannel::default();
+        let mut source: ProverChannel = ProverChannel::default();
         source.initialize(&hex!("0123456789abcded"));
         let rand_bytes: [u8; 32] = source.get_random();
         source.write(&rand_bytes[..]);
@@ -399,7 +440,7 @@ mod tests {
         ];
         source.write(written_big_int_vec.clone());
 
-        let mut verifier = VerifierChannel::new(source.proof.clone());
+        let mut verifier: VerifierChannel = VerifierChannel::new(source.proof.clone());
         verifier.initialize(&hex!("0123456789abcded"));
         let bytes_test: [u8; 32] = verifier.read_32_bytes();
         assert_eq!(bytes_test, rand_bytes);
@@ -428,11 +469,31 @@ mod tests {
         let bit_int_vec_test: Vec<U256> = verifier.replay_many(2);
         assert_eq!(bit_int_vec_test, written_big_int_vec);
         assert_eq!(verifier.coin.digest, source.coin.digest);
+        assert!(!verifier.has_invalid_encoding());
+    }
+
+    #[test]
+    fn verifier_channel_invalid_encoding() {
+        let mut source: ProverChannel = ProverChannel::default();
+        source.write(&FieldElement::one());
+        source.write(&[0xff_u8; 32][..]);
+
+        let mut verifier: VerifierChannel = VerifierChannel::new(source.proof.clone());
+        let element: FieldElement = verifier.replay();
+        assert_eq!(element, FieldElement::one());
+        assert!(!verifier.has_invalid_encoding());
+        let element: FieldElement = verifier.replay();
+        assert_eq!(element, FieldElement::zero());
+        assert!(verifier.has_invalid_encoding());
+
+        let mut verifier: VerifierChannel = VerifierChannel::new(source.proof);
+        let _: Vec<FieldElement> = verifier.replay_fri_layer(2);
+        assert!(verifier.has_invalid_encoding());
     }
 
     #[test]
     fn test_challenge_seed_from_channel() {
-        let mut rand_source = ProverChannel::default();
+        let mut rand_source: ProverChannel = ProverChannel::default();
         rand_source.initialize(&hex!("0123456789abcded"));
         // Verify that reading challenges does not depend on public coin counter.
         // FIX: Make it depend on public coin counter.
@@ -0,0 +1,420 @@
+//! Prover checkpoints, see [`ProverOptions`](crate::ProverOptions).
+//!
+//! A checkpoint directory contains a file for each object the prover keeps
+//! between stages and a channel file for the end of each completed stage. The
+//! `manifest` names the last completed stage. All files are written to a
+//! temporary file first and then renamed, and the manifest is written last, so
+//! an interrupted prover leaves a consistent checkpoint behind.
+use crate::{
+    channel::{ProverChannel, PublicCoin},
+    constraints::Constraints,
+    field::StarkField,
+    fri::{FriLeaves, FriProver},
+    polynomial::DensePolynomial,
+    prover::{Error, LDETree, PolyLDE, Stage},
+};
+use std::{
+    convert::TryFrom,
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+    prelude::v1::*,
+};
+use zkp_hash::{Hash, Hasher};
+use zkp_merkle_tree::{Tree, VectorCommitment};
+use zkp_mmap_vec::MmapVec;
+use zkp_primefield::RefFieldLike;
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// Start of the manifest, including the version of the format.
+const MAGIC: &[u8; 8] = b"zkpckpt1";
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Self::Checkpoint(err.kind())
+    }
+}
+
+/// Identifies the statement and parameters a checkpoint belongs to.
+///
+/// The expressions and the trace shape are included even when the transcript
+/// seed omits them, which it does without
+/// [`strong_fiat_shamir`](Constraints::strong_fiat_shamir).
+pub(crate) fn fingerprint<H, Field>(constraints: &Constraints<Field>) -> Hash
+where
+    H: Hasher,
+    Field: StarkField,
+    for<'a> &'a Field: RefFieldLike<Field>,
+{
+    let mut hasher = H::default();
+    hasher.update(&constraints.transcript_seed());
+    let stage_columns = (0..constraints.num_stages())
+        .map(|stage| constraints.stage_columns(stage).len())
+        .collect::<Vec<_>>();
+    for value in [
+        Field::BYTES,
+        constraints.trace_nrows(),
+        constraints.trace_ncolumns(),
+        constraints.num_stages(),
+        constraints.blowup,
+        constraints.pow_bits,
+        constraints.num_queries,
+        usize::from(constraints.zero_knowledge),
+    ]
+    .iter()
+    .chain(&stage_columns)
+    .chain(&constraints.fri_layout)
+    {
+        hasher.update(&(*value as u64).to_be_bytes());
+    }
+    let mut expressions = Vec::new();
+    for expression in constraints.expressions() {
+        expression.write_bytes(&mut expressions);
+    }
+    hasher.update(&(expressions.len() as u64).to_be_bytes());
+    hasher.update(&expressions);
+    hasher.hash()
+}
+
+pub(crate) struct Checkpoint {
+    directory:   PathBuf,
+    fingerprint: Hash,
+    /// Directory for the large buffers read from the checkpoint.
+    scratch:     Option<PathBuf>,
+}
+
+impl Checkpoint {
+    /// Start a new checkpoint, discarding any previous one in `directory`.
+    pub(crate) fn create(
+        directory: &Path,
+        fingerprint: Hash,
+        scratch: Option<&Path>,
+    ) -> Result<Self> {
+        fs::create_dir_all(directory)?;
+        match fs::remove_file(directory.join("manifest")) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err.into()),
+            _ => {}
+        }
+        Ok(Self {
+            directory: directory.to_path_buf(),
+            fingerprint,
+            scratch: scratch.map(Path::to_path_buf),
+        })
+    }
+
+    /// Open the checkpoint in `directory` and return the last completed
+    /// stage, if any.
+    pub(crate) fn open(
+        directory: &Path,
+        fingerprint: Hash,
+        scratch: Option<&Path>,
+    ) -> Result<(Self, Option<Stage>)> {
+        let checkpoint = Self {
+            directory: directory.to_path_buf(),
+            fingerprint,
+            scratch: scratch.map(Path::to_path_buf),
+        };
+        let stage = match checkpoint.read("manifest", |reader| {
+            let mut magic = [0_u8; 8];
+            reader.read_exact(&mut magic)?;
+            if &magic != MAGIC || reader.hash()? != checkpoint.fingerprint {
+                return Err(Error::InvalidCheckpoint);
+            }
+            Stage::from_index(reader.usize()?).ok_or(Error::InvalidCheckpoint)
+        }) {
+            Ok(stage) => Some(stage),
+            Err(Error::Checkpoint(io::ErrorKind::NotFound)) => None,
+            Err(err) => return Err(err),
+        };
+        Ok((checkpoint, stage))
+    }
+
+    /// Record `stage` as completed with the channel at its end.
+    pub(crate) fn complete<H: Hasher>(
+        &self,
+        stage: Stage,
+        channel: &ProverChannel<H>,
+    ) -> Result<()> {
+        self.write(&format!("channel-{}", stage.index()), |writer| {
+            writer.0.write_all(&channel.coin.digest)?;
+            writer.u64(channel.coin.counter)?;
+            writer.bytes(&channel.proof)
+        })?;
+        self.write("manifest", |writer| {
+            writer.0.write_all(MAGIC)?;
+            writer.hash(&self.fingerprint)?;
+            writer.usize(stage.index())
+        })
+    }
+
+    /// The channel at the end of a completed `stage`.
+    pub(crate) fn channel<H: Hasher>(&self, stage: Stage) -> Result<ProverChannel<H>> {
+        self.read(&format!("channel-{}", stage.index()), |reader| {
+            let mut coin = PublicCoin::default();
+            reader.read_exact(&mut coin.digest)?;
+            coin.counter = reader.u64()?;
+            Ok(ProverChannel {
+                coin,
+                proof: reader.bytes()?,
+            })
+        })
+    }
+
+    pub(crate) fn write_fields<Field: StarkField>(
+        &self,
+        name: &str,
+        values: &[Field],
+    ) -> Result<()> {
+        self.write(name, |writer| writer.fields(values))
+    }
+
+    pub(crate) fn fields<Field: StarkField>(&self, name: &str) -> Result<MmapVec<Field>> {
+        self.read(name, Reader::fields)
+    }
+
+    pub(crate) fn write_polynomials<Field: StarkField>(
+        &self,
+        name: &str,
+        polynomials: &[DensePolynomial<Field>],
+    ) -> Result<()> {
+        self.write(name, |writer| {
+            writer.usize(polynomials.len())?;
+            for polynomial in polynomials {
+                writer.fields(polynomial.coefficients())?;
+            }
+            Ok(())
+        })
+    }
+
+    pub(crate) fn polynomials<Field: StarkField>(
+        &self,
+        name: &str,
+    ) -> Result<Vec<DensePolynomial<Field>>> {
+        self.read(name, |reader| {
+            (0..reader.usize()?)
+                .map(|_| Ok(DensePolynomial::from_mmap_vec(reader.fields()?)))
+                .collect()
+        })
+    }
+
+    pub(crate) fn write_tree<H, Field>(
+        &self,
+        name: &str,
+        tree: &LDETree<Field, H>,
+    ) -> Result<()>
+    where
+        H: Hasher,
+        Field: StarkField,
+    {
+        self.write(name, |writer| {
+            writer.usize(tree.leaves().0.len())?;
+            for column in &tree.leaves().0 {
+                writer.fields(column)?;
+            }
+            writer.hashes(tree.nodes())
+        })
+    }
+
+    pub(crate) fn tree<H, Field>(&self, name: &str) -> Result<LDETree<Field, H>>
+    where
+        H: Hasher,
+        Field: StarkField,
+    {
+        self.read(name, |reader| {
+            let columns = (0..reader.usize()?)
+                .map(|_| reader.fields())
+                .collect::<Result<Vec<_>>>()?;
+            let leaves = PolyLDE(columns);
+            if leaves.0.iter().any(|column| column.len() != leaves.len()) {
+                return Err(Error::InvalidCheckpoint);
+            }
+            Ok(Tree::from_parts(leaves, reader.hashes()?)?)
+        })
+    }
+
+    pub(crate) fn write_fri<H, Field>(&self, fri: &FriProver<Field, H>) -> Result<()>
+    where
+        H: Hasher,
+        Field: StarkField,
+        for<'a> &'a Field: RefFieldLike<Field>,
+    {
+        self.write("fri", |writer| {
+            writer.usize(fri.trees.len())?;
+            for tree in &fri.trees {
+                writer.usize(tree.leaves().coset_size)?;
+                writer.fields(&tree.leaves().layer)?;
+                writer.fields(&tree.leaves().salts)?;
+                writer.hashes(tree.nodes())?;
+            }
+            writer.fields(&fri.final_layer)
+        })
+    }
+
+    pub(crate) fn fri<H, Field>(&self) -> Result<FriProver<Field, H>>
+    where
+        H: Hasher,
+        Field: StarkField,
+        for<'a> &'a Field: RefFieldLike<Field>,
+    {
+        self.read("fri", |reader| {
+            let trees = (0..reader.usize()?)
+                .map(|_| {
+                    let coset_size = reader.usize()?;
+                    let layer = reader.fields()?;
+                    let salts = reader.fields()?.to_vec();
+                    if coset_size == 0 || layer.len() % coset_size != 0 {
+                        return Err(Error::InvalidCheckpoint);
+                    }
+                    let leaves = FriLeaves {
+                        coset_size,
+                        layer,
+                        salts,
+                    };
+                    Ok(Tree::from_parts(leaves, reader.hashes()?)?)
+                })
+                .collect::<Result<Vec<_>>>()?;
+            Ok(FriProver {
+                trees,
+                final_layer: reader.fields()?.to_vec(),
+            })
+        })
+    }
+
+    fn write<F>(&self, name: &str, contents: F) -> Result<()>
+    where
+        F: FnOnce(&mut Writer) -> Result<()>,
+    {
+        let path = self.directory.join(name);
+        let temporary = path.with_extension("tmp");
+        let mut writer = Writer(BufWriter::new(File::create(&temporary)?));
+        contents(&mut writer)?;
+        let file = writer
+            .0
+            .into_inner()
+            .map_err(io::IntoInnerError::into_error)?;
+        file.sync_all()?;
+        fs::rename(&temporary, &path)?;
+        Ok(())
+    }
+
+    fn read<'a, T, F>(&'a self, name: &str, contents: F) -> Result<T>
+    where
+        F: FnOnce(&mut Reader<'a>) -> Result<T>,
+    {
+        let file = File::open(self.directory.join(name))?;
+        contents(&mut Reader {
+            remaining: file.metadata()?.len(),
+            file:      BufReader::new(file),
+            scratch:   self.scratch.as_deref(),
+        })
+    }
+}
+
+struct Writer(BufWriter<File>);
+
+impl Writer {
+    fn u64(&mut self, value: u64) -> Result<()> {
+        Ok(self.0.write_all(&value.to_be_bytes())?)
+    }
+
+    fn usize(&mut self, value: usize) -> Result<()> {
+        self.u64(u64::try_from(value).expect("Value does not fit 64 bits"))
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) -> Result<()> {
+        self.usize(bytes.len())?;
+        Ok(self.0.write_all(bytes)?)
+    }
+
+    fn hash(&mut self, hash: &Hash) -> Result<()> {
+        Ok(self.0.write_all(hash.as_bytes())?)
+    }
+
+    fn hashes(&mut self, hashes: &[Hash]) -> Result<()> {
+        self.usize(hashes.len())?;
+        hashes.iter().try_for_each(|hash| self.hash(hash))
+    }
+
+    fn fields<Field: StarkField>(&mut self, values: &[Field]) -> Result<()> {
+        self.usize(values.len())?;
+        let mut bytes = Vec::with_capacity(Field::BYTES);
+        for value in values {
+            bytes.clear();
+            value.write_bytes(&mut bytes);
+            self.0.write_all(&bytes)?;
+        }
+        Ok(())
+    }
+}
+
+/// Reads a checkpoint file, allocating large buffers in the scratch directory.
+///
+/// Length prefixes are checked against the bytes left in the file before
+/// anything is allocated, so a corrupt checkpoint can not cause huge
+/// allocations.
+struct Reader<'a> {
+    file:      BufReader<File>,
+    /// Bytes left in the file.
+    remaining: u64,
+    scratch:   Option<&'a Path>,
+}
+
+impl Reader<'_> {
+    fn read_exact(&mut self, bytes: &mut [u8]) -> Result<()> {
+        self.file.read_exact(bytes)?;
+        self.remaining = self.remaining.saturating_sub(bytes.len() as u64);
+        Ok(())
+    }
+
+    fn u64(&mut self) -> Result<u64> {
+        let mut bytes = [0_u8; 8];
+        self.read_exact(&mut bytes)?;
+        Ok(u64::from_be_bytes(bytes))
+    }
+
+    fn usize(&mut self) -> Result<usize> {
+        usize::try_from(self.u64()?).map_err(|_| Error::InvalidCheckpoint)
+    }
+
+    /// Read a length prefix for elements of `size` bytes.
+    fn length(&mut self, size: usize) -> Result<usize> {
+        let length = self.usize()?;
+        match (length as u64).checked_mul(size as u64) {
+            Some(bytes) if bytes <= self.remaining => Ok(length),
+            _ => Err(Error::InvalidCheckpoint),
+        }
+    }
+
+    fn bytes(&mut self) -> Result<Vec<u8>> {
+        let mut bytes = vec![0_u8; self.length(1)?];
+        self.read_exact(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    fn hash(&mut self) -> Result<Hash> {
+        let mut bytes = [0_u8; 32];
+        self.read_exact(&mut bytes)?;
+        Ok(Hash::new(bytes))
+    }
+
+    fn hashes(&mut self) -> Result<MmapVec<Hash>> {
+        let length = self.length(32)?;
+        let mut hashes = MmapVec::with_capacity_scratch(length, self.scratch);
+        for _ in 0..length {
+            hashes.push(self.hash()?);
+        }
+        Ok(hashes)
+    }
+
+    fn fields<Field: StarkField>(&mut self) -> Result<MmapVec<Field>> {
+        let length = self.length(Field::BYTES)?;
+        let mut values = MmapVec::with_capacity_scratch(length, self.scratch);
+        let mut bytes = vec![0_u8; Field::BYTES];
+        for _ in 0..length {
+            self.read_exact(&mut bytes)?;
+            values.push(Field::from_bytes(&bytes).ok_or(Error::InvalidCheckpoint)?);
+        }
+        Ok(values)
+    }
+}
@@ -1,6 +1,6 @@
 use super::{Component, Mapped, PolynomialWriter};
 use crate::RationalExpression;
-use zkp_primefield::fft::permute_index;
+use zkp_primefield::{fft::permute_index, FieldElement};
 
 /// Note: `Fold::new(Fold::new(A, m), n) == Fold::new(A, m + n)`
 #[derive(Clone, PartialEq, Eq)]
@@ -54,6 +54,16 @@ where
         self.element.polynomial_size() * reduction
     }
 
+    fn num_challenges(&self) -> usize {
+        self.element.num_challenges()
+    }
+
+    // The second stage polynomials are folded separately.
+    fn num_stage_polynomials(&self) -> usize {
+        let reduction = 1 << self.folds;
+        ceil_div(self.element.num_stage_polynomials(), reduction)
+    }
+
     fn claim(&self, witness: &Self::Witness) -> Self::Claim {
         self.element.claim(witness)
     }
@@ -61,6 +71,8 @@ where
     fn constraints(&self, claim: &Self::Claim) -> Vec<RationalExpression> {
         use RationalExpression::*;
         let reduction = 1 << self.folds;
+        let element_polynomials = self.element.num_polynomials();
+        let polynomials = self.num_polynomials();
         self.element
             .constraints(claim)
             .into_iter()
@@ -68,11 +80,16 @@ where
                 expression.map(&|node| {
                     match node {
                         Trace(column, row_offset) => {
+                            let (start, column) = if column < element_polynomials {
+                                (0, column)
+                            } else {
+                                (polynomials, column - element_polynomials)
+                            };
                             let column_offset = permute_index(reduction, column % reduction);
                             // Reductions should be small enough
                             #[allow(clippy::cast_possible_wrap)]
                             Trace(
-                                column / reduction,
+                                start + column / reduction,
                                 (reduction as isize) * row_offset + (column_offset as isize),
                             )
                         }
@@ -98,6 +115,27 @@ where
         );
         self.element.trace(&mut trace, witness)
     }
+
+    fn stage_trace<P: PolynomialWriter>(
+        &self,
+        trace: &mut P,
+        challenges: &[FieldElement],
+        witness: &Self::Witness,
+    ) {
+        let reduction = 1 << self.folds;
+        let mut trace = Mapped::new(
+            trace,
+            self.element.num_stage_polynomials(),
+            self.element.polynomial_size(),
+            |polynomial, location| {
+                let polynomial_folded = permute_index(reduction, polynomial % reduction);
+                let polynomial = polynomial / reduction;
+                let location = location * reduction + polynomial_folded;
+                (polynomial, location)
+            },
+        );
+        self.element.stage_trace(&mut trace, challenges, witness)
+    }
 }
 
 fn ceil_div(numerator: usize, denominator: usize) -> usize {
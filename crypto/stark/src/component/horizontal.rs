@@ -1,5 +1,6 @@
 use super::{Component, Mapped, PolynomialWriter};
 use crate::RationalExpression;
+use zkp_primefield::FieldElement;
 
 #[derive(Clone, PartialEq, Eq)]
 #[cfg_attr(feature = "std", derive(Debug))]
@@ -49,21 +50,47 @@ where
         left
     }
 
+    fn num_challenges(&self) -> usize {
+        self.left().num_challenges() + self.right().num_challenges()
+    }
+
+    fn num_stage_polynomials(&self) -> usize {
+        self.left().num_stage_polynomials() + self.right().num_stage_polynomials()
+    }
+
     fn claim(&self, witness: &Self::Witness) -> Self::Claim {
         (self.left.claim(&witness.0), self.right.claim(&witness.1))
     }
 
     fn constraints(&self, claim: &Self::Claim) -> Vec<RationalExpression> {
         use RationalExpression::*;
+        // The first stage polynomials of left and right are followed by the
+        // second stage polynomials of left and right.
         let left_polynomials = self.left().num_polynomials();
+        let right_polynomials = self.right().num_polynomials();
+        let left_stage_polynomials = self.left().num_stage_polynomials();
+        let left_challenges = self.left().num_challenges();
         let left = self.left().constraints(&claim.0);
         let right = self.right().constraints(&claim.1);
+        let left = left
+            .into_iter()
+            .map(|expression| {
+                expression.map(&|node| {
+                    match node {
+                        Trace(i, j) if i >= left_polynomials => Trace(i + right_polynomials, j),
+                        other => other,
+                    }
+                })
+            })
+            .collect::<Vec<RationalExpression>>();
         let right = right
             .into_iter()
             .map(|expression| {
                 expression.map(&|node| {
                     match node {
-                        Trace(i, j) => Trace(i + left_polynomials, j),
+                        Trace(i, j) if i < right_polynomials => Trace(i + left_polynomials, j),
+                        Trace(i, j) => Trace(i + left_polynomials + left_stage_polynomials, j),
+                        Challenge(i) => Challenge(i + left_challenges),
                         other => other,
                     }
                 })
@@ -92,6 +119,32 @@ where
         );
         self.right.trace(&mut right_trace, &witness.1)
     }
+
+    fn stage_trace<P: PolynomialWriter>(
+        &self,
+        trace: &mut P,
+        challenges: &[FieldElement],
+        witness: &Self::Witness,
+    ) {
+        let (left_challenges, right_challenges) = challenges.split_at(self.left.num_challenges());
+        let mut left_trace = Mapped::new(
+            trace,
+            self.left.num_stage_polynomials(),
+            self.left.polynomial_size(),
+            |polynomial, location| (polynomial, location),
+        );
+        self.left
+            .stage_trace(&mut left_trace, left_challenges, &witness.0);
+        let left_polys = self.left.num_stage_polynomials();
+        let mut right_trace = Mapped::new(
+            trace,
+            self.right.num_stage_polynomials(),
+            self.right.polynomial_size(),
+            |polynomial, location| (polynomial + left_polys, location),
+        );
+        self.right
+            .stage_trace(&mut right_trace, right_challenges, &witness.1)
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,443 @@
+use super::{Component, PolynomialWriter};
+use crate::{DensePolynomial, RationalExpression, TraceTable};
+use std::{collections::HashMap, prelude::v1::*};
+use zkp_primefield::{invert_batch, FieldElement, Pow, Root, Zero};
+
+/// The table values are looked up in
+#[derive(Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub enum Table {
+    /// A fixed table known to the verifier.
+    ///
+    /// It can have at most as many values as the polynomial size. Shorter
+    /// tables are padded by repeating the first value. The table is copied
+    /// into an added polynomial constrained to the fixed values, this keeps
+    /// the lookup constraints within the degree bounds.
+    Fixed(Vec<FieldElement>),
+    /// A table committed to as one of the polynomials of the element.
+    Column(usize),
+}
+
+// False positives on the Latex math.
+#[allow(clippy::doc_markdown)]
+/// Lookup argument for values of a component
+///
+/// Proves that on every row the `lookups` expressions evaluate to a value in
+/// the `table`. The expressions can refer to the polynomials of the element
+/// and to `X`. This uses the logarithmic derivative (LogUp) argument: a
+/// polynomial $m$ with the multiplicity of each table row is committed to
+/// in the first stage and with a challenge $\gamma$ the second stage
+/// polynomial with the running sum
+///
+/// $$
+/// s_0 = 0 \quad s_{i+1} = s_i + \sum_k \frac{1}{\gamma - v_{k,i}} -
+/// \frac{m_i}{\gamma - t_i}
+/// $$
+///
+/// is added. The sum over all rows is constrained to be zero, which with
+/// overwhelming probability only holds if all the looked up values $v_k$
+/// are in the table $t$.
+///
+/// The multiplicity polynomial and a fixed table polynomial follow the
+/// polynomials of the element, the challenge and the running sum polynomial
+/// follow those of the element.
+/// The constraint degree grows with the number of lookups, so only a few
+/// should be combined in one argument.
+#[derive(Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct LookupArgument<Element>
+where
+    Element: Component,
+{
+    element: Element,
+    table:   Table,
+    lookups: Vec<RationalExpression>,
+}
+
+impl<Element> LookupArgument<Element>
+where
+    Element: Component,
+{
+    pub fn new(element: Element, table: Table, lookups: &[RationalExpression]) -> Self {
+        assert!(!lookups.is_empty());
+        match &table {
+            Table::Fixed(values) => {
+                assert!(!values.is_empty());
+                assert!(values.len() <= element.polynomial_size());
+            }
+            Table::Column(column) => assert!(*column < element.num_polynomials()),
+        }
+        assert!(lookups.iter().all(|lookup| {
+            lookup
+                .trace_arguments()
+                .iter()
+                .all(|&(column, _)| column < element.num_polynomials())
+        }));
+        LookupArgument {
+            element,
+            table,
+            lookups: lookups.to_vec(),
+        }
+    }
+
+    pub fn element(&self) -> &Element {
+        &self.element
+    }
+
+    pub fn table(&self) -> &Table {
+        &self.table
+    }
+
+    pub fn lookups(&self) -> &[RationalExpression] {
+        &self.lookups
+    }
+
+    /// Index of the multiplicity polynomial
+    pub fn multiplicity_polynomial(&self) -> usize {
+        self.element.num_polynomials()
+    }
+
+    /// Index of the table polynomial
+    pub fn table_polynomial(&self) -> usize {
+        match &self.table {
+            Table::Fixed(_) => self.element.num_polynomials() + 1,
+            Table::Column(column) => *column,
+        }
+    }
+
+    /// Index of the running sum polynomial
+    pub fn sum_polynomial(&self) -> usize {
+        self.num_polynomials() + self.element.num_stage_polynomials()
+    }
+
+    /// Table values on each row of the element trace
+    fn table_values(&self, trace: &TraceTable) -> Vec<FieldElement> {
+        match &self.table {
+            Table::Fixed(values) => {
+                (0..self.polynomial_size())
+                    .map(|row| values.get(row).unwrap_or(&values[0]).clone())
+                    .collect()
+            }
+            Table::Column(column) => {
+                (0..self.polynomial_size())
+                    .map(|row| trace[(row, *column)].clone())
+                    .collect()
+            }
+        }
+    }
+
+    /// Values of each lookup expression on each row of the element trace
+    fn lookup_values(&self, trace: &TraceTable) -> Vec<Vec<FieldElement>> {
+        let size = self.polynomial_size();
+        let omega = FieldElement::root(size).expect("Size not a power of two.");
+        (0..size)
+            .map(|row| {
+                let x = omega.pow(row);
+                // Sizes are small enough
+                #[allow(clippy::cast_possible_wrap, clippy::cast_sign_loss)]
+                let trace_fn = |column: usize, row_offset: isize| {
+                    let row = (row as isize + row_offset).rem_euclid(size as isize) as usize;
+                    trace[(row, column)].clone()
+                };
+                self.lookups
+                    .iter()
+                    .map(|lookup| lookup.evaluate(&x, &trace_fn))
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+impl<Element> Component for LookupArgument<Element>
+where
+    Element: Component,
+{
+    type Claim = Element::Claim;
+    type Witness = Element::Witness;
+
+    fn num_polynomials(&self) -> usize {
+        match &self.table {
+            Table::Fixed(_) => self.element.num_polynomials() + 2,
+            Table::Column(_) => self.element.num_polynomials() + 1,
+        }
+    }
+
+    fn polynomial_size(&self) -> usize {
+        self.element.polynomial_size()
+    }
+
+    fn num_challenges(&self) -> usize {
+        self.element.num_challenges() + 1
+    }
+
+    fn num_stage_polynomials(&self) -> usize {
+        self.element.num_stage_polynomials() + 1
+    }
+
+    fn claim(&self, witness: &Self::Witness) -> Self::Claim {
+        self.element.claim(witness)
+    }
+
+    fn constraints(&self, claim: &Self::Claim) -> Vec<RationalExpression> {
+        use RationalExpression::*;
+        let size = self.polynomial_size();
+        let omega = FieldElement::root(size).expect("Size not a power of two.");
+        let last_row = Constant(omega.pow(size - 1));
+        let gamma = Challenge(self.element.num_challenges());
+        let m = Trace(self.multiplicity_polynomial(), 0);
+        let s = self.sum_polynomial();
+
+        // Multiply out the fractions to a common denominator
+        let lookups = self
+            .lookups
+            .iter()
+            .map(|lookup| gamma.clone() - lookup.clone())
+            .collect::<Vec<_>>();
+        let lookups_product = |skip: Option<usize>| {
+            lookups
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| Some(*i) != skip)
+                .fold(RationalExpression::from(1), |product, (_, factor)| {
+                    product * factor.clone()
+                })
+        };
+        let table = gamma - Trace(self.table_polynomial(), 0);
+        let denominator = lookups_product(None) * table.clone();
+        let numerator = (0..lookups.len())
+            .map(|i| lookups_product(Some(i)) * table.clone())
+            .fold(RationalExpression::from(0), |sum, term| sum + term)
+            - m * lookups_product(None);
+
+        // The element stage polynomials are shifted by the added polynomials
+        let element_polynomials = self.element.num_polynomials();
+        let added_polynomials = self.num_polynomials() - element_polynomials;
+        let mut constraints = self
+            .element
+            .constraints(claim)
+            .into_iter()
+            .map(|expression| {
+                expression.map(&|node| {
+                    match node {
+                        Trace(i, j) if i >= element_polynomials => Trace(i + added_polynomials, j),
+                        other => other,
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        // The fixed table values
+        if let Table::Fixed(values) = &self.table {
+            let mut column = TraceTable::new(size, 1);
+            for row in 0..size {
+                column[(row, 0)] = values.get(row).unwrap_or(&values[0]).clone();
+            }
+            let polynomial: DensePolynomial = column.interpolate().pop().unwrap();
+            constraints.push(
+                (Trace(self.table_polynomial(), 0) - Polynomial(polynomial, Box::new(X)))
+                    / (X.pow(size) - 1),
+            );
+        }
+        // s[0] = 0
+        constraints.push(Trace(s, 0) / (X - 1));
+        // (s[i + 1] - s[i]) * denominator[i] = numerator[i]
+        constraints.push(
+            ((Trace(s, 1) - Trace(s, 0)) * denominator.clone() - numerator.clone())
+                * (X - last_row.clone())
+                / (X.pow(size) - 1),
+        );
+        // s[n - 1] * denominator[n - 1] + numerator[n - 1] = 0
+        constraints.push((Trace(s, 0) * denominator + numerator) / (X - last_row));
+        constraints
+    }
+
+    fn trace<P: PolynomialWriter>(&self, trace: &mut P, witness: &Self::Witness) {
+        self.element.trace(trace, witness);
+
+        // OPT: The element trace is recomputed here.
+        let element_trace = self.element.trace_table(witness);
+        let table = self.table_values(&element_trace);
+        let mut rows = HashMap::new();
+        for (row, value) in table.iter().enumerate() {
+            let _ = rows.entry(value).or_insert(row);
+        }
+        // Values not in the table are not counted, the constraints will fail.
+        let mut multiplicities = vec![0_usize; table.len()];
+        for value in self.lookup_values(&element_trace).iter().flatten() {
+            if let Some(&row) = rows.get(value) {
+                multiplicities[row] += 1;
+            }
+        }
+        let polynomial = self.multiplicity_polynomial();
+        for (row, multiplicity) in multiplicities.into_iter().enumerate() {
+            trace.write(polynomial, row, multiplicity.into());
+        }
+        if let Table::Fixed(_) = &self.table {
+            let polynomial = self.table_polynomial();
+            for (row, value) in table.into_iter().enumerate() {
+                trace.write(polynomial, row, value);
+            }
+        }
+    }
+
+    fn stage_trace<P: PolynomialWriter>(
+        &self,
+        trace: &mut P,
+        challenges: &[FieldElement],
+        witness: &Self::Witness,
+    ) {
+        self.element.stage_trace(trace, challenges, witness);
+
+        // OPT: The trace is recomputed here.
+        let full_trace = self.trace_table(witness);
+        let gamma = &challenges[self.element.num_challenges()];
+        let table = self.table_values(&full_trace);
+        let lookups = self.lookup_values(&full_trace);
+        let size = self.polynomial_size();
+        let denominators = lookups
+            .iter()
+            .zip(table.iter())
+            .flat_map(|(values, table)| values.iter().chain(Some(table)))
+            .map(|value| gamma - value)
+            .collect::<Vec<_>>();
+        let inverses = invert_batch(&denominators);
+
+        let multiplicity = self.multiplicity_polynomial();
+        let polynomial = self.element.num_stage_polynomials();
+        let mut sum = FieldElement::zero();
+        for (row, inverses) in inverses.chunks(self.lookups.len() + 1).enumerate() {
+            trace.write(polynomial, row, sum.clone());
+            let (table, lookups) = inverses.split_last().unwrap();
+            sum += lookups
+                .iter()
+                .fold(FieldElement::zero(), |sum, inverse| sum + inverse);
+            sum -= &full_trace[(row, multiplicity)] * table;
+        }
+        debug_assert_eq!(inverses.len(), size * (self.lookups.len() + 1));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        super::{permutation_argument::tests::Columns, test::Test, Fold, Horizontal, Vertical},
+        *,
+    };
+    use proptest::{collection::vec, prelude::*};
+    use zkp_primefield::One;
+
+    /// A table column and a column with values taken from it.
+    fn looked_up_columns() -> impl Strategy<Value = Vec<Vec<FieldElement>>> {
+        (0_usize..6)
+            .prop_flat_map(|log_rows| {
+                let rows = 1 << log_rows;
+                (vec(any::<FieldElement>(), rows), vec(0..rows, rows))
+            })
+            .prop_map(|(table, indices)| {
+                let values = indices.iter().map(|&i| table[i].clone()).collect();
+                vec![table, values]
+            })
+    }
+
+    /// Two columns with values in `0..16`.
+    fn nibble_columns(rows: usize) -> impl Strategy<Value = Vec<Vec<FieldElement>>> {
+        vec(vec((0_u64..16).prop_map(FieldElement::from), rows), 2)
+    }
+
+    fn nibbles() -> Table {
+        Table::Fixed((0_u64..16).map(FieldElement::from).collect())
+    }
+
+    #[test]
+    fn test_check() {
+        use RationalExpression::*;
+        proptest!(|(witness in looked_up_columns())| {
+            let rows = witness[0].len();
+            let component = LookupArgument::new(Columns(2, rows), Table::Column(0), &[Trace(1, 0)]);
+            prop_assert_eq!(component.check(&witness), Ok(()));
+        });
+    }
+
+    #[test]
+    fn test_check_fails() {
+        use RationalExpression::*;
+        proptest!(|(mut witness in looked_up_columns(), row: usize)| {
+            let rows = witness[0].len();
+            witness[1][row % rows] = witness[0].iter().fold(FieldElement::one(), |sum, value| sum + value);
+            prop_assume!(!witness[0].contains(&witness[1][row % rows]));
+            let component = LookupArgument::new(Columns(2, rows), Table::Column(0), &[Trace(1, 0)]);
+            prop_assert!(component.check(&witness).is_err());
+        });
+    }
+
+    #[test]
+    fn test_check_fixed() {
+        use RationalExpression::*;
+        proptest!(|(mut witness in nibble_columns(32), row in 0_usize..32)| {
+            let component = LookupArgument::new(Columns(2, 32), nibbles(), &[Trace(0, 0), Trace(1, 0)]);
+            prop_assert_eq!(component.check(&witness), Ok(()));
+
+            witness[1][row] = 16.into();
+            prop_assert!(component.check(&witness).is_err());
+        });
+    }
+
+    #[test]
+    fn test_check_compose() {
+        use RationalExpression::*;
+        let config = ProptestConfig::with_cases(20);
+        proptest!(config, |(
+            witness in looked_up_columns(),
+            seed: FieldElement,
+            test_witness: (FieldElement, FieldElement),
+        )| {
+            let rows = witness[0].len();
+            let argument = LookupArgument::new(Columns(2, rows), Table::Column(0), &[Trace(1, 0)]);
+
+            // Next to another component and next to itself
+            let component = Horizontal::new(Test::new(rows, 2, &seed), argument.clone());
+            let combined = (test_witness, witness.clone());
+            prop_assert_eq!(component.check(&combined), Ok(()));
+            let component = Horizontal::new(argument.clone(), argument.clone());
+            let combined = (witness.clone(), witness.clone());
+            prop_assert_eq!(component.check(&combined), Ok(()));
+
+            // Repeated, each repetition is a separate lookup
+            let component = Vertical::new(argument.clone(), 2);
+            let repeated = vec![witness.clone(), witness.clone()];
+            prop_assert_eq!(component.check(&repeated), Ok(()));
+
+            // Folded, the sum polynomial is folded separately
+            let component = Fold::new(argument, 1);
+            prop_assert_eq!(component.check(&witness), Ok(()));
+        });
+    }
+
+    #[test]
+    fn test_fixed_proof_verify() {
+        use RationalExpression::*;
+        // Two nibble columns with a sum that is also a nibble
+        let rows = 16;
+        let witness = vec![
+            (0..rows)
+                .map(|i| FieldElement::from(i / 4))
+                .collect::<Vec<_>>(),
+            (0..rows)
+                .map(|i| FieldElement::from(15 - i))
+                .collect::<Vec<_>>(),
+        ];
+        let component = LookupArgument::new(Columns(2, rows), nibbles(), &[
+            Trace(0, 0),
+            Trace(1, 0),
+            Trace(0, 0) + Trace(1, 0),
+        ]);
+        assert_eq!(component.check(&witness), Ok(()));
+        let proof = component.prove(&witness).unwrap();
+        assert_eq!(component.verify(&(), &proof), Ok(()));
+
+        // The sum is no longer a nibble
+        let mut witness = witness;
+        witness[0][0] = 1.into();
+        assert!(component.check(&witness).is_err());
+        assert!(component.prove(&witness).is_err());
+    }
+}
@@ -1,14 +1,18 @@
 mod empty;
 mod fold;
 mod horizontal;
+mod lookup_argument;
 mod mapped;
+mod permutation;
+mod permutation_argument;
+mod range_check;
 mod test;
 mod vertical;
 
 use crate::{
-    constraint_check::check_constraints,
+    constraint_check::{check_constraints, complete_stages},
     proof::Proof,
-    prover::prove,
+    prover::prove_staged,
     verifier::{verify, Error as VerifierError},
     Constraints, ProverError, RationalExpression, TraceTable,
 };
@@ -18,7 +22,11 @@ use zkp_primefield::{FieldElement, Root};
 pub use empty::Empty;
 pub use fold::Fold;
 pub use horizontal::Horizontal;
+pub use lookup_argument::{LookupArgument, Table};
 pub use mapped::Mapped;
+pub use permutation::{PoseidonPermutation, RescuePermutation};
+pub use permutation_argument::PermutationArgument;
+pub use range_check::{RangeCheck, RangeCheckStrategy};
 pub use test::Test;
 pub use vertical::Vertical;
 
@@ -62,12 +70,36 @@ pub trait Component {
     /// can be set.
     fn polynomial_size(&self) -> usize;
 
+    /// Number of challenges drawn before the second trace stage.
+    ///
+    /// The constraints refer to them as `Challenge(i)`.
+    fn num_challenges(&self) -> usize {
+        0
+    }
+
+    /// Number of polynomials in the second trace stage.
+    ///
+    /// These are committed to after the first stage and the constraints refer
+    /// to them by the indices following the first stage polynomials.
+    fn num_stage_polynomials(&self) -> usize {
+        0
+    }
+
     fn constraints(&self, claim: &Self::Claim) -> Vec<RationalExpression>;
 
     // TODO: add claim_polynomials function here.
 
     fn trace<P: PolynomialWriter>(&self, trace: &mut P, witness: &Self::Witness);
 
+    /// Write the second stage polynomials, computed using the challenges.
+    fn stage_trace<P: PolynomialWriter>(
+        &self,
+        _trace: &mut P,
+        _challenges: &[FieldElement],
+        _witness: &Self::Witness,
+    ) {
+    }
+
     fn trace_generator(&self) -> RationalExpression {
         FieldElement::root(self.polynomial_size())
             .expect("num_polynomials not power of 2.")
@@ -85,38 +117,110 @@ pub trait Component {
         trace_table
     }
 
-    fn prove(&self, witness: &Self::Witness) -> Result<Proof, ProverError> {
-        let polynomials = self.num_polynomials();
+    /// Construct the trace table of the second stage
+    fn stage_trace_table(
+        &self,
+        challenges: &[FieldElement],
+        witness: &Self::Witness,
+    ) -> TraceTable {
+        let polynomials = self.num_stage_polynomials();
         let size = self.polynomial_size();
+        let mut trace_table = TraceTable::new(size, polynomials);
+        self.stage_trace(&mut trace_table, challenges, witness);
+        trace_table
+    }
+
+    fn prove(&self, witness: &Self::Witness) -> Result<Proof, ProverError> {
         let claim = self.claim(witness);
-        let channel_seed = Vec::new();
-        let expressions = self.constraints(&claim);
+        let constraints = constraint_system(self, &claim);
         let trace = self.trace_table(witness);
-        let constraints =
-            Constraints::from_expressions((size, polynomials), channel_seed, expressions).unwrap();
-        prove(&constraints, &trace)
+        prove_staged(&constraints, &trace, |_, challenges, _| {
+            Some(self.stage_trace_table(challenges, witness))
+        })
     }
 
     fn verify(&self, claim: &Self::Claim, proof: &Proof) -> Result<(), VerifierError> {
-        let polynomials = self.num_polynomials();
-        let size = self.polynomial_size();
-        let channel_seed = Vec::new();
-        let expressions = self.constraints(claim);
-        let constraints =
-            Constraints::from_expressions((size, polynomials), channel_seed, expressions).unwrap();
+        let constraints = constraint_system(self, claim);
         verify(&constraints, proof)
     }
 
     fn check(&self, witness: &Self::Witness) -> Result<(), (usize, usize)> {
-        let polynomials = self.num_polynomials();
-        let size = self.polynomial_size();
         let claim = self.claim(witness);
-        let channel_seed = Vec::new();
-        let expressions = self.constraints(&claim);
-        // TODO: Error handling
-        let constraints =
-            Constraints::from_expressions((size, polynomials), channel_seed, expressions).unwrap();
-        let trace = self.trace_table(witness);
+        let mut constraints = constraint_system(self, &claim);
+        let trace = complete_stages(
+            &mut constraints,
+            self.trace_table(witness),
+            |_, challenges, _| Some(self.stage_trace_table(challenges, witness)),
+        )
+        .expect("Component stage tables have the stage dimensions");
         check_constraints(&constraints, &trace)
     }
 }
+
+/// Constraints for a component, with a second trace stage if it has one.
+fn constraint_system<C: Component + ?Sized>(component: &C, claim: &C::Claim) -> Constraints {
+    let polynomials = component.num_polynomials();
+    let size = component.polynomial_size();
+    let channel_seed = Vec::new();
+    let expressions = component.constraints(claim);
+    // TODO: Error handling
+    let mut constraints =
+        Constraints::from_expressions((size, polynomials), channel_seed, expressions).unwrap();
+    if component.num_challenges() > 0 || component.num_stage_polynomials() > 0 {
+        constraints.add_stage(
+            component.num_challenges(),
+            component.num_stage_polynomials(),
+        );
+    }
+    constraints
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zkp_macros_decl::air;
+
+    fn round_constants() -> Vec<FieldElement> {
+        (1..=4).map(FieldElement::from).collect()
+    }
+
+    air! {
+        /// Recurrence with a periodic coefficient
+        struct Recurrence {
+            a, b,
+        }
+        periodic {
+            k = &round_constants();
+        }
+        transition {
+            a = b;
+            b = k * a - b * b + 3;
+        }
+    }
+
+    #[test]
+    fn test_air_macro() {
+        let component = Recurrence::new(32);
+        let witness = RecurrenceRow {
+            a: 1.into(),
+            b: 2.into(),
+        };
+        let rows = component.rows(&witness);
+        assert_eq!(Recurrence::COLUMNS, ["a", "b"]);
+        assert_eq!(rows[1], component.step(0, &witness));
+        assert_eq!(rows[6].a, rows[5].b);
+        assert_eq!(
+            rows[6].b,
+            FieldElement::from(2) * &rows[5].a - &rows[5].b * &rows[5].b + FieldElement::from(3)
+        );
+
+        let claim = component.claim(&witness);
+        assert_eq!(claim, (witness.clone(), rows[31].clone()));
+        assert_eq!(component.check(&witness), Ok(()));
+        let proof = component.prove(&witness).unwrap();
+        assert_eq!(component.verify(&claim, &proof), Ok(()));
+
+        let wrong_claim = (witness.clone(), rows[30].clone());
+        assert!(component.verify(&wrong_claim, &proof).is_err());
+    }
+}
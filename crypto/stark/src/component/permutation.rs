@@ -0,0 +1,391 @@
+use super::{Component, PolynomialWriter};
+use crate::{DensePolynomial, RationalExpression, TraceTable};
+use zkp_hash::{Permutation, Poseidon, RescuePrime};
+use zkp_primefield::{FieldElement, One, Pow, Root, Zero};
+
+/// Constraints fixing `count` columns starting at `column` to per-round
+/// values.
+///
+/// Row `round` of column `column + i` is `values(round)[i]`, rows past the
+/// last round are zero. The values are interpolated over the trace domain, so
+/// the constraints have the degree of the trace.
+fn fixed_columns<'a>(
+    size: usize,
+    rounds: usize,
+    column: usize,
+    count: usize,
+    values: impl Fn(usize) -> &'a [FieldElement],
+) -> Vec<RationalExpression> {
+    use RationalExpression::*;
+    let mut table = TraceTable::new(size, count);
+    for round in 0..rounds {
+        for (i, value) in values(round).iter().enumerate() {
+            table[(round, i)] = value.clone();
+        }
+    }
+    table
+        .interpolate()
+        .into_iter()
+        .enumerate()
+        .map(|(i, polynomial): (usize, DensePolynomial)| {
+            (Trace(column + i, 0) - Polynomial(polynomial, Box::new(X))) / (X.pow(size) - 1)
+        })
+        .collect()
+}
+
+/// Write per-round values to `count` columns starting at `column`, see
+/// [`fixed_columns`].
+fn write_fixed_columns<'a, P: PolynomialWriter>(
+    trace: &mut P,
+    rounds: usize,
+    column: usize,
+    count: usize,
+    values: impl Fn(usize) -> &'a [FieldElement],
+) {
+    for row in 0..trace.polynomial_size() {
+        for i in 0..count {
+            let value = if row < rounds {
+                values(row)[i].clone()
+            } else {
+                FieldElement::zero()
+            };
+            trace.write(column + i, row, value);
+        }
+    }
+}
+
+/// Row-major matrix-vector product of expressions.
+fn mix(matrix: &[FieldElement], state: &[RationalExpression]) -> Vec<RationalExpression> {
+    use RationalExpression::*;
+    matrix
+        .chunks_exact(state.len())
+        .map(|row| {
+            row.iter()
+                .zip(state.iter())
+                .map(|(a, b)| Constant(a.clone()) * b.clone())
+                .sum()
+        })
+        .collect()
+}
+
+/// Proof of a Poseidon permutation
+///
+/// The witness is the input state and the claim is the input and output
+/// state. The trace has a row per round containing the state before that
+/// round and a final row with the output. Fixed columns hold a one in full
+/// rounds and a zero in partial rounds, the round constants and a one in rows
+/// with a transition to the next round.
+#[derive(Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct PoseidonPermutation {
+    parameters: Poseidon,
+}
+
+impl PoseidonPermutation {
+    pub fn new(parameters: Poseidon) -> Self {
+        Self { parameters }
+    }
+
+    /// One in full rounds and zero in partial rounds.
+    fn full_rounds(&self) -> Vec<FieldElement> {
+        (0..self.parameters.rounds())
+            .map(|round| FieldElement::from(u64::from(self.parameters.is_full_round(round))))
+            .collect()
+    }
+}
+
+impl Component for PoseidonPermutation {
+    type Claim = (Vec<FieldElement>, Vec<FieldElement>);
+    type Witness = Vec<FieldElement>;
+
+    fn num_polynomials(&self) -> usize {
+        2 * self.parameters.width() + 2
+    }
+
+    fn polynomial_size(&self) -> usize {
+        (self.parameters.rounds() + 1).next_power_of_two()
+    }
+
+    fn claim(&self, witness: &Self::Witness) -> Self::Claim {
+        let mut state = witness.clone();
+        self.parameters.permute(&mut state);
+        (witness.clone(), state)
+    }
+
+    fn constraints(&self, claim: &Self::Claim) -> Vec<RationalExpression> {
+        use RationalExpression::*;
+        let (input, output) = claim;
+        assert_eq!(input.len(), self.parameters.width());
+        assert_eq!(output.len(), self.parameters.width());
+        let width = self.parameters.width();
+        let rounds = self.parameters.rounds();
+        let size = self.polynomial_size();
+        let omega = FieldElement::root(size).expect("Size not a power of two.");
+        let full_rounds = self.full_rounds();
+        let one = [FieldElement::one()];
+        let (selector, constants, transition) = (width, width + 1, 2 * width + 1);
+
+        let mut constraints = Vec::new();
+        // The fixed columns match the round types, constants and transitions
+        constraints.extend(fixed_columns(size, rounds, selector, 1, |round| {
+            &full_rounds[round..=round]
+        }));
+        constraints.extend(fixed_columns(size, rounds, constants, width, |round| {
+            self.parameters.round_constants(round)
+        }));
+        constraints.extend(fixed_columns(size, rounds, transition, 1, |_| &one));
+        // S-box on the first element in all rounds, on the others in full rounds
+        let sbox = (0..width)
+            .map(|i| {
+                let x = Trace(i, 0) + Trace(constants + i, 0);
+                if i == 0 {
+                    x.pow(3)
+                } else {
+                    Trace(selector, 0) * x.clone().pow(3)
+                        + (Constant(FieldElement::one()) - Trace(selector, 0)) * x
+                }
+            })
+            .collect::<Vec<_>>();
+        // The next row is the MDS mix of the S-box output
+        for (i, mixed) in mix(self.parameters.mds(), &sbox).into_iter().enumerate() {
+            constraints.push((Trace(i, 1) - mixed) * Trace(transition, 0) / (X.pow(size) - 1));
+        }
+        // The first row contains the input
+        for (i, value) in input.iter().enumerate() {
+            constraints.push((Trace(i, 0) - Constant(value.clone())) / (X - 1));
+        }
+        // The row after the last round contains the output
+        for (i, value) in output.iter().enumerate() {
+            constraints.push((Trace(i, 0) - Constant(value.clone())) / (X - omega.pow(rounds)));
+        }
+        constraints
+    }
+
+    fn trace<P: PolynomialWriter>(&self, trace: &mut P, witness: &Self::Witness) {
+        debug_assert_eq!(trace.num_polynomials(), self.num_polynomials());
+        debug_assert_eq!(trace.polynomial_size(), self.polynomial_size());
+        let width = self.parameters.width();
+        let rounds = self.parameters.rounds();
+        let full_rounds = self.full_rounds();
+        let one = [FieldElement::one()];
+        write_fixed_columns(trace, rounds, width, 1, |round| &full_rounds[round..=round]);
+        write_fixed_columns(trace, rounds, width + 1, width, |round| {
+            self.parameters.round_constants(round)
+        });
+        write_fixed_columns(trace, rounds, 2 * width + 1, 1, |_| &one);
+        let mut state = witness.clone();
+        for row in 0..self.polynomial_size() {
+            for (i, value) in state.iter().enumerate() {
+                trace.write(i, row, value.clone());
+            }
+            if row < rounds {
+                self.parameters.round(row, &mut state);
+            }
+        }
+    }
+}
+
+/// Proof of a Rescue-Prime permutation
+///
+/// The witness is the input state and the claim is the input and output
+/// state. The trace has a row per round containing the state before that
+/// round and the intermediate state after the inverse S-box, followed by a
+/// final row with the output. The inverse S-box is constrained by cubing the
+/// intermediate state. Fixed columns hold the constants of both halves of the
+/// round and a one in rows with a transition to the next round.
+#[derive(Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct RescuePermutation {
+    parameters: RescuePrime,
+}
+
+impl RescuePermutation {
+    pub fn new(parameters: RescuePrime) -> Self {
+        Self { parameters }
+    }
+}
+
+impl Component for RescuePermutation {
+    type Claim = (Vec<FieldElement>, Vec<FieldElement>);
+    type Witness = Vec<FieldElement>;
+
+    fn num_polynomials(&self) -> usize {
+        4 * self.parameters.width() + 1
+    }
+
+    fn polynomial_size(&self) -> usize {
+        (self.parameters.rounds() + 1).next_power_of_two()
+    }
+
+    fn claim(&self, witness: &Self::Witness) -> Self::Claim {
+        let mut state = witness.clone();
+        self.parameters.permute(&mut state);
+        (witness.clone(), state)
+    }
+
+    fn constraints(&self, claim: &Self::Claim) -> Vec<RationalExpression> {
+        use RationalExpression::*;
+        let (input, output) = claim;
+        assert_eq!(input.len(), self.parameters.width());
+        assert_eq!(output.len(), self.parameters.width());
+        let width = self.parameters.width();
+        let rounds = self.parameters.rounds();
+        let size = self.polynomial_size();
+        let omega = FieldElement::root(size).expect("Size not a power of two.");
+        let one = [FieldElement::one()];
+        let (first, second, transition) = (2 * width, 3 * width, 4 * width);
+        let cubes = (0..width).map(|i| Trace(i, 0).pow(3)).collect::<Vec<_>>();
+        let intermediate = (0..width).map(|i| Trace(width + i, 0)).collect::<Vec<_>>();
+
+        let mut constraints = Vec::new();
+        // The fixed columns match the round constants and transitions
+        constraints.extend(fixed_columns(size, rounds, first, width, |round| {
+            self.parameters.first_constants(round)
+        }));
+        constraints.extend(fixed_columns(size, rounds, second, width, |round| {
+            self.parameters.second_constants(round)
+        }));
+        constraints.extend(fixed_columns(size, rounds, transition, 1, |_| &one));
+        // The cube of the intermediate state is the first half of the round
+        for (i, mixed) in mix(self.parameters.mds(), &cubes).into_iter().enumerate() {
+            constraints.push(
+                (Trace(width + i, 0).pow(3) - mixed - Trace(first + i, 0)) * Trace(transition, 0)
+                    / (X.pow(size) - 1),
+            );
+        }
+        // The next row is the second half of the round
+        for (i, mixed) in mix(self.parameters.mds(), &intermediate)
+            .into_iter()
+            .enumerate()
+        {
+            constraints.push(
+                (Trace(i, 1) - mixed - Trace(second + i, 0)) * Trace(transition, 0)
+                    / (X.pow(size) - 1),
+            );
+        }
+        // The first row contains the input
+        for (i, value) in input.iter().enumerate() {
+            constraints.push((Trace(i, 0) - Constant(value.clone())) / (X - 1));
+        }
+        // The row after the last round contains the output
+        for (i, value) in output.iter().enumerate() {
+            constraints.push((Trace(i, 0) - Constant(value.clone())) / (X - omega.pow(rounds)));
+        }
+        constraints
+    }
+
+    fn trace<P: PolynomialWriter>(&self, trace: &mut P, witness: &Self::Witness) {
+        debug_assert_eq!(trace.num_polynomials(), self.num_polynomials());
+        debug_assert_eq!(trace.polynomial_size(), self.polynomial_size());
+        let width = self.parameters.width();
+        let rounds = self.parameters.rounds();
+        let one = [FieldElement::one()];
+        write_fixed_columns(trace, rounds, 2 * width, width, |round| {
+            self.parameters.first_constants(round)
+        });
+        write_fixed_columns(trace, rounds, 3 * width, width, |round| {
+            self.parameters.second_constants(round)
+        });
+        write_fixed_columns(trace, rounds, 4 * width, 1, |_| &one);
+        let mut state = witness.clone();
+        for row in 0..self.polynomial_size() {
+            for (i, value) in state.iter().enumerate() {
+                trace.write(i, row, value.clone());
+            }
+            if row < rounds {
+                self.parameters.first_half(row, &mut state);
+                self.parameters.inverse_sbox(&mut state);
+                for (i, value) in state.iter().enumerate() {
+                    trace.write(width + i, row, value.clone());
+                }
+                self.parameters.second_half(row, &mut state);
+            } else {
+                for i in 0..width {
+                    trace.write(width + i, row, FieldElement::zero());
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Constraints;
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_poseidon_check() {
+        let config = ProptestConfig::with_cases(10);
+        proptest!(config, |(witness: (FieldElement, FieldElement, FieldElement))| {
+            let component = PoseidonPermutation::new(Poseidon::default());
+            let witness = vec![witness.0, witness.1, witness.2];
+            prop_assert_eq!(component.check(&witness), Ok(()));
+        });
+    }
+
+    #[test]
+    fn test_rescue_check() {
+        let config = ProptestConfig::with_cases(10);
+        proptest!(config, |(witness: (FieldElement, FieldElement, FieldElement))| {
+            let component = RescuePermutation::new(RescuePrime::default());
+            let witness = vec![witness.0, witness.1, witness.2];
+            prop_assert_eq!(component.check(&witness), Ok(()));
+        });
+    }
+
+    #[test]
+    fn test_poseidon_proof_verify() {
+        let component = PoseidonPermutation::new(Poseidon::default());
+        let witness = vec![1.into(), 2.into(), 3.into()];
+        let claim = component.claim(&witness);
+        let proof = component.prove(&witness).unwrap();
+        assert_eq!(component.verify(&claim, &proof), Ok(()));
+        let mut wrong = claim;
+        wrong.0[1] += FieldElement::one();
+        assert!(component.verify(&wrong, &proof).is_err());
+    }
+
+    #[test]
+    fn test_rescue_proof_verify() {
+        let component = RescuePermutation::new(RescuePrime::default());
+        let witness = vec![1.into(), 2.into(), 3.into()];
+        let claim = component.claim(&witness);
+        let proof = component.prove(&witness).unwrap();
+        assert_eq!(component.verify(&claim, &proof), Ok(()));
+        let mut wrong = claim.clone();
+        wrong.1[0] += FieldElement::one();
+        assert!(component.verify(&wrong, &proof).is_err());
+        let mut wrong = claim;
+        wrong.0[0] += FieldElement::one();
+        assert!(component.verify(&wrong, &proof).is_err());
+    }
+
+    #[test]
+    fn test_constraint_degree() {
+        fn degree<C: Component<Witness = Vec<FieldElement>>>(component: &C) -> usize {
+            let claim = component.claim(&vec![1.into(), 2.into(), 3.into()]);
+            Constraints::from_expressions(
+                (component.polynomial_size(), component.num_polynomials()),
+                vec![],
+                component.constraints(&claim),
+            )
+            .unwrap()
+            .degree()
+        }
+        // The round constants and flags are columns, so only the S-box and the
+        // transition flag contribute to the degree
+        assert_eq!(degree(&PoseidonPermutation::new(Poseidon::default())), 5);
+        assert_eq!(degree(&RescuePermutation::new(RescuePrime::default())), 4);
+    }
+
+    #[test]
+    fn test_hash_matches_sponge() {
+        // A single absorb of a two element input is one permutation
+        let parameters = Poseidon::default();
+        let input = [FieldElement::from(5), FieldElement::from(7)];
+        let component = PoseidonPermutation::new(parameters.clone());
+        let claim = component.claim(&vec![input[0].clone(), input[1].clone(), 2.into()]);
+        assert_eq!(claim.1[0], parameters.hash(&input));
+    }
+}
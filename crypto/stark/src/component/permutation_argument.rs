@@ -0,0 +1,303 @@
+use super::{Component, PolynomialWriter};
+use crate::RationalExpression;
+use std::prelude::v1::*;
+use zkp_primefield::{invert_batch, FieldElement, One, Pow, Root, Zero};
+
+// False positives on the Latex math.
+#[allow(clippy::doc_markdown)]
+/// Permutation argument between columns of a component
+///
+/// Proves that the rows of the `left` columns of the element are a permutation
+/// of the rows of its `right` columns. Using two challenges $\gamma$ and
+/// $\alpha$ each row is fingerprinted as
+///
+/// $$
+/// f(c_0, c_1, \dots) = \gamma + c_0 + \alpha \cdot c_1 + \alpha^2 \cdot c_2 +
+/// \cdots
+/// $$
+///
+/// and a second stage polynomial with the running product
+///
+/// $$
+/// z_0 = 1 \quad z_{i+1} = z_i \cdot \frac{f(\mathrm{left}_i)}{f(\mathrm{
+/// right}_i)}
+/// $$
+///
+/// is added. The product over all rows is constrained to be one, which with
+/// overwhelming probability only holds for permutations.
+///
+/// The challenges and the running product polynomial follow those of the
+/// element.
+#[derive(Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct PermutationArgument<Element>
+where
+    Element: Component,
+{
+    element: Element,
+    left:    Vec<usize>,
+    right:   Vec<usize>,
+}
+
+impl<Element> PermutationArgument<Element>
+where
+    Element: Component,
+{
+    pub fn new(element: Element, left: &[usize], right: &[usize]) -> Self {
+        assert_eq!(left.len(), right.len());
+        assert!(!left.is_empty());
+        assert!(left
+            .iter()
+            .chain(right.iter())
+            .all(|&column| column < element.num_polynomials()));
+        PermutationArgument {
+            element,
+            left: left.to_vec(),
+            right: right.to_vec(),
+        }
+    }
+
+    pub fn element(&self) -> &Element {
+        &self.element
+    }
+
+    pub fn left(&self) -> &[usize] {
+        &self.left
+    }
+
+    pub fn right(&self) -> &[usize] {
+        &self.right
+    }
+
+    /// Index of the running product polynomial
+    pub fn product_polynomial(&self) -> usize {
+        self.element.num_polynomials() + self.element.num_stage_polynomials()
+    }
+}
+
+impl<Element> Component for PermutationArgument<Element>
+where
+    Element: Component,
+{
+    type Claim = Element::Claim;
+    type Witness = Element::Witness;
+
+    fn num_polynomials(&self) -> usize {
+        self.element.num_polynomials()
+    }
+
+    fn polynomial_size(&self) -> usize {
+        self.element.polynomial_size()
+    }
+
+    fn num_challenges(&self) -> usize {
+        self.element.num_challenges() + 2
+    }
+
+    fn num_stage_polynomials(&self) -> usize {
+        self.element.num_stage_polynomials() + 1
+    }
+
+    fn claim(&self, witness: &Self::Witness) -> Self::Claim {
+        self.element.claim(witness)
+    }
+
+    fn constraints(&self, claim: &Self::Claim) -> Vec<RationalExpression> {
+        use RationalExpression::*;
+        let size = self.polynomial_size();
+        let omega = FieldElement::root(size).expect("Size not a power of two.");
+        let last_row = Constant(omega.pow(size - 1));
+        let gamma = Challenge(self.element.num_challenges());
+        let alpha = Challenge(self.element.num_challenges() + 1);
+        let fingerprint = |columns: &[usize]| {
+            columns
+                .iter()
+                .rev()
+                .map(|&column| Trace(column, 0))
+                .fold(None, |sum, value| {
+                    Some(match sum {
+                        None => value,
+                        Some(sum) => sum * alpha.clone() + value,
+                    })
+                })
+                .unwrap()
+                + gamma.clone()
+        };
+        let left = fingerprint(&self.left);
+        let right = fingerprint(&self.right);
+        let z = self.product_polynomial();
+
+        let mut constraints = self.element.constraints(claim);
+        // z[0] = 1
+        constraints.push((Trace(z, 0) - 1) / (X - 1));
+        // z[i + 1] * right[i] = z[i] * left[i]
+        constraints.push(
+            (Trace(z, 1) * right.clone() - Trace(z, 0) * left.clone()) * (X - last_row.clone())
+                / (X.pow(size) - 1),
+        );
+        // z[n - 1] * left[n - 1] = right[n - 1]
+        constraints.push((Trace(z, 0) * left - right) / (X - last_row));
+        constraints
+    }
+
+    fn trace<P: PolynomialWriter>(&self, trace: &mut P, witness: &Self::Witness) {
+        self.element.trace(trace, witness)
+    }
+
+    fn stage_trace<P: PolynomialWriter>(
+        &self,
+        trace: &mut P,
+        challenges: &[FieldElement],
+        witness: &Self::Witness,
+    ) {
+        self.element.stage_trace(trace, challenges, witness);
+
+        // OPT: The element trace is recomputed here.
+        let table = self.element.trace_table(witness);
+        let gamma = &challenges[self.element.num_challenges()];
+        let alpha = &challenges[self.element.num_challenges() + 1];
+        let fingerprint = |row: usize, columns: &[usize]| {
+            columns
+                .iter()
+                .rev()
+                .fold(FieldElement::zero(), |sum, &column| {
+                    sum * alpha + &table[(row, column)]
+                })
+                + gamma
+        };
+        let size = self.polynomial_size();
+        let left = (0..size)
+            .map(|row| fingerprint(row, &self.left))
+            .collect::<Vec<_>>();
+        let right = (0..size)
+            .map(|row| fingerprint(row, &self.right))
+            .collect::<Vec<_>>();
+        let right_inv = invert_batch(&right);
+
+        let polynomial = self.element.num_stage_polynomials();
+        let mut product = FieldElement::one();
+        for row in 0..size {
+            trace.write(polynomial, row, product.clone());
+            product *= &left[row] * &right_inv[row];
+        }
+    }
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::{
+        super::{test::Test, Fold, Horizontal, Vertical},
+        *,
+    };
+    use proptest::{collection::vec, prelude::*};
+
+    /// Columns given by the witness, without constraints.
+    #[derive(Clone, PartialEq, Eq, Debug)]
+    pub(crate) struct Columns(pub(crate) usize, pub(crate) usize);
+
+    impl Component for Columns {
+        type Claim = ();
+        type Witness = Vec<Vec<FieldElement>>;
+
+        fn num_polynomials(&self) -> usize {
+            self.0
+        }
+
+        fn polynomial_size(&self) -> usize {
+            self.1
+        }
+
+        fn claim(&self, _witness: &Self::Witness) -> Self::Claim {}
+
+        fn constraints(&self, _claim: &Self::Claim) -> Vec<RationalExpression> {
+            Vec::new()
+        }
+
+        fn trace<P: PolynomialWriter>(&self, trace: &mut P, witness: &Self::Witness) {
+            for (polynomial, column) in witness.iter().enumerate() {
+                for (location, value) in column.iter().enumerate() {
+                    trace.write(polynomial, location, value.clone());
+                }
+            }
+        }
+    }
+
+    /// Two columns where the second is a shuffle of the first.
+    fn permuted_columns() -> impl Strategy<Value = Vec<Vec<FieldElement>>> {
+        (0_usize..6)
+            .prop_flat_map(|log_rows| vec(any::<FieldElement>(), 1 << log_rows))
+            .prop_flat_map(|column| (Just(column.clone()), Just(column).prop_shuffle()))
+            .prop_map(|(left, right)| vec![left, right])
+    }
+
+    #[test]
+    fn test_check() {
+        proptest!(|(witness in permuted_columns())| {
+            let rows = witness[0].len();
+            let component = PermutationArgument::new(Columns(2, rows), &[0], &[1]);
+            prop_assert_eq!(component.check(&witness), Ok(()));
+        });
+    }
+
+    #[test]
+    fn test_check_fails() {
+        proptest!(|(mut witness in permuted_columns(), row: usize)| {
+            let rows = witness[0].len();
+            witness[1][row % rows] += FieldElement::one();
+            let component = PermutationArgument::new(Columns(2, rows), &[0], &[1]);
+            prop_assert!(component.check(&witness).is_err());
+        });
+    }
+
+    #[test]
+    fn test_check_compose() {
+        let config = ProptestConfig::with_cases(20);
+        proptest!(config, |(
+            witness in permuted_columns(),
+            seed: FieldElement,
+            test_witness: (FieldElement, FieldElement),
+        )| {
+            let rows = witness[0].len();
+            let argument = PermutationArgument::new(Columns(2, rows), &[0], &[1]);
+
+            // Next to another component and next to itself
+            let component = Horizontal::new(Test::new(rows, 2, &seed), argument.clone());
+            let combined = (test_witness, witness.clone());
+            prop_assert_eq!(component.check(&combined), Ok(()));
+            let component = Horizontal::new(argument.clone(), argument.clone());
+            let combined = (witness.clone(), witness.clone());
+            prop_assert_eq!(component.check(&combined), Ok(()));
+
+            // Repeated, each repetition is a separate permutation
+            let component = Vertical::new(argument.clone(), 2);
+            let repeated = vec![witness.clone(), witness.clone()];
+            prop_assert_eq!(component.check(&repeated), Ok(()));
+
+            // Folded, the product polynomial is folded separately
+            let component = Fold::new(argument, 1);
+            prop_assert_eq!(component.check(&witness), Ok(()));
+        });
+    }
+
+    #[test]
+    fn test_tuples_proof_verify() {
+        // Rows (a, b) with the second pair of columns reversed
+        let rows = 16;
+        let a = (0..rows).map(FieldElement::from).collect::<Vec<_>>();
+        let b = (0..rows)
+            .map(|i| FieldElement::from(i * i))
+            .collect::<Vec<_>>();
+        let mut witness = vec![a.clone(), b.clone(), a, b];
+        witness[2].reverse();
+        witness[3].reverse();
+        let component = PermutationArgument::new(Columns(4, rows), &[0, 1], &[2, 3]);
+        assert_eq!(component.check(&witness), Ok(()));
+        let proof = component.prove(&witness).unwrap();
+        assert_eq!(component.verify(&(), &proof), Ok(()));
+
+        // Swapping values between tuples is not a permutation of the rows
+        witness[3].swap(0, 1);
+        assert!(component.check(&witness).is_err());
+        assert!(component.prove(&witness).is_err());
+    }
+}
@@ -0,0 +1,448 @@
+use super::{Component, PolynomialWriter};
+use crate::RationalExpression;
+use std::prelude::v1::*;
+use zkp_primefield::{invert_batch, FieldElement, One, Pow, Root};
+use zkp_u256::Binary;
+
+/// How a [`RangeCheck`] proves values are in range
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub enum RangeCheckStrategy {
+    /// Decompose each value into bits.
+    ///
+    /// Adds a polynomial for every bit of every checked column and only
+    /// degree two constraints.
+    Bits,
+    /// Sort the values together with filler values covering the range.
+    ///
+    /// Adds a filler polynomial and one more sorted polynomial than checked
+    /// columns. The sorted values start at zero, end at $2^n - 1$ and
+    /// increase by at most one each step. A running product stage shows they
+    /// are a permutation of the checked and filler values. This requires
+    /// $2^n$ to be at most the polynomial size.
+    Sorted,
+}
+
+/// Range check for the values of a component
+///
+/// Proves that all values in the `columns` polynomials of the element are in
+/// the range $[0, 2^n)$ where $n$ is the number of `bits`.
+///
+/// The added polynomials follow the polynomials of the element. For the
+/// sorted strategy the challenge and running product polynomial follow those
+/// of the element.
+#[derive(Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct RangeCheck<Element>
+where
+    Element: Component,
+{
+    element:  Element,
+    columns:  Vec<usize>,
+    bits:     usize,
+    strategy: RangeCheckStrategy,
+}
+
+impl<Element> RangeCheck<Element>
+where
+    Element: Component,
+{
+    /// # Panics
+    ///
+    /// Panics if `columns` is empty or refers to a polynomial outside the
+    /// element, if `bits` is zero, or if `bits` is too large for the
+    /// strategy: at least 252 for [`RangeCheckStrategy::Bits`], or more than
+    /// the polynomial size allows for [`RangeCheckStrategy::Sorted`].
+    pub fn new(
+        element: Element,
+        columns: &[usize],
+        bits: usize,
+        strategy: RangeCheckStrategy,
+    ) -> Self {
+        assert!(!columns.is_empty());
+        assert!(columns
+            .iter()
+            .all(|&column| column < element.num_polynomials()));
+        assert!(bits > 0);
+        match strategy {
+            RangeCheckStrategy::Bits => assert!(bits < 252),
+            RangeCheckStrategy::Sorted => {
+                assert!(bits < 64 && 1 << bits <= element.polynomial_size())
+            }
+        }
+        RangeCheck {
+            element,
+            columns: columns.to_vec(),
+            bits,
+            strategy,
+        }
+    }
+
+    pub fn element(&self) -> &Element {
+        &self.element
+    }
+
+    pub fn columns(&self) -> &[usize] {
+        &self.columns
+    }
+
+    pub fn bits(&self) -> usize {
+        self.bits
+    }
+
+    pub fn strategy(&self) -> RangeCheckStrategy {
+        self.strategy
+    }
+
+    /// Index of the polynomial with the `bit` of the checked column `index`
+    ///
+    /// Only for the bit decomposition strategy.
+    pub fn bit_polynomial(&self, index: usize, bit: usize) -> usize {
+        assert_eq!(self.strategy, RangeCheckStrategy::Bits);
+        self.element.num_polynomials() + index * self.bits + bit
+    }
+
+    /// Index of the filler polynomial
+    ///
+    /// Only for the sorted strategy.
+    pub fn filler_polynomial(&self) -> usize {
+        assert_eq!(self.strategy, RangeCheckStrategy::Sorted);
+        self.element.num_polynomials()
+    }
+
+    /// Index of the `index`-th sorted polynomial
+    ///
+    /// Only for the sorted strategy. The sorted values are laid out row by
+    /// row.
+    pub fn sorted_polynomial(&self, index: usize) -> usize {
+        assert_eq!(self.strategy, RangeCheckStrategy::Sorted);
+        self.element.num_polynomials() + 1 + index
+    }
+
+    /// Index of the running product polynomial
+    ///
+    /// Only for the sorted strategy.
+    pub fn product_polynomial(&self) -> usize {
+        assert_eq!(self.strategy, RangeCheckStrategy::Sorted);
+        self.num_polynomials() + self.element.num_stage_polynomials()
+    }
+
+    /// Polynomial indices of the checked and filler values
+    fn unsorted_polynomials(&self) -> Vec<usize> {
+        self.columns
+            .iter()
+            .copied()
+            .chain(Some(self.filler_polynomial()))
+            .collect()
+    }
+
+    fn sorted_polynomials(&self) -> Vec<usize> {
+        (0..=self.columns.len())
+            .map(|index| self.sorted_polynomial(index))
+            .collect()
+    }
+
+    fn bits_constraints(&self) -> Vec<RationalExpression> {
+        use RationalExpression::*;
+        let size = self.polynomial_size();
+        let mut constraints = Vec::new();
+        for (index, &column) in self.columns.iter().enumerate() {
+            let bits = (0..self.bits)
+                .map(|bit| Trace(self.bit_polynomial(index, bit), 0))
+                .collect::<Vec<_>>();
+            // Each bit is zero or one
+            for bit in &bits {
+                constraints.push(bit.clone() * (bit.clone() - 1) / (X.pow(size) - 1));
+            }
+            // The bits recompose to the value
+            let value = bits
+                .into_iter()
+                .rev()
+                .fold(RationalExpression::from(0), |sum, bit| {
+                    Constant(2.into()) * sum + bit
+                });
+            constraints.push((Trace(column, 0) - value) / (X.pow(size) - 1));
+        }
+        constraints
+    }
+
+    fn sorted_constraints(&self) -> Vec<RationalExpression> {
+        use RationalExpression::*;
+        let size = self.polynomial_size();
+        let omega = FieldElement::root(size).expect("Size not a power of two.");
+        let last_row = Constant(omega.pow(size - 1));
+        let sorted = self.sorted_polynomials();
+        let first = Trace(sorted[0], 0);
+        let last = Trace(*sorted.last().unwrap(), 0);
+        let step = |difference: RationalExpression| difference.clone() * (difference - 1);
+        let gamma = Challenge(self.element.num_challenges());
+        let product = |polynomials: &[usize]| {
+            polynomials
+                .iter()
+                .fold(RationalExpression::from(1), |product, &polynomial| {
+                    product * (gamma.clone() - Trace(polynomial, 0))
+                })
+        };
+        let unsorted = product(&self.unsorted_polynomials());
+        let sorted_product = product(&sorted);
+        let z = self.product_polynomial();
+
+        let mut constraints = Vec::new();
+        // The sorted values start at zero and end at 2^n - 1
+        constraints.push(first / (X - 1));
+        constraints.push(
+            (last.clone() - Constant(((1_u64 << self.bits) - 1).into())) / (X - last_row.clone()),
+        );
+        // They increase by zero or one within a row and to the next row
+        for pair in sorted.windows(2) {
+            constraints.push(step(Trace(pair[1], 0) - Trace(pair[0], 0)) / (X.pow(size) - 1));
+        }
+        constraints
+            .push(step(Trace(sorted[0], 1) - last) * (X - last_row.clone()) / (X.pow(size) - 1));
+        // z[0] = 1
+        constraints.push((Trace(z, 0) - 1) / (X - 1));
+        // z[i + 1] * sorted[i] = z[i] * unsorted[i]
+        constraints.push(
+            (Trace(z, 1) * sorted_product.clone() - Trace(z, 0) * unsorted.clone())
+                * (X - last_row.clone())
+                / (X.pow(size) - 1),
+        );
+        // z[n - 1] * unsorted[n - 1] = sorted[n - 1]
+        constraints.push((Trace(z, 0) * unsorted - sorted_product) / (X - last_row));
+        constraints
+    }
+}
+
+impl<Element> Component for RangeCheck<Element>
+where
+    Element: Component,
+{
+    type Claim = Element::Claim;
+    type Witness = Element::Witness;
+
+    fn num_polynomials(&self) -> usize {
+        self.element.num_polynomials()
+            + match self.strategy {
+                RangeCheckStrategy::Bits => self.columns.len() * self.bits,
+                RangeCheckStrategy::Sorted => self.columns.len() + 2,
+            }
+    }
+
+    fn polynomial_size(&self) -> usize {
+        self.element.polynomial_size()
+    }
+
+    fn num_challenges(&self) -> usize {
+        self.element.num_challenges()
+            + match self.strategy {
+                RangeCheckStrategy::Bits => 0,
+                RangeCheckStrategy::Sorted => 1,
+            }
+    }
+
+    fn num_stage_polynomials(&self) -> usize {
+        self.element.num_stage_polynomials()
+            + match self.strategy {
+                RangeCheckStrategy::Bits => 0,
+                RangeCheckStrategy::Sorted => 1,
+            }
+    }
+
+    fn claim(&self, witness: &Self::Witness) -> Self::Claim {
+        self.element.claim(witness)
+    }
+
+    fn constraints(&self, claim: &Self::Claim) -> Vec<RationalExpression> {
+        use RationalExpression::*;
+        // The element stage polynomials are shifted by the added polynomials
+        let element_polynomials = self.element.num_polynomials();
+        let added_polynomials = self.num_polynomials() - element_polynomials;
+        let mut constraints = self
+            .element
+            .constraints(claim)
+            .into_iter()
+            .map(|expression| {
+                expression.map(&|node| {
+                    match node {
+                        Trace(i, j) if i >= element_polynomials => Trace(i + added_polynomials, j),
+                        other => other,
+                    }
+                })
+            })
+            .collect::<Vec<_>>();
+        constraints.extend(match self.strategy {
+            RangeCheckStrategy::Bits => self.bits_constraints(),
+            RangeCheckStrategy::Sorted => self.sorted_constraints(),
+        });
+        constraints
+    }
+
+    fn trace<P: PolynomialWriter>(&self, trace: &mut P, witness: &Self::Witness) {
+        self.element.trace(trace, witness);
+
+        // OPT: The element trace is recomputed here.
+        let element_trace = self.element.trace_table(witness);
+        let size = self.polynomial_size();
+        match self.strategy {
+            RangeCheckStrategy::Bits => {
+                for (index, &column) in self.columns.iter().enumerate() {
+                    for row in 0..size {
+                        let value = element_trace[(row, column)].to_uint();
+                        for bit in 0..self.bits {
+                            let polynomial = self.bit_polynomial(index, bit);
+                            trace.write(polynomial, row, u64::from(value.bit(bit)).into());
+                        }
+                    }
+                }
+            }
+            RangeCheckStrategy::Sorted => {
+                // Filler values cover the range, the last is repeated
+                let max = (1_usize << self.bits) - 1;
+                let filler = (0..size)
+                    .map(|row| FieldElement::from(row.min(max)))
+                    .collect::<Vec<_>>();
+                let mut values = self
+                    .columns
+                    .iter()
+                    .flat_map(|&column| (0..size).map(move |row| (row, column)))
+                    .map(|location| element_trace[location].clone())
+                    .chain(filler.iter().cloned())
+                    .collect::<Vec<_>>();
+                values.sort_by_key(FieldElement::to_uint);
+                for (row, value) in filler.into_iter().enumerate() {
+                    trace.write(self.filler_polynomial(), row, value);
+                }
+                let width = self.columns.len() + 1;
+                for (i, value) in values.into_iter().enumerate() {
+                    trace.write(self.sorted_polynomial(i % width), i / width, value);
+                }
+            }
+        }
+    }
+
+    fn stage_trace<P: PolynomialWriter>(
+        &self,
+        trace: &mut P,
+        challenges: &[FieldElement],
+        witness: &Self::Witness,
+    ) {
+        self.element.stage_trace(trace, challenges, witness);
+        if self.strategy == RangeCheckStrategy::Bits {
+            return;
+        }
+
+        // OPT: The trace is recomputed here.
+        let full_trace = self.trace_table(witness);
+        let gamma = &challenges[self.element.num_challenges()];
+        let product = |row: usize, polynomials: &[usize]| {
+            polynomials
+                .iter()
+                .fold(FieldElement::one(), |product, &polynomial| {
+                    product * (gamma - &full_trace[(row, polynomial)])
+                })
+        };
+        let size = self.polynomial_size();
+        let unsorted = self.unsorted_polynomials();
+        let sorted = self.sorted_polynomials();
+        let denominators = (0..size)
+            .map(|row| product(row, &sorted))
+            .collect::<Vec<_>>();
+        let denominators_inv = invert_batch(&denominators);
+
+        let polynomial = self.element.num_stage_polynomials();
+        let mut product_value = FieldElement::one();
+        for (row, denominator_inv) in denominators_inv.iter().enumerate() {
+            trace.write(polynomial, row, product_value.clone());
+            product_value *= product(row, &unsorted) * denominator_inv;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        super::{permutation_argument::tests::Columns, test::Test, Horizontal, Vertical},
+        *,
+    };
+    use proptest::{collection::vec, prelude::*};
+    use zkp_u256::U256;
+
+    const STRATEGIES: [RangeCheckStrategy; 2] =
+        [RangeCheckStrategy::Bits, RangeCheckStrategy::Sorted];
+
+    /// Two columns with four bit values.
+    fn nibble_columns() -> impl Strategy<Value = Vec<Vec<FieldElement>>> {
+        (4_usize..7).prop_flat_map(|log_rows| {
+            vec(
+                vec((0_u64..16).prop_map(FieldElement::from), 1 << log_rows),
+                2,
+            )
+        })
+    }
+
+    #[test]
+    fn test_check() {
+        proptest!(|(witness in nibble_columns())| {
+            let rows = witness[0].len();
+            for &strategy in &STRATEGIES {
+                let component = RangeCheck::new(Columns(2, rows), &[0, 1], 4, strategy);
+                prop_assert_eq!(component.check(&witness), Ok(()));
+            }
+        });
+    }
+
+    #[test]
+    fn test_check_fails() {
+        proptest!(|(mut witness in nibble_columns(), row: usize, value: FieldElement)| {
+            let rows = witness[0].len();
+            prop_assume!(value.to_uint() >= U256::from(16_u64));
+            witness[1][row % rows] = value;
+            for &strategy in &STRATEGIES {
+                let component = RangeCheck::new(Columns(2, rows), &[0, 1], 4, strategy);
+                prop_assert!(component.check(&witness).is_err());
+            }
+        });
+    }
+
+    #[test]
+    fn test_check_compose() {
+        let config = ProptestConfig::with_cases(20);
+        proptest!(config, |(
+            witness in nibble_columns(),
+            seed: FieldElement,
+            test_witness: (FieldElement, FieldElement),
+        )| {
+            let rows = witness[0].len();
+            for &strategy in &STRATEGIES {
+                let range_check = RangeCheck::new(Columns(2, rows), &[1], 4, strategy);
+
+                let component = Horizontal::new(Test::new(rows, 2, &seed), range_check.clone());
+                let combined = (test_witness.clone(), witness.clone());
+                prop_assert_eq!(component.check(&combined), Ok(()));
+
+                let component = Vertical::new(range_check, 2);
+                let repeated = vec![witness.clone(), witness.clone()];
+                prop_assert_eq!(component.check(&repeated), Ok(()));
+            }
+        });
+    }
+
+    #[test]
+    fn test_proof_verify() {
+        let rows = 16;
+        let witness = vec![
+            (0..rows)
+                .map(|i| FieldElement::from(i * i % 13))
+                .collect::<Vec<_>>(),
+            (0..rows)
+                .map(|i| FieldElement::from(15 - i))
+                .collect::<Vec<_>>(),
+        ];
+        for &strategy in &STRATEGIES {
+            let component = RangeCheck::new(Columns(2, rows), &[0, 1], 4, strategy);
+            assert_eq!(component.check(&witness), Ok(()));
+            let proof = component.prove(&witness).unwrap();
+            assert_eq!(component.verify(&(), &proof), Ok(()));
+        }
+    }
+}
@@ -1,5 +1,6 @@
 use super::{Component, Mapped, PolynomialWriter};
 use crate::RationalExpression;
+use zkp_primefield::FieldElement;
 
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 #[cfg_attr(feature = "std", derive(Debug))]
@@ -46,6 +47,14 @@ where
         self.element.polynomial_size() * self.size
     }
 
+    fn num_challenges(&self) -> usize {
+        self.element.num_challenges()
+    }
+
+    fn num_stage_polynomials(&self) -> usize {
+        self.element.num_stage_polynomials()
+    }
+
     fn claim(&self, witness: &Self::Witness) -> Self::Claim {
         witness
             .iter()
@@ -85,6 +94,27 @@ where
             self.element.trace(&mut transformed, witness);
         })
     }
+
+    // All repetitions share the same challenges.
+    fn stage_trace<P: PolynomialWriter>(
+        &self,
+        trace: &mut P,
+        challenges: &[FieldElement],
+        witness: &Self::Witness,
+    ) {
+        let num_polynomials = self.element.num_stage_polynomials();
+        let polynomial_size = self.element.polynomial_size();
+        witness.iter().enumerate().for_each(|(i, witness)| {
+            let mut transformed = Mapped::new(
+                trace,
+                num_polynomials,
+                polynomial_size,
+                |polynomial, location| (polynomial, location + i * polynomial_size),
+            );
+            self.element
+                .stage_trace(&mut transformed, challenges, witness);
+        })
+    }
 }
 
 #[cfg(test)]
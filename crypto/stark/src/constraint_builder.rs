@@ -0,0 +1,461 @@
+use crate::{
+    constraints::{Constraints, Error},
+    field::StarkField,
+    rational_expression::RationalExpression,
+};
+use std::prelude::v1::*;
+use zkp_primefield::{FieldElement, Pow, RefFieldLike};
+
+/// A named trace column created by a [`ConstraintBuilder`]
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct Column(usize);
+
+impl Column {
+    /// Index of the column in the trace table.
+    pub fn index(self) -> usize {
+        self.0
+    }
+
+    /// The value of the column `offset` rows from the current row.
+    pub fn at<Field: StarkField>(self, offset: isize) -> RationalExpression<Field> {
+        RationalExpression::Trace(self.0, offset)
+    }
+
+    /// The value of the column on the current row.
+    pub fn current<Field: StarkField>(self) -> RationalExpression<Field> {
+        self.at(0)
+    }
+
+    /// The value of the column on the next row.
+    pub fn next<Field: StarkField>(self) -> RationalExpression<Field> {
+        self.at(1)
+    }
+}
+
+/// The set of trace rows a constraint applies to
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub enum Domain {
+    /// A single row.
+    Row(usize),
+    /// The rows `offset`, `offset + step`, `offset + 2 * step`, etc.
+    ///
+    /// The `step` is a power of two and `offset` is less than `step`. When
+    /// `except_last` is set the last of these rows is excluded, which is
+    /// useful for transition constraints referring to the next row.
+    Rows {
+        step:        usize,
+        offset:      usize,
+        except_last: bool,
+    },
+}
+
+impl Domain {
+    /// Only the row `row`.
+    pub fn on_row(row: usize) -> Self {
+        Domain::Row(row)
+    }
+
+    /// All rows of the trace.
+    pub fn every_row() -> Self {
+        Self::every_nth_row(1, 0)
+    }
+
+    /// Every `step`-th row starting from `offset`.
+    pub fn every_nth_row(step: usize, offset: usize) -> Self {
+        assert!(step.is_power_of_two());
+        assert!(offset < step);
+        Domain::Rows {
+            step,
+            offset,
+            except_last: false,
+        }
+    }
+
+    /// Exclude the last row of the domain.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the domain is a single row.
+    pub fn except_last(self) -> Self {
+        match self {
+            Domain::Row(_) => panic!("Can not exclude the only row of a domain"),
+            Domain::Rows { step, offset, .. } => {
+                Domain::Rows {
+                    step,
+                    offset,
+                    except_last: true,
+                }
+            }
+        }
+    }
+
+    /// The rows of the domain in a trace of `trace_nrows` rows.
+    pub fn rows(self, trace_nrows: usize) -> Vec<usize> {
+        match self {
+            Domain::Row(row) => vec![row],
+            Domain::Rows {
+                step,
+                offset,
+                except_last,
+            } => {
+                let mut rows = (offset..trace_nrows).step_by(step).collect::<Vec<_>>();
+                if except_last {
+                    let _ = rows.pop();
+                }
+                rows
+            }
+        }
+    }
+
+    /// Expression to multiply a constraint with to apply it on the domain.
+    ///
+    /// This is the inverse of the polynomial vanishing on the rows of the
+    /// domain. Returns `None` if the trace length is not a power of two or
+    /// the domain does not fit in the trace.
+    pub fn expression<Field>(self, trace_nrows: usize) -> Option<RationalExpression<Field>>
+    where
+        Field: StarkField,
+        for<'a> &'a Field: RefFieldLike<Field>,
+    {
+        use RationalExpression::*;
+        let generator = Field::root(trace_nrows)?;
+        match self {
+            Domain::Row(row) => {
+                if row >= trace_nrows {
+                    return None;
+                }
+                Some((X - Constant(generator.pow(row))).inv())
+            }
+            Domain::Rows {
+                step,
+                offset,
+                except_last,
+            } => {
+                if step > trace_nrows {
+                    return None;
+                }
+                // The rows are the roots of X^(n / step) - g^(offset * n / step)
+                let count = trace_nrows / step;
+                let vanishing = X.pow(count) - Constant(generator.pow(offset * count));
+                Some(if except_last {
+                    let last = offset + (count - 1) * step;
+                    (X - Constant(generator.pow(last))) / vanishing
+                } else {
+                    vanishing.inv()
+                })
+            }
+        }
+    }
+}
+
+/// Names of the columns, public inputs and constraints of a
+/// [`ConstraintBuilder`]
+///
+/// The constraint names are in the order of the expressions in the built
+/// [`Constraints`], so a failing constraint index can be mapped back to its
+/// name.
+#[derive(Clone, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct ConstraintMetadata {
+    columns:       Vec<String>,
+    public_inputs: Vec<String>,
+    constraints:   Vec<String>,
+}
+
+impl ConstraintMetadata {
+    pub fn column_names(&self) -> &[String] {
+        &self.columns
+    }
+
+    pub fn public_input_names(&self) -> &[String] {
+        &self.public_inputs
+    }
+
+    pub fn constraint_names(&self) -> &[String] {
+        &self.constraints
+    }
+
+    pub fn column_name(&self, index: usize) -> Option<&str> {
+        self.columns.get(index).map(String::as_str)
+    }
+
+    pub fn public_input_name(&self, index: usize) -> Option<&str> {
+        self.public_inputs.get(index).map(String::as_str)
+    }
+
+    pub fn constraint_name(&self, index: usize) -> Option<&str> {
+        self.constraints.get(index).map(String::as_str)
+    }
+
+    pub fn column_index(&self, name: &str) -> Option<usize> {
+        self.columns.iter().position(|column| column == name)
+    }
+
+    pub fn public_input_index(&self, name: &str) -> Option<usize> {
+        self.public_inputs.iter().position(|input| input == name)
+    }
+
+    pub fn constraint_index(&self, name: &str) -> Option<usize> {
+        self.constraints
+            .iter()
+            .position(|constraint| constraint == name)
+    }
+}
+
+/// Builder for [`Constraints`] with named columns and constraints
+///
+/// Constraints are given as expressions that should be zero on a [`Domain`]
+/// of rows, the builder takes care of dividing by the vanishing polynomial
+/// of the domain.
+///
+/// ```
+/// use zkp_stark::{primefield::FieldElement, ConstraintBuilder, Domain};
+///
+/// let mut builder = ConstraintBuilder::<FieldElement>::new(256);
+/// let a = builder.column("a");
+/// let b = builder.column("b");
+/// builder
+///     .constrain(
+///         "copy",
+///         a.next() - b.current(),
+///         Domain::every_row().except_last(),
+///     )
+///     .constrain(
+///         "sum",
+///         b.next() - a.current() - b.current(),
+///         Domain::every_row().except_last(),
+///     )
+///     .boundary("start", a, 0, 1.into());
+/// let (constraints, metadata) = builder.build(b"fibonacci".to_vec()).unwrap();
+/// assert_eq!(constraints.len(), 3);
+/// assert_eq!(metadata.constraint_name(1), Some("sum"));
+/// ```
+#[derive(Clone)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct ConstraintBuilder<Field: StarkField = FieldElement> {
+    trace_nrows:   usize,
+    metadata:      ConstraintMetadata,
+    public_inputs: Vec<Field>,
+    constraints:   Vec<(RationalExpression<Field>, Domain)>,
+}
+
+impl<Field> ConstraintBuilder<Field>
+where
+    Field: StarkField,
+    for<'a> &'a Field: RefFieldLike<Field>,
+{
+    pub fn new(trace_nrows: usize) -> Self {
+        Self {
+            trace_nrows,
+            metadata: ConstraintMetadata::default(),
+            public_inputs: Vec::new(),
+            constraints: Vec::new(),
+        }
+    }
+
+    pub fn trace_nrows(&self) -> usize {
+        self.trace_nrows
+    }
+
+    pub fn metadata(&self) -> &ConstraintMetadata {
+        &self.metadata
+    }
+
+    /// Add a trace column.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a column with the same name already exists.
+    pub fn column(&mut self, name: &str) -> Column {
+        assert!(
+            self.metadata.column_index(name).is_none(),
+            "Duplicate column name"
+        );
+        self.metadata.columns.push(name.to_owned());
+        Column(self.metadata.columns.len() - 1)
+    }
+
+    /// Add a public input.
+    ///
+    /// Returns the expression referring to the value. Public inputs are
+    /// registered on the built [`Constraints`] and absorbed into the proof
+    /// transcript.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a public input with the same name already exists.
+    pub fn public_input(&mut self, name: &str, value: Field) -> RationalExpression<Field> {
+        assert!(
+            self.metadata.public_input_index(name).is_none(),
+            "Duplicate public input name"
+        );
+        self.metadata.public_inputs.push(name.to_owned());
+        self.public_inputs.push(value);
+        RationalExpression::PublicInput(self.public_inputs.len() - 1)
+    }
+
+    /// Constrain `expression` to be zero on all rows of `domain`.
+    pub fn constrain(
+        &mut self,
+        name: &str,
+        expression: RationalExpression<Field>,
+        domain: Domain,
+    ) -> &mut Self {
+        self.metadata.constraints.push(name.to_owned());
+        self.constraints.push((expression, domain));
+        self
+    }
+
+    /// Constrain the cell of `column` on `row` to equal a public `value`.
+    ///
+    /// The value is added as a public input with the same name as the
+    /// constraint.
+    pub fn boundary(&mut self, name: &str, column: Column, row: usize, value: Field) -> &mut Self {
+        let value = self.public_input(name, value);
+        self.constrain(name, column.current() - value, Domain::on_row(row))
+    }
+
+    /// Build the constraints and their names.
+    ///
+    /// Returns an error if the trace length is not a power of two or one of
+    /// the domains does not fit in the trace.
+    pub fn build(
+        &self,
+        channel_seed: Vec<u8>,
+    ) -> Result<(Constraints<Field>, ConstraintMetadata), Error> {
+        let expressions = self
+            .constraints
+            .iter()
+            .map(|(expression, domain)| {
+                domain
+                    .expression(self.trace_nrows)
+                    .map(|domain| expression.clone() * domain)
+                    .ok_or(Error::InvalidTraceLength)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut constraints = Constraints::from_expressions(
+            (self.trace_nrows, self.metadata.columns.len()),
+            channel_seed,
+            expressions,
+        )?;
+        for value in &self.public_inputs {
+            let _ = constraints.add_public_input(value.clone());
+        }
+        Ok((constraints, self.metadata.clone()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{check_constraints, prove, verify, TraceTable};
+    use zkp_primefield::One;
+
+    #[test]
+    fn test_domain_rows() {
+        let domains = [
+            Domain::on_row(5),
+            Domain::every_row(),
+            Domain::every_row().except_last(),
+            Domain::every_nth_row(4, 1),
+            Domain::every_nth_row(4, 3).except_last(),
+        ];
+        let trace_nrows = 16;
+        for &domain in &domains {
+            let rows = domain.rows(trace_nrows);
+            for row in 0..trace_nrows {
+                // A single column that is zero exactly on the domain
+                let mut builder = ConstraintBuilder::<FieldElement>::new(trace_nrows);
+                let column = builder.column("column");
+                let _ = builder.constrain("zero", column.current(), domain);
+                let (constraints, _) = builder.build(Vec::new()).unwrap();
+                let mut trace = TraceTable::new(trace_nrows, 1);
+                for other in 0..trace_nrows {
+                    if !rows.contains(&other) {
+                        trace[(other, 0)] = FieldElement::one();
+                    }
+                }
+                assert_eq!(check_constraints(&constraints, &trace), Ok(()));
+                if rows.contains(&row) {
+                    trace[(row, 0)] = FieldElement::one();
+                    assert_eq!(check_constraints(&constraints, &trace), Err((row, 0)));
+                }
+            }
+        }
+        assert_eq!(Domain::every_nth_row(4, 3).except_last().rows(16), vec![
+            3, 7, 11
+        ]);
+    }
+
+    #[test]
+    fn test_invalid_domain() {
+        let mut builder = ConstraintBuilder::<FieldElement>::new(16);
+        let column = builder.column("column");
+        let _ = builder.boundary("out of range", column, 16, FieldElement::one());
+        assert!(builder.build(Vec::new()).is_err());
+        assert!(ConstraintBuilder::<FieldElement>::new(12)
+            .build(Vec::new())
+            .is_err());
+    }
+
+    #[test]
+    fn test_fibonacci_proof_verify() {
+        let trace_nrows = 256;
+        let mut trace = TraceTable::<FieldElement>::new(trace_nrows, 2);
+        trace[(0, 0)] = 1.into();
+        trace[(0, 1)] = 0xcafe.into();
+        for i in 1..trace_nrows {
+            trace[(i, 0)] = trace[(i - 1, 1)].clone();
+            trace[(i, 1)] = &trace[(i - 1, 0)] + &trace[(i - 1, 1)];
+        }
+        let value = trace[(200, 0)].clone();
+
+        let build = |value: FieldElement| {
+            let mut builder = ConstraintBuilder::new(trace_nrows);
+            let a = builder.column("a");
+            let b = builder.column("b");
+            let _ = builder
+                .constrain(
+                    "copy",
+                    a.next() - b.current(),
+                    Domain::every_row().except_last(),
+                )
+                .constrain(
+                    "sum",
+                    b.next() - a.current() - b.current(),
+                    Domain::every_row().except_last(),
+                )
+                .boundary("start", a, 0, 1.into())
+                .boundary("value", a, 200, value);
+            let (mut constraints, metadata) = builder.build(b"fibonacci".to_vec()).unwrap();
+            constraints.num_queries = 20;
+            (constraints, metadata)
+        };
+        let (constraints, metadata) = build(value.clone());
+        assert_eq!(metadata.column_index("b"), Some(1));
+        assert_eq!(metadata.constraint_names(), &[
+            "copy", "sum", "start", "value"
+        ]);
+        assert_eq!(metadata.public_input_index("value"), Some(1));
+        assert_eq!(constraints.public_inputs(), &[
+            FieldElement::one(),
+            value.clone()
+        ]);
+
+        let proof = prove(&constraints, &trace).unwrap();
+        assert!(verify(&constraints, &proof).is_ok());
+
+        // The proof is bound to the public inputs and the expressions
+        let (other, _) = build(value + FieldElement::one());
+        assert!(verify(&other, &proof).is_err());
+        let mut weak = constraints.clone();
+        weak.strong_fiat_shamir = false;
+        assert!(verify(&weak, &proof).is_err());
+
+        trace[(100, 1)] += FieldElement::one();
+        let (_, which) = check_constraints(&constraints, &trace).unwrap_err();
+        assert_eq!(metadata.constraint_name(which), Some("sum"));
+    }
+}
@@ -1,6 +1,57 @@
-use crate::{constraints::Constraints, trace_table::TraceTable};
-use std::convert::TryInto;
-use zkp_primefield::{FieldElement, One, Pow, Root};
+use crate::{
+    algebraic_dag::AlgebraicGraph,
+    constraints::Constraints,
+    field::{random_element, StarkField},
+    polynomial::DensePolynomial,
+    rational_expression::RationalExpression,
+    trace_table::TraceTable,
+};
+use rand::thread_rng;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{cmp::max, convert::TryInto, fmt};
+use zkp_primefield::{
+    fft::permute, geometric_series::geometric_series, Fft, FieldElement, Inv, Pow, RefFieldLike,
+};
+
+/// Reason a constraint fails [`check_constraints_exact`]
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Error {
+    /// The denominator does not divide the numerator on the trace.
+    NotDivisible { constraint: usize },
+    /// The quotient exceeds the degree bound of the constraint system.
+    DegreeTooHigh {
+        constraint: usize,
+        degree:     usize,
+        bound:      usize,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use Error::*;
+        match *self {
+            NotDivisible { constraint } => {
+                write!(
+                    f,
+                    "Constraint {} is not a polynomial on the trace",
+                    constraint
+                )
+            }
+            DegreeTooHigh {
+                constraint,
+                degree,
+                bound,
+            } => {
+                write!(
+                    f,
+                    "Constraint {} has degree {} but the bound is {}",
+                    constraint, degree, bound
+                )
+            }
+        }
+    }
+}
 
 #[allow(clippy::doc_markdown)]
 /// # Check a set of constraints on a trace table
@@ -31,13 +82,18 @@ use zkp_primefield::{FieldElement, One, Pow, Root};
 /// best experience using it to check constraints while developing it is best to
 /// limit the trace table to the smallest meeting your needs.
 
-pub fn check_constraints(
-    constraints: &Constraints,
-    table: &TraceTable,
-) -> Result<(), (usize, usize)> {
-    let trace_generator = FieldElement::root(table.num_rows()).unwrap();
-    let mut current_root = FieldElement::one();
+pub fn check_constraints<Field>(
+    constraints: &Constraints<Field>,
+    table: &TraceTable<Field>,
+) -> Result<(), (usize, usize)>
+where
+    Field: StarkField,
+    for<'a> &'a Field: RefFieldLike<Field>,
+{
+    let trace_generator = Field::root(table.num_rows()).unwrap();
+    let mut current_root = Field::one();
     let len = table.num_rows();
+    let expressions = substituted_expressions(constraints);
 
     for row in 0..len {
         // Note - Still in col row form
@@ -54,7 +110,7 @@ pub fn check_constraints(
                 }
             }
         };
-        for (which, expression) in constraints.expressions().iter().enumerate() {
+        for (which, expression) in expressions.iter().enumerate() {
             if !expression.check(&current_root, &trace).1 {
                 return Err((row, which));
             }
@@ -64,16 +120,345 @@ pub fn check_constraints(
     Ok(())
 }
 
-pub(crate) fn check_specific_constraint(
-    constraints: &Constraints,
-    table: &TraceTable,
+/// The expressions of the constraints with the public inputs substituted.
+fn substituted_expressions<Field>(
+    constraints: &Constraints<Field>,
+) -> Vec<RationalExpression<Field>>
+where
+    Field: StarkField,
+    for<'a> &'a Field: RefFieldLike<Field>,
+{
+    constraints
+        .expressions()
+        .iter()
+        .map(|expression| expression.substitute_public_inputs(constraints.public_inputs()))
+        .collect()
+}
+
+/// A trace cell used by a constraint
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct TraceCell<Field: StarkField = FieldElement> {
+    pub column: usize,
+    pub row:    usize,
+    pub value:  Field,
+}
+
+/// A constraint that does not hold on a row of the trace
+///
+/// The constraint evaluates to `numerator / denominator` on the row, it is
+/// violated when the denominator is zero but the numerator is not.
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct ConstraintViolation<Field: StarkField = FieldElement> {
+    pub row:         usize,
+    pub constraint:  usize,
+    pub name:        Option<String>,
+    pub numerator:   Field,
+    pub denominator: Field,
+    pub cells:       Vec<TraceCell<Field>>,
+}
+
+/// All constraint violations in a trace, see [`check_constraints_report`]
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct ConstraintReport<Field: StarkField = FieldElement> {
+    pub num_rows:        usize,
+    pub num_constraints: usize,
+    /// Violations ordered by row and then by constraint.
+    pub violations:      Vec<ConstraintViolation<Field>>,
+}
+
+impl<Field: StarkField> ConstraintReport<Field> {
+    pub fn is_ok(&self) -> bool {
+        self.violations.is_empty()
+    }
+
+    /// The first violation in the format of [`check_constraints`].
+    pub fn first(&self) -> Result<(), (usize, usize)> {
+        match self.violations.first() {
+            None => Ok(()),
+            Some(violation) => Err((violation.row, violation.constraint)),
+        }
+    }
+}
+
+impl<Field: StarkField> fmt::Display for ConstraintReport<Field> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "{} violations of {} constraints on {} rows",
+            self.violations.len(),
+            self.num_constraints,
+            self.num_rows
+        )?;
+        for violation in &self.violations {
+            write!(
+                f,
+                "row {}: constraint {}",
+                violation.row, violation.constraint
+            )?;
+            if let Some(name) = &violation.name {
+                write!(f, " ({})", name)?;
+            }
+            writeln!(
+                f,
+                ": numerator {:?}, denominator {:?}",
+                violation.numerator, violation.denominator
+            )?;
+            for cell in &violation.cells {
+                writeln!(
+                    f,
+                    "    trace[{}, {}] = {:?}",
+                    cell.row, cell.column, cell.value
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Evaluate an expression as an unreduced fraction.
+fn evaluate_fraction<Field>(
+    expression: &RationalExpression<Field>,
+    x: &Field,
+    trace: &dyn Fn(usize, isize) -> Field,
+) -> (Field, Field)
+where
+    Field: StarkField,
+    for<'a> &'a Field: RefFieldLike<Field>,
+{
+    use RationalExpression::*;
+    match expression {
+        X => (x.clone(), Field::one()),
+        Constant(c) => (c.clone(), Field::one()),
+        &Trace(i, j) => (trace(i, j), Field::one()),
+        Challenge(_) => panic!("Challenge should be substituted by Constant"),
+        PublicInput(_) => panic!("PublicInput should be substituted by Constant"),
+        Polynomial(polynomial, argument) => {
+            // Homogenize p(n / d) to avoid dividing by zero
+            let (numerator, denominator) = evaluate_fraction(argument, x, trace);
+            let coefficients = polynomial.coefficients();
+            let mut result = Field::zero();
+            let mut denominator_power = Field::one();
+            for coefficient in coefficients.iter().rev() {
+                result = result * &numerator + &(coefficient * &denominator_power);
+                denominator_power *= &denominator;
+            }
+            // Leave out the extra factor of d used for the constant term
+            let scale = denominator.pow(coefficients.len().saturating_sub(1));
+            (result, scale)
+        }
+        ClaimPolynomial(..) => panic!("ClaimPolynomial should be substituted by Polynomial"),
+        Add(a, b) => {
+            let (an, ad) = evaluate_fraction(a, x, trace);
+            let (bn, bd) = evaluate_fraction(b, x, trace);
+            (&an * &bd + &bn * &ad, ad * bd)
+        }
+        Neg(a) => {
+            let (numerator, denominator) = evaluate_fraction(a, x, trace);
+            (numerator.neg(), denominator)
+        }
+        Mul(a, b) => {
+            let (an, ad) = evaluate_fraction(a, x, trace);
+            let (bn, bd) = evaluate_fraction(b, x, trace);
+            (an * bn, ad * bd)
+        }
+        Inv(a) => {
+            let (numerator, denominator) = evaluate_fraction(a, x, trace);
+            (denominator, numerator)
+        }
+        Exp(a, e) => {
+            let (numerator, denominator) = evaluate_fraction(a, x, trace);
+            (numerator.pow(*e), denominator.pow(*e))
+        }
+    }
+}
+
+/// # Report all constraint violations in a trace table
+///
+/// Unlike [`check_constraints`] this does not stop at the first violation.
+/// The rows are checked in parallel. Each constraint is evaluated as an
+/// unreduced fraction and is violated on a row when the denominator is zero
+/// but the numerator is not. The optional `names` are used to label the
+/// constraints in the report.
+///
+/// Like [`check_constraints`] this can miss violations, for example when a
+/// numerator vanishes with a lower multiplicity than the denominator.
+pub fn check_constraints_report<Field>(
+    constraints: &Constraints<Field>,
+    table: &TraceTable<Field>,
+    names: Option<&[String]>,
+) -> ConstraintReport<Field>
+where
+    Field: StarkField,
+    for<'a> &'a Field: RefFieldLike<Field>,
+{
+    let len = table.num_rows();
+    let trace_generator = Field::root(len).unwrap();
+    let expressions = substituted_expressions(constraints);
+    let arguments = expressions
+        .iter()
+        .map(RationalExpression::trace_arguments)
+        .collect::<Vec<_>>();
+    // Offsets are small compared to the trace length
+    #[allow(clippy::cast_possible_wrap, clippy::cast_sign_loss)]
+    let cell_row =
+        |row: usize, offset: isize| (row as isize + offset).rem_euclid(len as isize) as usize;
+
+    let violations = (0..len)
+        .into_par_iter()
+        .flat_map(|row| {
+            let x = trace_generator.pow(row);
+            let trace = |i: usize, j: isize| table[(cell_row(row, j), i)].clone();
+            expressions
+                .iter()
+                .enumerate()
+                .filter_map(|(which, expression)| {
+                    let (numerator, denominator) = evaluate_fraction(expression, &x, &trace);
+                    if !denominator.is_zero() || numerator.is_zero() {
+                        return None;
+                    }
+                    Some(ConstraintViolation {
+                        row,
+                        constraint: which,
+                        name: names.and_then(|names| names.get(which).cloned()),
+                        numerator,
+                        denominator,
+                        cells: arguments[which]
+                            .iter()
+                            .map(|&(column, offset)| {
+                                let row = cell_row(row, offset);
+                                TraceCell {
+                                    column,
+                                    row,
+                                    value: table[(row, column)].clone(),
+                                }
+                            })
+                            .collect(),
+                    })
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    ConstraintReport {
+        num_rows: len,
+        num_constraints: constraints.len(),
+        violations,
+    }
+}
+
+/// # Exactly check a set of constraints on a trace table
+///
+/// Where [`check_constraints`] evaluates the constraints row by row, this
+/// follows the prover: the trace is interpolated and each constraint is
+/// evaluated with an [`AlgebraicGraph`] on a coset of the trace domain. The
+/// evaluations are interpolated again, giving the quotient of the numerator
+/// by the denominators.
+///
+/// The coset is larger than the numerator degree. If the denominators divide
+/// the numerator, the quotient is recovered exactly and has at most the
+/// degree of the numerator minus the denominator. Any higher degree means the
+/// division has a remainder and the constraint does not hold on the trace.
+/// Finally the quotient degree is checked against the bound of
+/// [`Constraints::degree`] used by the prover.
+///
+/// Returns the error for the first failing constraint. When this succeeds,
+/// the prover succeeds on the same constraints and trace. Challenges and
+/// claim polynomials need to be substituted before checking, public inputs
+/// are substituted here.
+pub fn check_constraints_exact<Field>(
+    constraints: &Constraints<Field>,
+    table: &TraceTable<Field>,
+) -> Result<(), Error>
+where
+    Field: StarkField,
+    for<'a> &'a Field: RefFieldLike<Field>,
+{
+    let trace_len = table.num_rows();
+    let bound = constraints.degree() * trace_len;
+    let expressions = substituted_expressions(constraints);
+    let degrees = expressions
+        .iter()
+        .map(|expression| expression.degree(trace_len - 1))
+        .collect::<Vec<_>>();
+    let coset_size = degrees
+        .iter()
+        .fold(bound, |size, (numerator, _)| max(size, numerator + 1))
+        .next_power_of_two();
+    let cofactor = Field::generator();
+    let trace_coset = evaluate_on_coset(&table.interpolate(), &cofactor, coset_size);
+    let shift = cofactor.inv().expect("Generator is zero");
+
+    let results = expressions
+        .par_iter()
+        .zip(degrees.par_iter())
+        .enumerate()
+        .map(|(constraint, (expression, &(numerator, denominator)))| {
+            let mut dag = AlgebraicGraph::new(&cofactor, coset_size, coset_size / trace_len);
+            let result = dag.expression(expression.clone());
+            let _ = dag.tree_shake(result);
+            dag.init(0);
+            let mut values = (0..coset_size)
+                .map(|_| dag.next(&trace_coset))
+                .collect::<Vec<_>>();
+            values.ifft();
+            permute(&mut values);
+            for (f, y) in geometric_series(&Field::one(), &shift).zip(values.iter_mut()) {
+                *y *= &f;
+            }
+            let degree = values.iter().rposition(|y| !y.is_zero()).unwrap_or(0);
+            if degree + denominator > numerator {
+                Err(Error::NotDivisible { constraint })
+            } else if degree >= bound {
+                Err(Error::DegreeTooHigh {
+                    constraint,
+                    degree,
+                    bound,
+                })
+            } else {
+                Ok(())
+            }
+        })
+        .collect::<Vec<_>>();
+    results.into_iter().collect()
+}
+
+/// Evaluate polynomials on the coset `cofactor * <root(size)>` in natural
+/// order.
+fn evaluate_on_coset<Field>(
+    polynomials: &[DensePolynomial<Field>],
+    cofactor: &Field,
+    size: usize,
+) -> TraceTable<Field>
+where
+    Field: StarkField,
+    for<'a> &'a Field: RefFieldLike<Field>,
+{
+    let mut table = TraceTable::new(size, polynomials.len());
+    for (column, polynomial) in polynomials.iter().enumerate() {
+        let mut values = vec![Field::zero(); size];
+        values.clone_shifted(polynomial.coefficients(), cofactor);
+        values.fft();
+        permute(&mut values);
+        for (row, value) in values.into_iter().enumerate() {
+            table[(row, column)] = value;
+        }
+    }
+    table
+}
+
+pub(crate) fn check_specific_constraint<Field>(
+    constraints: &Constraints<Field>,
+    table: &TraceTable<Field>,
     row: usize,
     which_constraint: usize,
-) -> bool {
-    let trace_generator = FieldElement::root(table.num_rows()).unwrap();
+) -> bool
+where
+    Field: StarkField,
+    for<'a> &'a Field: RefFieldLike<Field>,
+{
+    let trace_generator = Field::root(table.num_rows()).unwrap();
     let x;
     if row == 0 {
-        x = FieldElement::one();
+        x = Field::one();
     } else {
         x = trace_generator.pow(row - 1)
     }
@@ -94,16 +479,53 @@ pub(crate) fn check_specific_constraint(
     };
 
     constraints.expressions()[which_constraint]
+        .substitute_public_inputs(constraints.public_inputs())
         .check(&x, &trace)
         .1
 }
 
+/// Compute the later trace stages using random challenges.
+///
+/// Substitutes the challenges in the constraints and returns the trace table
+/// with the columns of all stages, ready to be checked. See
+/// [`prove_staged`](crate::prove_staged) for the arguments of `stages`.
+/// Returns `None` if a stage is missing or has the wrong dimensions.
+pub(crate) fn complete_stages<Field, S>(
+    constraints: &mut Constraints<Field>,
+    trace: TraceTable<Field>,
+    mut stages: S,
+) -> Option<TraceTable<Field>>
+where
+    Field: StarkField,
+    for<'a> &'a Field: RefFieldLike<Field>,
+    S: FnMut(usize, &[Field], &TraceTable<Field>) -> Option<TraceTable<Field>>,
+{
+    let mut rng = thread_rng();
+    let mut challenges = Vec::with_capacity(constraints.num_challenges());
+    let mut trace = trace;
+    for stage in 1..constraints.num_stages() {
+        for _ in constraints.stage_challenges(stage) {
+            challenges.push(random_element(&mut rng));
+        }
+        let stage_trace = stages(stage, &challenges, &trace)?;
+        if stage_trace.num_rows() != trace.num_rows()
+            || stage_trace.num_columns() != constraints.stage_columns(stage).len()
+        {
+            return None;
+        }
+        trace = trace.concat_columns(&stage_trace);
+    }
+    constraints.substitute_challenges(&challenges);
+    constraints.substitute_public_inputs();
+    Some(trace)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::{traits::tests::Recurrance, Provable, Verifiable};
     use zkp_macros_decl::field_element;
-    use zkp_primefield::Zero;
+    use zkp_primefield::{FieldElement, One, Zero};
     use zkp_u256::U256;
 
     #[test]
@@ -150,4 +572,98 @@ mod tests {
         trace[(0, 0)] = FieldElement::zero();
         assert_eq!(check_specific_constraint(&constraints, &trace, 0, 2), false);
     }
+
+    #[test]
+    fn report_test() {
+        let recurrance = Recurrance {
+            index:         1000,
+            initial_value: field_element!("cafebabe"),
+            exponent:      1,
+        };
+        let witness = recurrance.witness();
+        let claim = recurrance.claim();
+
+        let constraints = claim.constraints();
+        let mut trace = claim.trace(&witness);
+        let report = check_constraints_report(&constraints, &trace, None);
+        assert!(report.is_ok());
+        assert_eq!(report.num_constraints, constraints.len());
+
+        // Breaks the transitions into and out of row 800
+        trace[(800, 0)] = FieldElement::zero();
+        let names = (0..constraints.len())
+            .map(|i| format!("constraint {}", i))
+            .collect::<Vec<_>>();
+        let report = check_constraints_report(&constraints, &trace, Some(&names));
+        assert_eq!(report.first(), check_constraints(&constraints, &trace));
+        let locations = report
+            .violations
+            .iter()
+            .map(|violation| (violation.row, violation.constraint))
+            .collect::<Vec<_>>();
+        assert_eq!(locations, vec![(799, 0), (800, 1)]);
+        let violation = &report.violations[0];
+        assert_eq!(violation.name.as_deref(), Some("constraint 0"));
+        assert!(violation.denominator.is_zero());
+        assert!(!violation.numerator.is_zero());
+        assert!(violation.cells.contains(&TraceCell {
+            column: 0,
+            row:    800,
+            value:  FieldElement::zero(),
+        }));
+        assert!(report
+            .to_string()
+            .contains("row 799: constraint 0 (constraint 0)"));
+
+        let json = serde_json::to_string(&report).unwrap();
+        let deserialized: ConstraintReport = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized, report);
+    }
+
+    #[test]
+    fn exact_test() {
+        let recurrance = Recurrance {
+            index:         1000,
+            initial_value: field_element!("cafebabe"),
+            exponent:      1,
+        };
+        let witness = recurrance.witness();
+        let claim = recurrance.claim();
+
+        let constraints = claim.constraints();
+        let mut trace = claim.trace(&witness);
+        assert_eq!(check_constraints_exact(&constraints, &trace), Ok(()));
+        trace[(800, 0)] = FieldElement::zero();
+        assert_eq!(
+            check_constraints_exact(&constraints, &trace),
+            Err(Error::NotDivisible { constraint: 0 })
+        );
+    }
+
+    #[test]
+    fn exact_test_multiplicity() {
+        use RationalExpression::*;
+        let mut trace = TraceTable::new(8, 1);
+        for row in 1..8 {
+            trace[(row, 0)] = FieldElement::from(row);
+        }
+        // The trace polynomial has a simple root at one, which the row-wise
+        // checks accept for a double denominator.
+        let constraints =
+            Constraints::from_expressions((8, 1), vec![], vec![Trace(0, 0) / (X - 1).pow(2)])
+                .unwrap();
+        assert_eq!(check_constraints(&constraints, &trace), Ok(()));
+        assert!(check_constraints_report(&constraints, &trace, None).is_ok());
+        assert_eq!(
+            check_constraints_exact(&constraints, &trace),
+            Err(Error::NotDivisible { constraint: 0 })
+        );
+
+        let constraints = Constraints::from_expressions((8, 1), vec![], vec![
+            Trace(0, 0) / (X - 1),
+            Trace(0, 0) * (Trace(0, 0) - FieldElement::one()) * (X - 1),
+        ])
+        .unwrap();
+        assert_eq!(check_constraints_exact(&constraints, &trace), Ok(()));
+    }
 }
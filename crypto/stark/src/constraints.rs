@@ -1,11 +1,20 @@
-use crate::{polynomial::DensePolynomial, rational_expression::RationalExpression};
+use crate::{
+    field::StarkField, fri::FriParameters, polynomial::DensePolynomial,
+    rational_expression::RationalExpression,
+};
+#[cfg(feature = "std")]
+use crate::{
+    parameters::{OptimizationGoal, Parameters, SecurityModel},
+    soundness::Soundness,
+};
 use itertools::Itertools;
-use std::{collections::BTreeSet, fmt, prelude::v1::*};
-use zkp_primefield::{FieldElement, Root};
+use std::{collections::BTreeSet, convert::TryFrom, fmt, ops::Range, prelude::v1::*};
+use zkp_primefield::{FieldElement, RefFieldLike};
 
 #[derive(Clone, Debug)]
 pub enum Error {
     InvalidTraceLength,
+    UnreachableSecurity,
 }
 
 impl fmt::Display for Error {
@@ -13,6 +22,7 @@ impl fmt::Display for Error {
         use Error::*;
         match *self {
             InvalidTraceLength => write!(f, "Invalid trace length (must be power of two)"),
+            UnreachableSecurity => write!(f, "No parameters reach the target security"),
         }
     }
 }
@@ -29,12 +39,18 @@ impl fmt::Display for Error {
 // TODO Implement PartialEq
 #[derive(Clone)]
 #[cfg_attr(feature = "std", derive(Debug))]
-pub struct Constraints {
+pub struct Constraints<Field: StarkField = FieldElement> {
     channel_seed:   Vec<u8>,
     trace_nrows:    usize,
     trace_ncolumns: usize,
 
-    expressions: Vec<RationalExpression>,
+    /// Number of challenges and columns of each trace stage
+    stages: Vec<(usize, usize)>,
+
+    expressions: Vec<RationalExpression<Field>>,
+
+    /// Values of the `PublicInput(i)` leaves
+    public_inputs: Vec<Field>,
 
     /// The blowup factor
     ///
@@ -66,10 +82,32 @@ pub struct Constraints {
     /// they can be evaluated
     /// The following Vec of dense polys can be used to substitute claim
     /// polynomials inside of the prover.
-    pub claim_polynomials: Vec<DensePolynomial>,
+    pub claim_polynomials: Vec<DensePolynomial<Field>>,
+
+    /// Produce zero-knowledge proofs
+    ///
+    /// When set, the trace polynomials are masked with a random multiple of
+    /// the trace domain vanishing polynomial, a random masking polynomial is
+    /// committed to alongside the constraint polynomials and every Merkle
+    /// leaf is salted. This doubles the size of the committed polynomials.
+    pub zero_knowledge: bool,
+
+    /// Bind the constraint system to the proof transcript
+    ///
+    /// When set, a canonical encoding of the expressions and the claim
+    /// polynomials is absorbed into the transcript, see
+    /// [`transcript_seed`](Self::transcript_seed). This is the default. It
+    /// can be disabled for compatibility with verifiers that only hash the
+    /// channel seed, in which case the channel seed needs to capture the full
+    /// claim.
+    pub strong_fiat_shamir: bool,
 }
 
-impl Constraints {
+impl<Field> Constraints<Field>
+where
+    Field: StarkField,
+    for<'a> &'a Field: RefFieldLike<Field>,
+{
     fn default_fri_layout(trace_nrows: usize) -> Vec<usize> {
         // The binary logarithm of the final layer polynomial degree.
         const LOG2_TARGET: usize = 8;
@@ -102,10 +140,9 @@ impl Constraints {
     pub fn from_expressions(
         (trace_nrows, trace_ncolumns): (usize, usize),
         channel_seed: Vec<u8>,
-        expressions: Vec<RationalExpression>,
+        expressions: Vec<RationalExpression<Field>>,
     ) -> Result<Self, Error> {
-        let _ = FieldElement::root(trace_nrows).ok_or(Error::InvalidTraceLength)?;
-        // TODO: Hash expressions into channel seed
+        let _ = Field::root(trace_nrows).ok_or(Error::InvalidTraceLength)?;
         // TODO - Examine if we want to up these security params further.
         // 22.5*4  + 0 queries = 90
         // TODO: Sensible default for pow_bits. For small proofs it should be small.
@@ -113,12 +150,16 @@ impl Constraints {
             channel_seed,
             trace_nrows,
             trace_ncolumns,
+            stages: vec![(0, trace_ncolumns)],
             expressions,
+            public_inputs: vec![],
             blowup: 16,
             pow_bits: 0,
             num_queries: 45,
             fri_layout: Self::default_fri_layout(trace_nrows),
             claim_polynomials: vec![],
+            zero_knowledge: false,
+            strong_fiat_shamir: true,
         })
     }
 
@@ -132,20 +173,21 @@ impl Constraints {
     pub fn from_expressions_detailed(
         (trace_nrows, trace_ncolumns): (usize, usize),
         channel_seed: Vec<u8>,
-        expressions: Vec<RationalExpression>,
+        expressions: Vec<RationalExpression<Field>>,
         op_blowup: Option<usize>,
         op_pow_bits: Option<usize>,
         op_num_queries: Option<usize>,
         op_fri_layout: Option<Vec<usize>>,
     ) -> Result<Self, Error> {
-        let _ = FieldElement::root(trace_nrows).ok_or(Error::InvalidTraceLength)?;
-        // TODO: Hash expressions into channel seed
+        let _ = Field::root(trace_nrows).ok_or(Error::InvalidTraceLength)?;
         // 15*4 + 30 queries = 90
         Ok(Self {
             channel_seed,
             trace_nrows,
             trace_ncolumns,
+            stages: vec![(0, trace_ncolumns)],
             expressions,
+            public_inputs: vec![],
             blowup: match op_blowup {
                 Some(x) => x,
                 None => 16,
@@ -169,13 +211,146 @@ impl Constraints {
                 None => Self::default_fri_layout(trace_nrows),
             },
             claim_polynomials: vec![],
+            zero_knowledge: false,
+            strong_fiat_shamir: true,
         })
     }
 
+    /// Create constraints with parameters selected for a security target.
+    ///
+    /// See [`select_parameters`](Self::select_parameters), the selection is
+    /// returned to explain the choice.
+    #[cfg(feature = "std")]
+    pub fn from_expressions_with_security(
+        trace_dimensions: (usize, usize),
+        channel_seed: Vec<u8>,
+        expressions: Vec<RationalExpression<Field>>,
+        security_bits: usize,
+        model: SecurityModel,
+        goal: OptimizationGoal,
+    ) -> Result<(Self, Parameters), Error> {
+        let mut constraints = Self::from_expressions(trace_dimensions, channel_seed, expressions)?;
+        let parameters = constraints.select_parameters(security_bits, model, goal)?;
+        Ok((constraints, parameters))
+    }
+
+    /// Select the proof parameters for a target security level.
+    ///
+    /// Sets `blowup`, `num_queries`, `pow_bits` and `fri_layout` to the
+    /// combination reaching `security_bits` under `model` at the lowest
+    /// estimated cost for `goal`. The blowup is at least the constraint
+    /// degree. Stages and zero-knowledge change the costs, so these should be
+    /// configured first.
+    ///
+    /// Returns [`Error::UnreachableSecurity`] if no parameters reach
+    /// `security_bits`, for example because the field is too small. The
+    /// constraints are left unchanged in that case.
+    #[cfg(feature = "std")]
+    pub fn select_parameters(
+        &mut self,
+        security_bits: usize,
+        model: SecurityModel,
+        goal: OptimizationGoal,
+    ) -> Result<Parameters, Error> {
+        let parameters = Parameters::select(self, security_bits, model, goal)?;
+        self.blowup = parameters.blowup;
+        self.num_queries = parameters.num_queries;
+        self.pow_bits = parameters.pow_bits;
+        self.fri_layout = parameters.fri_layout.clone();
+        Ok(parameters)
+    }
+
     pub fn channel_seed(&self) -> &[u8] {
         &self.channel_seed
     }
 
+    /// Seed for the proof transcript.
+    ///
+    /// Starts with the channel seed, followed by the public inputs, the
+    /// [`zero_knowledge`](Self::zero_knowledge) flag, the proof parameters
+    /// (trace size, the number of challenges and columns of each stage,
+    /// blowup, proof of work bits, number of queries and FRI layout) and,
+    /// with [`strong_fiat_shamir`](Self::strong_fiat_shamir), the encoded
+    /// expressions and claim polynomials. All parts are length prefixed. For a
+    /// single stage without public inputs and with both `zero_knowledge` and
+    /// `strong_fiat_shamir` disabled, this is just the channel seed.
+    pub fn transcript_seed(&self) -> Vec<u8> {
+        fn write_usize(bytes: &mut Vec<u8>, value: usize) {
+            let value = u64::try_from(value).expect("Value does not fit 64 bits");
+            bytes.extend_from_slice(&value.to_be_bytes());
+        }
+        if self.public_inputs.is_empty()
+            && self.num_stages() == 1
+            && !self.zero_knowledge
+            && !self.strong_fiat_shamir
+        {
+            return self.channel_seed.clone();
+        }
+        let mut seed = Vec::new();
+        write_usize(&mut seed, self.channel_seed.len());
+        seed.extend_from_slice(&self.channel_seed);
+        write_usize(&mut seed, self.public_inputs.len());
+        for value in &self.public_inputs {
+            value.write_bytes(&mut seed);
+        }
+        seed.push(u8::from(self.zero_knowledge));
+        write_usize(&mut seed, self.trace_nrows);
+        write_usize(&mut seed, self.trace_ncolumns);
+        write_usize(&mut seed, self.num_stages());
+        for &(num_challenges, num_columns) in &self.stages {
+            write_usize(&mut seed, num_challenges);
+            write_usize(&mut seed, num_columns);
+        }
+        write_usize(&mut seed, self.blowup);
+        write_usize(&mut seed, self.pow_bits);
+        write_usize(&mut seed, self.num_queries);
+        write_usize(&mut seed, self.fri_layout.len());
+        for &reduction in &self.fri_layout {
+            write_usize(&mut seed, reduction);
+        }
+        if self.strong_fiat_shamir {
+            write_usize(&mut seed, self.expressions.len());
+            for expression in &self.expressions {
+                expression.write_bytes(&mut seed);
+            }
+            write_usize(&mut seed, self.claim_polynomials.len());
+            for polynomial in &self.claim_polynomials {
+                write_usize(&mut seed, polynomial.len());
+                for coefficient in polynomial.coefficients() {
+                    coefficient.write_bytes(&mut seed);
+                }
+            }
+        }
+        seed
+    }
+
+    /// Register a public input.
+    ///
+    /// Returns the index `i` by which the expressions refer to the value as
+    /// `PublicInput(i)`. Public inputs are absorbed into the proof
+    /// transcript.
+    pub fn add_public_input(&mut self, value: Field) -> usize {
+        self.public_inputs.push(value);
+        self.public_inputs.len() - 1
+    }
+
+    pub fn public_inputs(&self) -> &[Field] {
+        &self.public_inputs
+    }
+
+    /// Replace all `PublicInput(i)` leaves in the expressions by their
+    /// values.
+    ///
+    /// This changes the [`transcript_seed`](Self::transcript_seed), so it
+    /// should only be applied to copies used for evaluation.
+    pub fn substitute_public_inputs(&mut self) {
+        self.expressions = self
+            .expressions
+            .iter()
+            .map(|x| x.substitute_public_inputs(&self.public_inputs))
+            .collect();
+    }
+
     pub fn trace_nrows(&self) -> usize {
         self.trace_nrows
     }
@@ -184,6 +359,88 @@ impl Constraints {
         self.trace_ncolumns
     }
 
+    /// Add a trace stage that is committed to after all previous stages.
+    ///
+    /// Before the stage is committed to, `num_challenges` random values are
+    /// drawn from the channel. The expressions refer to them as
+    /// `Challenge(i)`, numbered consecutively over all stages. The
+    /// `num_columns` columns of the stage are numbered after those of the
+    /// previous stages and can be computed from the challenges.
+    pub fn add_stage(&mut self, num_challenges: usize, num_columns: usize) {
+        self.stages.push((num_challenges, num_columns));
+        self.trace_ncolumns += num_columns;
+    }
+
+    /// Number of separately committed trace stages, including the first.
+    pub fn num_stages(&self) -> usize {
+        self.stages.len()
+    }
+
+    /// Total number of challenges over all stages.
+    pub fn num_challenges(&self) -> usize {
+        self.stages.iter().map(|(challenges, _)| challenges).sum()
+    }
+
+    /// The trace columns committed to in the given stage.
+    pub fn stage_columns(&self, stage: usize) -> Range<usize> {
+        let start = self.stages[..stage]
+            .iter()
+            .map(|(_, columns)| columns)
+            .sum();
+        start..start + self.stages[stage].1
+    }
+
+    /// The challenges drawn right before the given stage is committed to.
+    pub fn stage_challenges(&self, stage: usize) -> Range<usize> {
+        let start = self.stages[..stage]
+            .iter()
+            .map(|(challenges, _)| challenges)
+            .sum();
+        start..start + self.stages[stage].0
+    }
+
+    /// Number of coefficients in the committed trace polynomials.
+    ///
+    /// This equals the trace length, unless the proof is zero-knowledge. In
+    /// that case the masked trace polynomials are twice as large.
+    pub fn trace_polynomial_len(&self) -> usize {
+        if self.zero_knowledge {
+            2 * self.trace_nrows
+        } else {
+            self.trace_nrows
+        }
+    }
+
+    /// Most evaluations of a trace polynomial opened in a proof.
+    ///
+    /// Each column is opened at every query and at the out of domain points
+    /// of its trace arguments. A zero-knowledge proof masks the trace
+    /// polynomials with only `trace_nrows` random coefficients, so it hides
+    /// the trace only if this does not exceed the trace length.
+    pub fn max_trace_openings(&self) -> usize {
+        let mut arguments = vec![0; self.trace_ncolumns()];
+        for (column, _) in self.trace_arguments() {
+            arguments[column] += 1;
+        }
+        self.num_queries + arguments.into_iter().max().unwrap_or(0)
+    }
+
+    /// Size of the low degree extension domain.
+    pub fn eval_domain_size(&self) -> usize {
+        self.trace_polynomial_len() * self.blowup
+    }
+
+    /// Parameters of the FRI low degree test of the DEEP composition
+    /// polynomial.
+    pub fn fri_parameters(&self) -> FriParameters {
+        FriParameters {
+            domain_size:    self.eval_domain_size(),
+            blowup:         self.blowup,
+            layout:         self.fri_layout.clone(),
+            zero_knowledge: self.zero_knowledge,
+        }
+    }
+
     pub fn len(&self) -> usize {
         self.expressions.len()
     }
@@ -192,22 +449,41 @@ impl Constraints {
         self.expressions().is_empty()
     }
 
-    pub fn expressions(&self) -> &[RationalExpression] {
+    pub fn expressions(&self) -> &[RationalExpression<Field>] {
         &self.expressions
     }
 
+    /// Degree of the constraints in multiples of the trace length.
+    ///
+    /// This is the maximum over the expressions of their trace degree and
+    /// their degree as a polynomial in `X` divided by the trace length,
+    /// rounded up. The latter counts powers of `X`, for example from
+    /// periodic columns multiplied with trace values, which the trace degree
+    /// alone does not. The number of constraint polynomials in a proof is
+    /// this degree rounded up to a power of two.
     pub fn degree(&self) -> usize {
+        let trace_len = self.trace_polynomial_len();
         self.expressions
             .iter()
             .map(|c| {
                 let (numerator_degree, denominator_degree) = c.trace_degree();
-                numerator_degree - denominator_degree
+                // The trace degree does not count powers of `X`, which matter
+                // for periodic columns multiplied with trace values.
+                let (numerator, denominator) = c.degree(trace_len - 1);
+                let degree = (numerator + 1).saturating_sub(denominator);
+                std::cmp::max(
+                    numerator_degree - denominator_degree,
+                    (degree + trace_len - 1) / trace_len,
+                )
             })
             .max()
             .expect("no constraints")
     }
 
-    // TODO: Better explanation with literature references.
+    /// Quick estimate of the security from the queries and proof of work.
+    ///
+    /// See [`soundness`](Self::soundness) for a breakdown that includes the
+    /// field size and the constraint degree.
     pub fn security_bits(&self) -> usize {
         // Our conservative formula is (1/2^blowup_log)^(queries/2)*(1/2^pow_bits)
         // So the bit security should be blowup_log*(queries/2) + pow_bits
@@ -215,6 +491,15 @@ impl Constraints {
         blowup_log * (self.num_queries / 2) + self.pow_bits
     }
 
+    /// Estimate the soundness of proofs under `model`.
+    ///
+    /// Accounts for the field size, trace length, constraint degree, blowup,
+    /// FRI layout, number of queries and proof of work.
+    #[cfg(feature = "std")]
+    pub fn soundness(&self, model: SecurityModel) -> Soundness {
+        Soundness::estimate(self, model)
+    }
+
     // Returns an upper bound on proof size in terms of bytes in the proof.
     // Note we expect that actual sizes are compressed by the removal of overlaps in
     // decommitments
@@ -224,8 +509,8 @@ impl Constraints {
         let trace_len_log = self.trace_nrows().trailing_zeros() as usize;
         // First we decommit two proofs for each query [one which is the evaluation
         // domain decommitment and one is the constraints]
-        let mut total_decommitment =
-            self.num_queries * (trace_len_log * self.trace_ncolumns() + trace_len_log);
+        let mut total_decommitment = self.num_queries
+            * (trace_len_log * self.trace_ncolumns() + trace_len_log * self.num_stages());
         // Now we account for the first layer which is 8 elements [assuming the worst
         // case we need to decommit 7 other elements].
         let mut current_size = trace_len_log - 3;
@@ -245,17 +530,18 @@ impl Constraints {
         32 * total_decommitment
     }
 
-    pub fn combine(&self, constraint_coefficients: &[FieldElement]) -> RationalExpression {
+    pub fn combine(&self, constraint_coefficients: &[Field]) -> RationalExpression<Field> {
         use RationalExpression::*;
         assert_eq!(2 * self.len(), constraint_coefficients.len());
-        let target_degree = self.degree() * self.trace_nrows() - 1;
+        let trace_len = self.trace_polynomial_len();
+        let target_degree = self.degree() * trace_len - 1;
 
         self.expressions
             .iter()
             .zip(constraint_coefficients.iter().tuples())
             .map(
-                |(constraint, (coefficient_low, coefficient_high))| -> RationalExpression {
-                    let (num, den) = constraint.degree(self.trace_nrows() - 1);
+                |(constraint, (coefficient_low, coefficient_high))| -> RationalExpression<Field> {
+                    let (num, den) = constraint.degree(trace_len - 1);
                     let adjustment_degree = target_degree + den - num;
                     let adjustment = Constant(coefficient_low.clone())
                         + Constant(coefficient_high.clone()) * X.pow(adjustment_degree);
@@ -277,10 +563,21 @@ impl Constraints {
     // This sets a the claim polynomials field
     // Note that since we didn't want to change the interface this is the
     // only way to set or change the field
-    pub fn add_claim_polynomials(&mut self, polys: Vec<DensePolynomial>) {
+    pub fn add_claim_polynomials(&mut self, polys: Vec<DensePolynomial<Field>>) {
         self.claim_polynomials = polys;
     }
 
+    /// Replace all `Challenge(i)` leaves in the expressions by the drawn
+    /// challenge values.
+    pub fn substitute_challenges(&mut self, challenges: &[Field]) {
+        assert_eq!(challenges.len(), self.num_challenges());
+        self.expressions = self
+            .expressions
+            .iter()
+            .map(|x| x.substitute_challenges(challenges))
+            .collect();
+    }
+
     // This function if called on a set of constraints which has both
     // Rational Expression claim polynomials in the constraints
     // and has set a claim_polynomials constraint field, will use the
@@ -304,6 +601,169 @@ mod tests {
     use zkp_primefield::FieldElement;
     use zkp_u256::U256;
 
+    #[test]
+    fn transcript_seed_test() {
+        use crate::{Constraints, RationalExpression::*};
+        let expressions = vec![(Trace(0, 0) - PublicInput(0)) / (X - 1)];
+        let mut constraints =
+            Constraints::from_expressions((16, 1), b"seed".to_vec(), expressions).unwrap();
+        assert_eq!(constraints.add_public_input(FieldElement::from(5)), 0);
+        let seed = constraints.transcript_seed();
+
+        // Public inputs and expressions are part of the seed
+        let mut other = constraints.clone();
+        let _ = other.add_public_input(FieldElement::from(6));
+        assert_ne!(other.transcript_seed(), seed);
+        let mut other = constraints.clone();
+        other.substitute_public_inputs();
+        assert_ne!(other.transcript_seed(), seed);
+
+        // So are the zero knowledge flag and the proof parameters
+        let mut other = constraints.clone();
+        other.zero_knowledge = true;
+        assert_ne!(other.transcript_seed(), seed);
+        let mut other = constraints.clone();
+        other.blowup *= 2;
+        assert_ne!(other.transcript_seed(), seed);
+        let mut other = constraints.clone();
+        other.num_queries += 1;
+        assert_ne!(other.transcript_seed(), seed);
+        let mut other = constraints.clone();
+        other.pow_bits += 1;
+        assert_ne!(other.transcript_seed(), seed);
+        let mut other = constraints.clone();
+        other.fri_layout.push(1);
+        assert_ne!(other.transcript_seed(), seed);
+
+        // Stages with the same total number of columns differ
+        let mut other = constraints.clone();
+        other.add_stage(1, 2);
+        assert_ne!(other.transcript_seed(), seed);
+        let mut split = constraints.clone();
+        split.add_stage(1, 1);
+        split.add_stage(0, 1);
+        assert_ne!(split.transcript_seed(), other.transcript_seed());
+        let mut challenges = constraints.clone();
+        challenges.add_stage(2, 2);
+        assert_ne!(challenges.transcript_seed(), other.transcript_seed());
+
+        // Without both the seed is the channel seed
+        let mut constraints =
+            Constraints::<FieldElement>::from_expressions((16, 1), b"seed".to_vec(), vec![X])
+                .unwrap();
+        constraints.strong_fiat_shamir = false;
+        assert_eq!(constraints.transcript_seed(), b"seed".to_vec());
+        let mut staged = constraints.clone();
+        staged.add_stage(1, 1);
+        assert_ne!(staged.transcript_seed(), b"seed".to_vec());
+        constraints.zero_knowledge = true;
+        assert_ne!(constraints.transcript_seed(), b"seed".to_vec());
+    }
+
+    #[test]
+    fn degree_test() {
+        use crate::{Constraints, DensePolynomial, RationalExpression::*};
+        let trace_length = 16;
+        let every_row = || (X - 1) / (X.pow(trace_length) - 1);
+        let degree = |expression| {
+            Constraints::from_expressions((trace_length, 1), vec![], vec![expression])
+                .unwrap()
+                .degree()
+        };
+        assert_eq!(degree((Trace(0, 1) - Trace(0, 0)) * every_row()), 1);
+        assert_eq!(degree((Trace(0, 1) - Trace(0, 0).pow(3)) * every_row()), 3);
+
+        // Powers of `X` multiplied with the trace add to the degree
+        let periodic = DensePolynomial::new(&vec![FieldElement::from(3); 16]);
+        let column = Polynomial(periodic.clone(), Box::new(X));
+        assert_eq!(
+            degree((Trace(0, 1) - Trace(0, 0) * column) * every_row()),
+            1
+        );
+        let column = Polynomial(periodic, Box::new(X.pow(4)));
+        assert_eq!(
+            degree((Trace(0, 1) - Trace(0, 0) * column) * every_row()),
+            4
+        );
+        assert_eq!(degree(Trace(0, 0) * X.pow(trace_length) * every_row()), 2);
+    }
+
+    /// The maximum trace degree, which used to be the constraint degree.
+    fn trace_degree(constraints: &crate::Constraints) -> usize {
+        constraints
+            .expressions()
+            .iter()
+            .map(|expression| {
+                let (numerator, denominator) = expression.trace_degree();
+                numerator - denominator
+            })
+            .max()
+            .unwrap()
+    }
+
+    #[test]
+    fn degree_counts_powers_of_x() {
+        use crate::{Constraints, DensePolynomial, RationalExpression::*};
+        let trace_length = 16;
+        let every_row = || (X - 1) / (X.pow(trace_length) - 1);
+
+        // A periodic column of degree 15 evaluated at `X^4` and multiplied
+        // with the trace, the trace degree does not see the powers of `X`.
+        let periodic = DensePolynomial::new(&vec![FieldElement::from(3); 16]);
+        let column = Polynomial(periodic, Box::new(X.pow(4)));
+        let constraints = Constraints::from_expressions((trace_length, 1), vec![], vec![
+            (Trace(0, 1) - Trace(0, 0) * column) * every_row(),
+        ])
+        .unwrap();
+        assert_eq!(trace_degree(&constraints), 1);
+        assert_eq!(constraints.degree(), 4);
+
+        // The same for a constraint weighted with a power of `X`
+        let constraints = Constraints::from_expressions((trace_length, 1), vec![], vec![
+            Trace(0, 0) * X.pow(trace_length) * every_row(),
+        ])
+        .unwrap();
+        assert_eq!(trace_degree(&constraints), 1);
+        assert_eq!(constraints.degree(), 2);
+    }
+
+    #[test]
+    fn degree_of_existing_systems() {
+        use crate::{Constraints, DensePolynomial, RationalExpression::*};
+
+        // The StarkWare and Solidity recurrance proofs
+        for &exponent in &[1, 2] {
+            let recurrance = Recurrance {
+                index:         1000,
+                initial_value: field_element!("cafebabe"),
+                exponent,
+            };
+            let constraints = recurrance.claim().constraints();
+            assert_eq!(constraints.degree(), trace_degree(&constraints));
+            assert_eq!(constraints.degree(), exponent);
+        }
+
+        // The `constant` and `claim_polynomial` Solidity examples
+        let value = FieldElement::from(1_325_123_410);
+        let constant = Constraints::from_expressions((2, 1), vec![], vec![
+            (Trace(0, 0) - Constant(value.clone())) / (X - 1),
+        ])
+        .unwrap();
+        let claim = Constraints::from_expressions((2, 1), vec![], vec![
+            (Trace(0, 0) - ClaimPolynomial(0, 0, Box::new(X), None)) / (X - 1),
+        ])
+        .unwrap();
+        let claim_polynomial = DensePolynomial::new(&[value]);
+        let concrete = Constraints::from_expressions((2, 1), vec![], vec![
+            claim.expressions()[0].substitute_claim(&[claim_polynomial]),
+        ])
+        .unwrap();
+        for constraints in &[constant, claim, concrete] {
+            assert_eq!(constraints.degree(), trace_degree(constraints));
+            assert_eq!(constraints.degree(), 1);
+        }
+    }
+
     #[test]
     fn size_estimate_test() {
         let recurrance = Recurrance {
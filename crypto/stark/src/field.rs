@@ -0,0 +1,275 @@
+// False positive: attribute has a use
+#[allow(clippy::useless_attribute)]
+// False positive: Importing preludes is allowed
+#[allow(clippy::wildcard_imports)]
+use std::prelude::v1::*;
+
+#[cfg(feature = "prover")]
+use rand::RngCore;
+use std::{convert::TryInto, fmt::Debug, hash::Hash};
+#[cfg(feature = "prover")]
+use tiny_keccak::{Hasher, Keccak};
+use zkp_hash::Hashable;
+use zkp_primefield::{
+    BabyBear, FieldLike, Goldilocks, Inv, Mersenne31, Parameters, Pow, PrimeField, RefFieldLike,
+};
+use zkp_u256::{Binary, U256};
+
+/// Fields that Stark proofs can be constructed over.
+///
+/// On top of the arithmetic in [`FieldLike`] this requires a multiplicative
+/// generator, used to shift the evaluation domain off the trace domain, and a
+/// byte encoding, used for the proof transcript and the Merkle tree leaves.
+/// The field needs roots of unity of sufficiently large powers of two for the
+/// evaluation domain, see [`Root`](zkp_primefield::Root).
+///
+/// Generic code additionally requires
+/// `for<'a> &'a Field: RefFieldLike<Field>` for inversions and
+/// exponentiations.
+pub trait StarkField:
+    FieldLike + From<usize> + From<i32> + Hash + Hashable + Debug + Send + Sync + 'static
+{
+    /// Number of bytes in the proof encoding of an element.
+    const BYTES: usize;
+
+    /// Generator of the multiplicative group.
+    fn generator() -> Self;
+
+    /// Number of bits in the modulus.
+    fn modulus_bits() -> usize;
+
+    /// Append the proof encoding of the element, exactly [`Self::BYTES`]
+    /// long.
+    fn write_bytes(&self, bytes: &mut Vec<u8>);
+
+    /// Decode an element from its proof encoding.
+    ///
+    /// Returns `None` if the bytes do not encode a field element.
+    fn from_bytes(bytes: &[u8]) -> Option<Self>;
+
+    /// Sample an element from 32 uniformly random bytes.
+    ///
+    /// Returns `None` if the sample is rejected, in which case the caller
+    /// should retry with fresh bytes. The resulting distribution must be
+    /// uniform.
+    fn from_random_bytes(bytes: &[u8; 32]) -> Option<Self>;
+}
+
+/// Prime fields with 256 bit representation, including the default
+/// [`FieldElement`](zkp_primefield::FieldElement).
+///
+/// Elements are encoded as 32 big-endian bytes in Montgomery form. Random
+/// elements are sampled by masking the random bytes to the bit length of the
+/// modulus and rejecting values that are out of range. The resulting value
+/// is interpreted in Montgomery form.
+impl<P> StarkField for PrimeField<P>
+where
+    P: Parameters<UInt = U256>,
+    Self: FieldLike,
+    for<'a> &'a Self: RefFieldLike<Self>,
+{
+    const BYTES: usize = 32;
+
+    fn generator() -> Self {
+        Self::generator()
+    }
+
+    fn modulus_bits() -> usize {
+        Self::modulus().most_significant_bit().unwrap_or(0) + 1
+    }
+
+    fn write_bytes(&self, bytes: &mut Vec<u8>) {
+        bytes.extend_from_slice(&self.as_montgomery().to_bytes_be());
+    }
+
+    fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        let uint = U256::from_bytes_be(bytes.try_into().ok()?);
+        if uint < Self::modulus() {
+            Some(Self::from_montgomery(uint))
+        } else {
+            None
+        }
+    }
+
+    fn from_random_bytes(bytes: &[u8; 32]) -> Option<Self> {
+        let mask = U256::MAX >> (256 - Self::modulus_bits());
+        let uint = U256::from_bytes_be(bytes) & mask;
+        if uint < Self::modulus() {
+            Some(Self::from_montgomery(uint))
+        } else {
+            None
+        }
+    }
+}
+
+/// Word sized fields.
+///
+/// Elements are encoded as big-endian bytes of the canonical value. Random
+/// elements are sampled from the leading bytes, masked to the bit length of
+/// the modulus and rejected when out of range.
+///
+/// Note that these fields are too small for the out of domain sampling to be
+/// sound on its own.
+macro_rules! small_stark_field {
+    ($field:ident, $uint:ty) => {
+        impl StarkField for $field {
+            const BYTES: usize = std::mem::size_of::<$uint>();
+
+            fn generator() -> Self {
+                Self::generator()
+            }
+
+            fn modulus_bits() -> usize {
+                (<$uint>::BITS - Self::MODULUS.leading_zeros()) as usize
+            }
+
+            fn write_bytes(&self, bytes: &mut Vec<u8>) {
+                bytes.extend_from_slice(&self.to_uint().to_be_bytes());
+            }
+
+            fn from_bytes(bytes: &[u8]) -> Option<Self> {
+                let uint = <$uint>::from_be_bytes(bytes.try_into().ok()?);
+                if uint < Self::MODULUS {
+                    Some(Self::from_uint(&uint))
+                } else {
+                    None
+                }
+            }
+
+            fn from_random_bytes(bytes: &[u8; 32]) -> Option<Self> {
+                let mask = <$uint>::MAX >> (<$uint>::BITS as usize - Self::modulus_bits());
+                let uint = <$uint>::from_be_bytes(bytes[..Self::BYTES].try_into().unwrap()) & mask;
+                if uint < Self::MODULUS {
+                    Some(Self::from_uint(&uint))
+                } else {
+                    None
+                }
+            }
+        }
+    };
+}
+
+small_stark_field!(Goldilocks, u64);
+small_stark_field!(BabyBear, u32);
+small_stark_field!(Mersenne31, u32);
+
+/// Derive a field element from the output of a hash.
+///
+/// Rejected samples are hashed again until one is accepted.
+#[cfg(feature = "prover")]
+pub(crate) fn from_entropy<Field: StarkField>(keccak: Keccak) -> Field {
+    let mut result = [0; 32];
+    keccak.finalize(&mut result);
+    loop {
+        if let Some(element) = Field::from_random_bytes(&result) {
+            break element;
+        }
+        let mut keccak = Keccak::v256();
+        keccak.update(&result);
+        keccak.finalize(&mut result);
+    }
+}
+
+/// Uniformly random field element.
+#[cfg(feature = "prover")]
+pub(crate) fn random_element<Field: StarkField>(rng: &mut impl RngCore) -> Field {
+    let mut bytes = [0_u8; 32];
+    loop {
+        rng.fill_bytes(&mut bytes);
+        if let Some(element) = Field::from_random_bytes(&bytes) {
+            break element;
+        }
+    }
+}
+
+/// Raise to a signed power, returns `None` for negative powers of zero.
+pub(crate) fn pow_signed<Field>(base: &Field, exponent: isize) -> Option<Field>
+where
+    Field: StarkField,
+    for<'a> &'a Field: RefFieldLike<Field>,
+{
+    let abs = exponent.unsigned_abs();
+    if exponent < 0 {
+        base.inv().map(|inverse| inverse.pow(abs))
+    } else {
+        Some(base.pow(abs))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zkp_macros_decl::hex;
+    use zkp_primefield::{FieldElement, One, Zero};
+
+    #[test]
+    fn test_bytes_roundtrip() {
+        let element = FieldElement::from(0x1234_5678_usize);
+        let mut bytes = Vec::new();
+        element.write_bytes(&mut bytes);
+        assert_eq!(bytes.len(), FieldElement::BYTES);
+        assert_eq!(bytes, element.as_montgomery().to_bytes_be().to_vec());
+        assert_eq!(FieldElement::from_bytes(&bytes), Some(element));
+    }
+
+    #[test]
+    fn test_from_bytes_out_of_range() {
+        let bytes = hex!("ffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff");
+        assert_eq!(FieldElement::from_bytes(&bytes), None);
+        assert_eq!(FieldElement::from_bytes(&bytes[1..]), None);
+    }
+
+    #[test]
+    fn test_from_random_bytes_masks() {
+        // The Stark field has 252 bits, so the top nibble is masked
+        let bytes = hex!("f000000000000000000000000000000000000000000000000000000000000001");
+        assert_eq!(
+            FieldElement::from_random_bytes(&bytes),
+            Some(FieldElement::from_montgomery(U256::ONE))
+        );
+        let bytes = hex!("0fffffffffffffffffffffffffffffffffffffffffffffffffffffffffffffff");
+        assert_eq!(FieldElement::from_random_bytes(&bytes), None);
+    }
+
+    #[test]
+    fn test_small_field_bytes() {
+        let element = Goldilocks::from(0x0102_0304_0506_0708_u64);
+        let mut bytes = Vec::new();
+        element.write_bytes(&mut bytes);
+        assert_eq!(bytes, vec![1, 2, 3, 4, 5, 6, 7, 8]);
+        assert_eq!(Goldilocks::from_bytes(&bytes), Some(element));
+        assert_eq!(Goldilocks::from_bytes(&[0xff; 8]), None);
+        assert_eq!(BabyBear::from_bytes(&[0x78, 0, 0, 1]), None);
+        assert_eq!(
+            Mersenne31::from_random_bytes(&[0xff; 32]),
+            None,
+            "2^31 - 1 is out of range"
+        );
+        let mut random = [0xff; 32];
+        random[3] = 0xfe;
+        assert_eq!(
+            Mersenne31::from_random_bytes(&random),
+            Some(Mersenne31::from(0x7fff_fffe_u32))
+        );
+    }
+
+    #[test]
+    fn test_pow_signed() {
+        let base = FieldElement::from(7);
+        assert_eq!(pow_signed(&base, 3), Some(base.pow(3_usize)));
+        assert_eq!(pow_signed(&base, -3), base.pow(3_usize).inv());
+        assert_eq!(pow_signed(&FieldElement::zero(), -1), None);
+        assert_eq!(
+            pow_signed(&FieldElement::one(), isize::MIN),
+            Some(FieldElement::one())
+        );
+    }
+
+    #[test]
+    fn test_modulus_bits() {
+        assert_eq!(FieldElement::modulus_bits(), 252);
+        assert_eq!(Goldilocks::modulus_bits(), 64);
+        assert_eq!(BabyBear::modulus_bits(), 31);
+        assert_eq!(Mersenne31::modulus_bits(), 31);
+    }
+}
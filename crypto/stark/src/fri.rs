@@ -0,0 +1,756 @@
+#[cfg(feature = "prover")]
+use crate::channel::Writable;
+#[cfg(feature = "prover")]
+use crate::field::random_element;
+#[cfg(feature = "prover")]
+use crate::observer::fraction;
+use crate::{
+    channel::{RandomGenerator, Replayable, ReplayableSlice},
+    field::StarkField,
+    polynomial::DensePolynomial,
+};
+#[cfg(feature = "prover")]
+use itertools::Itertools;
+#[cfg(feature = "prover")]
+use rand::thread_rng;
+#[cfg(feature = "prover")]
+use std::path::Path;
+use std::{collections::BTreeMap, fmt, prelude::v1::*};
+use zkp_hash::{Hash, Hasher};
+#[cfg(feature = "prover")]
+use zkp_hash::MaskedKeccak;
+use zkp_merkle_tree::{Commitment, Error as MerkleError, Proof as MerkleProof};
+#[cfg(feature = "prover")]
+use zkp_merkle_tree::{Tree, VectorCommitment};
+#[cfg(feature = "prover")]
+use zkp_mmap_vec::MmapVec;
+#[cfg(feature = "prover")]
+use zkp_primefield::{fft::permute, Fft};
+use zkp_primefield::{fft::permute_index, FieldElement, Inv, Pow, RefFieldLike};
+
+type Result<T> = std::result::Result<T, Error>;
+
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum Error {
+    RootUnavailable,
+    InvalidLayout,
+    InvalidCommitment,
+    FinalLayerMismatch,
+    Merkle(MerkleError),
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use Error::*;
+        match *self {
+            RootUnavailable => write!(f, "The prime field doesn't have a root of this order"),
+            InvalidLayout => {
+                write!(
+                    f,
+                    "The FRI layout must fold one to four times per layer within the degree bound"
+                )
+            }
+            InvalidCommitment => write!(f, "A FRI layer commitment is incorrect"),
+            FinalLayerMismatch => {
+                write!(
+                    f,
+                    "The folded values do not match the final layer polynomial"
+                )
+            }
+            Merkle(ref e) => fmt::Display::fmt(e, f),
+        }
+    }
+}
+
+impl From<MerkleError> for Error {
+    fn from(err: MerkleError) -> Self {
+        Self::Merkle(err)
+    }
+}
+
+/// Configuration of a FRI low degree test
+///
+/// The first layer consists of the evaluations of a polynomial of degree less
+/// than `domain_size / blowup` on a coset of size `domain_size`, in
+/// bit-reversed order. Each committed layer is folded into the next by the
+/// number of reductions in `layout`, after which the final layer is written in
+/// coefficient form.
+#[derive(Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct FriParameters {
+    /// Size of the evaluation domain of the first layer
+    pub domain_size: usize,
+
+    /// Ratio of the evaluation domain size to the degree bound
+    pub blowup: usize,
+
+    /// Number of reductions between layer commitments
+    ///
+    /// Leaves of a layer commitment hold cosets of `2^reductions` values,
+    /// which fold into a single value of the next layer. Each layer folds one
+    /// to four times, see [`validate`](Self::validate).
+    pub layout: Vec<usize>,
+
+    /// Append a random salt to every leaf
+    pub zero_knowledge: bool,
+}
+
+impl FriParameters {
+    pub fn new(domain_size: usize, blowup: usize, layout: Vec<usize>) -> Self {
+        Self {
+            domain_size,
+            blowup,
+            layout,
+            zero_knowledge: false,
+        }
+    }
+
+    /// Fold in cosets of `coset_size` until the degree bound is at most
+    /// `final_layer_degree`.
+    ///
+    /// The last layer folds the remaining reductions if they are fewer. Coset
+    /// sizes above 16 give an invalid layout.
+    pub fn with_coset_size(
+        domain_size: usize,
+        blowup: usize,
+        coset_size: usize,
+        final_layer_degree: usize,
+    ) -> Self {
+        assert!(coset_size.is_power_of_two() && coset_size > 1);
+        let log_coset_size = coset_size.trailing_zeros() as usize;
+        let log_degree = (domain_size / blowup).trailing_zeros() as usize;
+        let log_final = final_layer_degree.next_power_of_two().trailing_zeros() as usize;
+        let num_reductions = log_degree.saturating_sub(log_final);
+        let mut layout = vec![log_coset_size; num_reductions / log_coset_size];
+        if num_reductions % log_coset_size != 0 {
+            layout.push(num_reductions % log_coset_size);
+        }
+        Self::new(domain_size, blowup, layout)
+    }
+
+    /// Check that every layer folds one to four times and that the layers do
+    /// not fold below a single coefficient.
+    pub fn validate(&self) -> Result<()> {
+        let log_degree = self.degree_bound().trailing_zeros() as usize;
+        if self
+            .layout
+            .iter()
+            .any(|reductions| !(1..=4).contains(reductions))
+            || self.layout.iter().sum::<usize>() > log_degree
+        {
+            return Err(Error::InvalidLayout);
+        }
+        Ok(())
+    }
+
+    /// Degree bound of the first layer.
+    pub fn degree_bound(&self) -> usize {
+        self.domain_size / self.blowup
+    }
+
+    /// Degree bound of the final layer, the number of coefficients written.
+    pub fn final_layer_degree(&self) -> usize {
+        self.degree_bound() >> self.layout.iter().sum::<usize>()
+    }
+}
+
+#[cfg(feature = "prover")]
+#[derive(Clone, Debug)]
+pub(crate) struct FriLeaves<Field: StarkField> {
+    pub(crate) coset_size: usize,
+    pub(crate) layer:      MmapVec<Field>,
+    /// Random salt for each leaf, empty if the proof is not zero-knowledge.
+    pub(crate) salts:      Vec<Field>,
+}
+
+#[cfg(feature = "prover")]
+type FriTree<Field, H> = Tree<FriLeaves<Field>, H>;
+
+// Merkle tree for FRI layers with coset size
+#[cfg(feature = "prover")]
+impl<Field: StarkField> VectorCommitment for FriLeaves<Field> {
+    type Leaf = Vec<Field>;
+
+    fn len(&self) -> usize {
+        debug_assert_eq!(self.layer.len() % self.coset_size, 0);
+        self.layer.len() / self.coset_size
+    }
+
+    fn leaf(&self, index: usize) -> Self::Leaf {
+        let mut internal_leaf = Vec::with_capacity(self.coset_size);
+        for j in 0..self.coset_size {
+            internal_leaf.push(self.layer[index * self.coset_size + j].clone());
+        }
+        if let Some(salt) = self.salts.get(index) {
+            internal_leaf.push(salt.clone());
+        }
+        internal_leaf
+    }
+
+    fn leaf_hash<H: Hasher>(&self, index: usize) -> Hash {
+        if self.coset_size == 1 && self.salts.is_empty() {
+            // For a single element, return its hash.
+            self.layer[index].hash_with::<H>()
+        } else {
+            // Concatenate the element hashes and hash the result.
+            let mut hasher = H::default();
+            for j in 0..self.coset_size {
+                hasher.update(
+                    self.layer[index * self.coset_size + j]
+                        .hash_with::<H>()
+                        .as_bytes(),
+                );
+            }
+            if let Some(salt) = self.salts.get(index) {
+                hasher.update(salt.hash_with::<H>().as_bytes());
+            }
+            hasher.hash()
+        }
+    }
+}
+
+/// Prover side of the FRI low degree test
+///
+/// [`commit`](Self::commit) writes the layer commitments and the final layer
+/// to the channel, drawing a folding coefficient after each commitment.
+/// [`decommit`](Self::decommit) then opens the layers at the query indices.
+#[cfg(feature = "prover")]
+#[derive(Debug)]
+pub struct FriProver<Field: StarkField = FieldElement, H: Hasher = MaskedKeccak> {
+    pub(crate) trees:       Vec<FriTree<Field, H>>,
+    pub(crate) final_layer: Vec<Field>,
+}
+
+#[cfg(feature = "prover")]
+impl<Field, H> FriProver<Field, H>
+where
+    Field: StarkField,
+    for<'a> &'a Field: RefFieldLike<Field>,
+    H: Hasher,
+{
+    pub fn commit<C>(
+        parameters: &FriParameters,
+        first_layer: MmapVec<Field>,
+        channel: &mut C,
+    ) -> Result<Self>
+    where
+        C: RandomGenerator<Field>
+            + for<'a> Writable<&'a Commitment>
+            + for<'a> Writable<&'a [Field]>,
+    {
+        Self::commit_with_progress(parameters, first_layer, channel, None, |_| Ok(()))
+    }
+
+    /// Like [`commit`](Self::commit), calling `progress` with the fraction
+    /// of the layers done after each layer. An error from `progress` stops
+    /// the commitment. Large layers and trees are allocated in `scratch`, see
+    /// [`MmapVec::with_capacity_scratch`].
+    #[allow(clippy::too_many_lines)]
+    pub(crate) fn commit_with_progress<C, E, P>(
+        parameters: &FriParameters,
+        first_layer: MmapVec<Field>,
+        channel: &mut C,
+        scratch: Option<&Path>,
+        mut progress: P,
+    ) -> std::result::Result<Self, E>
+    where
+        C: RandomGenerator<Field>
+            + for<'a> Writable<&'a Commitment>
+            + for<'a> Writable<&'a [Field]>,
+        E: From<Error>,
+        P: FnMut(f64) -> std::result::Result<(), E>,
+    {
+        assert_eq!(first_layer.len(), parameters.domain_size);
+        parameters.validate()?;
+        let mut trees: Vec<FriTree<Field, H>> = Vec::with_capacity(parameters.layout.len());
+
+        // Compute 1/x for the fri layer. We only compute the even coordinates.
+        // OPT: Can these be efficiently computed on the fly?
+        let x_inv = {
+            let n = first_layer.len();
+            let root_inv = Field::root(n).ok_or(Error::RootUnavailable)?.inv().unwrap();
+            let mut x_inv = MmapVec::with_capacity_scratch(n / 2, scratch);
+            let mut accumulator = Field::one();
+            for _ in 0..n / 2 {
+                x_inv.push(accumulator.clone());
+                accumulator *= &root_inv;
+            }
+            permute(&mut x_inv);
+            x_inv
+        };
+
+        let mut next_layer = first_layer;
+        for (index, &n_reductions) in parameters.layout.iter().enumerate() {
+            // Allocate next and swap ownership
+            let mut layer =
+                MmapVec::with_capacity_scratch(next_layer.len() / (1 << n_reductions), scratch);
+            std::mem::swap(&mut layer, &mut next_layer);
+
+            // Create tree from layer
+            // FRI layout values are small.
+            #[allow(clippy::cast_possible_truncation)]
+            let coset_size = 2_usize.pow(n_reductions as u32);
+            let salts = if parameters.zero_knowledge {
+                let mut rng = thread_rng();
+                (0..layer.len() / coset_size)
+                    .map(|_| random_element(&mut rng))
+                    .collect()
+            } else {
+                Vec::new()
+            };
+            let tree = FriTree::from_leaves_in(
+                FriLeaves {
+                    coset_size,
+                    layer,
+                    salts,
+                },
+                scratch,
+            )
+            .map_err(Error::from)?;
+            trees.push(tree);
+            let tree = trees.last().unwrap();
+            let layer = &tree.leaves().layer;
+
+            // Write commitment and pull coefficient
+            channel.write(tree.commitment());
+            let coefficient: Field = channel.get_random();
+
+            // Fold layer up to three times
+            // TODO: Capture the pattern in a macro and DRY.
+            // OPT: Parallelization
+            // OPT: The structure in x_inv should allow faster methods,
+            // like in a radix-4 and radix-8 fft.
+            let layer = layer.iter();
+            match n_reductions {
+                1 => {
+                    next_layer.extend(
+                        layer
+                            .tuples()
+                            .zip(x_inv.iter())
+                            .map(|((p0, p1), x_inv)| (p0 + p1) + &coefficient * x_inv * (p0 - p1)),
+                    )
+                }
+                2 => {
+                    let coefficient_2 = coefficient.square();
+                    next_layer.extend(
+                        layer
+                            .tuples()
+                            .zip(x_inv.iter())
+                            .map(|((p0, p1), x_inv)| (p0 + p1) + &coefficient * x_inv * (p0 - p1))
+                            .tuples()
+                            .zip(x_inv.iter())
+                            .map(|((p0, p1), x_inv)| {
+                                (&p0 + &p1) + &coefficient_2 * x_inv * (p0 - p1)
+                            }),
+                    )
+                }
+                3 => {
+                    let coefficient_2 = coefficient.square();
+                    let coefficient_4 = coefficient_2.square();
+                    next_layer.extend(
+                        layer
+                            .tuples()
+                            .zip(x_inv.iter())
+                            .map(|((p0, p1), x_inv)| (p0 + p1) + &coefficient * x_inv * (p0 - p1))
+                            .tuples()
+                            .zip(x_inv.iter())
+                            .map(|((p0, p1), x_inv)| {
+                                (&p0 + &p1) + &coefficient_2 * x_inv * (p0 - p1)
+                            })
+                            .tuples()
+                            .zip(x_inv.iter())
+                            .map(|((p0, p1), x_inv)| {
+                                (&p0 + &p1) + &coefficient_4 * x_inv * (p0 - p1)
+                            }),
+                    )
+                }
+                // TODO: Is there a use case for 4 layer folds?
+                4 => {
+                    let coefficient_2 = coefficient.square();
+                    let coefficient_4 = coefficient_2.square();
+                    let coefficient_8 = coefficient_4.square();
+                    next_layer.extend(
+                        layer
+                            .tuples()
+                            .zip(x_inv.iter())
+                            .map(|((p0, p1), x_inv)| (p0 + p1) + &coefficient * x_inv * (p0 - p1))
+                            .tuples()
+                            .zip(x_inv.iter())
+                            .map(|((p0, p1), x_inv)| {
+                                (&p0 + &p1) + &coefficient_2 * x_inv * (p0 - p1)
+                            })
+                            .tuples()
+                            .zip(x_inv.iter())
+                            .map(|((p0, p1), x_inv)| {
+                                (&p0 + &p1) + &coefficient_4 * x_inv * (p0 - p1)
+                            })
+                            .tuples()
+                            .zip(x_inv.iter())
+                            .map(|((p0, p1), x_inv)| {
+                                (&p0 + &p1) + &coefficient_8 * x_inv * (p0 - p1)
+                            }),
+                    )
+                }
+                // The layout is validated above
+                _ => unreachable!(),
+            }
+            progress(fraction(index + 1, parameters.layout.len()))?;
+        }
+
+        // Write the final layer coefficients
+        let n_coefficients = next_layer.len() / parameters.blowup;
+        let points = &mut next_layer[0..n_coefficients];
+        permute(points);
+        points.ifft();
+        permute(points);
+        channel.write(&*points);
+
+        Ok(Self {
+            trees,
+            final_layer: points.to_vec(),
+        })
+    }
+
+    /// Commitments to the layers, in the order they are written.
+    pub fn commitments(&self) -> Vec<Commitment> {
+        self.trees
+            .iter()
+            .map(|tree| tree.commitment().clone())
+            .collect()
+    }
+
+    /// Coefficients of the final layer polynomial.
+    pub fn final_layer(&self) -> &[Field] {
+        &self.final_layer
+    }
+
+    /// Open the layers at the sorted first layer indices.
+    ///
+    /// Values at the indices themselves are not written, the verifier obtains
+    /// them from the previous layer.
+    pub fn decommit<C>(&self, query_indices: &[usize], channel: &mut C) -> Result<()>
+    where
+        C: for<'a> Writable<&'a Field> + for<'a> Writable<&'a MerkleProof<H>>,
+    {
+        let mut previous_indices: Vec<usize> = query_indices.to_vec();
+
+        for tree in &self.trees {
+            let coset_size = tree.leaves().coset_size;
+
+            let new_indices: Vec<usize> = previous_indices
+                .iter()
+                .map(|x| x / coset_size)
+                .dedup()
+                .collect();
+
+            for i in &new_indices {
+                // TODO: Write entire tree.leaf(i)
+                for j in 0..coset_size {
+                    let n = i * coset_size + j;
+                    match previous_indices.binary_search(&n) {
+                        Ok(_) => (),
+                        Err(_) => channel.write(&tree.leaves().layer[n]),
+                    }
+                }
+                if let Some(salt) = tree.leaves().salts.get(*i) {
+                    channel.write(salt);
+                }
+            }
+            channel.write(&tree.open(&new_indices)?);
+            previous_indices = new_indices;
+        }
+        Ok(())
+    }
+}
+
+/// Verifier side of the FRI low degree test
+///
+/// [`read_commitments`](Self::read_commitments) replays what
+/// [`FriProver::commit`] writes, [`verify`](Self::verify) what
+/// [`FriProver::decommit`] writes.
+#[derive(Clone)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct FriVerifier<Field: StarkField = FieldElement> {
+    parameters:   FriParameters,
+    commitments:  Vec<Commitment>,
+    coefficients: Vec<Field>,
+    final_layer:  Vec<Field>,
+}
+
+impl<Field> FriVerifier<Field>
+where
+    Field: StarkField,
+    for<'a> &'a Field: RefFieldLike<Field>,
+{
+    pub fn read_commitments<C>(parameters: FriParameters, channel: &mut C) -> Result<Self>
+    where
+        C: RandomGenerator<Field> + Replayable<Hash> + ReplayableSlice<Field>,
+    {
+        parameters.validate()?;
+        let mut commitments = Vec::with_capacity(parameters.layout.len());
+        let mut coefficients = Vec::with_capacity(parameters.layout.len());
+        let mut size = parameters.domain_size;
+        for &reductions in &parameters.layout {
+            size >>= reductions;
+            let hash: Hash = channel.replay();
+            commitments.push(Commitment::from_size_hash(size, &hash)?);
+            coefficients.push(channel.get_random());
+        }
+        let final_layer = channel.replay_slice(parameters.final_layer_degree());
+        Ok(Self {
+            parameters,
+            commitments,
+            coefficients,
+            final_layer,
+        })
+    }
+
+    /// Coefficients of the final layer polynomial.
+    pub fn final_layer(&self) -> &[Field] {
+        &self.final_layer
+    }
+
+    /// Check the layer openings against the first layer values.
+    ///
+    /// The `(index, value)` pairs of the first layer are sorted by index. The
+    /// caller is responsible for their correctness, for example by opening
+    /// another commitment or computing them from opened values.
+    pub fn verify<H, C>(&self, first_layer: &[(usize, Field)], channel: &mut C) -> Result<()>
+    where
+        H: Hasher,
+        C: Replayable<Field> + Replayable<Hash>,
+    {
+        let mut values: BTreeMap<usize, Field> = first_layer.iter().cloned().collect();
+        let mut len = self.parameters.domain_size;
+        for ((commitment, coefficient), &reductions) in self
+            .commitments
+            .iter()
+            .zip(&self.coefficients)
+            .zip(&self.parameters.layout)
+        {
+            let coset_size = 1 << reductions;
+            let mut indices: Vec<usize> = values.keys().map(|i| i / coset_size).collect();
+            indices.dedup();
+
+            // Leaves with the values of the previous layer and the openings
+            let mut leaves = Vec::with_capacity(indices.len());
+            for &i in &indices {
+                let mut leaf = Vec::with_capacity(coset_size + 1);
+                for n in i * coset_size..(i + 1) * coset_size {
+                    leaf.push(match values.get(&n) {
+                        Some(value) => value.clone(),
+                        None => channel.replay(),
+                    });
+                }
+                if self.parameters.zero_knowledge {
+                    // The salt is part of the leaf, but not of the coset
+                    leaf.push(channel.replay());
+                }
+                leaves.push((i, leaf));
+            }
+            let hashes: Vec<Hash> = channel.replay_many(commitment.proof_size(&indices)?);
+            let proof = MerkleProof::<H>::from_hashes_with(commitment, &indices, &hashes)?;
+            if proof.verify(&leaves).is_err() {
+                return Err(Error::InvalidCommitment);
+            }
+
+            let root_inv = Field::root(len)
+                .ok_or(Error::RootUnavailable)?
+                .inv()
+                .unwrap();
+            values = leaves
+                .iter()
+                .map(|(i, leaf)| {
+                    let value = fold(
+                        &leaf[..coset_size],
+                        coefficient,
+                        &root_inv,
+                        i * coset_size / 2,
+                        len,
+                    );
+                    (*i, value)
+                })
+                .collect();
+            len >>= reductions;
+        }
+
+        // The folded values are evaluations of the final layer polynomial
+        let root = Field::root(len).ok_or(Error::RootUnavailable)?;
+        let final_layer = DensePolynomial::new(&self.final_layer);
+        for (index, value) in &values {
+            if final_layer.evaluate(&root.pow(permute_index(len, *index))) != *value {
+                return Err(Error::FinalLayerMismatch);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Fold a coset of a layer of size `len` into a single value.
+///
+/// The coset starts at pair `index` and `root_inv` is the inverse of the
+/// `len`-th root of unity.
+fn fold<Field>(
+    coset: &[Field],
+    coefficient: &Field,
+    root_inv: &Field,
+    mut index: usize,
+    mut len: usize,
+) -> Field
+where
+    Field: StarkField,
+    for<'a> &'a Field: RefFieldLike<Field>,
+{
+    let mut coefficient = coefficient.clone();
+    let mut root_inv = root_inv.clone();
+    let mut coset = coset.to_vec();
+    while coset.len() > 1 {
+        coset = coset
+            .chunks(2)
+            .enumerate()
+            .map(|(k, pair)| {
+                let x_inv = root_inv.pow(permute_index(len / 2, index + k));
+                (&pair[0] + &pair[1]) + &coefficient * x_inv * (&pair[0] - &pair[1])
+            })
+            .collect();
+        len /= 2;
+        index /= 2;
+        coefficient = coefficient.square();
+        root_inv = root_inv.square();
+    }
+    coset.pop().unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::channel::{ProverChannel, VerifierChannel};
+    use zkp_primefield::{Goldilocks, One, Zero};
+
+    fn random_layer<Field>(parameters: &FriParameters, seed: usize) -> MmapVec<Field>
+    where
+        Field: StarkField,
+        for<'a> &'a Field: RefFieldLike<Field>,
+    {
+        let coefficients = (0..parameters.degree_bound())
+            .map(|i| Field::from(i * seed + 1))
+            .collect::<Vec<_>>();
+        DensePolynomial::new(&coefficients).low_degree_extension(parameters.blowup)
+    }
+
+    // Prove and verify, the first layer values are written to the channel.
+    fn prove_and_verify<Field>(parameters: &FriParameters, layer: MmapVec<Field>) -> Result<()>
+    where
+        Field: StarkField,
+        for<'a> &'a Field: RefFieldLike<Field>,
+    {
+        let mut channel = ProverChannel::<MaskedKeccak>::default();
+        channel.initialize(b"fri");
+        let values = layer.to_vec();
+        let prover = FriProver::<Field>::commit(parameters, layer, &mut channel).unwrap();
+        let last = parameters.domain_size - 1;
+        let indices = vec![0, 1, 5, 5, 37, 511, 512, 1000, last];
+        for &index in &indices {
+            channel.write(&values[index]);
+        }
+        prover.decommit(&indices, &mut channel).unwrap();
+
+        let mut channel = VerifierChannel::<MaskedKeccak>::new(channel.proof);
+        channel.initialize(b"fri");
+        let verifier = FriVerifier::<Field>::read_commitments(parameters.clone(), &mut channel)?;
+        assert_eq!(verifier.final_layer(), prover.final_layer());
+        let first_layer = indices
+            .iter()
+            .map(|&index| (index, channel.replay()))
+            .collect::<Vec<_>>();
+        verifier.verify::<MaskedKeccak, _>(&first_layer, &mut channel)?;
+        assert!(channel.at_end());
+        Ok(())
+    }
+
+    #[test]
+    fn test_with_coset_size() {
+        let parameters = FriParameters::with_coset_size(1 << 14, 16, 8, 16);
+        assert_eq!(parameters.layout, vec![3, 3]);
+        assert_eq!(parameters.final_layer_degree(), 16);
+        let parameters = FriParameters::with_coset_size(1 << 14, 16, 4, 1);
+        assert_eq!(parameters.layout, vec![2, 2, 2, 2, 2]);
+        assert_eq!(parameters.final_layer_degree(), 1);
+        let parameters = FriParameters::with_coset_size(1 << 14, 16, 16, 1);
+        assert_eq!(parameters.layout, vec![4, 4, 2]);
+    }
+
+    #[test]
+    fn test_fri_layouts() {
+        for layout in &[vec![], vec![1], vec![3, 2], vec![2, 1, 4, 2], vec![4, 4, 2]] {
+            let parameters = FriParameters::new(1 << 12, 4, layout.clone());
+            let layer = random_layer::<FieldElement>(&parameters, 7);
+            prove_and_verify(&parameters, layer).unwrap();
+        }
+    }
+
+    #[test]
+    fn test_fri_invalid_layouts() {
+        let coset_32 = FriParameters::with_coset_size(1 << 12, 4, 32, 1);
+        assert_eq!(coset_32.layout, vec![5, 5]);
+        for parameters in &[
+            coset_32,
+            FriParameters::new(1 << 12, 4, vec![3, 0, 2]),
+            FriParameters::new(1 << 12, 4, vec![4, 4, 4]),
+        ] {
+            assert_eq!(parameters.validate(), Err(Error::InvalidLayout));
+            let layer = random_layer::<FieldElement>(parameters, 7);
+            let mut channel = ProverChannel::<MaskedKeccak>::default();
+            assert_eq!(
+                FriProver::<FieldElement>::commit(parameters, layer, &mut channel).err(),
+                Some(Error::InvalidLayout)
+            );
+            let mut channel = VerifierChannel::<MaskedKeccak>::new(channel.proof);
+            assert_eq!(
+                FriVerifier::<FieldElement>::read_commitments(parameters.clone(), &mut channel)
+                    .err(),
+                Some(Error::InvalidLayout)
+            );
+        }
+    }
+
+    #[test]
+    fn test_fri_zero_knowledge() {
+        let mut parameters = FriParameters::new(1 << 12, 8, vec![3, 3]);
+        parameters.zero_knowledge = true;
+        let layer = random_layer::<Goldilocks>(&parameters, 3);
+        prove_and_verify(&parameters, layer).unwrap();
+    }
+
+    #[test]
+    fn test_fri_high_degree() {
+        // Claim a degree bound that is too small by a factor two
+        let parameters = FriParameters::new(1 << 12, 8, vec![3, 2]);
+        let mut high_degree = parameters.clone();
+        high_degree.blowup = 4;
+        let layer = random_layer::<FieldElement>(&high_degree, 5);
+        assert_eq!(
+            prove_and_verify(&parameters, layer),
+            Err(Error::FinalLayerMismatch)
+        );
+    }
+
+    #[test]
+    fn test_fri_wrong_values() {
+        let parameters = FriParameters::new(1 << 10, 4, vec![2, 2]);
+        let layer = random_layer::<FieldElement>(&parameters, 11);
+        let mut channel = ProverChannel::<MaskedKeccak>::default();
+        let prover = FriProver::<FieldElement>::commit(&parameters, layer, &mut channel).unwrap();
+        let indices = vec![0, 1];
+        prover.decommit(&indices, &mut channel).unwrap();
+
+        let mut channel = VerifierChannel::<MaskedKeccak>::new(channel.proof);
+        let verifier =
+            FriVerifier::<FieldElement>::read_commitments(parameters, &mut channel).unwrap();
+        let first_layer = vec![(0, FieldElement::zero()), (1, FieldElement::one())];
+        assert_eq!(
+            verifier.verify::<MaskedKeccak, _>(&first_layer, &mut channel),
+            Err(Error::InvalidCommitment)
+        );
+    }
+}
@@ -47,8 +47,14 @@
 // TODO: False positives <https://github.com/rust-lang/rust-clippy/issues/5917>
 #![allow(clippy::wildcard_imports)]
 
+mod batch;
 mod channel;
+mod constraint_builder;
 mod constraints;
+mod field;
+mod fri;
+#[cfg(feature = "std")]
+mod parameters;
 mod polynomial;
 mod proof;
 mod proof_of_work;
@@ -57,6 +63,8 @@ mod rational_expression;
 mod solidity_seralizer;
 #[cfg(feature = "std")]
 mod solidity_verifier;
+#[cfg(feature = "std")]
+mod soundness;
 mod traits;
 mod verifier;
 
@@ -65,14 +73,20 @@ mod verifier;
 #[cfg(feature = "prover")]
 mod algebraic_dag;
 #[cfg(feature = "prover")]
+mod checkpoint;
+#[cfg(feature = "prover")]
 pub mod component;
 #[cfg(feature = "prover")]
 mod constraint_check;
 #[cfg(feature = "prover")]
+mod observer;
+#[cfg(feature = "prover")]
 mod prover;
 #[cfg(feature = "prover")]
 mod rational_equality;
 #[cfg(feature = "prover")]
+mod report;
+#[cfg(feature = "prover")]
 mod trace_table;
 // TODO: Have unconditional Debug trait on all types
 
@@ -81,32 +95,63 @@ mod trace_table;
 #[cfg_attr(feature = "std", macro_use)]
 extern crate no_std_compat as std;
 
+// Allows macro generated code to refer to this crate in tests
+#[cfg(test)]
+extern crate self as zkp_stark;
+
 // Re-exports dependencies that are part of the public interface
+pub use zkp_hash as hash;
 pub use zkp_primefield as primefield;
 
 // Exports for verifier
+pub use batch::{verify_batch, verify_batch_with_hasher};
+pub use channel::{RandomGenerator, Replayable, ReplayableSlice, Writable};
+pub use constraint_builder::{Column, ConstraintBuilder, ConstraintMetadata, Domain};
 pub use constraints::{Constraints, Error as ConstraintError};
+pub use field::StarkField;
+pub use fri::{Error as FriError, FriParameters, FriVerifier};
 pub use polynomial::DensePolynomial;
-pub use proof::Proof;
+pub use proof::{
+    FriLayerDecommitment, Proof, ProofHeader, StructuredProof, TraceDecommitment, PROOF_VERSION,
+};
 pub use rational_expression::RationalExpression;
 pub use traits::Verifiable;
-pub use verifier::{verify, Error as VerifierError};
+pub use verifier::{verify, verify_with_hasher, Error as VerifierError};
 
 // We want std for this so that we can use hex encode
 #[cfg(feature = "std")]
+pub use parameters::{OptimizationGoal, Parameters, SecurityModel};
+#[cfg(feature = "std")]
 pub use solidity_seralizer::proof_serialize;
 #[cfg(feature = "std")]
-pub use solidity_verifier::generate;
+pub use solidity_verifier::{generate, GenerateError};
+#[cfg(feature = "std")]
+pub use soundness::Soundness;
 
 // Exports for prover
 #[cfg(feature = "prover")]
-pub use constraint_check::check_constraints;
+pub use batch::{prove_batch, prove_batch_with_hasher};
+#[cfg(feature = "prover")]
+pub use constraint_check::{
+    check_constraints, check_constraints_exact, check_constraints_report, ConstraintReport,
+    ConstraintViolation, Error as ConstraintCheckError, TraceCell,
+};
+#[cfg(feature = "prover")]
+pub use fri::FriProver;
+#[cfg(feature = "prover")]
+pub use observer::ProverObserver;
+#[cfg(feature = "prover")]
+pub use prover::{
+    prove, prove_resume, prove_staged, prove_staged_with_hasher, prove_with_hasher,
+    prove_with_options, prove_with_report, Error as ProverError, ProverOptions,
+    Stage as ProverStage,
+};
 #[cfg(feature = "prover")]
-pub use prover::{prove, Error as ProverError};
+pub use report::{ProofReport, StageReport};
 #[cfg(feature = "prover")]
 pub use trace_table::TraceTable;
 #[cfg(feature = "prover")]
-pub use traits::Provable;
+pub use traits::{CheckError, Provable};
 
 #[cfg(test)]
 mod tests {
@@ -0,0 +1,69 @@
+use crate::prover::{Error, Stage};
+use std::time::{Duration, Instant};
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// Observes the progress of a proof and can cancel it.
+///
+/// The prover calls the observer at the start and end of each [`Stage`] and
+/// repeatedly during the long running parts: the low degree extensions, the
+/// constraint evaluation, the FRI layers and the proof of work. The
+/// constraint evaluation and proof of work run on multiple threads and call
+/// the observer concurrently, so calls should be quick.
+///
+/// Closures taking the stage, fraction and elapsed time are observers that
+/// never cancel.
+pub trait ProverObserver: Send + Sync {
+    /// The `stage` is `fraction` done, `elapsed` since the start of the proof.
+    ///
+    /// The fraction goes from `0.0` to `1.0` within a stage. For the proof of
+    /// work it is an estimate based on the expected number of attempts.
+    fn progress(&self, stage: Stage, fraction: f64, elapsed: Duration);
+
+    /// Polled after each call to `progress`. Returning `true` stops the
+    /// prover with [`Error::Cancelled`].
+    fn is_cancelled(&self) -> bool {
+        false
+    }
+}
+
+impl<F> ProverObserver for F
+where
+    F: Fn(Stage, f64, Duration) + Send + Sync,
+{
+    fn progress(&self, stage: Stage, fraction: f64, elapsed: Duration) {
+        self(stage, fraction, elapsed)
+    }
+}
+
+/// Reports to an optional observer with the time since the start.
+pub(crate) struct Progress<'a> {
+    observer: Option<&'a dyn ProverObserver>,
+    start:    Instant,
+}
+
+impl<'a> Progress<'a> {
+    pub(crate) fn new(observer: Option<&'a dyn ProverObserver>) -> Self {
+        Self {
+            observer,
+            start: Instant::now(),
+        }
+    }
+
+    pub(crate) fn report(&self, stage: Stage, fraction: f64) -> Result<()> {
+        if let Some(observer) = self.observer {
+            observer.progress(stage, fraction, self.start.elapsed());
+            if observer.is_cancelled() {
+                return Err(Error::Cancelled);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The fraction `done / total` of a stage.
+// Progress does not need to be exact.
+#[allow(clippy::cast_precision_loss)]
+pub(crate) fn fraction(done: usize, total: usize) -> f64 {
+    done as f64 / total as f64
+}
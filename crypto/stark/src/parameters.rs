@@ -0,0 +1,438 @@
+use crate::{
+    constraints::{Constraints, Error},
+    field::StarkField,
+    soundness::Soundness,
+};
+use std::{
+    cmp::{max, min},
+    fmt,
+    prelude::v1::*,
+};
+use zkp_primefield::RefFieldLike;
+
+/// Largest proof of work difficulty considered.
+///
+/// Grinding `2^20` hashes takes in the order of a second.
+const MAX_POW_BITS: usize = 20;
+
+/// Largest binary logarithm of the blowup considered.
+const MAX_LOG_BLOWUP: usize = 6;
+
+/// Largest binary logarithm of the final FRI layer size considered.
+const MAX_LOG_FINAL_LAYER: usize = 8;
+
+/// Size of a Merkle tree node in the proof.
+const HASH_BYTES: usize = 32;
+
+/// Soundness model used to count the security of the queries
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub enum SecurityModel {
+    /// Each query adds `log2(blowup)` bits, following the ethSTARK
+    /// conjecture.
+    Conjectured,
+    /// Each query adds `log2(blowup) / 2` bits, following the Johnson bound
+    /// for list decoding.
+    Provable,
+}
+
+impl SecurityModel {
+    /// Security in bits from the queries and the proof of work.
+    pub fn security_bits(self, log_blowup: usize, num_queries: usize, pow_bits: usize) -> usize {
+        match self {
+            Self::Conjectured => num_queries * log_blowup + pow_bits,
+            Self::Provable => num_queries * log_blowup / 2 + pow_bits,
+        }
+    }
+
+    /// Number of queries required for `bits` of security, ignoring the other
+    /// protocol components.
+    fn num_queries(self, log_blowup: usize, bits: usize) -> usize {
+        let bits = match self {
+            Self::Conjectured => bits,
+            Self::Provable => 2 * bits,
+        };
+        (bits + log_blowup - 1) / log_blowup
+    }
+}
+
+impl fmt::Display for SecurityModel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Conjectured => write!(f, "conjectured"),
+            Self::Provable => write!(f, "provable"),
+        }
+    }
+}
+
+/// Cost to minimize when selecting proof parameters
+#[derive(Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub enum OptimizationGoal {
+    ProofSize,
+    ProverTime,
+    VerifierCost,
+}
+
+impl fmt::Display for OptimizationGoal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::ProofSize => write!(f, "proof size"),
+            Self::ProverTime => write!(f, "prover time"),
+            Self::VerifierCost => write!(f, "verifier cost"),
+        }
+    }
+}
+
+/// Proof parameters selected for a target security level
+///
+/// Created by [`Constraints::select_parameters`]. The costs are estimates
+/// used to compare the candidates, the prover and verifier costs count field
+/// operations and hashes equally. The `Display` implementation explains the
+/// choice.
+#[derive(Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct Parameters {
+    pub blowup:        usize,
+    pub num_queries:   usize,
+    pub pow_bits:      usize,
+    pub fri_layout:    Vec<usize>,
+    /// Security of the selected parameters under `model`.
+    pub security_bits: usize,
+    pub target_bits:   usize,
+    pub model:         SecurityModel,
+    pub goal:          OptimizationGoal,
+    /// Smallest blowup that fits the constraint degree.
+    pub min_blowup:    usize,
+    /// Number of coefficients of the polynomial tested by FRI.
+    pub fri_size:      usize,
+    /// Estimated proof size in bytes.
+    pub proof_size:    usize,
+    /// Estimated prover work.
+    pub prover_cost:   usize,
+    /// Estimated verifier work.
+    pub verifier_cost: usize,
+}
+
+/// Dimensions of a constraint system that determine the costs.
+struct Shape {
+    log_size:           usize,
+    num_stages:         usize,
+    trace_columns:      usize,
+    constraint_columns: usize,
+    element_bytes:      usize,
+}
+
+impl Shape {
+    /// Proof size, prover and verifier cost of a candidate.
+    ///
+    /// Merkle paths are counted in full for every query, ignoring the overlap
+    /// between them.
+    fn costs(
+        &self,
+        log_blowup: usize,
+        num_queries: usize,
+        pow_bits: usize,
+        fri_layout: &[usize],
+    ) -> (usize, usize, usize) {
+        let depth = self.log_size + log_blowup;
+        let final_layer = 1 << (self.log_size - fri_layout.iter().sum::<usize>());
+
+        // Trace and constraint leaves with a Merkle path for each commitment
+        let mut query_elements = self.trace_columns + self.constraint_columns;
+        let mut query_hashes = (self.num_stages + 1) * (depth + 1);
+        let mut layer_depth = depth;
+        for &reductions in fri_layout {
+            query_elements += (1 << reductions) - 1;
+            query_hashes += layer_depth - reductions + 1;
+            layer_depth -= reductions;
+        }
+        let commitments = self.num_stages + 1 + fri_layout.len();
+        let oods_values = self.trace_columns + self.constraint_columns;
+        let proof_size = HASH_BYTES * (commitments + num_queries * (query_hashes - 1))
+            + self.element_bytes * (oods_values + final_layer + num_queries * query_elements)
+            + 8;
+
+        let columns = self.trace_columns + self.constraint_columns;
+        let prover_cost = (columns << depth) * (depth + 1) + (1 << pow_bits);
+        let verifier_cost = num_queries * (query_hashes + query_elements + final_layer);
+        (proof_size, prover_cost, verifier_cost)
+    }
+}
+
+impl Parameters {
+    /// Search for the cheapest parameters reaching `target_bits`.
+    ///
+    /// The security of each candidate is the [`Soundness`] estimate, which
+    /// accounts for the field size and the out of domain sampling and FRI
+    /// commit phase errors. Returns [`Error::UnreachableSecurity`] if no
+    /// candidate reaches the target.
+    pub(crate) fn select<Field>(
+        constraints: &Constraints<Field>,
+        target_bits: usize,
+        model: SecurityModel,
+        goal: OptimizationGoal,
+    ) -> Result<Self, Error>
+    where
+        Field: StarkField,
+        for<'a> &'a Field: RefFieldLike<Field>,
+    {
+        let fri_size = constraints.trace_polynomial_len();
+        let log_size = fri_size.trailing_zeros() as usize;
+        let degree = constraints.degree();
+        let eval_degree = degree.next_power_of_two();
+        let min_log_blowup = max(1, eval_degree.trailing_zeros() as usize);
+        let shape = Shape {
+            log_size,
+            num_stages: constraints.num_stages(),
+            trace_columns: constraints.trace_ncolumns(),
+            constraint_columns: eval_degree + usize::from(constraints.zero_knowledge),
+            element_bytes: Field::BYTES,
+        };
+
+        // FRI layouts with equal reductions and a small final layer
+        let mut fri_layouts = Vec::new();
+        for reductions in 1..=4 {
+            for log_final in 0..=min(MAX_LOG_FINAL_LAYER, log_size) {
+                let total = log_size - log_final;
+                let mut layout = vec![reductions; total / reductions];
+                if total % reductions != 0 {
+                    layout.push(total % reductions);
+                }
+                fri_layouts.push(layout);
+            }
+        }
+
+        let mut best: Option<((usize, usize, usize), Self)> = None;
+        for log_blowup in min_log_blowup..=max(min_log_blowup, MAX_LOG_BLOWUP) {
+            let max_queries = fri_size << log_blowup;
+            for pow_bits in 0..=min(MAX_POW_BITS, target_bits.saturating_sub(1)) {
+                for fri_layout in &fri_layouts {
+                    let soundness = |num_queries| {
+                        Soundness::with_parameters::<Field>(
+                            model,
+                            fri_size,
+                            degree,
+                            1 << log_blowup,
+                            num_queries,
+                            pow_bits,
+                            fri_layout,
+                        )
+                        .total
+                    };
+                    #[allow(clippy::cast_precision_loss)]
+                    let reaches = |num_queries| soundness(num_queries) >= target_bits as f64;
+                    if !reaches(max_queries) {
+                        continue;
+                    }
+                    // The security increases with the number of queries, starting
+                    // from the queries needed when the other errors are ignored.
+                    let mut low = min(
+                        model.num_queries(log_blowup, target_bits - pow_bits),
+                        max_queries,
+                    );
+                    let mut high = low;
+                    while !reaches(high) {
+                        low = high + 1;
+                        high = min(2 * high, max_queries);
+                    }
+                    while low < high {
+                        let middle = low + (high - low) / 2;
+                        if reaches(middle) {
+                            high = middle;
+                        } else {
+                            low = middle + 1;
+                        }
+                    }
+                    let num_queries = low;
+                    let (proof_size, prover_cost, verifier_cost) =
+                        shape.costs(log_blowup, num_queries, pow_bits, fri_layout);
+                    let key = match goal {
+                        OptimizationGoal::ProofSize => (proof_size, prover_cost, verifier_cost),
+                        OptimizationGoal::ProverTime => (prover_cost, proof_size, verifier_cost),
+                        OptimizationGoal::VerifierCost => (verifier_cost, proof_size, prover_cost),
+                    };
+                    if best.as_ref().map_or(true, |(best_key, _)| key < *best_key) {
+                        #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+                        let security_bits = soundness(num_queries).floor() as usize;
+                        best = Some((key, Self {
+                            blowup: 1 << log_blowup,
+                            num_queries,
+                            pow_bits,
+                            fri_layout: fri_layout.clone(),
+                            security_bits,
+                            target_bits,
+                            model,
+                            goal,
+                            min_blowup: 1 << min_log_blowup,
+                            fri_size,
+                            proof_size,
+                            prover_cost,
+                            verifier_cost,
+                        }));
+                    }
+                }
+            }
+        }
+        best.map(|(_, parameters)| parameters)
+            .ok_or(Error::UnreachableSecurity)
+    }
+}
+
+impl fmt::Display for Parameters {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let log_blowup = self.blowup.trailing_zeros();
+        writeln!(
+            f,
+            "{} bits of {} security, optimized for {}:",
+            self.target_bits, self.model, self.goal
+        )?;
+        write!(
+            f,
+            "  blowup {}: the constraint degree requires at least {}, each query adds ",
+            self.blowup, self.min_blowup
+        )?;
+        match self.model {
+            SecurityModel::Conjectured => writeln!(f, "{} bits", log_blowup)?,
+            SecurityModel::Provable if log_blowup % 2 == 0 => {
+                writeln!(f, "{} bits", log_blowup / 2)?
+            }
+            SecurityModel::Provable => writeln!(f, "{}.5 bits", log_blowup / 2)?,
+        }
+        writeln!(
+            f,
+            "  {} queries and {} bits of proof of work give {} bits of security",
+            self.num_queries, self.pow_bits, self.security_bits
+        )?;
+        writeln!(
+            f,
+            "  FRI layout {:?} reduces {} coefficients to {}",
+            self.fri_layout,
+            self.fri_size,
+            self.fri_size >> self.fri_layout.iter().sum::<usize>()
+        )?;
+        writeln!(
+            f,
+            "  estimated proof size {} bytes, prover cost {}, verifier cost {}",
+            self.proof_size, self.prover_cost, self.verifier_cost
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        prove,
+        traits::tests::{recurrance_constraints, Recurrance},
+        verify, Provable,
+        RationalExpression::*,
+        Verifiable,
+    };
+    use zkp_macros_decl::field_element;
+    use zkp_primefield::{FieldElement, Goldilocks};
+    use zkp_u256::U256;
+
+    #[test]
+    fn test_select_goals() {
+        let constraints = recurrance_constraints();
+        let select =
+            |bits, model, goal| Parameters::select(&constraints, bits, model, goal).unwrap();
+        for &bits in &[80, 100, 128] {
+            for &model in &[SecurityModel::Conjectured, SecurityModel::Provable] {
+                let size = select(bits, model, OptimizationGoal::ProofSize);
+                let prover = select(bits, model, OptimizationGoal::ProverTime);
+                let verifier = select(bits, model, OptimizationGoal::VerifierCost);
+                for parameters in &[&size, &prover, &verifier] {
+                    assert!(parameters.security_bits >= bits);
+                    assert!(parameters.blowup >= constraints.degree().next_power_of_two());
+                }
+                assert!(size.proof_size <= prover.proof_size);
+                assert!(size.proof_size <= verifier.proof_size);
+                assert!(prover.prover_cost <= size.prover_cost);
+                assert!(prover.prover_cost <= verifier.prover_cost);
+                assert!(verifier.verifier_cost <= size.verifier_cost);
+                assert!(verifier.verifier_cost <= prover.verifier_cost);
+            }
+        }
+    }
+
+    #[test]
+    fn test_provable_requires_more_queries() {
+        let constraints = recurrance_constraints();
+        let conjectured = Parameters::select(
+            &constraints,
+            100,
+            SecurityModel::Conjectured,
+            OptimizationGoal::ProofSize,
+        )
+        .unwrap();
+        let provable = Parameters::select(
+            &constraints,
+            100,
+            SecurityModel::Provable,
+            OptimizationGoal::ProofSize,
+        )
+        .unwrap();
+        assert!(provable.proof_size > conjectured.proof_size);
+        assert!(
+            SecurityModel::Provable.security_bits(
+                conjectured.blowup.trailing_zeros() as usize,
+                conjectured.num_queries,
+                conjectured.pow_bits
+            ) < 100
+        );
+        assert!(provable
+            .to_string()
+            .starts_with("100 bits of provable security, optimized for proof size:"));
+    }
+
+    #[test]
+    fn test_field_limits_security() {
+        let constraints = recurrance_constraints();
+        let small_field = Constraints::<Goldilocks>::from_expressions((1024, 1), vec![], vec![
+            (Trace(0, 1) - Trace(0, 0)) / (X.pow(1024) - Constant(1.into())),
+        ])
+        .unwrap();
+        for &model in &[SecurityModel::Conjectured, SecurityModel::Provable] {
+            let parameters =
+                Parameters::select(&constraints, 128, model, OptimizationGoal::ProofSize).unwrap();
+            let mut selected = constraints.clone();
+            selected.blowup = parameters.blowup;
+            selected.num_queries = parameters.num_queries;
+            selected.pow_bits = parameters.pow_bits;
+            selected.fri_layout = parameters.fri_layout.clone();
+            #[allow(clippy::cast_precision_loss)]
+            let security = parameters.security_bits as f64;
+            assert!(selected.soundness(model).total >= security);
+            assert!(matches!(
+                Parameters::select(&constraints, 260, model, OptimizationGoal::ProofSize),
+                Err(Error::UnreachableSecurity)
+            ));
+            // A 64 bit field can not reach 80 bits, whatever the number of queries
+            assert!(matches!(
+                Parameters::select(&small_field, 80, model, OptimizationGoal::ProofSize),
+                Err(Error::UnreachableSecurity)
+            ));
+        }
+    }
+
+    #[test]
+    fn test_selected_proof_verify() {
+        let recurrance = Recurrance {
+            index:         1000,
+            initial_value: field_element!("cafebabe"),
+            exponent:      1,
+        };
+        let claim = recurrance.claim();
+        let mut constraints = claim.constraints();
+        let parameters = constraints
+            .select_parameters(40, SecurityModel::Conjectured, OptimizationGoal::ProverTime)
+            .unwrap();
+        assert_eq!(constraints.blowup, parameters.blowup);
+        assert_eq!(constraints.fri_layout, parameters.fri_layout);
+        let trace = claim.trace(&recurrance.witness());
+        let proof = prove(&constraints, &trace).unwrap();
+        assert!(verify(&constraints, &proof).is_ok());
+    }
+}
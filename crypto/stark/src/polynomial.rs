@@ -1,44 +1,48 @@
 // TODO: Naming?
 #![allow(clippy::module_name_repetitions)]
+use crate::field::StarkField;
 #[cfg(feature = "std")]
 use log::trace;
+#[cfg(feature = "std")]
+use std::path::Path;
 use std::prelude::v1::*;
-use zkp_macros_decl::field_element;
 use zkp_mmap_vec::MmapVec;
 #[cfg(feature = "std")]
-use zkp_primefield::{fft::permute_index, Fft, Pow, Root};
-use zkp_primefield::{FieldElement, Zero};
-use zkp_u256::U256;
+use zkp_primefield::{fft::permute_index, Fft, Pow};
+use zkp_primefield::{FieldElement, RefFieldLike};
+
+/// Arbitrary point used to compare polynomials by evaluation.
+pub(crate) const EVALUATION_POINT: usize = 0x754e_d488;
 
 #[derive(Clone)]
-pub struct DensePolynomial(MmapVec<FieldElement>);
+pub struct DensePolynomial<Field: StarkField = FieldElement>(MmapVec<Field>);
 
 // We normally don't want to spill thousands of coefficients in the logs.
 #[cfg(feature = "std")]
-impl std::fmt::Debug for DensePolynomial {
+impl<Field: StarkField> std::fmt::Debug for DensePolynomial<Field> {
     fn fmt(&self, fmt: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(fmt, "DensePolynomial(degree = {:?})", self.degree())
     }
 }
 
-impl PartialEq for DensePolynomial {
+impl<Field: StarkField> PartialEq for DensePolynomial<Field> {
     fn eq(&self, other: &Self) -> bool {
         // Check equality with evaluation
-        let x = field_element!("754ed488ec9208d1b552bb254c0890042078a9e1f7e36072ebff1bf4e193d11b");
+        let x = Field::from(EVALUATION_POINT);
         self.evaluate(&x) == other.evaluate(&x)
     }
 }
-impl Eq for DensePolynomial {}
+impl<Field: StarkField> Eq for DensePolynomial<Field> {}
 
-impl DensePolynomial {
-    pub fn from_mmap_vec(coefficients: MmapVec<FieldElement>) -> Self {
+impl<Field: StarkField> DensePolynomial<Field> {
+    pub fn from_mmap_vec(coefficients: MmapVec<Field>) -> Self {
         assert!(coefficients.len().is_power_of_two());
         Self(coefficients)
     }
 
     // Coefficients are in order of ascending degree. E.g. &[1, 2] corresponds to
     // the polynomial f(x) = 1 + 2x.
-    pub fn new(coefficients: &[FieldElement]) -> Self {
+    pub fn new(coefficients: &[Field]) -> Self {
         assert!(coefficients.len().is_power_of_two());
         let mut vec = MmapVec::with_capacity(coefficients.len());
         vec.extend_from_slice(coefficients);
@@ -48,7 +52,17 @@ impl DensePolynomial {
     pub fn zeros(size: usize) -> Self {
         assert!(size.is_power_of_two());
         let mut vec = MmapVec::with_capacity(size);
-        vec.resize(size, FieldElement::zero());
+        vec.resize(size, Field::zero());
+        Self(vec)
+    }
+
+    /// Like [`zeros`](Self::zeros), see
+    /// [`MmapVec::with_capacity_scratch`] for `scratch`.
+    #[cfg(feature = "prover")]
+    pub(crate) fn zeros_in(size: usize, scratch: Option<&Path>) -> Self {
+        assert!(size.is_power_of_two());
+        let mut vec = MmapVec::with_capacity_scratch(size, scratch);
+        vec.resize(size, Field::zero());
         Self(vec)
     }
 
@@ -62,7 +76,7 @@ impl DensePolynomial {
         self.0.is_empty()
     }
 
-    pub fn coefficients(&self) -> &[FieldElement] {
+    pub fn coefficients(&self) -> &[Field] {
         &self.0
     }
 
@@ -70,14 +84,14 @@ impl DensePolynomial {
     // more correctly left undefined or sometimes assigned `-1` or `-∞`.
     pub fn degree(&self) -> usize {
         let mut degree = self.len() - 1;
-        while self.0[degree] == FieldElement::zero() && degree > 0 {
+        while self.0[degree].is_zero() && degree > 0 {
             degree -= 1;
         }
         degree
     }
 
-    pub fn evaluate(&self, x: &FieldElement) -> FieldElement {
-        let mut result = FieldElement::zero();
+    pub fn evaluate(&self, x: &Field) -> Field {
+        let mut result = Field::zero();
         for coefficient in self.0.iter().rev() {
             result *= x;
             result += coefficient;
@@ -86,18 +100,34 @@ impl DensePolynomial {
     }
 
     #[cfg(feature = "std")]
-    pub fn low_degree_extension(&self, blowup: usize) -> MmapVec<FieldElement> {
+    pub fn low_degree_extension(&self, blowup: usize) -> MmapVec<Field>
+    where
+        for<'a> &'a Field: RefFieldLike<Field>,
+    {
+        self.low_degree_extension_in(blowup, None)
+    }
+
+    /// Like [`low_degree_extension`](Self::low_degree_extension), see
+    /// [`MmapVec::with_capacity_scratch`] for `scratch`.
+    #[cfg(feature = "std")]
+    pub(crate) fn low_degree_extension_in(
+        &self,
+        blowup: usize,
+        scratch: Option<&Path>,
+    ) -> MmapVec<Field>
+    where
+        for<'a> &'a Field: RefFieldLike<Field>,
+    {
         trace!("BEGIN Low degree extension");
-        // TODO: shift polynomial by FieldElement::generator() outside of this function.
+        // TODO: shift polynomial by Field::generator() outside of this function.
         // TODO: Parameterize cofactor
-        let shift_factor: FieldElement = FieldElement::generator();
+        let shift_factor = Field::generator();
         let length = self.len() * blowup;
-        let generator =
-            FieldElement::root(length).expect("No generator for extended_domain_length.");
+        let generator = Field::root(length).expect("No generator for extended_domain_length.");
 
-        // FieldElement is safe to initialize zero (which maps to zero)
-        #[allow(unsafe_code)]
-        let mut result: MmapVec<FieldElement> = unsafe { MmapVec::zero_initialized(length) };
+        // OPT: Zero initialize the memory map for fields where that is safe.
+        let mut result: MmapVec<Field> = MmapVec::with_capacity_scratch(length, scratch);
+        result.resize(length, Field::zero());
 
         // Compute cosets
         result
@@ -117,8 +147,11 @@ impl DensePolynomial {
     ///
     /// target += c * (P(X) - P(z)) / (X - z)
     /// See: <https://en.wikipedia.org/wiki/Synthetic_division>
-    pub fn divide_out_point_into(&self, z: &FieldElement, c: &FieldElement, target: &mut Self) {
-        let mut remainder = FieldElement::zero();
+    pub fn divide_out_point_into(&self, z: &Field, c: &Field, target: &mut Self)
+    where
+        for<'a> &'a Field: RefFieldLike<Field>,
+    {
+        let mut remainder = Field::zero();
         for (coefficient, target) in self.0.iter().rev().zip(target.0.iter_mut().rev()) {
             *target += c * &remainder;
             remainder *= z;
@@ -1,5 +1,23 @@
+use crate::{
+    channel::{RandomGenerator, Replayable, VerifierChannel},
+    constraints::Constraints,
+    field::StarkField,
+    proof_of_work,
+    verifier::{get_indices, Error as VerifierError},
+};
+#[cfg(feature = "std")]
+use serde::{Deserialize, Serialize};
 use std::prelude::v1::*;
+use zkp_hash::{Hash, Hasher, MaskedKeccak};
+use zkp_merkle_tree::Commitment;
+use zkp_primefield::{FieldElement, RefFieldLike};
 
+type Result<T> = std::result::Result<T, VerifierError>;
+
+/// Proof transcript bytes.
+///
+/// The transcript has no header. Its layout, and so its version, is
+/// determined by the [`Constraints`] it was created for.
 #[derive(Clone, Debug, PartialEq)]
 pub struct Proof(Vec<u8>);
 
@@ -12,3 +30,492 @@ impl Proof {
         &self.0
     }
 }
+
+/// Version of the [`StructuredProof`] layout.
+///
+/// Increment this whenever the transcript layout changes in a way that makes
+/// older structured proofs unparseable. Only the structured form records the
+/// version, the [`Proof`] bytes do not.
+pub const PROOF_VERSION: u32 = 2;
+
+/// Proof parameters required to interpret a [`StructuredProof`].
+///
+/// These are copied from the [`Constraints`] the proof was created for.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub struct ProofHeader {
+    #[cfg_attr(feature = "std", serde(deserialize_with = "deserialize_version"))]
+    pub version:        u32,
+    pub trace_nrows:    usize,
+    pub trace_ncolumns: usize,
+    pub blowup:         usize,
+    pub pow_bits:       usize,
+    pub num_queries:    usize,
+    pub fri_layout:     Vec<usize>,
+    pub zero_knowledge: bool,
+}
+
+/// Decommitment of a single committed FRI layer.
+#[derive(Clone, PartialEq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub struct FriLayerDecommitment<Field: StarkField = FieldElement> {
+    /// Coset values that the verifier can not derive from the previous layer.
+    ///
+    /// For zero-knowledge proofs each coset is followed by its leaf salt.
+    pub values:       Vec<Field>,
+    /// Merkle proof for the opened cosets.
+    pub decommitment: Vec<Hash>,
+}
+
+/// Decommitment of the trace columns committed in a single stage.
+#[derive(Clone, PartialEq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub struct TraceDecommitment<Field: StarkField = FieldElement> {
+    /// Values of the stage columns for each query, in the same order as
+    /// `queries`.
+    ///
+    /// For zero-knowledge proofs the last value of each leaf is its salt.
+    pub values:       Vec<Vec<Field>>,
+    /// Merkle proof for the opened rows.
+    pub decommitment: Vec<Hash>,
+}
+
+/// Typed representation of a [`Proof`] transcript.
+///
+/// Parsing the transcript bytes requires the [`Constraints`] the proof was
+/// created for, see [`parse`](Self::parse). Conversion back to the bytes is
+/// lossless and only needs the structured form, so once parsed a proof can
+/// be serialized, inspected, diffed and re-created without the constraint
+/// system.
+#[derive(Clone, PartialEq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+#[cfg_attr(feature = "std", derive(Serialize, Deserialize))]
+pub struct StructuredProof<Field: StarkField = FieldElement> {
+    pub header:                  ProofHeader,
+    /// Trace commitments, one for each stage.
+    pub trace_commitments:       Vec<Hash>,
+    pub constraint_commitment:   Hash,
+    pub trace_oods_values:       Vec<Field>,
+    pub constraint_oods_values:  Vec<Field>,
+    pub fri_commitments:         Vec<Hash>,
+    pub last_layer_coefficients: Vec<Field>,
+    pub pow_nonce:               u64,
+    /// Query indices in the low degree extension domain.
+    ///
+    /// These are derived from the channel and are not part of the transcript.
+    pub queries:                 Vec<usize>,
+    /// Trace decommitments, one for each stage.
+    pub trace_decommitments:     Vec<TraceDecommitment<Field>>,
+    /// Constraint polynomial values for each query, in the same order as
+    /// `queries`.
+    ///
+    /// For zero-knowledge proofs these are followed by the masking polynomial
+    /// value and the leaf salt.
+    pub constraint_values:       Vec<Vec<Field>>,
+    pub constraint_decommitment: Vec<Hash>,
+    pub fri_layers:              Vec<FriLayerDecommitment<Field>>,
+}
+
+impl ProofHeader {
+    pub fn from_constraints<Field>(constraints: &Constraints<Field>) -> Self
+    where
+        Field: StarkField,
+        for<'a> &'a Field: RefFieldLike<Field>,
+    {
+        Self {
+            version:        PROOF_VERSION,
+            trace_nrows:    constraints.trace_nrows(),
+            trace_ncolumns: constraints.trace_ncolumns(),
+            blowup:         constraints.blowup,
+            pow_bits:       constraints.pow_bits,
+            num_queries:    constraints.num_queries,
+            fri_layout:     constraints.fri_layout.clone(),
+            zero_knowledge: constraints.zero_knowledge,
+        }
+    }
+
+    /// Fails if the header is for a different [`PROOF_VERSION`].
+    pub fn validate(&self) -> Result<()> {
+        if self.version == PROOF_VERSION {
+            Ok(())
+        } else {
+            Err(VerifierError::UnsupportedVersion)
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+fn deserialize_version<'de, D>(deserializer: D) -> std::result::Result<u32, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let version = u32::deserialize(deserializer)?;
+    if version == PROOF_VERSION {
+        Ok(version)
+    } else {
+        Err(serde::de::Error::custom(VerifierError::UnsupportedVersion))
+    }
+}
+
+impl<Field> StructuredProof<Field>
+where
+    Field: StarkField,
+    for<'a> &'a Field: RefFieldLike<Field>,
+{
+    /// Parse a proof transcript.
+    ///
+    /// The constraints are required to replay the channel, which determines
+    /// the number of values in the transcript and the query indices. This
+    /// does not verify the proof.
+    ///
+    /// Transcripts are always parsed as the current [`PROOF_VERSION`].
+    pub fn parse(constraints: &Constraints<Field>, proof: &Proof) -> Result<Self> {
+        Self::parse_with_hasher::<MaskedKeccak>(constraints, proof)
+    }
+
+    /// Parse a proof transcript created with hash function `H`.
+    // TODO: Share the transcript walk with `verify` and `proof_serialize`.
+    #[allow(clippy::too_many_lines)]
+    pub fn parse_with_hasher<H: Hasher>(
+        constraints: &Constraints<Field>,
+        proof: &Proof,
+    ) -> Result<Self> {
+        let header = ProofHeader::from_constraints(constraints);
+        let eval_domain_size = constraints.eval_domain_size();
+        let zk_columns = usize::from(header.zero_knowledge);
+
+        let mut channel = VerifierChannel::<H>::new(proof.as_bytes().to_vec());
+        channel.initialize(&constraints.transcript_seed());
+
+        let mut trace_commitments = Vec::with_capacity(constraints.num_stages());
+        for stage in 0..constraints.num_stages() {
+            let _: Vec<Field> = channel.get_coefficients(constraints.stage_challenges(stage).len());
+            channel.require(32)?;
+            trace_commitments.push(channel.replay());
+        }
+        let _: Vec<Field> = channel.get_coefficients(2 * constraints.len());
+        channel.require(32)?;
+        let constraint_commitment: Hash = channel.replay();
+
+        let _: Field = channel.get_random();
+        let mut parseable_constraints = constraints.clone();
+        parseable_constraints.substitute();
+        parseable_constraints.substitute_public_inputs();
+        let num_trace_arguments = parseable_constraints.trace_arguments().len();
+        channel.require(Field::BYTES * num_trace_arguments)?;
+        let trace_oods_values: Vec<Field> = channel.replay_many(num_trace_arguments);
+
+        let num_constraint_columns = constraints.degree().next_power_of_two() + zk_columns;
+        channel.require(Field::BYTES * num_constraint_columns)?;
+        let constraint_oods_values: Vec<Field> = channel.replay_many(num_constraint_columns);
+        let _: Vec<Field> = channel.get_coefficients(num_trace_arguments + num_constraint_columns);
+
+        let mut fri_commitments = Vec::with_capacity(header.fri_layout.len());
+        let mut fri_size = eval_domain_size;
+        for &num_folds in &header.fri_layout {
+            fri_size >>= num_folds;
+            channel.require(32)?;
+            fri_commitments.push(channel.replay());
+            let _: Field = channel.get_random();
+        }
+        channel.require(Field::BYTES * (fri_size / header.blowup))?;
+        let last_layer_coefficients = channel.replay_fri_layer(fri_size / header.blowup);
+
+        let _: proof_of_work::ChallengeSeed = channel.get_random();
+        channel.require(8)?;
+        let pow_response: proof_of_work::Response = channel.replay();
+
+        let queries = get_indices(
+            header.num_queries,
+            eval_domain_size.trailing_zeros(),
+            &mut channel,
+        );
+
+        let mut trace_decommitments = Vec::with_capacity(trace_commitments.len());
+        for (stage, trace_commitment) in trace_commitments.iter().enumerate() {
+            let leaf_size = constraints.stage_columns(stage).len() + zk_columns;
+            let mut values = Vec::with_capacity(queries.len());
+            for _ in &queries {
+                channel.require(Field::BYTES * leaf_size)?;
+                values.push(channel.replay_fri_layer(leaf_size));
+            }
+            let lde_commitment = Commitment::from_size_hash(eval_domain_size, trace_commitment)?;
+            let decommitment = channel.replay_hashes(lde_commitment.proof_size(&queries)?)?;
+            trace_decommitments.push(TraceDecommitment {
+                values,
+                decommitment,
+            });
+        }
+
+        let mut constraint_values = Vec::with_capacity(queries.len());
+        for _ in &queries {
+            let leaf_size = num_constraint_columns + zk_columns;
+            channel.require(Field::BYTES * leaf_size)?;
+            constraint_values.push(channel.replay_fri_layer(leaf_size));
+        }
+        let constraint_commitment_sized =
+            Commitment::from_size_hash(eval_domain_size, &constraint_commitment)?;
+        let constraint_decommitment =
+            channel.replay_hashes(constraint_commitment_sized.proof_size(&queries)?)?;
+
+        let mut fri_layers = Vec::with_capacity(header.fri_layout.len());
+        let mut previous_indices = queries.clone();
+        let mut fri_size = eval_domain_size;
+        for &num_folds in &header.fri_layout {
+            let coset_size = 1_usize << num_folds;
+            fri_size >>= num_folds;
+            let mut fri_indices: Vec<usize> =
+                previous_indices.iter().map(|i| i / coset_size).collect();
+            fri_indices.dedup();
+
+            let mut values = Vec::new();
+            for i in &fri_indices {
+                for j in 0..coset_size {
+                    let n = i * coset_size + j;
+                    if previous_indices.binary_search(&n).is_err() {
+                        channel.require(Field::BYTES)?;
+                        values.push(channel.replay());
+                    }
+                }
+                if header.zero_knowledge {
+                    channel.require(Field::BYTES)?;
+                    values.push(channel.replay());
+                }
+            }
+            let commitment = Commitment::from_size_hash(fri_size, &Hash::default())?;
+            let decommitment = channel.replay_hashes(commitment.proof_size(&fri_indices)?)?;
+            fri_layers.push(FriLayerDecommitment {
+                values,
+                decommitment,
+            });
+            previous_indices = fri_indices;
+        }
+        if !channel.at_end() {
+            return Err(VerifierError::ProofTooLong);
+        }
+        if channel.has_invalid_encoding() {
+            return Err(VerifierError::InvalidEncoding);
+        }
+
+        Ok(Self {
+            header,
+            trace_commitments,
+            constraint_commitment,
+            trace_oods_values,
+            constraint_oods_values,
+            fri_commitments,
+            last_layer_coefficients,
+            pow_nonce: pow_response.nonce(),
+            queries,
+            trace_decommitments,
+            constraint_values,
+            constraint_decommitment,
+            fri_layers,
+        })
+    }
+
+    /// Re-create the transcript bytes.
+    pub fn to_proof(&self) -> Proof {
+        fn push_hashes(bytes: &mut Vec<u8>, hashes: &[Hash]) {
+            for hash in hashes {
+                bytes.extend_from_slice(hash.as_bytes());
+            }
+        }
+
+        let mut bytes = Vec::new();
+        push_hashes(&mut bytes, &self.trace_commitments);
+        bytes.extend_from_slice(self.constraint_commitment.as_bytes());
+        self.trace_oods_values
+            .iter()
+            .chain(&self.constraint_oods_values)
+            .for_each(|element| element.write_bytes(&mut bytes));
+        push_hashes(&mut bytes, &self.fri_commitments);
+        self.last_layer_coefficients
+            .iter()
+            .for_each(|element| element.write_bytes(&mut bytes));
+        bytes.extend_from_slice(&self.pow_nonce.to_be_bytes());
+        for stage in &self.trace_decommitments {
+            stage
+                .values
+                .iter()
+                .flatten()
+                .for_each(|element| element.write_bytes(&mut bytes));
+            push_hashes(&mut bytes, &stage.decommitment);
+        }
+        self.constraint_values
+            .iter()
+            .flatten()
+            .for_each(|element| element.write_bytes(&mut bytes));
+        push_hashes(&mut bytes, &self.constraint_decommitment);
+        for layer in &self.fri_layers {
+            layer
+                .values
+                .iter()
+                .for_each(|element| element.write_bytes(&mut bytes));
+            push_hashes(&mut bytes, &layer.decommitment);
+        }
+        Proof::from_bytes(bytes)
+    }
+}
+
+impl<Field> From<&StructuredProof<Field>> for Proof
+where
+    Field: StarkField,
+    for<'a> &'a Field: RefFieldLike<Field>,
+{
+    fn from(structured: &StructuredProof<Field>) -> Self {
+        structured.to_proof()
+    }
+}
+
+impl<H: Hasher> VerifierChannel<H> {
+    /// Fails if fewer than `bytes` bytes remain in the transcript.
+    pub(crate) fn require(&self, bytes: usize) -> Result<()> {
+        if self.remaining() < bytes {
+            Err(VerifierError::ProofTooShort)
+        } else {
+            Ok(())
+        }
+    }
+
+    fn replay_hashes(&mut self, count: usize) -> Result<Vec<Hash>> {
+        self.require(32 * count)?;
+        Ok(self.replay_many(count))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{prove, traits::tests::Recurrance, Provable, Verifiable};
+    use zkp_macros_decl::field_element;
+    use zkp_u256::U256;
+
+    fn recurrance_proof(zero_knowledge: bool) -> (Constraints, Proof) {
+        let recurrance = Recurrance {
+            index:         1000,
+            initial_value: field_element!("cafebabe"),
+            exponent:      1,
+        };
+        let claim = recurrance.claim();
+        let mut constraints = claim.constraints();
+        constraints.pow_bits = 8;
+        constraints.num_queries = 20;
+        constraints.fri_layout = vec![3, 2];
+        constraints.zero_knowledge = zero_knowledge;
+        let proof = prove(&constraints, &claim.trace(&recurrance.witness())).unwrap();
+        (constraints, proof)
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let (constraints, proof) = recurrance_proof(false);
+        let structured = StructuredProof::parse(&constraints, &proof).unwrap();
+        assert_eq!(structured.header.version, PROOF_VERSION);
+        assert_eq!(structured.trace_commitments.len(), 1);
+        assert_eq!(structured.trace_decommitments[0].values.len(), 20);
+        assert_eq!(structured.fri_layers.len(), 2);
+        assert_eq!(structured.to_proof(), proof);
+    }
+
+    #[test]
+    fn test_json_roundtrip() {
+        let (constraints, proof) = recurrance_proof(false);
+        let structured = StructuredProof::parse(&constraints, &proof).unwrap();
+        let json = serde_json::to_string(&structured).unwrap();
+        let deserialized: StructuredProof = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized, structured);
+        assert_eq!(Proof::from(&deserialized), proof);
+    }
+
+    #[test]
+    fn test_json_version() {
+        let (constraints, proof) = recurrance_proof(false);
+        let mut structured = StructuredProof::parse(&constraints, &proof).unwrap();
+        structured.header.version = PROOF_VERSION - 1;
+        assert_eq!(
+            structured.header.validate(),
+            Err(VerifierError::UnsupportedVersion)
+        );
+        let json = serde_json::to_string(&structured).unwrap();
+        assert!(serde_json::from_str::<StructuredProof>(&json).is_err());
+    }
+
+    #[test]
+    fn test_zero_knowledge_roundtrip() {
+        let (constraints, proof) = recurrance_proof(true);
+        let structured = StructuredProof::parse(&constraints, &proof).unwrap();
+        assert!(structured.header.zero_knowledge);
+        assert_eq!(
+            structured.trace_decommitments[0].values[0].len(),
+            constraints.trace_ncolumns() + 1
+        );
+        assert_eq!(structured.to_proof(), proof);
+    }
+
+    #[test]
+    fn test_multi_stage_roundtrip() {
+        use crate::{prove_staged, verify, RationalExpression::*, TraceTable};
+        use zkp_primefield::Root;
+
+        let recurrance = Recurrance {
+            index:         1000,
+            initial_value: field_element!("cafebabe"),
+            exponent:      1,
+        };
+        let claim = recurrance.claim();
+        let trace = claim.trace(&recurrance.witness());
+        let trace_length = trace.num_rows();
+
+        // Accumulate a random linear combination of the first column in a
+        // second stage column.
+        let g = Constant(FieldElement::root(trace_length).unwrap());
+        let every_row = || (X - g.pow(trace_length - 1)) / (X.pow(trace_length) - 1);
+        let mut expressions = claim.constraints().expressions().to_vec();
+        expressions.push((Trace(2, 1) - Challenge(0) * Trace(2, 0) - Trace(0, 1)) * every_row());
+        expressions.push((Trace(2, 0) - Trace(0, 0)) / (X - 1));
+        let mut constraints =
+            Constraints::from_expressions((trace_length, 2), claim.seed(), expressions).unwrap();
+        constraints.add_stage(1, 1);
+        constraints.num_queries = 20;
+        constraints.fri_layout = vec![3, 2];
+        constraints.zero_knowledge = true;
+
+        let proof = prove_staged(&constraints, &trace, |_, challenges, trace| {
+            let mut column = TraceTable::new(trace.num_rows(), 1);
+            column[(0, 0)] = trace[(0, 0)].clone();
+            for i in 1..trace.num_rows() {
+                column[(i, 0)] = &challenges[0] * &column[(i - 1, 0)] + &trace[(i, 0)];
+            }
+            Some(column)
+        })
+        .unwrap();
+        assert!(verify(&constraints, &proof).is_ok());
+
+        let structured = StructuredProof::parse(&constraints, &proof).unwrap();
+        assert_eq!(structured.trace_commitments.len(), 2);
+        assert_eq!(structured.trace_decommitments.len(), 2);
+        assert_eq!(structured.trace_decommitments[0].values[0].len(), 3);
+        assert_eq!(structured.trace_decommitments[1].values[0].len(), 2);
+        assert_eq!(structured.to_proof(), proof);
+    }
+
+    #[test]
+    fn test_truncated() {
+        let (constraints, proof) = recurrance_proof(false);
+        let bytes = proof.as_bytes();
+        let truncated = Proof::from_bytes(bytes[..bytes.len() - 1].to_vec());
+        assert_eq!(
+            StructuredProof::parse(&constraints, &truncated),
+            Err(VerifierError::ProofTooShort)
+        );
+        let mut extended = bytes.to_vec();
+        extended.push(0);
+        assert_eq!(
+            StructuredProof::parse(&constraints, &Proof::from_bytes(extended)),
+            Err(VerifierError::ProofTooLong)
+        );
+    }
+}
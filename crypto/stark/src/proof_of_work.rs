@@ -2,22 +2,30 @@
 use log::{info, trace};
 #[cfg(all(feature = "std", feature = "prover"))]
 use rayon::prelude::*;
-use std::convert::TryFrom;
 #[cfg(all(feature = "std", feature = "prover"))]
-use std::sync::atomic::{AtomicU64, Ordering::Relaxed};
-use tiny_keccak::{Hasher, Keccak};
+use std::{
+    convert::Infallible,
+    sync::atomic::{AtomicU64, Ordering::Relaxed},
+};
+use std::{convert::TryFrom, marker::PhantomData};
+use zkp_hash::{Hasher, Keccak};
 use zkp_macros_decl::hex;
 use zkp_u256::{Binary, U256};
 
+/// Number of attempts per thread between progress reports.
+#[cfg(all(feature = "std", feature = "prover"))]
+const PROGRESS_INTERVAL: usize = 1 << 16;
+
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord)]
 #[cfg_attr(feature = "std", derive(Debug))]
 pub(crate) struct ChallengeSeed([u8; 32]);
 
 #[derive(Clone, PartialEq, Eq, PartialOrd, Ord)]
 #[cfg_attr(feature = "std", derive(Debug))]
-pub(crate) struct Challenge {
+pub(crate) struct Challenge<H: Hasher = Keccak> {
     seed:       [u8; 32],
     difficulty: usize,
+    hasher:     PhantomData<H>,
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
@@ -31,26 +39,28 @@ impl ChallengeSeed {
         Self(seed)
     }
 
-    pub(crate) fn with_difficulty(self, difficulty: usize) -> Challenge {
-        let mut seed = [0_u8; 32];
-        let mut keccak = Keccak::v256();
-        keccak.update(&hex!("0123456789abcded"));
-        keccak.update(&self.0);
-        keccak.update(&[u8::try_from(difficulty).unwrap()]);
-        keccak.finalize(&mut seed);
-        Challenge { difficulty, seed }
+    /// The challenge is derived and verified using the full digest of `H`.
+    pub(crate) fn with_difficulty<H: Hasher>(self, difficulty: usize) -> Challenge<H> {
+        let mut hasher = H::default();
+        hasher.update(&hex!("0123456789abcded"));
+        hasher.update(&self.0);
+        hasher.update(&[u8::try_from(difficulty).unwrap()]);
+        Challenge {
+            difficulty,
+            seed: hasher.finalize(),
+            hasher: PhantomData,
+        }
     }
 }
 
-impl Challenge {
+impl<H: Hasher> Challenge<H> {
     pub(crate) fn verify(&self, response: Response) -> bool {
         // TODO: return Result<()>
         // OPT: Inline Keccak256 and work directly on buffer using 'keccakf'
-        let mut keccak = Keccak::v256();
-        let mut digest = [0_u8; 32];
-        keccak.update(&self.seed);
-        keccak.update(&(response.nonce.to_be_bytes()));
-        keccak.finalize(&mut digest);
+        let mut hasher = H::default();
+        hasher.update(&self.seed);
+        hasher.update(&(response.nonce.to_be_bytes()));
+        let digest = hasher.finalize();
         // OPT: Check performance impact of conversion
         let work = U256::from_bytes_be(&digest).leading_zeros();
         work >= self.difficulty
@@ -58,7 +68,7 @@ impl Challenge {
 }
 
 #[cfg(feature = "prover")]
-impl Challenge {
+impl<H: Hasher> Challenge<H> {
     #[cfg(not(feature = "std"))]
     pub(crate) fn solve(&self) -> Response {
         // We assume a nonce exists and will be found in reasonable time.
@@ -75,27 +85,55 @@ impl Challenge {
 
     #[cfg(feature = "std")]
     pub(crate) fn solve(&self) -> Response {
+        match self.solve_with_progress(|_| Ok::<_, Infallible>(())) {
+            Ok(response) => response,
+            Err(infallible) => match infallible {},
+        }
+    }
+
+    /// Solve the challenge, calling `progress` with the fraction of the
+    /// expected number of attempts made so far. An error from `progress`
+    /// stops the search.
+    #[cfg(feature = "std")]
+    pub(crate) fn solve_with_progress<E, P>(&self, progress: P) -> Result<Response, E>
+    where
+        E: Send,
+        P: Fn(f64) -> Result<(), E> + Sync,
+    {
         let num_threads = rayon::current_num_threads();
         info!(
             "Solving {} bit proof of work with {} threads.",
             self.difficulty, num_threads
         );
         trace!("BEGIN Proof of work");
+        let expected = 2_f64.powi(i32::try_from(self.difficulty).unwrap_or(i32::MAX));
+        let attempts = AtomicU64::new(0);
         let first_nonce = AtomicU64::new(u64::max_value());
-        (0..num_threads as u64).into_par_iter().for_each(|offset| {
-            for nonce in (offset..).step_by(num_threads) {
-                if self.verify(Response { nonce }) {
-                    let _ = fetch_min(&first_nonce, nonce);
-                }
-                if nonce >= first_nonce.load(Relaxed) {
-                    break;
+        (0..num_threads as u64)
+            .into_par_iter()
+            .try_for_each(|offset| {
+                for (attempt, nonce) in (offset..).step_by(num_threads).enumerate() {
+                    if self.verify(Response { nonce }) {
+                        let _ = fetch_min(&first_nonce, nonce);
+                    }
+                    if nonce >= first_nonce.load(Relaxed) {
+                        break;
+                    }
+                    if attempt % PROGRESS_INTERVAL == PROGRESS_INTERVAL - 1 {
+                        let done = attempts.fetch_add(PROGRESS_INTERVAL as u64, Relaxed)
+                            + PROGRESS_INTERVAL as u64;
+                        // Progress does not need to be exact.
+                        #[allow(clippy::cast_precision_loss)]
+                        let fraction = done as f64 / expected;
+                        progress(fraction.min(1.0))?;
+                    }
                 }
-            }
-        });
+                Ok(())
+            })?;
         trace!("END Proof of work");
-        Response {
+        Ok(Response {
             nonce: first_nonce.into_inner(),
-        }
+        })
     }
 }
 
@@ -127,15 +165,31 @@ fn fetch_min(atom: &AtomicU64, value: u64) -> u64 {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use zkp_hash::{MaskedKeccak, Sha256};
 
     #[test]
     fn proof_of_work_test() {
         let challenge = ChallengeSeed::from_bytes(hex!(
             "0123456789abcded0123456789abcded0123456789abcded0123456789abcded"
         ))
-        .with_difficulty(8);
+        .with_difficulty::<Keccak>(8);
         let response = challenge.solve();
         assert_eq!(response.nonce, 138);
         assert!(challenge.verify(response));
     }
+
+    #[test]
+    fn proof_of_work_hasher_test() {
+        let seed = ChallengeSeed::from_bytes(hex!(
+            "0123456789abcded0123456789abcded0123456789abcded0123456789abcded"
+        ));
+        // The masked hasher has the same full digest as Keccak
+        let masked = seed.clone().with_difficulty::<MaskedKeccak>(8);
+        assert_eq!(masked.solve().nonce, 138);
+
+        let challenge = seed.with_difficulty::<Sha256>(8);
+        let response = challenge.solve();
+        assert!(challenge.verify(response));
+        assert_ne!(response.nonce, 138);
+    }
 }
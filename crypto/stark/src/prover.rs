@@ -1,21 +1,41 @@
 use crate::{
-    algebraic_dag::AlgebraicGraph,
+    algebraic_dag::{AlgebraicGraph, CHUNK_SIZE as DAG_CHUNK_SIZE},
     channel::{ProverChannel, RandomGenerator, Writable},
+    checkpoint::{self, Checkpoint},
     constraints::Constraints,
+    field::{pow_signed, random_element, StarkField},
+    fri::{Error as FriError, FriProver},
+    observer::{fraction, Progress, ProverObserver},
     polynomial::DensePolynomial,
-    proof_of_work, verify, Proof, TraceTable, VerifierError,
+    proof_of_work,
+    report::{ProofReport, Recorder},
+    verifier::verify_with_hasher,
+    Proof, TraceTable, VerifierError,
 };
-use itertools::Itertools;
 use log::{info, trace};
+use rand::thread_rng;
 use rayon::prelude::*;
-use std::{fmt, prelude::v1::*, vec};
-use zkp_hash::{Hash, Hashable, MaskedKeccak};
+use serde::{Deserialize, Serialize};
+use std::{
+    cmp::max,
+    fmt, io,
+    path::{Path, PathBuf},
+    prelude::v1::*,
+    slice,
+    sync::{
+        atomic::{AtomicUsize, Ordering::Relaxed},
+        Arc,
+    },
+};
+use zkp_hash::{Hash, Hasher, MaskedKeccak};
+#[cfg(feature = "memory-report")]
+use zkp_logging_allocator::LoggingAllocator;
 use zkp_merkle_tree::{Error as MerkleError, Tree, VectorCommitment};
 use zkp_mmap_vec::MmapVec;
 use zkp_primefield::{
     fft::{permute, permute_index},
     geometric_series::geometric_series,
-    Fft, FieldElement, Inv, One, Pow, Root, SquareInline, Zero,
+    Fft, Inv, Pow, RefFieldLike,
 };
 use zkp_u256::U256;
 
@@ -24,6 +44,13 @@ type Result<T> = std::result::Result<T, Error>;
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 pub enum Error {
     RootUnavailable,
+    InvalidTraceStage,
+    TraceTooShort,
+    InvalidFriLayout,
+    InvalidBatch,
+    Checkpoint(io::ErrorKind),
+    InvalidCheckpoint,
+    Cancelled,
     MerkleFailed(MerkleError),
     VerificationFailed(VerifierError),
 }
@@ -33,6 +60,28 @@ impl fmt::Display for Error {
         use Error::*;
         match *self {
             RootUnavailable => write!(f, "The prime field doesn't have a root of this order"),
+            InvalidTraceStage => {
+                write!(
+                    f,
+                    "A later trace stage is missing or has the wrong dimensions"
+                )
+            }
+            TraceTooShort => {
+                write!(
+                    f,
+                    "The trace is too short to hide the opened values in a zero-knowledge proof"
+                )
+            }
+            InvalidFriLayout => write!(f, "The FRI layout is not supported"),
+            InvalidBatch => write!(f, "The statements can not be proven as a batch"),
+            Checkpoint(kind) => write!(f, "Could not read or write the checkpoint: {}", kind),
+            InvalidCheckpoint => {
+                write!(
+                    f,
+                    "The checkpoint is missing, corrupt or for a different proof"
+                )
+            }
+            Cancelled => write!(f, "The proof was cancelled by the observer"),
             MerkleFailed(ref e) => std::fmt::Display::fmt(e, f),
             VerificationFailed(ref e) => std::fmt::Display::fmt(e, f),
         }
@@ -45,21 +94,86 @@ impl From<MerkleError> for Error {
     }
 }
 
+impl From<FriError> for Error {
+    fn from(err: FriError) -> Self {
+        match err {
+            FriError::RootUnavailable => Self::RootUnavailable,
+            FriError::InvalidLayout => Self::InvalidFriLayout,
+            FriError::Merkle(err) => Self::MerkleFailed(err),
+            err => Self::VerificationFailed(err.into()),
+        }
+    }
+}
+
 impl From<VerifierError> for Error {
     fn from(err: VerifierError) -> Self {
         Self::VerificationFailed(err)
     }
 }
 
+/// The stages of a proof, in order.
+///
+/// A checkpoint records the last completed stage, see
+/// [`ProverOptions::checkpoint_directory`].
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize, Deserialize)]
+pub enum Stage {
+    TraceCommitment,
+    ConstraintCommitment,
+    OutOfDomainSampling,
+    Fri,
+    ProofOfWork,
+    Decommitment,
+}
+
+impl Stage {
+    pub(crate) fn index(self) -> usize {
+        self as usize
+    }
+
+    pub(crate) fn from_index(index: usize) -> Option<Self> {
+        use Stage::*;
+        [
+            TraceCommitment,
+            ConstraintCommitment,
+            OutOfDomainSampling,
+            Fri,
+            ProofOfWork,
+            Decommitment,
+        ]
+        .get(index)
+        .copied()
+    }
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use Stage::*;
+        match *self {
+            TraceCommitment => write!(f, "trace commitment"),
+            ConstraintCommitment => write!(f, "constraint commitment"),
+            OutOfDomainSampling => write!(f, "out of domain sampling"),
+            Fri => write!(f, "FRI commitment"),
+            ProofOfWork => write!(f, "proof of work"),
+            Decommitment => write!(f, "query decommitment"),
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
-struct PolyLDE(Vec<MmapVec<FieldElement>>);
+pub(crate) struct PolyLDE<Field: StarkField>(pub(crate) Vec<MmapVec<Field>>);
+
+/// Merkle tree over the LDE of a set of polynomials
+pub(crate) type LDETree<Field, H> = Tree<PolyLDE<Field>, H>;
+
+/// Committed trace polynomials and the Merkle tree over their LDE
+type CommittedTrace<Field, H> = (Vec<DensePolynomial<Field>>, LDETree<Field, H>);
 
 /// Merkle trees over trace table LDE and constraint LDE
 // Clippy false positive
 #[allow(clippy::use_self)]
-impl VectorCommitment for PolyLDE {
+impl<Field: StarkField> VectorCommitment for PolyLDE<Field> {
     // TODO: Copy free implementation. Maybe have index as a leaf type.
-    type Leaf = Vec<U256>;
+    type Leaf = Vec<Field>;
 
     fn len(&self) -> usize {
         self.0.first().map_or(0, MmapVec::len)
@@ -68,66 +182,122 @@ impl VectorCommitment for PolyLDE {
     fn leaf(&self, index: usize) -> Self::Leaf {
         let mut ret = Vec::with_capacity(self.0.len());
         for item in &self.0 {
-            ret.push(item[index].as_montgomery().clone())
+            ret.push(item[index].clone())
         }
         ret
     }
 
-    fn leaf_hash(&self, index: usize) -> Hash {
+    fn leaf_hash<H: Hasher>(&self, index: usize) -> Hash {
         if self.0.len() == 1 {
             // For a single element, return its hash.
-            self.0[0][index].hash()
+            self.0[0][index].hash_with::<H>()
         } else {
             // Concatenate the element hashes and hash the result.
-            let mut hasher = MaskedKeccak::new();
+            let mut hasher = H::default();
             for value in &self.0 {
-                hasher.update(value[index].hash().as_bytes());
+                hasher.update(value[index].hash_with::<H>().as_bytes());
             }
             hasher.hash()
         }
     }
 }
 
-#[derive(Clone, Debug)]
-struct FriLeaves {
-    coset_size: usize,
-    layer:      MmapVec<FieldElement>,
-}
-
-type FriTree = Tree<FriLeaves>;
-
-// Merkle tree for FRI layers with coset size
-impl VectorCommitment for FriLeaves {
-    type Leaf = Vec<U256>;
+/// Default number of rows per chunk in the constraint evaluation.
+pub(crate) const DEFAULT_CHUNK_SIZE: usize = 65536;
 
-    fn len(&self) -> usize {
-        debug_assert_eq!(self.layer.len() % self.coset_size, 0);
-        self.layer.len() / self.coset_size
-    }
+/// Options for the prover that do not affect the proof.
+///
+/// ## Memory use
+///
+/// Let $n$ be the trace length, $w$ the number of trace columns, $b$ the
+/// blowup, $d$ the constraint degree rounded up to a power of two and $S$ the
+/// size of a field element in bytes. The evaluation domain has $N = b \cdot n$
+/// elements. The large buffers of a proof are
+///
+/// * the trace polynomials and their LDE with $(n + N) \cdot w \cdot S$ bytes
+///   and a Merkle tree of $64 \cdot N$ bytes,
+/// * the trace on the constraint evaluation coset with $d \cdot n \cdot w \cdot
+///   S$ bytes and the evaluated constraints with $d \cdot n \cdot S$ bytes,
+///   which are released after the constraint evaluation,
+/// * the constraint polynomials and their LDE with $(n + N) \cdot d \cdot S$
+///   bytes and a Merkle tree of $64 \cdot N$ bytes,
+/// * the DEEP composition polynomial with $n \cdot S$ bytes,
+/// * the FRI layers with less than $2 \cdot N \cdot S$ bytes and their Merkle
+///   trees with less than $16 \cdot N$ bytes.
+///
+/// In total at most
+///
+/// $$
+/// S \cdot n \cdot \left( (w + d) \cdot (b + 1) + d \cdot w + d +
+/// 2 \cdot b + 1 \right) + 144 \cdot N
+/// $$
+///
+/// bytes, plus the trace table itself. Without a scratch directory these are
+/// all held in anonymous memory.
+///
+/// With a `scratch_directory` every buffer of at least
+/// [`SCRATCH_THRESHOLD`](zkp_mmap_vec::SCRATCH_THRESHOLD) bytes is a file in
+/// that directory, so the above is the required disk space instead. The pages
+/// of these files are only resident while they are accessed and the operating
+/// system can write them back and evict them. The resident memory is then
+/// bounded by the pages in use: the LDE and FFT passes work on one column and
+/// on $\sqrt{N}$ sized twiddle tables at a time, and the constraint
+/// evaluation holds `chunk_size` rows of the coset trace and of the result per
+/// thread, about $t \cdot \mathrm{chunk\\_size} \cdot (w + 1) \cdot S$
+/// bytes for $t$ threads. For example, a $2^{26}$ row trace with $w = 8$, $d =
+/// 2$ and $b = 16$ needs about $630\ \mathrm{GB}$ of scratch space, but only a
+/// few gigabytes of resident memory. Use a trace table allocated with
+/// [`TraceTable::new_in`] to also keep the trace out of memory.
+#[derive(Clone)]
+pub struct ProverOptions {
+    /// Directory for the large buffers of the proof, `None` keeps them in
+    /// anonymous memory. Only the buffers of this proof are allocated there.
+    pub scratch_directory:    Option<PathBuf>,
+    /// Number of rows evaluated at a time in the constraint evaluation. It is
+    /// rounded up to a power of two of at least 16.
+    pub chunk_size:           usize,
+    /// Directory to checkpoint the proof in after each [`Stage`], `None`
+    /// disables checkpoints. The checkpoint holds the commitments and
+    /// polynomials of the completed stages, about the size of the buffers
+    /// above, and an interrupted proof can be continued with
+    /// [`prove_resume`]. A new proof replaces the checkpoint in the
+    /// directory.
+    pub checkpoint_directory: Option<PathBuf>,
+    /// Receives the progress of the proof and can cancel it, see
+    /// [`ProverObserver`].
+    pub observer:             Option<Arc<dyn ProverObserver>>,
+    /// The global allocator, to measure the peak memory of each stage in
+    /// [`prove_with_report`]. Its peak is reset at the start of each stage.
+    #[cfg(feature = "memory-report")]
+    pub allocator:            Option<&'static LoggingAllocator>,
+}
 
-    fn leaf(&self, index: usize) -> Self::Leaf {
-        let mut internal_leaf = Vec::with_capacity(self.coset_size);
-        for j in 0..self.coset_size {
-            internal_leaf.push(
-                self.layer[(index * self.coset_size + j)]
-                    .as_montgomery()
-                    .clone(),
+impl fmt::Debug for ProverOptions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut debug = f.debug_struct("ProverOptions");
+        let _ = debug
+            .field("scratch_directory", &self.scratch_directory)
+            .field("chunk_size", &self.chunk_size)
+            .field("checkpoint_directory", &self.checkpoint_directory)
+            .field(
+                "observer",
+                &self.observer.as_ref().map(|_| "ProverObserver"),
             );
-        }
-        internal_leaf
+        #[cfg(feature = "memory-report")]
+        let _ = debug.field("allocator", &self.allocator);
+        debug.finish()
     }
+}
 
-    fn leaf_hash(&self, index: usize) -> Hash {
-        if self.coset_size == 1 {
-            // For a single element, return its hash.
-            self.layer[index].hash()
-        } else {
-            // Concatenate the element hashes and hash the result.
-            let mut hasher = MaskedKeccak::new();
-            for j in 0..self.coset_size {
-                hasher.update(self.layer[(index * self.coset_size + j)].hash().as_bytes());
-            }
-            hasher.hash()
+impl Default for ProverOptions {
+    fn default() -> Self {
+        Self {
+            scratch_directory: None,
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            checkpoint_directory: None,
+            observer: None,
+            #[cfg(feature = "memory-report")]
+            allocator: None,
         }
     }
 }
@@ -340,11 +510,196 @@ impl VectorCommitment for FriLeaves {
 ///
 /// with merkle proofs to that layer. This process is repeated for all FRI layer
 /// commitments.
+///
+/// ### Zero-knowledge
+///
+/// When `constraints.zero_knowledge` is set, each trace polynomial is replaced
+/// by
+///
+/// $$
+/// T_i(x) + (x^n - 1) \cdot R_i(x)
+/// $$
+///
+/// for a random $R_i$ of degree less than $n$. This does not change the values
+/// on the trace domain, but doubles the size of the trace polynomials and all
+/// derived polynomials. The mask hides at most $n$ openings of each column, so
+/// proving fails with [`Error::TraceTooShort`] if a column is opened more
+/// often, see
+/// [`Constraints::max_trace_openings`](crate::Constraints::max_trace_openings).
+/// A random masking polynomial $M$ is appended to the constraint polynomials,
+/// so $M(z^{\mathrm{d}})$ is written together with the values
+/// $A_i(z^{\mathrm{d}})$ and $M$ enters the final polynomial $P$ with its own
+/// coefficient. Finally, every Merkle leaf, including the FRI cosets, has a
+/// random salt appended to it which is revealed whenever the leaf is
+/// decommitted.
+///
+/// ### Multi-stage traces
+///
+/// Constraints with more than one trace stage need to be proven with
+/// [`prove_staged`].
+pub fn prove<Field>(constraints: &Constraints<Field>, trace: &TraceTable<Field>) -> Result<Proof>
+where
+    Field: StarkField,
+    for<'a> &'a Field: RefFieldLike<Field>,
+{
+    prove_with_hasher::<MaskedKeccak, _>(constraints, trace)
+}
+
+/// Produce a Stark proof for constraints with multiple trace stages.
+///
+/// The trace table contains the columns of the first stage. Each later stage
+/// is committed to separately, see
+/// [`Constraints::add_stage`](crate::Constraints::add_stage). Before it is
+/// committed, its challenges are drawn from the channel and `stages` is called
+/// with the stage number, all challenges drawn so far and the columns of all
+/// previous stages. It returns the columns of the stage, or `None` to fail
+/// with [`Error::InvalidTraceStage`].
+///
+/// Otherwise this is identical to [`prove`].
+pub fn prove_staged<Field, S>(
+    constraints: &Constraints<Field>,
+    trace: &TraceTable<Field>,
+    stages: S,
+) -> Result<Proof>
+where
+    Field: StarkField,
+    for<'a> &'a Field: RefFieldLike<Field>,
+    S: FnMut(usize, &[Field], &TraceTable<Field>) -> Option<TraceTable<Field>>,
+{
+    prove_staged_with_hasher::<MaskedKeccak, _, _>(constraints, trace, stages)
+}
+
+/// Produce a Stark proof using the hash function `H`.
+///
+/// The hash function is used for all Merkle commitments and for the channel.
+/// See [`prove`] for details. The proof must be verified with
+/// [`verify_with_hasher`](crate::verify_with_hasher) using the same hash.
+pub fn prove_with_hasher<H, Field>(
+    constraints: &Constraints<Field>,
+    trace: &TraceTable<Field>,
+) -> Result<Proof>
+where
+    H: Hasher,
+    Field: StarkField,
+    for<'a> &'a Field: RefFieldLike<Field>,
+{
+    if constraints.num_stages() > 1 {
+        return Err(Error::InvalidTraceStage);
+    }
+    prove_staged_with_hasher::<H, _, _>(constraints, trace, |_, _, _| {
+        unreachable!("Constraints have a single stage")
+    })
+}
+
+/// Produce a Stark proof for multiple trace stages using the hash function
+/// `H`.
+///
+/// See [`prove_staged`] and [`prove_with_hasher`] for details.
+pub fn prove_staged_with_hasher<H, Field, S>(
+    constraints: &Constraints<Field>,
+    trace: &TraceTable<Field>,
+    stages: S,
+) -> Result<Proof>
+where
+    H: Hasher,
+    Field: StarkField,
+    for<'a> &'a Field: RefFieldLike<Field>,
+    S: FnMut(usize, &[Field], &TraceTable<Field>) -> Option<TraceTable<Field>>,
+{
+    prove_with_options::<H, _, _>(constraints, trace, stages, &ProverOptions::default())
+}
+
+/// Produce a Stark proof with the given prover options.
+///
+/// The options do not affect the proof, which is identical to the one from
+/// [`prove_staged_with_hasher`]. Use this to prove traces that do not fit in
+/// memory, see [`ProverOptions`].
+pub fn prove_with_options<H, Field, S>(
+    constraints: &Constraints<Field>,
+    trace: &TraceTable<Field>,
+    stages: S,
+    options: &ProverOptions,
+) -> Result<Proof>
+where
+    H: Hasher,
+    Field: StarkField,
+    for<'a> &'a Field: RefFieldLike<Field>,
+    S: FnMut(usize, &[Field], &TraceTable<Field>) -> Option<TraceTable<Field>>,
+{
+    prove_checkpointed::<H, _, _>(constraints, trace, stages, options, false)
+        .map(|(proof, _)| proof)
+}
+
+/// Produce a Stark proof with the given prover options and report on it.
+///
+/// Like [`prove_with_options`], but also returns a [`ProofReport`] with the
+/// time and proof bytes of each stage and the sizes of the FFTs, Merkle trees
+/// and constraint graph. With the `memory-report` feature the report also has
+/// the peak memory of each stage, separately for the `MmapVec` buffers and the
+/// heap. The heap is only measured with a `LoggingAllocator` installed as the
+/// global allocator and set in `options.allocator`.
+pub fn prove_with_report<H, Field, S>(
+    constraints: &Constraints<Field>,
+    trace: &TraceTable<Field>,
+    stages: S,
+    options: &ProverOptions,
+) -> Result<(Proof, ProofReport)>
+where
+    H: Hasher,
+    Field: StarkField,
+    for<'a> &'a Field: RefFieldLike<Field>,
+    S: FnMut(usize, &[Field], &TraceTable<Field>) -> Option<TraceTable<Field>>,
+{
+    prove_checkpointed::<H, _, _>(constraints, trace, stages, options, false)
+}
+
+/// Resume a Stark proof from the checkpoint in the options.
+///
+/// Continues after the last [`Stage`] completed in
+/// `options.checkpoint_directory` and keeps writing checkpoints, or starts a
+/// new proof if the directory has no checkpoint. The arguments must be the
+/// same as for the interrupted proof. The proof is byte-identical to the one
+/// an uninterrupted prover would have produced. Zero knowledge proofs are
+/// randomized, the randomness of the completed stages is kept but the
+/// remaining stages draw new randomness.
+///
+/// Returns [`Error::InvalidCheckpoint`] if there is no checkpoint directory or
+/// the checkpoint belongs to a different claim or different parameters.
+pub fn prove_resume<H, Field, S>(
+    constraints: &Constraints<Field>,
+    trace: &TraceTable<Field>,
+    stages: S,
+    options: &ProverOptions,
+) -> Result<Proof>
+where
+    H: Hasher,
+    Field: StarkField,
+    for<'a> &'a Field: RefFieldLike<Field>,
+    S: FnMut(usize, &[Field], &TraceTable<Field>) -> Option<TraceTable<Field>>,
+{
+    prove_checkpointed::<H, _, _>(constraints, trace, stages, options, true).map(|(proof, _)| proof)
+}
+
 // TODO: Simplify
 #[allow(clippy::cognitive_complexity)]
 // TODO: Split up
 #[allow(clippy::too_many_lines)]
-pub fn prove(constraints: &Constraints, trace: &TraceTable) -> Result<Proof> {
+fn prove_checkpointed<H, Field, S>(
+    constraints: &Constraints<Field>,
+    trace: &TraceTable<Field>,
+    mut stages: S,
+    options: &ProverOptions,
+    resume: bool,
+) -> Result<(Proof, ProofReport)>
+where
+    H: Hasher,
+    Field: StarkField,
+    for<'a> &'a Field: RefFieldLike<Field>,
+    S: FnMut(usize, &[Field], &TraceTable<Field>) -> Option<TraceTable<Field>>,
+{
+    let scratch = options.scratch_directory.as_deref();
+    let progress = Progress::new(options.observer.as_deref());
+    let mut recorder = Recorder::new(options);
     // This hack allows us to avoid changing the interface to mut for the
     // claim polynomials but is ugly and should be removed.
     let original_constraints = constraints.clone();
@@ -359,7 +714,7 @@ pub fn prove(constraints: &Constraints, trace: &TraceTable) -> Result<Proof> {
     trace!("BEGIN Stark proof");
     // TODO: Use a proper size human formating function
     #[allow(clippy::cast_precision_loss)]
-    let size_mb = (trace.num_rows() * trace.num_columns() * 32) as f64 / 1_000_000_f64;
+    let size_mb = (trace.num_rows() * trace.num_columns() * Field::BYTES) as f64 / 1_000_000_f64;
     info!(
         "Trace table {} rows {} columns ({} MB)",
         trace.num_rows(),
@@ -367,115 +722,320 @@ pub fn prove(constraints: &Constraints, trace: &TraceTable) -> Result<Proof> {
         size_mb
     );
     info!("{} constraints", constraints.len(),);
+    if constraints.zero_knowledge && constraints.max_trace_openings() > trace.num_rows() {
+        return Err(Error::TraceTooShort);
+    }
 
-    info!("Initialize channel with claim.");
-    let mut proof = ProverChannel::default();
-    proof.initialize(constraints.channel_seed());
+    // Stages completed in the checkpoint are loaded instead of computed.
+    let (checkpoint, resumed) = match &options.checkpoint_directory {
+        Some(directory) => {
+            let fingerprint = checkpoint::fingerprint::<H, _>(&constraints);
+            if resume {
+                let (checkpoint, resumed) = Checkpoint::open(directory, fingerprint, scratch)?;
+                (Some(checkpoint), resumed)
+            } else {
+                (
+                    Some(Checkpoint::create(directory, fingerprint, scratch)?),
+                    None,
+                )
+            }
+        }
+        None if resume => return Err(Error::InvalidCheckpoint),
+        None => (None, None),
+    };
+    let completed = |stage: Stage| resumed.map_or(false, |resumed| stage <= resumed);
+    let saved = || checkpoint.as_ref().ok_or(Error::InvalidCheckpoint);
+
+    let mut proof = if let Some(resumed) = resumed {
+        info!("Resume after {}.", resumed);
+        saved()?.channel::<H>(resumed)?
+    } else {
+        info!("Initialize channel with claim.");
+        let mut proof = ProverChannel::<H>::default();
+        proof.initialize(&constraints.transcript_seed());
+        proof
+    };
+    if completed(Stage::Decommitment) {
+        info!("Verify proof.");
+        let proof = Proof::from_bytes(proof.proof);
+        verify_with_hasher::<H, _>(&original_constraints, &proof)?;
+        let report = recorder.finish(proof.as_bytes().len());
+        return Ok((proof, report));
+    }
+    constraints.substitute_public_inputs();
 
     // 1. Trace commitment.
-    trace!("BEGIN Trace commitment");
-
-    // Compute the low degree extension of the trace table.
-    info!("Compute the low degree extension of the trace table.");
-    let trace_polynomials = trace.interpolate();
-    info!(
-        "Trace degrees: {:?}",
-        trace_polynomials
-            .iter()
-            .map(DensePolynomial::degree)
-            .collect::<Vec<_>>()
-    );
-    let trace_lde = PolyLDE(
-        trace_polynomials
-            .iter()
-            .map(|p| p.low_degree_extension(constraints.blowup))
-            .collect::<Vec<_>>(),
-    );
-
-    // Construct a merkle tree over the LDE trace
-    // and write the root to the channel.
-    info!("Construct a merkle tree over the LDE trace and write the root to the channel.");
-    let (commitment, tree) = trace_lde.commit()?;
-    proof.write(&commitment);
-    trace!("END Trace commitment");
+    let num_stages = constraints.num_stages();
+    recorder.begin(Stage::TraceCommitment, proof.proof.len());
+    let (trace_polynomials, trees, challenges) = if completed(Stage::TraceCommitment) {
+        info!("Load the trace commitment.");
+        let trees = (0..num_stages)
+            .map(|stage| saved()?.tree(&format!("trace-tree-{}", stage)))
+            .collect::<Result<Vec<_>>>()?;
+        // The trace polynomials are only used in out of domain sampling.
+        let trace_polynomials = if completed(Stage::OutOfDomainSampling) {
+            Vec::new()
+        } else {
+            saved()?.polynomials("trace-polynomials")?
+        };
+        let challenges = saved()?.fields("challenges")?.to_vec();
+        (trace_polynomials, trees, challenges)
+    } else {
+        trace!("BEGIN Trace commitment");
+        progress.report(Stage::TraceCommitment, 0.0)?;
+        let mut trace_polynomials = Vec::with_capacity(constraints.trace_ncolumns());
+        let mut trees = Vec::with_capacity(num_stages);
+        let mut challenges: Vec<Field> = Vec::with_capacity(constraints.num_challenges());
+        // All columns of the previous stages, only constructed for later stages.
+        let mut previous_trace: Option<TraceTable<Field>> = None;
+        for stage in 0..num_stages {
+            let stage_trace = if stage == 0 {
+                None
+            } else {
+                info!("Read the challenges for trace stage {}.", stage);
+                let stage_challenges: Vec<Field> =
+                    get_coefficients(&mut proof, constraints.stage_challenges(stage).len());
+                challenges.extend(stage_challenges);
+                let previous = previous_trace.get_or_insert_with(|| trace.clone());
+                let stage_trace =
+                    stages(stage, &challenges, previous).ok_or(Error::InvalidTraceStage)?;
+                if stage_trace.num_rows() != trace.num_rows()
+                    || stage_trace.num_columns() != constraints.stage_columns(stage).len()
+                {
+                    return Err(Error::InvalidTraceStage);
+                }
+                Some(stage_trace)
+            };
+            let (polynomials, tree) = commit_trace(
+                stage_trace.as_ref().unwrap_or(trace),
+                &constraints,
+                &mut proof,
+                scratch,
+                &|done| {
+                    progress.report(
+                        Stage::TraceCommitment,
+                        fraction(stage, num_stages) + done * fraction(1, num_stages),
+                    )
+                },
+            )?;
+            recorder.fft(trace.num_rows(), polynomials.len());
+            for polynomial in &polynomials {
+                recorder.fft(polynomial.len(), constraints.blowup);
+            }
+            recorder.merkle_tree(tree.leaves().len());
+            trace_polynomials.extend(polynomials);
+            trees.push(tree);
+            if let (Some(previous), Some(stage_trace)) = (&previous_trace, &stage_trace) {
+                if stage + 1 < num_stages {
+                    previous_trace = Some(previous.concat_columns(stage_trace));
+                }
+            }
+        }
+        trace!("END Trace commitment");
+        if let Some(checkpoint) = &checkpoint {
+            checkpoint.write_fields("challenges", &challenges)?;
+            checkpoint.write_polynomials("trace-polynomials", &trace_polynomials)?;
+            for (stage, tree) in trees.iter().enumerate() {
+                checkpoint.write_tree(&format!("trace-tree-{}", stage), tree)?;
+            }
+            checkpoint.complete(Stage::TraceCommitment, &proof)?;
+        }
+        (trace_polynomials, trees, challenges)
+    };
+    recorder.end(proof.proof.len());
+    progress.report(Stage::TraceCommitment, 1.0)?;
+    constraints.substitute_challenges(&challenges);
 
     // 2. Constraint commitment
-    trace!("BEGIN Constraint commitment");
-
-    // Read constraint coefficients from the channel.
-    info!("Read constraint coefficients from the channel.");
-    let constraint_coefficients = get_coefficients(&mut proof, 2 * constraints.len());
-
-    info!("Compute constraint polynomials.");
-    let constraint_polynomials = get_constraint_polynomials(
-        &tree.leaves(),
-        &mut constraints,
-        &constraint_coefficients,
-        trace.num_rows(),
-    );
-    info!(
-        "Constraint degrees: {:?}",
-        constraint_polynomials
+    let eval_degree = constraints.degree().next_power_of_two();
+    recorder.begin(Stage::ConstraintCommitment, proof.proof.len());
+    let (constraint_polynomials, c_tree) = if completed(Stage::ConstraintCommitment) {
+        info!("Load the constraint commitment.");
+        // Left over from the constraint evaluation.
+        constraints.substitute();
+        // The constraint polynomials are only used in out of domain sampling.
+        let constraint_polynomials = if completed(Stage::OutOfDomainSampling) {
+            Vec::new()
+        } else {
+            saved()?.polynomials("constraint-polynomials")?
+        };
+        (constraint_polynomials, saved()?.tree("constraint-tree")?)
+    } else {
+        trace!("BEGIN Constraint commitment");
+        progress.report(Stage::ConstraintCommitment, 0.0)?;
+
+        // Read constraint coefficients from the channel.
+        info!("Read constraint coefficients from the channel.");
+        let constraint_coefficients = get_coefficients(&mut proof, 2 * constraints.len());
+
+        info!("Compute constraint polynomials.");
+        // Salts are not part of the trace
+        let trace_lde = trees
             .iter()
-            .map(DensePolynomial::degree)
-            .collect::<Vec<_>>()
-    );
+            .enumerate()
+            .flat_map(|(stage, tree)| {
+                tree.leaves().0[..constraints.stage_columns(stage).len()].iter()
+            })
+            .collect::<Vec<_>>();
+        let (mut constraint_polynomials, graph_nodes) = get_constraint_polynomials(
+            &trace_lde,
+            &mut constraints,
+            &constraint_coefficients,
+            trace.num_rows(),
+            options.chunk_size,
+            scratch,
+            &|done| progress.report(Stage::ConstraintCommitment, done / 2.0),
+        )?;
+        recorder.constraint_graph(graph_nodes);
+        recorder.fft(constraints.trace_polynomial_len() * eval_degree, 1);
+        if constraints.zero_knowledge {
+            // The masking polynomial is committed to and opened like the
+            // constraint polynomials, so it randomizes the DEEP composition.
+            constraint_polynomials.push(DensePolynomial::from_mmap_vec(random_column(
+                constraints.trace_polynomial_len(),
+                scratch,
+            )));
+        }
+        info!(
+            "Constraint degrees: {:?}",
+            constraint_polynomials
+                .iter()
+                .map(DensePolynomial::degree)
+                .collect::<Vec<_>>()
+        );
 
-    // OPT: It may be faster to compute the constraint LDE from the trace LDE,
-    // instead of using an FFT.
-    info!("Compute the low degree extension of constraint polynomials.");
-    let constraint_lde = PolyLDE(
-        constraint_polynomials
-            .iter()
-            .map(|p| p.low_degree_extension(constraints.blowup))
-            .collect::<Vec<_>>(),
-    );
-    // Construct a merkle tree over the LDE combined constraints
-    // and write the root to the channel.
-    info!("Compute the merkle tree over the LDE constraint polynomials.");
-    let (commitment, c_tree) = constraint_lde.commit()?;
-    proof.write(&commitment);
-    trace!("END Constraint commitment");
+        // OPT: It may be faster to compute the constraint LDE from the trace LDE,
+        // instead of using an FFT.
+        info!("Compute the low degree extension of constraint polynomials.");
+        let mut constraint_lde = PolyLDE(Vec::with_capacity(constraint_polynomials.len() + 1));
+        for (i, polynomial) in constraint_polynomials.iter().enumerate() {
+            constraint_lde
+                .0
+                .push(polynomial.low_degree_extension_in(constraints.blowup, scratch));
+            recorder.fft(polynomial.len(), constraints.blowup);
+            progress.report(
+                Stage::ConstraintCommitment,
+                0.5 + fraction(i + 1, constraint_polynomials.len()) / 2.0,
+            )?;
+        }
+        if constraints.zero_knowledge {
+            constraint_lde
+                .0
+                .push(random_column(constraints.eval_domain_size(), scratch));
+        }
+        // Construct a merkle tree over the LDE combined constraints
+        // and write the root to the channel.
+        info!("Compute the merkle tree over the LDE constraint polynomials.");
+        let c_tree = Tree::<_, H>::from_leaves_in(constraint_lde, scratch)?;
+        recorder.merkle_tree(c_tree.leaves().len());
+        proof.write(c_tree.commitment());
+        trace!("END Constraint commitment");
+        if let Some(checkpoint) = &checkpoint {
+            checkpoint.write_polynomials("constraint-polynomials", &constraint_polynomials)?;
+            checkpoint.write_tree("constraint-tree", &c_tree)?;
+            checkpoint.complete(Stage::ConstraintCommitment, &proof)?;
+        }
+        (constraint_polynomials, c_tree)
+    };
+    recorder.end(proof.proof.len());
+    progress.report(Stage::ConstraintCommitment, 1.0)?;
 
     // 3. Out of domain sampling
-    info!("Divide out OODS point and combine polynomials.");
-    trace!("BEGIN Out of domain sampling");
-    let oods_polynomial = oods_combine(
-        &mut proof,
-        &trace_polynomials,
-        &constraints.trace_arguments(),
-        &constraint_polynomials,
-    );
-    trace!("END Out of domain sampling");
-    info!("Oods poly degree: {}", oods_polynomial.degree());
+    recorder.begin(Stage::OutOfDomainSampling, proof.proof.len());
+    let oods_polynomial = if completed(Stage::OutOfDomainSampling) {
+        None
+    } else {
+        info!("Divide out OODS point and combine polynomials.");
+        trace!("BEGIN Out of domain sampling");
+        progress.report(Stage::OutOfDomainSampling, 0.0)?;
+        let oods_polynomial = oods_combine(
+            &mut proof,
+            &trace_polynomials,
+            &constraints.trace_arguments(),
+            &constraint_polynomials,
+            trace.num_rows(),
+            eval_degree,
+            scratch,
+        );
+        trace!("END Out of domain sampling");
+        info!("Oods poly degree: {}", oods_polynomial.degree());
+        if let Some(checkpoint) = &checkpoint {
+            checkpoint.write_polynomials("oods-polynomial", slice::from_ref(&oods_polynomial))?;
+            checkpoint.complete(Stage::OutOfDomainSampling, &proof)?;
+        }
+        Some(oods_polynomial)
+    };
+    recorder.end(proof.proof.len());
+    progress.report(Stage::OutOfDomainSampling, 1.0)?;
 
     // 4. FRI layers with trees
-    info!("LDE extension of final polynomial.");
-    trace!("BEGIN FRI commitment");
-    let first_fri_layer = oods_polynomial.low_degree_extension(constraints.blowup);
-    info!("Fri layers.");
-    let fri_trees = perform_fri_layering(
-        first_fri_layer,
-        &mut proof,
-        &constraints.fri_layout,
-        constraints.blowup,
-    )?;
-    trace!("END FRI commitment");
+    recorder.begin(Stage::Fri, proof.proof.len());
+    let fri_prover = if completed(Stage::Fri) {
+        info!("Load the FRI layers.");
+        saved()?.fri()?
+    } else {
+        let oods_polynomial = match oods_polynomial {
+            Some(oods_polynomial) => oods_polynomial,
+            None => {
+                saved()?
+                    .polynomials("oods-polynomial")?
+                    .pop()
+                    .ok_or(Error::InvalidCheckpoint)?
+            }
+        };
+        info!("LDE extension of final polynomial.");
+        trace!("BEGIN FRI commitment");
+        progress.report(Stage::Fri, 0.0)?;
+        let first_fri_layer = oods_polynomial.low_degree_extension_in(constraints.blowup, scratch);
+        recorder.fft(oods_polynomial.len(), constraints.blowup);
+        info!("Fri layers.");
+        let fri_prover = FriProver::<_, H>::commit_with_progress(
+            &constraints.fri_parameters(),
+            first_fri_layer,
+            &mut proof,
+            scratch,
+            |done| progress.report(Stage::Fri, done),
+        )?;
+        trace!("END FRI commitment");
+        for tree in &fri_prover.trees {
+            recorder.merkle_tree(tree.leaves().len());
+        }
+        recorder.fft(fri_prover.final_layer().len(), 1);
+        if let Some(checkpoint) = &checkpoint {
+            checkpoint.write_fri(&fri_prover)?;
+            checkpoint.complete(Stage::Fri, &proof)?;
+        }
+        fri_prover
+    };
+    recorder.end(proof.proof.len());
+    progress.report(Stage::Fri, 1.0)?;
 
     // 5. Proof of work
-    info!("Proof of work.");
-    let pow_seed: proof_of_work::ChallengeSeed = proof.get_random();
-    let pow_challenge = pow_seed.with_difficulty(constraints.pow_bits);
-    let pow_response = pow_challenge.solve();
-    debug_assert!(pow_challenge.verify(pow_response));
-    proof.write(pow_response);
+    recorder.begin(Stage::ProofOfWork, proof.proof.len());
+    if !completed(Stage::ProofOfWork) {
+        info!("Proof of work.");
+        progress.report(Stage::ProofOfWork, 0.0)?;
+        let pow_seed: proof_of_work::ChallengeSeed = proof.get_random();
+        let pow_challenge = pow_seed.with_difficulty::<H>(constraints.pow_bits);
+        let pow_response =
+            pow_challenge.solve_with_progress(|done| progress.report(Stage::ProofOfWork, done))?;
+        debug_assert!(pow_challenge.verify(pow_response));
+        proof.write(pow_response);
+        if let Some(checkpoint) = &checkpoint {
+            checkpoint.complete(Stage::ProofOfWork, &proof)?;
+        }
+    }
+    recorder.end(proof.proof.len());
+    progress.report(Stage::ProofOfWork, 1.0)?;
 
     // 6. Query decommitments
     //
 
     // Fetch query indices from channel.
     info!("Fetch query indices from channel.");
-    let eval_domain_size = trace.num_rows() * constraints.blowup;
+    recorder.begin(Stage::Decommitment, proof.proof.len());
+    progress.report(Stage::Decommitment, 0.0)?;
+    let eval_domain_size = constraints.eval_domain_size();
     let query_indices = get_indices(
         constraints.num_queries,
         64 - eval_domain_size.leading_zeros() - 1,
@@ -485,10 +1045,12 @@ pub fn prove(constraints: &Constraints, trace: &TraceTable) -> Result<Proof> {
 
     // Decommit the trace table values.
     info!("Decommit the trace table values.");
-    for &index in &query_indices {
-        proof.write(tree.leaf(index));
+    for tree in &trees {
+        for &index in &query_indices {
+            proof.write(tree.leaf(index));
+        }
+        proof.write(&tree.open(&query_indices)?);
     }
-    proof.write(&tree.open(&query_indices)?);
 
     // Decommit the constraint values
     info!("Decommit the constraint values.");
@@ -499,23 +1061,91 @@ pub fn prove(constraints: &Constraints, trace: &TraceTable) -> Result<Proof> {
 
     // Decommit the FRI layer values
     info!("Decommit the FRI layer values.");
-    decommit_fri_layers_and_trees(fri_trees.as_slice(), query_indices.as_slice(), &mut proof)?;
+    fri_prover.decommit(&query_indices, &mut proof)?;
+    if let Some(checkpoint) = &checkpoint {
+        checkpoint.complete(Stage::Decommitment, &proof)?;
+    }
+    recorder.end(proof.proof.len());
+    progress.report(Stage::Decommitment, 1.0)?;
 
     // Verify proof
     info!("Verify proof.");
     // TODO: Rename channel / transcript object
     let proof = Proof::from_bytes(proof.proof);
-    verify(&original_constraints, &proof)?;
+    verify_with_hasher::<H, _>(&original_constraints, &proof)?;
+    let report = recorder.finish(proof.as_bytes().len());
 
     trace!("END Stark proof");
-    Ok(proof)
+    Ok((proof, report))
+}
+
+/// Interpolate and low degree extend a trace stage and commit to it.
+///
+/// Returns the (masked) trace polynomials and the Merkle tree over the LDE.
+fn commit_trace<H, Field>(
+    trace: &TraceTable<Field>,
+    constraints: &Constraints<Field>,
+    proof: &mut ProverChannel<H>,
+    scratch: Option<&Path>,
+    progress: &dyn Fn(f64) -> Result<()>,
+) -> Result<CommittedTrace<Field, H>>
+where
+    H: Hasher,
+    Field: StarkField,
+    for<'a> &'a Field: RefFieldLike<Field>,
+{
+    // Compute the low degree extension of the trace table.
+    info!("Compute the low degree extension of the trace table.");
+    let trace_polynomials = trace.interpolate_in(scratch);
+    let trace_polynomials = if constraints.zero_knowledge {
+        info!("Mask the trace polynomials.");
+        trace_polynomials
+            .iter()
+            .map(|polynomial| mask_trace_polynomial(polynomial, scratch))
+            .collect::<Vec<_>>()
+    } else {
+        trace_polynomials
+    };
+    info!(
+        "Trace degrees: {:?}",
+        trace_polynomials
+            .iter()
+            .map(DensePolynomial::degree)
+            .collect::<Vec<_>>()
+    );
+    let mut trace_lde = PolyLDE(Vec::with_capacity(trace_polynomials.len() + 1));
+    for (i, polynomial) in trace_polynomials.iter().enumerate() {
+        trace_lde
+            .0
+            .push(polynomial.low_degree_extension_in(constraints.blowup, scratch));
+        progress(fraction(i + 1, trace_polynomials.len()))?;
+    }
+    if constraints.zero_knowledge {
+        trace_lde
+            .0
+            .push(random_column(constraints.eval_domain_size(), scratch));
+    }
+
+    // Construct a merkle tree over the LDE trace
+    // and write the root to the channel.
+    info!("Construct a merkle tree over the LDE trace and write the root to the channel.");
+    let tree = Tree::from_leaves_in(trace_lde, scratch)?;
+    proof.write(tree.commitment());
+    Ok((trace_polynomials, tree))
 }
 
 // Constructs a trace table on a coset domain of `size`.
-fn extract_trace_coset(trace_lde: &PolyLDE, size: usize) -> TraceTable {
-    let trace_lde: &[MmapVec<FieldElement>] = &trace_lde.0;
+fn extract_trace_coset<Field>(
+    trace_lde: &[&MmapVec<Field>],
+    size: usize,
+    scratch: Option<&Path>,
+) -> TraceTable<Field>
+where
+    Field: StarkField,
+    for<'a> &'a Field: RefFieldLike<Field>,
+{
     let lde_size = trace_lde[0].len();
-    let mut trace_coset = TraceTable::new(size, trace_lde.len());
+    let mut trace_coset = TraceTable::new_in(size, trace_lde.len(), scratch);
     trace!("BEGIN Extract Trace Coset");
     for i in 0..trace_coset.num_rows() {
         let index = i * lde_size / size;
@@ -528,7 +1158,11 @@ fn extract_trace_coset(trace_lde: &PolyLDE, size: usize) -> TraceTable {
     trace_coset
 }
 
-fn get_indices(num: usize, bits: u32, proof: &mut ProverChannel) -> Vec<usize> {
+pub(crate) fn get_indices<H: Hasher>(
+    num: usize,
+    bits: u32,
+    proof: &mut ProverChannel<H>,
+) -> Vec<usize> {
     let mut query_indices = Vec::with_capacity(num + 3);
     while query_indices.len() < num {
         let val: U256 = proof.get_random();
@@ -543,26 +1177,35 @@ fn get_indices(num: usize, bits: u32, proof: &mut ProverChannel) -> Vec<usize> {
     query_indices
 }
 
-fn get_constraint_polynomials(
-    trace_lde: &PolyLDE,
-    constraints: &mut Constraints,
-    constraint_coefficients: &[FieldElement],
-    trace_length: usize,
-) -> Vec<DensePolynomial> {
+pub(crate) fn get_constraint_polynomials<Field>(
+    trace_lde: &[&MmapVec<Field>],
+    constraints: &mut Constraints<Field>,
+    constraint_coefficients: &[Field],
+    trace_nrows: usize,
+    chunk_size: usize,
+    scratch: Option<&Path>,
+    progress: &(dyn Fn(f64) -> Result<()> + Sync),
+) -> Result<(Vec<DensePolynomial<Field>>, usize)>
+where
+    Field: StarkField,
+    for<'a> &'a Field: RefFieldLike<Field>,
+{
     // OPT: Better parallelization strategies. Probably the best would be to
     // split to domain up in smaller cosets and solve their expressions
     // independently. This will make all periods and therefore lookup tables
     // smaller.
-    const CHUNK_SIZE: usize = 65536;
+    // The DAG evaluates a fixed number of rows at a time.
+    let chunk_size = max(chunk_size, DAG_CHUNK_SIZE).next_power_of_two();
     trace!("BEGIN Compute constraint polynomials");
 
     // We need to evaluate on a power of two degree
     let constraint_degree = constraints.degree();
     let eval_degree = constraint_degree.next_power_of_two();
+    let trace_length = constraints.trace_polynomial_len();
     let coset_size = trace_length * eval_degree;
 
     info!("Compute offset trace table");
-    let trace_coset = extract_trace_coset(trace_lde, coset_size);
+    let trace_coset = extract_trace_coset(trace_lde, coset_size, scratch);
 
     info!("Combine rational expressions");
     let mut combined_constraints = constraints.combine(constraint_coefficients);
@@ -579,9 +1222,9 @@ fn get_constraint_polynomials(
     constraints.substitute();
 
     let mut dag = AlgebraicGraph::new(
-        &FieldElement::generator(),
+        &Field::generator(),
         trace_coset.num_rows(),
-        eval_degree,
+        coset_size / trace_nrows,
     );
     trace!("Convert to DAG");
     let result = dag.expression(combined_constraints);
@@ -597,21 +1240,24 @@ fn get_constraint_polynomials(
     // Evaluate on the coset trace table
     info!("Evaluate DAG on the coset trace table");
     trace!("BEGIN Evaluate");
-    let mut result: MmapVec<FieldElement> = MmapVec::with_capacity(coset_size);
-    result.resize(coset_size, FieldElement::zero());
+    let mut result: MmapVec<Field> = MmapVec::with_capacity_scratch(coset_size, scratch);
+    result.resize(coset_size, Field::zero());
     let values = &mut result;
+    let num_chunks = (coset_size + chunk_size - 1) / chunk_size;
+    let chunks_done = AtomicUsize::new(0);
     values
-        .par_chunks_mut(CHUNK_SIZE)
+        .par_chunks_mut(chunk_size)
         .enumerate()
-        .for_each(|(mut i, chunk)| {
-            i *= CHUNK_SIZE;
+        .try_for_each(|(mut i, chunk)| {
+            i *= chunk_size;
             let mut dag = dag.clone();
             dag.init(i);
             for value in chunk {
                 *value = dag.next(&trace_coset);
                 i += 1;
             }
-        });
+            progress(fraction(chunks_done.fetch_add(1, Relaxed) + 1, num_chunks))
+        })?;
     trace!("END Evaluate");
 
     info!("Convert from values to coefficients");
@@ -621,21 +1267,19 @@ fn get_constraint_polynomials(
     permute(values);
     trace!("END Interpolate");
     // OPT: Merge with even-odd separation loop.
-    for (f, y) in geometric_series(
-        &FieldElement::one(),
-        &FieldElement::generator().inv().unwrap(),
-    )
-    .zip(values.iter_mut())
+    for (f, y) in
+        geometric_series(&Field::one(), &Field::generator().inv().unwrap()).zip(values.iter_mut())
     {
         // Shift out the generator from the evaluation domain.
         *y *= &f;
     }
 
     // Convert to even and odd coefficient polynomials
-    let mut constraint_polynomials: Vec<MmapVec<FieldElement>> =
-        vec![MmapVec::with_capacity(trace_length); eval_degree];
+    let mut constraint_polynomials: Vec<MmapVec<Field>> = (0..eval_degree)
+        .map(|_| MmapVec::with_capacity_scratch(trace_length, scratch))
+        .collect();
     let (coefficients, zeros) = values.split_at(eval_degree * trace_length);
-    assert!(zeros.iter().all(|z| z == &FieldElement::zero()));
+    assert!(zeros.iter().all(Field::is_zero));
     for chunk in coefficients.chunks_exact(eval_degree) {
         for (i, coefficient) in chunk.iter().enumerate() {
             constraint_polynomials[i].push(coefficient.clone());
@@ -646,30 +1290,40 @@ fn get_constraint_polynomials(
         .map(DensePolynomial::from_mmap_vec)
         .collect();
     trace!("END Compute constraint polynomials");
-    result
+    Ok((result, dag.num_nodes()))
 }
 
-fn oods_combine(
-    proof: &mut ProverChannel,
-    trace_polynomials: &[DensePolynomial],
+pub(crate) fn oods_combine<H, Field>(
+    proof: &mut ProverChannel<H>,
+    trace_polynomials: &[DensePolynomial<Field>],
     trace_arguments: &[(usize, isize)],
-    constraint_polynomials: &[DensePolynomial],
-) -> DensePolynomial {
+    constraint_polynomials: &[DensePolynomial<Field>],
+    trace_nrows: usize,
+    eval_degree: usize,
+    scratch: Option<&Path>,
+) -> DensePolynomial<Field>
+where
+    H: Hasher,
+    Field: StarkField,
+    for<'a> &'a Field: RefFieldLike<Field>,
+{
     // Fetch the oods sampling point
     let trace_length = trace_polynomials[0].len();
-    let oods_point: FieldElement = proof.get_random();
+    let oods_point: Field = proof.get_random();
     dbg!(oods_point.clone());
-    let g = FieldElement::root(trace_length).expect("No root for trace polynomial length.");
+    let g = Field::root(trace_nrows).expect("No root for trace length.");
 
     // Write point evaluations to proof
     // OPT: Parallelization
     for (column, offset) in trace_arguments {
-        proof
-            .write(&trace_polynomials[*column].evaluate(&(&oods_point * &g.pow(*offset).unwrap())));
+        proof.write(
+            &trace_polynomials[*column]
+                .evaluate(&(&oods_point * &pow_signed(&g, *offset).unwrap())),
+        );
     }
 
-    let oods_point_pow = oods_point.pow(constraint_polynomials.len());
-    let mut oods_value = FieldElement::zero();
+    let oods_point_pow = oods_point.pow(eval_degree);
+    let mut oods_value = Field::zero();
     for (i, constraint_polynomial) in constraint_polynomials.iter().enumerate() {
         let value = constraint_polynomial.evaluate(&oods_point_pow);
         proof.write(&value);
@@ -683,10 +1337,10 @@ fn oods_combine(
     let trace_coefficients = get_coefficients(proof, trace_arguments.len());
     let constraint_coefficients = get_coefficients(proof, constraint_polynomials.len());
 
-    let mut combined_polynomial = DensePolynomial::zeros(trace_length);
+    let mut combined_polynomial = DensePolynomial::zeros_in(trace_length, scratch);
     for ((column, offset), coefficient) in trace_arguments.iter().zip(&trace_coefficients) {
         trace_polynomials[*column].divide_out_point_into(
-            &(&oods_point * g.pow(*offset).unwrap()),
+            &(&oods_point * pow_signed(&g, *offset).unwrap()),
             coefficient,
             &mut combined_polynomial,
         );
@@ -703,174 +1357,60 @@ fn oods_combine(
     combined_polynomial
 }
 
-// TODO: remove this and refactor ProverChannel.
-fn get_coefficients(proof: &mut ProverChannel, n: usize) -> Vec<FieldElement> {
-    (0..n).map(|_| proof.get_random()).collect()
+/// Mask a trace polynomial with a random multiple of the vanishing polynomial.
+///
+/// Returns $T(x) + (x^n - 1) \cdot R(x)$ for a random $R$ with $n$
+/// coefficients. The values on the trace domain are unchanged, so the
+/// constraints still hold, but any $n$ evaluations outside of the trace domain
+/// are uniformly random.
+fn mask_trace_polynomial<Field>(
+    polynomial: &DensePolynomial<Field>,
+    scratch: Option<&Path>,
+) -> DensePolynomial<Field>
+where
+    Field: StarkField,
+    for<'a> &'a Field: RefFieldLike<Field>,
+{
+    let mask = random_column(polynomial.len(), scratch);
+    let mut coefficients = MmapVec::with_capacity_scratch(2 * polynomial.len(), scratch);
+    coefficients.extend(
+        polynomial
+            .coefficients()
+            .iter()
+            .zip(mask.iter())
+            .map(|(coefficient, r)| coefficient - r),
+    );
+    coefficients.extend_from_slice(&mask);
+    DensePolynomial::from_mmap_vec(coefficients)
 }
 
-fn perform_fri_layering(
-    first_layer: MmapVec<FieldElement>,
-    proof: &mut ProverChannel,
-    fri_layout: &[usize],
-    blowup: usize,
-) -> Result<Vec<FriTree>> {
-    let mut fri_trees: Vec<FriTree> = Vec::with_capacity(fri_layout.len());
-
-    // Compute 1/x for the fri layer. We only compute the even coordinates.
-    // OPT: Can these be efficiently computed on the fly?
-    let x_inv = {
-        let n = first_layer.len();
-        let root_inv = FieldElement::root(n)
-            .ok_or(Error::RootUnavailable)?
-            .inv()
-            .unwrap();
-        let mut x_inv = MmapVec::with_capacity(n / 2);
-        let mut accumulator = FieldElement::one();
-        for _ in 0..n / 2 {
-            x_inv.push(accumulator.clone());
-            accumulator *= &root_inv;
-        }
-        permute(&mut x_inv);
-        x_inv
-    };
-
-    let mut next_layer = first_layer;
-    for &n_reductions in fri_layout {
-        // Allocate next and swap ownership
-        let mut layer = MmapVec::with_capacity(next_layer.len() / (1 << n_reductions));
-        std::mem::swap(&mut layer, &mut next_layer);
-
-        // Create tree from layer
-        // FRI layout values are small.
-        #[allow(clippy::cast_possible_truncation)]
-        let coset_size = 2_usize.pow(n_reductions as u32);
-        let tree = FriTree::from_leaves(FriLeaves { coset_size, layer })?;
-        fri_trees.push(tree);
-        let tree = fri_trees.last().unwrap();
-        let layer = &tree.leaves().layer;
-
-        // Write commitment and pull coefficient
-        proof.write(tree.commitment());
-        let coefficient = proof.get_random();
-
-        // Fold layer up to three times
-        // TODO: Capture the pattern in a macro and DRY.
-        // OPT: Parallelization
-        // OPT: The structure in x_inv should allow faster methods,
-        // like in a radix-4 and radix-8 fft.
-        let layer = layer.iter();
-        match n_reductions {
-            1 => {
-                next_layer.extend(
-                    layer
-                        .tuples()
-                        .zip(x_inv.iter())
-                        .map(|((p0, p1), x_inv)| (p0 + p1) + &coefficient * x_inv * (p0 - p1)),
-                )
-            }
-            2 => {
-                let coefficient_2 = coefficient.square();
-                next_layer.extend(
-                    layer
-                        .tuples()
-                        .zip(x_inv.iter())
-                        .map(|((p0, p1), x_inv)| (p0 + p1) + &coefficient * x_inv * (p0 - p1))
-                        .tuples()
-                        .zip(x_inv.iter())
-                        .map(|((p0, p1), x_inv)| (&p0 + &p1) + &coefficient_2 * x_inv * (p0 - p1)),
-                )
-            }
-            3 => {
-                let coefficient_2 = coefficient.square();
-                let coefficient_4 = coefficient_2.square();
-                next_layer.extend(
-                    layer
-                        .tuples()
-                        .zip(x_inv.iter())
-                        .map(|((p0, p1), x_inv)| (p0 + p1) + &coefficient * x_inv * (p0 - p1))
-                        .tuples()
-                        .zip(x_inv.iter())
-                        .map(|((p0, p1), x_inv)| (&p0 + &p1) + &coefficient_2 * x_inv * (p0 - p1))
-                        .tuples()
-                        .zip(x_inv.iter())
-                        .map(|((p0, p1), x_inv)| (&p0 + &p1) + &coefficient_4 * x_inv * (p0 - p1)),
-                )
-            }
-            // TODO: Is there a use case for 4 layer folds?
-            4 => {
-                let coefficient_2 = coefficient.square();
-                let coefficient_4 = coefficient_2.square();
-                let coefficient_8 = coefficient_4.square();
-                next_layer.extend(
-                    layer
-                        .tuples()
-                        .zip(x_inv.iter())
-                        .map(|((p0, p1), x_inv)| (p0 + p1) + &coefficient * x_inv * (p0 - p1))
-                        .tuples()
-                        .zip(x_inv.iter())
-                        .map(|((p0, p1), x_inv)| (&p0 + &p1) + &coefficient_2 * x_inv * (p0 - p1))
-                        .tuples()
-                        .zip(x_inv.iter())
-                        .map(|((p0, p1), x_inv)| (&p0 + &p1) + &coefficient_4 * x_inv * (p0 - p1))
-                        .tuples()
-                        .zip(x_inv.iter())
-                        .map(|((p0, p1), x_inv)| (&p0 + &p1) + &coefficient_8 * x_inv * (p0 - p1)),
-                )
-            }
-            _ => unimplemented!(),
-        };
-    }
-
-    // Write the final layer coefficients
-    let n_coefficients = next_layer.len() / blowup;
-    let points = &mut next_layer[0..n_coefficients];
-    permute(points);
-    points.ifft();
-    permute(points);
-    proof.write(&*points);
-
-    Ok(fri_trees)
+/// Uniformly random field elements, used for masking and salting.
+fn random_column<Field: StarkField>(size: usize, scratch: Option<&Path>) -> MmapVec<Field> {
+    let mut rng = thread_rng();
+    let mut column = MmapVec::with_capacity_scratch(size, scratch);
+    column.extend((0..size).map(|_| random_element::<Field>(&mut rng)));
+    column
 }
 
-fn decommit_fri_layers_and_trees(
-    fri_trees: &[FriTree],
-    query_indices: &[usize],
-    proof: &mut ProverChannel,
-) -> Result<()> {
-    let mut previous_indices: Vec<usize> = query_indices.to_vec();
-
-    for tree in fri_trees {
-        let coset_size = tree.leaves().coset_size;
-
-        let new_indices: Vec<usize> = previous_indices
-            .iter()
-            .map(|x| x / coset_size)
-            .dedup()
-            .collect();
-
-        for i in &new_indices {
-            // TODO: Write entire tree.leaf(i)
-            for j in 0..coset_size {
-                let n = i * coset_size + j;
-                match previous_indices.binary_search(&n) {
-                    Ok(_) => (),
-                    Err(_) => proof.write(&tree.leaves().layer[n]),
-                };
-            }
-        }
-        proof.write(&tree.open(&new_indices)?);
-        previous_indices = new_indices;
-    }
-    Ok(())
+// TODO: remove this and refactor ProverChannel.
+pub(crate) fn get_coefficients<H: Hasher, Field: StarkField>(
+    proof: &mut ProverChannel<H>,
+    n: usize,
+) -> Vec<Field> {
+    (0..n).map(|_| proof.get_random()).collect()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::{traits::tests::Recurrance, verify, Provable, Verifiable};
+    use crate::{
+        traits::tests::Recurrance, verify, verify_with_hasher, CheckError, Provable, Verifiable,
+    };
     use tiny_keccak::{Hasher, Sha3};
     use zkp_macros_decl::{field_element, hex, u256h};
-    use zkp_primefield::{fft::permute_index, geometric_series::geometric_series};
+    use zkp_primefield::{
+        fft::permute_index, geometric_series::geometric_series, FieldElement, Root,
+    };
     use zkp_u256::U256;
 
     #[test]
@@ -890,6 +1430,8 @@ mod tests {
         constraints.pow_bits = 0;
         constraints.num_queries = 20;
         constraints.fri_layout = vec![3, 2];
+        // The reference verifier only hashes the channel seed
+        constraints.strong_fiat_shamir = false;
 
         let trace = claim.trace(&witness);
         let actual = prove(&constraints, &trace).unwrap();
@@ -931,6 +1473,8 @@ mod tests {
         constraints.pow_bits = 12;
         constraints.num_queries = 20;
         constraints.fri_layout = vec![3, 2];
+        // The Python reference only hashes the channel seed
+        constraints.strong_fiat_shamir = false;
         let proof = prove(&constraints, &trace).unwrap();
 
         let mut output = [0; 32];
@@ -963,6 +1507,617 @@ mod tests {
         verify(&constraints, &actual).unwrap();
     }
 
+    #[test]
+    fn out_of_core() {
+        let recurrance = Recurrance {
+            index:         1000,
+            initial_value: field_element!("cafebabe"),
+            exponent:      1,
+        };
+        let claim = recurrance.claim();
+        let trace = claim.trace(&recurrance.witness());
+        let mut constraints = claim.constraints();
+        constraints.blowup = 64;
+        constraints.num_queries = 20;
+        constraints.fri_layout = vec![3, 2];
+
+        let directory = std::env::temp_dir().join(format!("zkp-stark-{}", std::process::id()));
+        std::fs::create_dir_all(&directory).unwrap();
+        let options = ProverOptions {
+            scratch_directory: Some(directory.clone()),
+            chunk_size: 100,
+            ..ProverOptions::default()
+        };
+        let proof = prove_with_options::<MaskedKeccak, _, _>(
+            &constraints,
+            &trace,
+            |_, _, _| unreachable!(),
+            &options,
+        )
+        .unwrap();
+        let _ = std::fs::remove_dir(&directory);
+        assert_eq!(proof, prove(&constraints, &trace).unwrap());
+    }
+
+    #[test]
+    fn checkpoint_resume() {
+        let recurrance = Recurrance {
+            index:         1000,
+            initial_value: field_element!("cafebabe"),
+            exponent:      1,
+        };
+        let claim = recurrance.claim();
+        let trace = claim.trace(&recurrance.witness());
+        let mut constraints = claim.constraints();
+        constraints.num_queries = 20;
+        constraints.fri_layout = vec![3, 2];
+
+        let directory =
+            std::env::temp_dir().join(format!("zkp-stark-checkpoint-{}", std::process::id()));
+        let options = ProverOptions {
+            checkpoint_directory: Some(directory.clone()),
+            ..ProverOptions::default()
+        };
+        let no_stages = |_: usize, _: &[FieldElement], _: &TraceTable<FieldElement>| unreachable!();
+        let proof =
+            prove_with_options::<MaskedKeccak, _, _>(&constraints, &trace, no_stages, &options)
+                .unwrap();
+        let fingerprint = checkpoint::fingerprint::<MaskedKeccak, _>(&constraints);
+        let (checkpoint, last) = Checkpoint::open(&directory, fingerprint, None).unwrap();
+        assert_eq!(last, Some(Stage::Decommitment));
+
+        // Resume after every stage, including the last one.
+        for stage in (0..=Stage::Decommitment.index()).rev() {
+            let stage = Stage::from_index(stage).unwrap();
+            let channel = checkpoint.channel::<MaskedKeccak>(stage).unwrap();
+            checkpoint.complete(stage, &channel).unwrap();
+            let resumed =
+                prove_resume::<MaskedKeccak, _, _>(&constraints, &trace, no_stages, &options)
+                    .unwrap();
+            assert_eq!(resumed, proof, "resumed after {}", stage);
+        }
+
+        // Zero knowledge proofs are valid when resumed, the later stages are
+        // randomized again.
+        constraints.zero_knowledge = true;
+        let proof =
+            prove_with_options::<MaskedKeccak, _, _>(&constraints, &trace, no_stages, &options)
+                .unwrap();
+        let fingerprint = checkpoint::fingerprint::<MaskedKeccak, _>(&constraints);
+        let (checkpoint, _) = Checkpoint::open(&directory, fingerprint, None).unwrap();
+        for stage in &[Stage::Decommitment, Stage::Fri, Stage::TraceCommitment] {
+            let channel = checkpoint.channel::<MaskedKeccak>(*stage).unwrap();
+            checkpoint.complete(*stage, &channel).unwrap();
+            let resumed =
+                prove_resume::<MaskedKeccak, _, _>(&constraints, &trace, no_stages, &options)
+                    .unwrap();
+            verify(&constraints, &resumed).unwrap();
+            if *stage == Stage::Decommitment {
+                assert_eq!(resumed, proof);
+            }
+        }
+
+        // A checkpoint of a different proof is rejected.
+        constraints.num_queries = 21;
+        assert_eq!(
+            prove_resume::<MaskedKeccak, _, _>(&constraints, &trace, no_stages, &options),
+            Err(Error::InvalidCheckpoint)
+        );
+        let _ = std::fs::remove_dir_all(&directory);
+    }
+
+    #[test]
+    fn checkpoint_fingerprint() {
+        let recurrance = Recurrance {
+            index:         1000,
+            initial_value: field_element!("cafebabe"),
+            exponent:      1,
+        };
+        let constraints = recurrance.claim().constraints();
+        let weak = |expressions: &[crate::RationalExpression]| {
+            let mut weak = Constraints::from_expressions(
+                (constraints.trace_nrows(), constraints.trace_ncolumns()),
+                constraints.channel_seed().to_vec(),
+                expressions.to_vec(),
+            )
+            .unwrap();
+            weak.strong_fiat_shamir = false;
+            weak
+        };
+
+        // The expressions are part of the fingerprint, even when the
+        // transcript seed does not include them.
+        let all = weak(constraints.expressions());
+        let fewer = weak(&constraints.expressions()[1..]);
+        assert_eq!(all.transcript_seed(), fewer.transcript_seed());
+        assert_ne!(
+            checkpoint::fingerprint::<MaskedKeccak, _>(&all),
+            checkpoint::fingerprint::<MaskedKeccak, _>(&fewer)
+        );
+    }
+
+    #[test]
+    fn checkpoint_corrupt_length() {
+        let directory = std::env::temp_dir().join(format!(
+            "zkp-stark-checkpoint-length-{}",
+            std::process::id()
+        ));
+        let checkpoint = Checkpoint::create(&directory, Hash::default(), None).unwrap();
+        let values = (0..4_usize).map(FieldElement::from).collect::<Vec<_>>();
+        checkpoint.write_fields("values", &values).unwrap();
+        assert_eq!(
+            checkpoint
+                .fields::<FieldElement>("values")
+                .unwrap()
+                .to_vec(),
+            values
+        );
+
+        // Length prefixes beyond the end of the file are rejected before
+        // allocating.
+        let path = directory.join("values");
+        let mut bytes = std::fs::read(&path).unwrap();
+        for &length in &[5_u64, 1 << 40, 1 << 63] {
+            bytes[..8].copy_from_slice(&length.to_be_bytes());
+            std::fs::write(&path, &bytes).unwrap();
+            assert_eq!(
+                checkpoint.fields::<FieldElement>("values").err(),
+                Some(Error::InvalidCheckpoint)
+            );
+        }
+        let _ = std::fs::remove_dir_all(&directory);
+    }
+
+    #[test]
+    fn observer_progress() {
+        let recurrance = Recurrance {
+            index:         1000,
+            initial_value: field_element!("cafebabe"),
+            exponent:      1,
+        };
+        let claim = recurrance.claim();
+        let trace = claim.trace(&recurrance.witness());
+        let mut constraints = claim.constraints();
+        constraints.num_queries = 20;
+        constraints.fri_layout = vec![3, 2];
+
+        let reports = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let observer = {
+            let reports = Arc::clone(&reports);
+            move |stage: Stage, fraction: f64, _| reports.lock().unwrap().push((stage, fraction))
+        };
+        let options = ProverOptions {
+            observer: Some(Arc::new(observer)),
+            ..ProverOptions::default()
+        };
+        let proof = prove_with_options::<MaskedKeccak, _, _>(
+            &constraints,
+            &trace,
+            |_, _, _| unreachable!(),
+            &options,
+        )
+        .unwrap();
+        assert_eq!(proof, prove(&constraints, &trace).unwrap());
+
+        // Every stage is reported from start to end and in order.
+        let reports = reports.lock().unwrap();
+        for stage in 0..=Stage::Decommitment.index() {
+            let stage = Stage::from_index(stage).unwrap();
+            let fractions = reports
+                .iter()
+                .filter(|(reported, _)| *reported == stage)
+                .map(|(_, fraction)| *fraction)
+                .collect::<Vec<_>>();
+            assert_eq!(fractions.first(), Some(&0.0));
+            assert_eq!(fractions.last(), Some(&1.0));
+            assert!(fractions
+                .iter()
+                .all(|fraction| (0.0..=1.0).contains(fraction)));
+        }
+        assert!(reports.windows(2).all(|pair| pair[0].0 <= pair[1].0));
+        assert!(reports.iter().any(|(stage, fraction)| {
+            *stage == Stage::ConstraintCommitment && *fraction > 0.0 && *fraction < 1.0
+        }));
+    }
+
+    #[test]
+    fn observer_cancel() {
+        struct CancelAt(Stage, AtomicUsize);
+
+        impl ProverObserver for CancelAt {
+            fn progress(&self, stage: Stage, _: f64, _: std::time::Duration) {
+                let _ = self.1.fetch_max(stage.index(), Relaxed);
+            }
+
+            fn is_cancelled(&self) -> bool {
+                self.1.load(Relaxed) >= self.0.index()
+            }
+        }
+
+        let recurrance = Recurrance {
+            index:         1000,
+            initial_value: field_element!("cafebabe"),
+            exponent:      1,
+        };
+        let claim = recurrance.claim();
+        let trace = claim.trace(&recurrance.witness());
+        let constraints = claim.constraints();
+
+        let observer = Arc::new(CancelAt(Stage::Fri, AtomicUsize::new(0)));
+        let options = ProverOptions {
+            observer: Some(observer.clone()),
+            ..ProverOptions::default()
+        };
+        assert_eq!(
+            prove_with_options::<MaskedKeccak, _, _>(
+                &constraints,
+                &trace,
+                |_, _, _| unreachable!(),
+                &options,
+            ),
+            Err(Error::Cancelled)
+        );
+        assert_eq!(observer.1.load(Relaxed), Stage::Fri.index());
+    }
+
+    #[test]
+    fn proof_report() {
+        let recurrance = Recurrance {
+            index:         1000,
+            initial_value: field_element!("cafebabe"),
+            exponent:      1,
+        };
+        let claim = recurrance.claim();
+        let trace = claim.trace(&recurrance.witness());
+        let mut constraints = claim.constraints();
+        constraints.num_queries = 20;
+        constraints.fri_layout = vec![3, 2];
+
+        let options = ProverOptions {
+            #[cfg(feature = "memory-report")]
+            allocator: Some(&zkp_logging_allocator::ALLOCATOR),
+            ..ProverOptions::default()
+        };
+        let (proof, report) = prove_with_report::<MaskedKeccak, _, _>(
+            &constraints,
+            &trace,
+            |_, _, _| unreachable!(),
+            &options,
+        )
+        .unwrap();
+        assert_eq!(proof, prove(&constraints, &trace).unwrap());
+
+        assert_eq!(report.proof_bytes, proof.as_bytes().len());
+        assert_eq!(
+            report
+                .stages
+                .iter()
+                .map(|stage| stage.output_bytes)
+                .sum::<usize>(),
+            report.proof_bytes
+        );
+        assert_eq!(
+            report
+                .stages
+                .iter()
+                .map(|stage| stage.stage.index())
+                .collect::<Vec<_>>(),
+            (0..=Stage::Decommitment.index()).collect::<Vec<_>>()
+        );
+        #[cfg(feature = "memory-report")]
+        assert!(report
+            .stages
+            .iter()
+            .all(|stage| stage.peak_memory.is_some()));
+        assert!(report.constraint_graph_nodes > 0);
+        let trace_commitment = &report.stages[0];
+        // The trace LDE is mapped during the trace commitment
+        #[cfg(feature = "memory-report")]
+        assert!(
+            trace_commitment.peak_mapped_memory
+                >= constraints.eval_domain_size()
+                    * trace.num_columns()
+                    * std::mem::size_of::<FieldElement>()
+        );
+        assert_eq!(trace_commitment.merkle_tree_sizes, vec![
+            constraints.eval_domain_size()
+        ]);
+        assert_eq!(
+            trace_commitment.fft_sizes.get(&trace.num_rows()),
+            Some(&(trace.num_columns() * (1 + constraints.blowup)))
+        );
+        assert_eq!(report.stages[3].merkle_tree_sizes.len(), 2);
+
+        let json = serde_json::to_string(&report).unwrap();
+        let deserialized: ProofReport = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized, report);
+    }
+
+    #[test]
+    fn expressions_bound_to_proof() {
+        let recurrance = Recurrance {
+            index:         1000,
+            initial_value: field_element!("cafebabe"),
+            exponent:      1,
+        };
+        let claim = recurrance.claim();
+        let trace = claim.trace(&recurrance.witness());
+        let with_expressions = |constraints: &Constraints, expressions| {
+            let mut result = Constraints::from_expressions(
+                (constraints.trace_nrows(), constraints.trace_ncolumns()),
+                constraints.channel_seed().to_vec(),
+                expressions,
+            )
+            .unwrap();
+            result.num_queries = constraints.num_queries;
+            result.fri_layout = constraints.fri_layout.clone();
+            result.strong_fiat_shamir = constraints.strong_fiat_shamir;
+            result
+        };
+        // An equivalent expression that only differs in its encoding
+        let changed = |constraints: &Constraints| {
+            let mut expressions = constraints.expressions().to_vec();
+            let last = expressions.pop().unwrap();
+            expressions.push(last * 1);
+            with_expressions(constraints, expressions)
+        };
+
+        let mut constraints = claim.constraints();
+        constraints.num_queries = 20;
+        constraints.fri_layout = vec![3, 2];
+        let proof = prove(&constraints, &trace).unwrap();
+        assert!(verify(&constraints, &proof).is_ok());
+        assert!(verify(&changed(&constraints), &proof).is_err());
+
+        // Without strong Fiat-Shamir only the values matter
+        constraints.strong_fiat_shamir = false;
+        let proof = prove(&constraints, &trace).unwrap();
+        assert!(verify(&changed(&constraints), &proof).is_ok());
+    }
+
+    #[test]
+    fn zero_knowledge_is_randomized() {
+        let recurrance = Recurrance {
+            index:         1000,
+            initial_value: field_element!("cafebabe"),
+            exponent:      1,
+        };
+        let claim = recurrance.claim();
+        let trace = claim.trace(&recurrance.witness());
+        let mut constraints = claim.constraints();
+        constraints.num_queries = 20;
+        constraints.fri_layout = vec![3, 2];
+        constraints.zero_knowledge = true;
+
+        let first = prove(&constraints, &trace).unwrap();
+        let second = prove(&constraints, &trace).unwrap();
+        assert_ne!(first.as_bytes()[0..32], second.as_bytes()[0..32]);
+        assert!(verify(&constraints, &first).is_ok());
+        assert!(verify(&constraints, &second).is_ok());
+    }
+
+    #[test]
+    fn zero_knowledge_short_trace() {
+        let recurrance = Recurrance {
+            index:         20,
+            initial_value: field_element!("cafebabe"),
+            exponent:      1,
+        };
+        let claim = recurrance.claim();
+        let trace = claim.trace(&recurrance.witness());
+        let mut constraints = claim.constraints();
+        constraints.fri_layout = vec![3, 2];
+        constraints.zero_knowledge = true;
+        assert_eq!(trace.num_rows(), 32);
+        assert_eq!(
+            constraints.max_trace_openings(),
+            constraints.num_queries + 2
+        );
+        assert!(matches!(
+            prove(&constraints, &trace),
+            Err(Error::TraceTooShort)
+        ));
+
+        // Without zero knowledge the openings don't need to be hidden
+        constraints.zero_knowledge = false;
+        assert!(prove(&constraints, &trace).is_ok());
+
+        // Few enough queries can be hidden by the mask
+        constraints.zero_knowledge = true;
+        constraints.num_queries = 30;
+        let proof = prove(&constraints, &trace).unwrap();
+        assert!(verify(&constraints, &proof).is_ok());
+    }
+
+    #[test]
+    fn alternative_hashers() {
+        use zkp_hash::{Blake2s, Keccak, Sha256};
+
+        fn prove_and_verify<H: zkp_hash::Hasher>(
+            constraints: &Constraints,
+            trace: &TraceTable,
+        ) -> Proof {
+            let proof = prove_with_hasher::<H, _>(constraints, trace).unwrap();
+            assert!(verify_with_hasher::<H, _>(constraints, &proof).is_ok());
+            proof
+        }
+
+        let recurrance = Recurrance {
+            index:         1000,
+            initial_value: field_element!("cafebabe"),
+            exponent:      1,
+        };
+        let claim = recurrance.claim();
+        let trace = claim.trace(&recurrance.witness());
+        let mut constraints = claim.constraints();
+        constraints.num_queries = 20;
+        constraints.fri_layout = vec![3, 2];
+
+        let default = prove(&constraints, &trace).unwrap();
+        assert_eq!(
+            prove_and_verify::<MaskedKeccak>(&constraints, &trace),
+            default
+        );
+        for proof in &[
+            prove_and_verify::<Keccak>(&constraints, &trace),
+            prove_and_verify::<Sha256>(&constraints, &trace),
+            prove_and_verify::<Blake2s>(&constraints, &trace),
+        ] {
+            assert_ne!(proof, &default);
+        }
+    }
+
+    #[test]
+    fn small_fields() {
+        use crate::RationalExpression;
+        use zkp_primefield::{BabyBear, Goldilocks};
+
+        fn fibonacci<Field>()
+        where
+            Field: StarkField,
+            for<'a> &'a Field: RefFieldLike<Field>,
+        {
+            use RationalExpression::*;
+            let trace_length = 256;
+            let mut trace = TraceTable::<Field>::new(trace_length, 2);
+            trace[(0, 0)] = Field::one();
+            trace[(0, 1)] = Field::from(0xcafe_usize);
+            for i in 1..trace_length {
+                trace[(i, 0)] = trace[(i - 1, 1)].clone();
+                trace[(i, 1)] = &trace[(i - 1, 0)] + &trace[(i - 1, 1)];
+            }
+            let value = trace[(200, 0)].clone();
+
+            let g = Constant(Field::root(trace_length).unwrap());
+            let on_row = |index| (X - g.pow(index)).inv();
+            let every_row = || (X - g.pow(trace_length - 1)) / (X.pow(trace_length) - 1);
+            let mut constraints =
+                Constraints::from_expressions((trace_length, 2), b"small fields".to_vec(), vec![
+                    (Trace(0, 1) - Trace(1, 0)) * every_row(),
+                    (Trace(1, 1) - Trace(0, 0) - Trace(1, 0)) * every_row(),
+                    (Trace(0, 0) - 1) * on_row(0),
+                    (Trace(0, 0) - Constant(value)) * on_row(200),
+                ])
+                .unwrap();
+            constraints.num_queries = 20;
+            constraints.fri_layout = vec![3, 2];
+
+            let proof = prove(&constraints, &trace).unwrap();
+            assert!(verify(&constraints, &proof).is_ok());
+            trace[(100, 1)] += Field::one();
+            assert!(prove(&constraints, &trace).is_err());
+        }
+
+        fibonacci::<Goldilocks>();
+        fibonacci::<BabyBear>();
+    }
+
+    #[test]
+    fn multi_stage() {
+        use crate::RationalExpression::*;
+
+        let recurrance = Recurrance {
+            index:         1000,
+            initial_value: field_element!("cafebabe"),
+            exponent:      1,
+        };
+        let claim = recurrance.claim();
+        let trace = claim.trace(&recurrance.witness());
+        let trace_length = trace.num_rows();
+
+        // Accumulate a random linear combination of the first column in a
+        // second stage column.
+        let g = Constant(FieldElement::root(trace_length).unwrap());
+        let on_row = |index| (X - g.pow(index)).inv();
+        let every_row = || (X - g.pow(trace_length - 1)) / (X.pow(trace_length) - 1);
+        let mut expressions = claim.constraints().expressions().to_vec();
+        expressions.push((Trace(2, 1) - Challenge(0) * Trace(2, 0) - Trace(0, 1)) * every_row());
+        expressions.push((Trace(2, 0) - Trace(0, 0)) * on_row(0));
+        let mut constraints =
+            Constraints::from_expressions((trace_length, 2), claim.seed(), expressions).unwrap();
+        constraints.add_stage(1, 1);
+        constraints.num_queries = 20;
+        constraints.fri_layout = vec![3, 2];
+        assert_eq!(constraints.trace_ncolumns(), 3);
+        assert_eq!(constraints.stage_columns(1), 2..3);
+
+        let accumulate = |challenges: &[FieldElement], trace: &TraceTable| {
+            let mut column = TraceTable::new(trace.num_rows(), 1);
+            column[(0, 0)] = trace[(0, 0)].clone();
+            for i in 1..trace.num_rows() {
+                column[(i, 0)] = &challenges[0] * &column[(i - 1, 0)] + &trace[(i, 0)];
+            }
+            column
+        };
+        let proof = prove_staged(&constraints, &trace, |stage, challenges, trace| {
+            assert_eq!(stage, 1);
+            assert_eq!(challenges.len(), 1);
+            assert_eq!(trace.num_columns(), 2);
+            Some(accumulate(challenges, trace))
+        })
+        .unwrap();
+        assert!(verify(&constraints, &proof).is_ok());
+
+        // The second stage must use the challenge from the channel
+        let fixed = [FieldElement::from(5)];
+        assert!(prove_staged(&constraints, &trace, |_, _, trace| {
+            Some(accumulate(&fixed, trace))
+        })
+        .is_err());
+
+        // The second stage must be provided with the right dimensions
+        assert!(matches!(
+            prove(&constraints, &trace),
+            Err(Error::InvalidTraceStage)
+        ));
+        assert!(matches!(
+            prove_staged(&constraints, &trace, |_, _, trace| Some(trace.clone())),
+            Err(Error::InvalidTraceStage)
+        ));
+        assert!(matches!(
+            prove_staged(&constraints, &trace, |_, _, _| None),
+            Err(Error::InvalidTraceStage)
+        ));
+    }
+
+    #[test]
+    fn provable_missing_stage() {
+        use crate::traits::tests::{Claim, Witness};
+
+        struct Staged(Claim);
+
+        impl Verifiable for Staged {
+            fn constraints(&self) -> Constraints {
+                let mut constraints = self.0.constraints();
+                constraints.add_stage(1, 1);
+                constraints
+            }
+        }
+
+        impl Provable<&Witness> for Staged {
+            fn trace(&self, witness: &Witness) -> TraceTable {
+                self.0.trace(witness)
+            }
+        }
+
+        let recurrance = Recurrance {
+            index:         100,
+            initial_value: field_element!("cafebabe"),
+            exponent:      1,
+        };
+        let staged = Staged(recurrance.claim());
+        let witness = recurrance.witness();
+        assert!(matches!(
+            staged.prove(&witness),
+            Err(Error::InvalidTraceStage)
+        ));
+        assert_eq!(staged.check(&witness), Err(CheckError::InvalidTraceStage));
+        assert_eq!(
+            staged.check_specified(&witness, 0, 0),
+            Err(CheckError::InvalidTraceStage)
+        );
+    }
+
     // TODO: What are we actually testing here? Should we add these as debug_assert
     // to the main implementation? Should we break up the implementation so we
     // can test the individual steps?
@@ -1024,15 +2179,15 @@ mod tests {
 
         // Checks that the groupable trait is properly grouping for &[Vec<FieldElement>]
         assert_eq!(
-            (LDEn.leaf(3243))[0].clone(),
+            (LDEn.leaf(3243))[0].as_montgomery().clone(),
             u256h!("01ddd9e389a326817ad1d2a5311e1bc2cf7fa734ebdc2961085b5acfa87a58ff")
         );
         assert_eq!(
-            (LDEn.leaf(3243))[1].clone(),
+            (LDEn.leaf(3243))[1].as_montgomery().clone(),
             u256h!("03dbc6c47df0606997c2cefb20c4277caf2b76bca1d31c13432f71cdd93b3718")
         );
 
-        let (commitment, tree) = LDEn.commit().unwrap();
+        let (commitment, tree) = LDEn.commit::<MaskedKeccak>().unwrap();
         // Checks that the merklelizable implementation is working [implicit check of
         // most previous steps]
         assert_eq!(
@@ -1040,7 +2195,7 @@ mod tests {
             hex!("018dc61f748b1a6c440827876f30f63cb6c4c188000000000000000000000000")
         );
 
-        let mut proof = ProverChannel::default();
+        let mut proof: ProverChannel = ProverChannel::default();
         proof.initialize(&claim.seed());
         // Checks that the channel is inited properly
         assert_eq!(
@@ -1068,11 +2223,16 @@ mod tests {
         }
 
         let constraint_polynomials = get_constraint_polynomials(
-            &tree.leaves(),
+            &tree.leaves().0.iter().collect::<Vec<_>>(),
             &mut constraints,
             &constraint_coefficients,
             trace.num_rows(),
-        );
+            DEFAULT_CHUNK_SIZE,
+            None,
+            &|_| Ok(()),
+        )
+        .unwrap()
+        .0;
         assert_eq!(constraint_polynomials.len(), 1);
         assert_eq!(constraint_polynomials[0].len(), 1024);
         let CC = PolyLDE(
@@ -1087,7 +2247,7 @@ mod tests {
             field_element!("05b841208b357e29ac1fe7a654efebe1ae152104571e695f311a353d4d5cabfb")
         );
 
-        let (commitment, c_tree) = CC.commit().unwrap();
+        let (commitment, c_tree) = CC.commit::<MaskedKeccak>().unwrap();
         // Checks both that the merkle tree is working for this groupable type and that
         // the constraints are properly calculated on the domain
         assert_eq!(
@@ -1097,7 +2257,15 @@ mod tests {
         proof.write(&commitment);
 
         let trace_arguments = constraints.trace_arguments();
-        let CO = oods_combine(&mut proof, &TPn, &trace_arguments, &constraint_polynomials);
+        let CO = oods_combine(
+            &mut proof,
+            &TPn,
+            &trace_arguments,
+            &constraint_polynomials,
+            trace.num_rows(),
+            1,
+            None,
+        );
         // Checks that our get out of domain function call has written the right values
         // to the proof
         assert_eq!(
@@ -1112,22 +2280,22 @@ mod tests {
             field_element!("03c6b730c58b55f44bbf3cb7ea82b2e6a0a8b23558e908b5466dfe42e821ee96")
         );
 
-        let fri_trees = perform_fri_layering(
+        let fri_prover = FriProver::<_, MaskedKeccak>::commit(
+            &constraints.fri_parameters(),
             CO.low_degree_extension(constraints.blowup),
             &mut proof,
-            &constraints.fri_layout,
-            constraints.blowup,
         )
         .unwrap();
+        let fri_commitments = fri_prover.commitments();
 
         // Checks that the first fri merkle tree root is right
         assert_eq!(
-            hex::encode(fri_trees[0].commitment().hash().as_bytes()),
+            hex::encode(fri_commitments[0].hash().as_bytes()),
             "620a934880b6c7d893acf17a21cc9c10058a7add000000000000000000000000"
         );
         // Checks that the second fri merkle tree root is right
         assert_eq!(
-            hex::encode(fri_trees[1].commitment().hash().as_bytes()),
+            hex::encode(fri_commitments[1].hash().as_bytes()),
             "effd58adf9f2dac6bfd338772d0d7750c0c6f8b2000000000000000000000000"
         );
         // Checks that the fri layering function decommited the right values.
@@ -1137,7 +2305,7 @@ mod tests {
         );
 
         let pow_seed: proof_of_work::ChallengeSeed = proof.get_random();
-        let pow_challenge = pow_seed.with_difficulty(constraints.pow_bits);
+        let pow_challenge = pow_seed.with_difficulty::<MaskedKeccak>(constraints.pow_bits);
         let pow_response = pow_challenge.solve();
         debug_assert!(pow_challenge.verify(pow_response));
         // Checks that the pow function is working [may also fail if the previous steps
@@ -1177,8 +2345,7 @@ mod tests {
             "f2d3e6593dc23fa32655040ad5023739e15fff1d645bb809467cfccb676d6343"
         );
 
-        decommit_fri_layers_and_trees(fri_trees.as_slice(), query_indices.as_slice(), &mut proof)
-            .unwrap();
+        fri_prover.decommit(&query_indices, &mut proof).unwrap();
         // Checks that our fri decommitment is successful
         assert_eq!(
             hex::encode(proof.coin.digest),
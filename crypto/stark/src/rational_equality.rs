@@ -1,27 +1,58 @@
-use crate::RationalExpression;
-use rand::random;
+use crate::{
+    field::{from_entropy, random_element, StarkField},
+    RationalExpression,
+};
+use rand::thread_rng;
+use std::prelude::v1::*;
 use tiny_keccak::{Hasher, Keccak};
-use zkp_primefield::FieldElement;
-use zkp_u256::U256;
+use zkp_primefield::RefFieldLike;
 
-impl RationalExpression {
+impl<Field> RationalExpression<Field>
+where
+    Field: StarkField,
+    for<'a> &'a Field: RefFieldLike<Field>,
+{
     /// Probabilistic extrinsic equality check
     pub fn equals(&self, other: &Self) -> bool {
         // Random evaluation point, also serves as random seed for trace.
-        let x = random::<FieldElement>();
+        let x: Field = random_element(&mut thread_rng());
+        let mut seed = Vec::with_capacity(Field::BYTES);
+        x.write_bytes(&mut seed);
         let trace = |column: usize, offset: isize| {
             let mut hasher = Keccak::v256();
-            hasher.update(&x.as_montgomery().to_bytes_be());
+            hasher.update(&seed);
             hasher.update(&column.to_be_bytes());
             hasher.update(&offset.to_be_bytes());
-            let mut output = [0_u8; 32];
-            hasher.finalize(&mut output);
-            U256::from_bytes_be(&output).into()
+            from_entropy(hasher)
+        };
+
+        // Challenges and public inputs are random constants derived from the
+        // same seed.
+        let challenges = |expression: &Self| {
+            expression.map(&|node| {
+                match node {
+                    Self::Challenge(i) => {
+                        let mut hasher = Keccak::v256();
+                        hasher.update(&seed);
+                        hasher.update(b"challenge");
+                        hasher.update(&i.to_be_bytes());
+                        Self::Constant(from_entropy(hasher))
+                    }
+                    Self::PublicInput(i) => {
+                        let mut hasher = Keccak::v256();
+                        hasher.update(&seed);
+                        hasher.update(b"public_input");
+                        hasher.update(&i.to_be_bytes());
+                        Self::Constant(from_entropy(hasher))
+                    }
+                    other => other,
+                }
+            })
         };
 
         // Check equality by evaluating at a random point
-        let lhs = self.evaluate(&x, &trace);
-        let rhs = other.evaluate(&x, &trace);
+        let lhs = challenges(self).evaluate(&x, &trace);
+        let rhs = challenges(other).evaluate(&x, &trace);
         lhs == rhs
     }
 }
@@ -33,7 +64,7 @@ mod tests {
     #[test]
     fn test_equal() {
         use RationalExpression::*;
-        let left = X;
+        let left: RationalExpression = X;
         let right = X.pow(2) / X;
         assert!(left.equals(&right));
     }
@@ -41,8 +72,15 @@ mod tests {
     #[test]
     fn test_unequal() {
         use RationalExpression::*;
-        let left = X;
+        let left: RationalExpression = X;
         let right = X.pow(3) / X;
         assert!(!left.equals(&right));
     }
+    #[test]
+    fn test_challenges() {
+        use RationalExpression::*;
+        let left: RationalExpression = Challenge(0) * Trace(0, 0);
+        assert!(left.equals(&(Trace(0, 0) * Challenge(0))));
+        assert!(!left.equals(&(Challenge(1) * Trace(0, 0))));
+    }
 }
@@ -1,36 +1,59 @@
-use crate::polynomial::DensePolynomial;
+use crate::{
+    field::StarkField,
+    polynomial::{DensePolynomial, EVALUATION_POINT},
+};
 #[cfg(feature = "std")]
 use std::{cmp::Ordering, collections::hash_map::DefaultHasher};
 use std::{
     collections::BTreeSet,
+    convert::TryFrom,
     hash::{Hash, Hasher},
     iter::Sum,
     ops::{Add, Div, Mul, Sub},
     prelude::v1::*,
 };
-use zkp_macros_decl::field_element;
-use zkp_primefield::{FieldElement, Inv, One, Pow, Zero};
-use zkp_u256::U256;
+use zkp_primefield::{FieldElement, Inv, Pow, RefFieldLike};
 
 // TODO: Rename to algebraic expression
 #[derive(Clone, Eq, PartialEq)]
 #[cfg_attr(feature = "std", derive(Debug))]
-pub enum RationalExpression {
+pub enum RationalExpression<Field: StarkField = FieldElement> {
     X,
-    Constant(FieldElement),
+    Constant(Field),
     Trace(usize, isize),
-    Polynomial(DensePolynomial, Box<RationalExpression>),
+    /// The `i`-th random challenge drawn for the later trace stages, see
+    /// [`Constraints::add_stage`](crate::Constraints::add_stage).
+    Challenge(usize),
+    /// The `i`-th public input, see
+    /// [`Constraints::add_public_input`](crate::Constraints::add_public_input).
+    PublicInput(usize),
+    Polynomial(DensePolynomial<Field>, Box<RationalExpression<Field>>),
     // TODO - Make this a struct with internally named members
     // the members are (index, degree bound, expression, name)
-    ClaimPolynomial(usize, usize, Box<RationalExpression>, Option<&'static str>),
-    Add(Box<RationalExpression>, Box<RationalExpression>),
-    Neg(Box<RationalExpression>),
-    Mul(Box<RationalExpression>, Box<RationalExpression>),
-    Inv(Box<RationalExpression>),
-    Exp(Box<RationalExpression>, usize),
+    ClaimPolynomial(
+        usize,
+        usize,
+        Box<RationalExpression<Field>>,
+        Option<&'static str>,
+    ),
+    Add(
+        Box<RationalExpression<Field>>,
+        Box<RationalExpression<Field>>,
+    ),
+    Neg(Box<RationalExpression<Field>>),
+    Mul(
+        Box<RationalExpression<Field>>,
+        Box<RationalExpression<Field>>,
+    ),
+    Inv(Box<RationalExpression<Field>>),
+    Exp(Box<RationalExpression<Field>>, usize),
 }
 
-impl RationalExpression {
+impl<Field> RationalExpression<Field>
+where
+    Field: StarkField,
+    for<'a> &'a Field: RefFieldLike<Field>,
+{
     pub fn neg(&self) -> Self {
         Self::Neg(Box::new(self.clone()))
     }
@@ -66,7 +89,7 @@ impl RationalExpression {
         f(e)
     }
 
-    pub fn substitute_claim(&self, claim_polynomials: &[DensePolynomial]) -> Self {
+    pub fn substitute_claim(&self, claim_polynomials: &[DensePolynomial<Field>]) -> Self {
         use RationalExpression::*;
         let f = |x| {
             match x {
@@ -83,27 +106,136 @@ impl RationalExpression {
         };
         self.map(&f)
     }
+
+    /// Replace the `Challenge(i)` leaves by `Constant(challenges[i])`.
+    pub fn substitute_challenges(&self, challenges: &[Field]) -> Self {
+        use RationalExpression::*;
+        let f = |x| {
+            match x {
+                Challenge(i) => {
+                    Constant(
+                        challenges
+                            .get(i)
+                            .expect("Challenge index out of bounds")
+                            .clone(),
+                    )
+                }
+                _ => x,
+            }
+        };
+        self.map(&f)
+    }
+
+    /// Replace the `PublicInput(i)` leaves by `Constant(public_inputs[i])`.
+    pub fn substitute_public_inputs(&self, public_inputs: &[Field]) -> Self {
+        use RationalExpression::*;
+        let f = |x| {
+            match x {
+                PublicInput(i) => {
+                    Constant(
+                        public_inputs
+                            .get(i)
+                            .expect("PublicInput index out of bounds")
+                            .clone(),
+                    )
+                }
+                _ => x,
+            }
+        };
+        self.map(&f)
+    }
+
+    /// Append a canonical encoding of the expression.
+    ///
+    /// The encoding is prefix-free, so a sequence of encoded expressions can
+    /// be absorbed into the proof transcript unambiguously. Names of claim
+    /// polynomials are not encoded.
+    pub fn write_bytes(&self, bytes: &mut Vec<u8>) {
+        use RationalExpression::*;
+        fn write_usize(bytes: &mut Vec<u8>, value: usize) {
+            let value = u64::try_from(value).expect("Value does not fit 64 bits");
+            bytes.extend_from_slice(&value.to_be_bytes());
+        }
+        match self {
+            X => bytes.push(0),
+            Constant(c) => {
+                bytes.push(1);
+                c.write_bytes(bytes);
+            }
+            &Trace(i, j) => {
+                bytes.push(2);
+                write_usize(bytes, i);
+                let j = i64::try_from(j).expect("Offset does not fit 64 bits");
+                bytes.extend_from_slice(&j.to_be_bytes());
+            }
+            &Challenge(i) => {
+                bytes.push(3);
+                write_usize(bytes, i);
+            }
+            &PublicInput(i) => {
+                bytes.push(4);
+                write_usize(bytes, i);
+            }
+            Polynomial(p, a) => {
+                bytes.push(5);
+                write_usize(bytes, p.len());
+                for coefficient in p.coefficients() {
+                    coefficient.write_bytes(bytes);
+                }
+                a.write_bytes(bytes);
+            }
+            ClaimPolynomial(i, n, a, _) => {
+                bytes.push(6);
+                write_usize(bytes, *i);
+                write_usize(bytes, *n);
+                a.write_bytes(bytes);
+            }
+            Add(a, b) => {
+                bytes.push(7);
+                a.write_bytes(bytes);
+                b.write_bytes(bytes);
+            }
+            Neg(a) => {
+                bytes.push(8);
+                a.write_bytes(bytes);
+            }
+            Mul(a, b) => {
+                bytes.push(9);
+                a.write_bytes(bytes);
+                b.write_bytes(bytes);
+            }
+            Inv(a) => {
+                bytes.push(10);
+                a.write_bytes(bytes);
+            }
+            Exp(a, e) => {
+                bytes.push(11);
+                a.write_bytes(bytes);
+                write_usize(bytes, *e);
+            }
+        }
+    }
 }
 
-impl From<i32> for RationalExpression {
+impl<Field: StarkField> From<i32> for RationalExpression<Field> {
     fn from(value: i32) -> Self {
         Self::Constant(value.into())
     }
 }
 
-impl From<&FieldElement> for RationalExpression {
-    fn from(value: &FieldElement) -> Self {
+impl<Field: StarkField> From<&Field> for RationalExpression<Field> {
+    fn from(value: &Field) -> Self {
         Self::Constant(value.clone())
     }
 }
 
-impl From<FieldElement> for RationalExpression {
-    fn from(value: FieldElement) -> Self {
+impl<Field: StarkField> From<Field> for RationalExpression<Field> {
+    fn from(value: Field) -> Self {
         Self::Constant(value)
     }
 }
 
-impl<T: Into<RationalExpression>> Add<T> for RationalExpression {
+impl<Field: StarkField, T: Into<Self>> Add<T> for RationalExpression<Field> {
     type Output = Self;
 
     fn add(self, other: T) -> Self {
@@ -113,15 +245,15 @@ impl<T: Into<RationalExpression>> Add<T> for RationalExpression {
 
 // Clippy false positive
 #[allow(clippy::suspicious_arithmetic_impl)]
-impl<T: Into<RationalExpression>> Sub<T> for RationalExpression {
+impl<Field: StarkField, T: Into<Self>> Sub<T> for RationalExpression<Field> {
     type Output = Self;
 
     fn sub(self, other: T) -> Self {
-        self + other.into().neg()
+        self + Self::Neg(Box::new(other.into()))
     }
 }
 
-impl<T: Into<RationalExpression>> Mul<T> for RationalExpression {
+impl<Field: StarkField, T: Into<Self>> Mul<T> for RationalExpression<Field> {
     type Output = Self;
 
     fn mul(self, other: T) -> Self {
@@ -131,25 +263,29 @@ impl<T: Into<RationalExpression>> Mul<T> for RationalExpression {
 
 // Clippy false positive
 #[allow(clippy::suspicious_arithmetic_impl)]
-impl<T: Into<RationalExpression>> Div<T> for RationalExpression {
+impl<Field: StarkField, T: Into<Self>> Div<T> for RationalExpression<Field> {
     type Output = Self;
 
     fn div(self, other: T) -> Self {
-        self * other.into().inv()
+        self * Self::Inv(Box::new(other.into()))
     }
 }
 
-impl Sum<RationalExpression> for RationalExpression {
+impl<Field: StarkField> Sum<Self> for RationalExpression<Field> {
     fn sum<I>(mut iter: I) -> Self
     where
-        I: Iterator<Item = RationalExpression>,
+        I: Iterator<Item = Self>,
     {
         iter.next()
             .map_or(0.into(), |expr| iter.fold(expr, |a, b| a + b))
     }
 }
 
-impl RationalExpression {
+impl<Field> RationalExpression<Field>
+where
+    Field: StarkField,
+    for<'a> &'a Field: RefFieldLike<Field>,
+{
     /// Numerator and denominator degree of the expression in X.
     ///
     /// Calculates an upper bound. Cancelations may occur.
@@ -167,7 +303,7 @@ impl RationalExpression {
         use RationalExpression::*;
         match self {
             X => (x_degree, 0),
-            Constant(_) => (0, 0),
+            Constant(_) | Challenge(_) | PublicInput(_) => (0, 0),
             Trace(..) => (trace_degree, 0),
             Polynomial(p, a) => {
                 let (n, d) = a.degree_impl(x_degree, trace_degree);
@@ -205,23 +341,21 @@ impl RationalExpression {
     // Note - This function is incomplete in its treatment of rational expressions
     // and may not produce the right answer when used with nested expressions
     // containing inverses
-    pub fn check(
-        &self,
-        x: &FieldElement,
-        trace: &dyn Fn(usize, isize) -> FieldElement,
-    ) -> (FieldElement, bool) {
+    pub fn check(&self, x: &Field, trace: &dyn Fn(usize, isize) -> Field) -> (Field, bool) {
         use RationalExpression::*;
         match self {
             X => (x.clone(), true),
             Constant(c) => (c.clone(), true),
             &Trace(i, j) => (trace(i, j), true),
+            Challenge(_) => panic!("Challenge should be substituted by Constant"),
+            PublicInput(_) => panic!("PublicInput should be substituted by Constant"),
 
             Polynomial(p, a) => {
                 let (res, is_ok) = a.check(x, trace);
                 if is_ok {
                     (p.evaluate(&res), true)
                 } else {
-                    (FieldElement::one(), false)
+                    (Field::one(), false)
                 }
             }
             ClaimPolynomial(..) => panic!("ClaimPolynomial should be substituted by Polynomial"),
@@ -231,13 +365,13 @@ impl RationalExpression {
                 if a_ok && b_ok {
                     (res_a + res_b, true)
                 } else {
-                    (FieldElement::one(), false)
+                    (Field::one(), false)
                 }
             }
             Neg(a) => {
                 let (res_a, a_ok) = a.check(x, trace);
                 // Note - this means a false should be either one or -one
-                (-&res_a, a_ok)
+                (res_a.neg(), a_ok)
             }
             Mul(a, b) => {
                 let (res_a, a_ok) = a.check(x, trace);
@@ -246,27 +380,27 @@ impl RationalExpression {
                 if a_ok && b_ok {
                     (res_a * res_b, true)
                 } else if a_ok && !b_ok {
-                    if res_a == FieldElement::zero() {
-                        (FieldElement::zero(), true)
+                    if res_a.is_zero() {
+                        (Field::zero(), true)
                     } else {
-                        (FieldElement::one(), false)
+                        (Field::one(), false)
                     }
                 } else if !a_ok && b_ok {
-                    if res_b == FieldElement::zero() {
-                        (FieldElement::zero(), true)
+                    if res_b.is_zero() {
+                        (Field::zero(), true)
                     } else {
-                        (FieldElement::one(), false)
+                        (Field::one(), false)
                     }
                 } else {
-                    (FieldElement::one(), false)
+                    (Field::one(), false)
                 }
             }
             // TODO - This behavior is suspect
             Inv(a) => {
                 let (res_a, a_ok) = a.clone().check(x, trace);
                 if a_ok {
-                    if res_a == FieldElement::zero() {
-                        (FieldElement::one(), false)
+                    if res_a.is_zero() {
+                        (Field::one(), false)
                     } else {
                         (res_a, true)
                     }
@@ -274,7 +408,7 @@ impl RationalExpression {
                     match *(a.clone()) {
                         Inv(b) => b.check(x, trace),
                         // TODO - Fully enumerate all checks
-                        _ => (FieldElement::one(), false),
+                        _ => (Field::one(), false),
                     }
                 }
             }
@@ -283,29 +417,27 @@ impl RationalExpression {
                 if a_ok {
                     (res_a.pow(*e), true)
                 } else {
-                    (FieldElement::one(), false)
+                    (Field::one(), false)
                 }
             }
         }
     }
 
-    pub fn evaluate(
-        &self,
-        x: &FieldElement,
-        trace: &dyn Fn(usize, isize) -> FieldElement,
-    ) -> FieldElement {
+    pub fn evaluate(&self, x: &Field, trace: &dyn Fn(usize, isize) -> Field) -> Field {
         use RationalExpression::*;
         match self {
             X => x.clone(),
             Constant(c) => c.clone(),
             &Trace(i, j) => trace(i, j),
+            Challenge(_) => panic!("Challenge should be substituted by Constant"),
+            PublicInput(_) => panic!("PublicInput should be substituted by Constant"),
             Polynomial(p, a) => {
                 let inner = a.evaluate(x, trace);
                 p.evaluate(&inner)
             }
             ClaimPolynomial(..) => panic!("ClaimPolynomial should be substituted by Polynomial"),
             Add(a, b) => a.evaluate(x, trace) + b.evaluate(x, trace),
-            Neg(a) => -&a.evaluate(x, trace),
+            Neg(a) => a.evaluate(x, trace).neg(),
             Mul(a, b) => a.evaluate(x, trace) * b.evaluate(x, trace),
             Inv(a) => a.evaluate(x, trace).inv().expect("divided by zero"),
             Exp(a, e) => a.evaluate(x, trace).pow(*e),
@@ -324,7 +456,7 @@ impl RationalExpression {
             &Trace(i, j) => {
                 let _ = s.insert((i, j));
             }
-            X | Constant(_) => (),
+            X | Constant(_) | Challenge(_) | PublicInput(_) => (),
             Polynomial(_, a) | Exp(a, _) | Neg(a) | Inv(a) => a.trace_arguments_impl(s),
             Add(a, b) | Mul(a, b) => {
                 a.trace_arguments_impl(s);
@@ -336,7 +468,11 @@ impl RationalExpression {
 }
 
 #[allow(clippy::derive_hash_xor_eq)]
-impl Hash for RationalExpression {
+impl<Field> Hash for RationalExpression<Field>
+where
+    Field: StarkField,
+    for<'a> &'a Field: RefFieldLike<Field>,
+{
     fn hash<H: Hasher>(&self, state: &mut H) {
         use RationalExpression::*;
         match self {
@@ -344,20 +480,28 @@ impl Hash for RationalExpression {
                 "x".hash(state);
             }
             Constant(c) => {
-                c.hash(state);
+                Hash::hash(c, state);
             }
             &Trace(i, j) => {
                 "trace".hash(state);
                 i.hash(state);
                 j.hash(state);
             }
+            Challenge(i) => {
+                "challenge".hash(state);
+                i.hash(state);
+            }
+            PublicInput(i) => {
+                "public_input".hash(state);
+                i.hash(state);
+            }
             Polynomial(..) => {
                 "poly".hash(state);
-                let x = field_element!(
-                    "754ed488ec9208d1c552bb254c0890042078a9e1f7e36072ebff1bf4e193d11b"
+                let x = Field::from(EVALUATION_POINT);
+                Hash::hash(
+                    &self.evaluate(&x, &|_, _| panic!("Trace in polynomial not supported")),
+                    state,
                 );
-                (self.evaluate(&x, &|_, _| panic!("Trace in polynomial not supported")))
-                    .hash(state);
             }
             Add(a, b) => {
                 "add".hash(state);
@@ -393,21 +537,33 @@ impl Hash for RationalExpression {
 }
 
 #[cfg(feature = "std")]
-impl PartialOrd for RationalExpression {
+impl<Field> PartialOrd for RationalExpression<Field>
+where
+    Field: StarkField,
+    for<'a> &'a Field: RefFieldLike<Field>,
+{
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(self.cmp(other))
     }
 }
 
 #[cfg(feature = "std")]
-fn get_hash(r: &RationalExpression) -> u64 {
+fn get_hash<Field>(r: &RationalExpression<Field>) -> u64
+where
+    Field: StarkField,
+    for<'a> &'a Field: RefFieldLike<Field>,
+{
     let mut hasher = DefaultHasher::new();
     r.hash(&mut hasher);
     hasher.finish()
 }
 
 #[cfg(feature = "std")]
-impl Ord for RationalExpression {
+impl<Field> Ord for RationalExpression<Field>
+where
+    Field: StarkField,
+    for<'a> &'a Field: RefFieldLike<Field>,
+{
     fn cmp(&self, other: &Self) -> Ordering {
         get_hash(self).cmp(&get_hash(other))
     }
@@ -0,0 +1,145 @@
+use crate::prover::{ProverOptions, Stage};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    time::{Duration, Instant},
+};
+#[cfg(feature = "memory-report")]
+use zkp_logging_allocator::LoggingAllocator;
+
+/// Measurements of a single proof, see
+/// [`prove_with_report`](crate::prove_with_report).
+///
+/// Serializes with `serde`, for example to JSON to track regressions.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProofReport {
+    /// Wall time of the whole proof, including its verification.
+    pub wall_time:              Duration,
+    /// Size of the proof in bytes.
+    pub proof_bytes:            usize,
+    /// Number of nodes in the graph the constraints are evaluated with, after
+    /// removing unused nodes. Zero if the constraint commitment was loaded
+    /// from a checkpoint.
+    pub constraint_graph_nodes: usize,
+    /// The stages in the order they ran. Stages loaded from a checkpoint are
+    /// included with the time it took to load them.
+    pub stages:                 Vec<StageReport>,
+}
+
+/// Measurements of a single [`Stage`] of a proof.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct StageReport {
+    pub stage:              Stage,
+    /// Wall time of the stage.
+    pub wall_time:          Duration,
+    /// Peak heap memory in bytes, if
+    /// [`ProverOptions::allocator`](crate::ProverOptions::allocator) is set.
+    /// Requires the `memory-report` feature.
+    ///
+    /// Only allocations through the global allocator are counted. The large
+    /// buffers of the proof are in
+    /// [`peak_mapped_memory`](Self::peak_mapped_memory).
+    #[cfg(feature = "memory-report")]
+    pub peak_memory:        Option<usize>,
+    /// Peak memory in bytes mapped by `MmapVec`s, in anonymous memory or in
+    /// the scratch directory. Requires the `memory-report` feature.
+    ///
+    /// These hold the trace and the LDEs, the Merkle trees and the FRI
+    /// layers. The count is for the whole process, so it includes any other
+    /// proofs running at the same time.
+    #[cfg(feature = "memory-report")]
+    pub peak_mapped_memory: usize,
+    /// Bytes written to the proof.
+    pub output_bytes:       usize,
+    /// Number of FFTs by size.
+    pub fft_sizes:          BTreeMap<usize, usize>,
+    /// Number of leaves of each Merkle tree committed to.
+    pub merkle_tree_sizes:  Vec<usize>,
+}
+
+/// Collects a [`ProofReport`] while the prover runs.
+pub(crate) struct Recorder {
+    #[cfg(feature = "memory-report")]
+    allocator:   Option<&'static LoggingAllocator>,
+    start:       Instant,
+    stage_start: Instant,
+    report:      ProofReport,
+}
+
+impl Recorder {
+    // Options are only read with the `memory-report` feature
+    #[allow(unused_variables)]
+    pub(crate) fn new(options: &ProverOptions) -> Self {
+        let now = Instant::now();
+        Self {
+            #[cfg(feature = "memory-report")]
+            allocator: options.allocator,
+            start: now,
+            stage_start: now,
+            report: ProofReport::default(),
+        }
+    }
+
+    /// Start a stage with `output` bytes of proof written so far.
+    pub(crate) fn begin(&mut self, stage: Stage, output: usize) {
+        #[cfg(feature = "memory-report")]
+        {
+            if let Some(allocator) = self.allocator {
+                let _ = allocator.reset_peak_allocated();
+            }
+            let _ = zkp_mmap_vec::reset_peak_mapped();
+        }
+        self.stage_start = Instant::now();
+        self.report.stages.push(StageReport {
+            stage,
+            wall_time: Duration::default(),
+            #[cfg(feature = "memory-report")]
+            peak_memory: None,
+            #[cfg(feature = "memory-report")]
+            peak_mapped_memory: 0,
+            output_bytes: output,
+            fft_sizes: BTreeMap::new(),
+            merkle_tree_sizes: Vec::new(),
+        });
+    }
+
+    /// End the current stage with `output` bytes of proof written so far.
+    pub(crate) fn end(&mut self, output: usize) {
+        let wall_time = self.stage_start.elapsed();
+        #[cfg(feature = "memory-report")]
+        let peak_memory = self.allocator.map(LoggingAllocator::peak_allocated);
+        if let Some(report) = self.report.stages.last_mut() {
+            report.wall_time = wall_time;
+            #[cfg(feature = "memory-report")]
+            {
+                report.peak_memory = peak_memory;
+                report.peak_mapped_memory = zkp_mmap_vec::peak_mapped();
+            }
+            report.output_bytes = output - report.output_bytes;
+        }
+    }
+
+    /// Record `count` FFTs of `size` in the current stage.
+    pub(crate) fn fft(&mut self, size: usize, count: usize) {
+        if let Some(report) = self.report.stages.last_mut() {
+            *report.fft_sizes.entry(size).or_default() += count;
+        }
+    }
+
+    /// Record a Merkle tree with `leaves` in the current stage.
+    pub(crate) fn merkle_tree(&mut self, leaves: usize) {
+        if let Some(report) = self.report.stages.last_mut() {
+            report.merkle_tree_sizes.push(leaves);
+        }
+    }
+
+    pub(crate) fn constraint_graph(&mut self, nodes: usize) {
+        self.report.constraint_graph_nodes = nodes;
+    }
+
+    pub(crate) fn finish(mut self, proof_bytes: usize) -> ProofReport {
+        self.report.wall_time = self.start.elapsed();
+        self.report.proof_bytes = proof_bytes;
+        self.report
+    }
+}
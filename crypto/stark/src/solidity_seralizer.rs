@@ -67,9 +67,9 @@ pub fn proof_serialize(
     let trace_cols = constraints.trace_ncolumns();
     let eval_domain_size = trace_length * constraints.blowup;
 
-    let mut channel = VerifierChannel::new(proof.to_vec());
+    let mut channel: VerifierChannel = VerifierChannel::new(proof.to_vec());
     // TODO - Add method to seralize public input
-    channel.initialize(constraints.channel_seed());
+    channel.initialize(&constraints.transcript_seed());
 
     // Get the low degree root commitment, and constraint root commitment
     // TODO: Make it work as channel.read()
@@ -79,7 +79,7 @@ pub fn proof_serialize(
         low_degree_extension_root.sol_encode()
     ));
     let lde_commitment = Commitment::from_size_hash(eval_domain_size, &low_degree_extension_root)?;
-    let _ = channel.get_coefficients(2 * constraints.len());
+    let _: Vec<FieldElement> = channel.get_coefficients(2 * constraints.len());
 
     let constraint_evaluated_root: Hash = channel.replay();
     result_string.push_str(&format!(
@@ -95,6 +95,7 @@ pub fn proof_serialize(
     // This hack around claim polynomials is awful and should be removed
     let mut parseable_constraints = constraints.clone();
     parseable_constraints.substitute();
+    parseable_constraints.substitute_public_inputs();
 
     let trace_arguments = parseable_constraints.trace_arguments();
     let trace_values: Vec<FieldElement> = channel.replay_many(trace_arguments.len());
@@ -115,7 +116,8 @@ pub fn proof_serialize(
         claimed_constraint_values.sol_encode()
     ));
 
-    let _ = channel.get_coefficients(claimed_trace_map.len() + claimed_constraint_values.len());
+    let _: Vec<FieldElement> =
+        channel.get_coefficients(claimed_trace_map.len() + claimed_constraint_values.len());
 
     let mut fri_commitments: Vec<Commitment> = Vec::with_capacity(constraints.fri_layout.len() + 1);
 
@@ -133,7 +135,8 @@ pub fn proof_serialize(
     ));
 
     // Gets the last layer coeffiencts
-    let last_layer_coefficients = channel.replay_fri_layer(fri_size / constraints.blowup);
+    let last_layer_coefficients: Vec<FieldElement> =
+        channel.replay_fri_layer(fri_size / constraints.blowup);
     result_string.push_str(&format!(
         "\"last_layer_coefficients\": {}, \n",
         last_layer_coefficients.sol_encode()
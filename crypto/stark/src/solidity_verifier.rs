@@ -33,6 +33,14 @@ pub enum GenerateError {
     TemplateError(#[from] tinytemplate::error::Error),
     #[error("Bug: invalid expression ocurred")]
     InvalidExpression,
+    #[error("Multi-stage traces are not supported")]
+    UnsupportedStages,
+    #[error("Public inputs are not supported")]
+    UnsupportedPublicInputs,
+    #[error("Strong Fiat-Shamir is not supported, the contract only hashes the channel seed")]
+    UnsupportedStrongFiatShamir,
+    #[error("Zero-knowledge proofs are not supported")]
+    UnsupportedZeroKnowledge,
 }
 
 #[derive(Clone, PartialEq, Eq, Debug, Default, Serialize)]
@@ -197,7 +205,7 @@ impl RationalExpression {
         use RationalExpression::*;
 
         match self {
-            X | Constant(..) => BTreeMap::new(),
+            X | Constant(..) | Challenge(_) | PublicInput(_) => BTreeMap::new(),
             Trace(..) => [(self.clone(), true)].iter().cloned().collect(),
             Add(a, b) | Mul(a, b) => {
                 let mut first = a.trace_search();
@@ -215,7 +223,7 @@ impl RationalExpression {
         use RationalExpression::*;
 
         match self {
-            X | Constant(_) | Trace(..) => BTreeMap::new(),
+            X | Constant(_) | Trace(..) | Challenge(_) | PublicInput(_) => BTreeMap::new(),
             Add(a, b) | Mul(a, b) => {
                 let mut first = a.inv_search();
                 first.extend(b.inv_search());
@@ -231,7 +239,9 @@ impl RationalExpression {
         use RationalExpression::*;
 
         match self {
-            X | Constant(_) | Trace(..) | ClaimPolynomial(..) => BTreeMap::new(),
+            X | Constant(_) | Trace(..) | Challenge(_) | PublicInput(_) | ClaimPolynomial(..) => {
+                BTreeMap::new()
+            }
             Polynomial(..) => [(self.clone(), true)].iter().cloned().collect(),
             Add(a, b) | Mul(a, b) => {
                 let mut first = a.periodic_search();
@@ -248,7 +258,9 @@ impl RationalExpression {
 
         match self {
             ClaimPolynomial(..) => once(self).cloned().collect(),
-            X | Constant(_) | Trace(..) | Polynomial(..) => BTreeSet::new(),
+            X | Constant(_) | Trace(..) | Challenge(_) | PublicInput(_) | Polynomial(..) => {
+                BTreeSet::new()
+            }
             Add(a, b) | Mul(a, b) => {
                 let mut first = a.claim_polynomial_search();
                 first.extend(b.claim_polynomial_search());
@@ -257,6 +269,24 @@ impl RationalExpression {
             Inv(a) | Exp(a, _) | Neg(a) => a.claim_polynomial_search(),
         }
     }
+
+    #[cfg(feature = "std")]
+    pub fn public_input_search(&self) -> BTreeSet<Self> {
+        use RationalExpression::*;
+
+        match self {
+            PublicInput(_) => once(self).cloned().collect(),
+            X | Constant(_) | Trace(..) | Challenge(_) => BTreeSet::new(),
+            Add(a, b) | Mul(a, b) => {
+                let mut first = a.public_input_search();
+                first.extend(b.public_input_search());
+                first
+            }
+            ClaimPolynomial(_, _, a, _) | Polynomial(_, a) | Inv(a) | Exp(a, _) | Neg(a) => {
+                a.public_input_search()
+            }
+        }
+    }
 }
 
 #[cfg(feature = "std")]
@@ -299,6 +329,24 @@ pub fn generate(
     output_directory: &str,
     system_name: &str,
 ) -> Result<(), GenerateError> {
+    if constraints.num_stages() > 1 {
+        return Err(GenerateError::UnsupportedStages);
+    }
+    // The contract seeds the channel with the channel seed only
+    if constraints.strong_fiat_shamir {
+        return Err(GenerateError::UnsupportedStrongFiatShamir);
+    }
+    if constraints.zero_knowledge {
+        return Err(GenerateError::UnsupportedZeroKnowledge);
+    }
+    if !constraints.public_inputs().is_empty()
+        || constraints
+            .expressions()
+            .iter()
+            .any(|expression| !expression.public_input_search().is_empty())
+    {
+        return Err(GenerateError::UnsupportedPublicInputs);
+    }
     let blowup = constraints.blowup;
     let n_cols = constraints.trace_ncolumns();
     let trace_len = constraints.trace_nrows();
@@ -335,14 +383,9 @@ pub fn generate(
         trace_keys.len(),
     )?;
 
-    let max_degree = constraint_expressions
-        .iter()
-        .map(|c| {
-            let (numerator_degree, denominator_degree) = c.trace_degree();
-            numerator_degree - denominator_degree
-        })
-        .max()
-        .expect("No constraints");
+    // Same target degree as `Constraints::combine`, which is at least the
+    // degree of every constraint, so the adjustments can not underflow.
+    let max_degree = constraints.degree();
     let target_degree = trace_len * max_degree - 1;
     let adjustment_degrees: Vec<usize> = constraint_expressions
         .iter()
@@ -354,6 +397,7 @@ pub fn generate(
     autogen_oods_contract(
         constraint_expressions,
         n_cols,
+        max_degree,
         blowup,
         output_directory,
         system_name,
@@ -533,6 +577,7 @@ fn autogen_periodic(
 fn autogen_oods_contract(
     constraints: &[RationalExpression],
     n_cols: usize,
+    max_degree: usize,
     blowup: usize,
     output_directory: &str,
     system_name: &str,
@@ -549,15 +594,6 @@ fn autogen_oods_contract(
     let mut trace_keys: Vec<&RationalExpression> = traces.keys().collect();
     trace_keys.sort_by(|a, b| lexicographic_compare(a, b));
 
-    let max_degree = constraints
-        .iter()
-        .map(|c| {
-            let (numerator_degree, denominator_degree) = c.trace_degree();
-            numerator_degree - denominator_degree
-        })
-        .max()
-        .expect("No constraints");
-
     let trace_contract =
         autogen_trace_layout(&trace_keys, n_cols, max_degree, blowup, system_name)?;
 
@@ -796,3 +832,41 @@ fn lexicographic_compare(first: &RationalExpression, second: &RationalExpression
         panic!("The lexicographic compare should only be used on traces");
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unsupported_constraints() {
+        use RationalExpression::*;
+        let constraints = |expression| {
+            let mut constraints =
+                Constraints::from_expressions((16, 1), b"seed".to_vec(), vec![expression]).unwrap();
+            constraints.strong_fiat_shamir = false;
+            constraints
+        };
+        let expression = (Trace(0, 0) - 1) / (X - 1);
+        let unused = "/nonexistent";
+
+        let mut strong = constraints(expression.clone());
+        strong.strong_fiat_shamir = true;
+        assert!(matches!(
+            generate(&strong, unused, "Test"),
+            Err(GenerateError::UnsupportedStrongFiatShamir)
+        ));
+
+        let mut zero_knowledge = constraints(expression);
+        zero_knowledge.zero_knowledge = true;
+        assert!(matches!(
+            generate(&zero_knowledge, unused, "Test"),
+            Err(GenerateError::UnsupportedZeroKnowledge)
+        ));
+
+        let public_input = constraints((Trace(0, 0) - PublicInput(0)) / (X - 1));
+        assert!(matches!(
+            generate(&public_input, unused, "Test"),
+            Err(GenerateError::UnsupportedPublicInputs)
+        ));
+    }
+}
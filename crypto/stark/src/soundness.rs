@@ -0,0 +1,225 @@
+use crate::{constraints::Constraints, field::StarkField, parameters::SecurityModel};
+use std::{convert::TryFrom, fmt, prelude::v1::*};
+use zkp_primefield::RefFieldLike;
+
+/// Largest Johnson proximity parameter `m` considered.
+const MAX_PROXIMITY_PARAMETER: usize = 64;
+
+/// Soundness of a proof broken down by protocol component
+///
+/// Created by [`Constraints::soundness`]. Each component is given in bits,
+/// the binary logarithm of the inverse of its soundness error. The errors of
+/// the components add up, except for the proof of work which multiplies the
+/// error of the queries.
+///
+/// In the [`Conjectured`](SecurityModel::Conjectured) model the list size is
+/// one and every query rejects a far word with probability `1 - rho`,
+/// following the ethSTARK conjecture [ethSTARK, §5.10]. In the
+/// [`Provable`](SecurityModel::Provable) model the list size is bounded by
+/// the Johnson bound `(m + 1/2) / sqrt(rho)` [ethSTARK, §5.9], queries reject
+/// with probability `1 - sqrt(rho) (1 + 1 / 2m)` and the FRI commit phase
+/// error follows [BCIKS20, Thm. 8.3]. The proximity parameter `m` is chosen
+/// to maximize the total.
+///
+/// The size of the field is bounded from below by `2^(bits - 1)`. Challenges
+/// drawn for later trace stages are not accounted for.
+///
+/// [ethSTARK]: https://eprint.iacr.org/2021/582
+/// [BCIKS20]: https://eprint.iacr.org/2020/654
+#[derive(Clone, PartialEq)]
+#[cfg_attr(feature = "std", derive(Debug))]
+pub struct Soundness {
+    pub model:               SecurityModel,
+    /// Johnson proximity parameter `m`, `None` in the conjectured model.
+    pub proximity_parameter: Option<usize>,
+    /// Random linear combination of the constraints (ALI).
+    pub composition:         f64,
+    /// Out of domain sampling (DEEP).
+    pub oods:                f64,
+    /// FRI folding challenges.
+    pub fri_commit:          f64,
+    /// FRI queries, without the proof of work.
+    pub fri_query:           f64,
+    /// Proof of work.
+    pub grinding:            f64,
+    /// Security of the proof.
+    pub total:               f64,
+}
+
+impl Soundness {
+    pub(crate) fn estimate<Field>(constraints: &Constraints<Field>, model: SecurityModel) -> Self
+    where
+        Field: StarkField,
+        for<'a> &'a Field: RefFieldLike<Field>,
+    {
+        Self::with_parameters::<Field>(
+            model,
+            constraints.trace_polynomial_len(),
+            constraints.degree(),
+            constraints.blowup,
+            constraints.num_queries,
+            constraints.pow_bits,
+            &constraints.fri_layout,
+        )
+    }
+
+    /// Estimate for constraints of `degree` with the given proof parameters.
+    // The sizes involved are far below 2^52.
+    #[allow(
+        clippy::cast_precision_loss,
+        clippy::cast_possible_truncation,
+        clippy::cast_possible_wrap
+    )]
+    pub(crate) fn with_parameters<Field: StarkField>(
+        model: SecurityModel,
+        trace_len: usize,
+        degree: usize,
+        blowup: usize,
+        num_queries: usize,
+        pow_bits: usize,
+        fri_layout: &[usize],
+    ) -> Self {
+        let field_size = 2_f64.powi(Field::modulus_bits() as i32 - 1);
+        let trace_len = trace_len as f64;
+        let domain_size = trace_len * blowup as f64;
+        let rate = 1.0 / blowup as f64;
+        let degree = degree.max(1) as f64 * trace_len;
+        let folding = fri_layout
+            .iter()
+            .map(|&reductions| (1_u64 << reductions) as f64)
+            .sum::<f64>();
+        let num_queries = i32::try_from(num_queries).unwrap_or(i32::MAX);
+        let grinding = pow_bits as f64;
+
+        // The out of domain point is sampled outside the evaluation domain
+        let oods_size = field_size - domain_size;
+        match model {
+            SecurityModel::Conjectured => {
+                Self::from_errors(model, None, [
+                    1.0 / field_size,
+                    degree / oods_size,
+                    (domain_size + 1.0) * folding / field_size,
+                    rate.powi(num_queries),
+                    grinding,
+                ])
+            }
+            SecurityModel::Provable => {
+                (3..=MAX_PROXIMITY_PARAMETER)
+                    .map(|m| {
+                        let m_f = m as f64;
+                        let list_size = (m_f + 0.5) / rate.sqrt();
+                        let fri_commit = (m_f + 0.5).powi(7) * domain_size.powi(2)
+                            / (2.0 * rate.powf(1.5) * field_size)
+                            + (2.0 * m_f + 1.0) * (domain_size + 1.0) * folding
+                                / (rate.sqrt() * field_size);
+                        let proximity = rate.sqrt() * (1.0 + 1.0 / (2.0 * m_f));
+                        Self::from_errors(model, Some(m), [
+                            list_size / field_size,
+                            list_size * degree / oods_size,
+                            fri_commit,
+                            proximity.powi(num_queries),
+                            grinding,
+                        ])
+                    })
+                    .max_by(|a, b| a.total.partial_cmp(&b.total).unwrap())
+                    .unwrap()
+            }
+        }
+    }
+
+    /// Combine the component errors, the last entry is the proof of work in
+    /// bits.
+    fn from_errors(
+        model: SecurityModel,
+        proximity_parameter: Option<usize>,
+        [composition, oods, fri_commit, fri_query, grinding]: [f64; 5],
+    ) -> Self {
+        let bits = |error: f64| 0.0 - error.min(1.0).log2();
+        let total = composition + oods + fri_commit + fri_query * 2_f64.powf(-grinding);
+        Self {
+            model,
+            proximity_parameter,
+            composition: bits(composition),
+            oods: bits(oods),
+            fri_commit: bits(fri_commit),
+            fri_query: bits(fri_query),
+            grinding,
+            total: bits(total),
+        }
+    }
+}
+
+impl fmt::Display for Soundness {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:.1} bits of {} security", self.total, self.model)?;
+        if let Some(m) = self.proximity_parameter {
+            write!(f, " (proximity parameter {})", m)?;
+        }
+        writeln!(f, ":")?;
+        writeln!(f, "  composition {:.1} bits", self.composition)?;
+        writeln!(f, "  out of domain sampling {:.1} bits", self.oods)?;
+        writeln!(f, "  FRI commit phase {:.1} bits", self.fri_commit)?;
+        writeln!(
+            f,
+            "  FRI query phase {:.1} bits plus {:.1} bits of grinding",
+            self.fri_query, self.grinding
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{traits::tests::recurrance_constraints, RationalExpression::*};
+    use zkp_primefield::Goldilocks;
+
+    #[test]
+    fn test_conjectured_breakdown() {
+        let mut constraints = recurrance_constraints();
+        constraints.blowup = 16;
+        constraints.num_queries = 20;
+        constraints.pow_bits = 10;
+        let soundness = constraints.soundness(SecurityModel::Conjectured);
+        assert_eq!(soundness.proximity_parameter, None);
+        assert!((soundness.fri_query - 80.0).abs() < 1e-9);
+        assert!((soundness.grinding - 10.0).abs() < 1e-9);
+        assert!(soundness.composition >= 251.0);
+        assert!(soundness.oods > 200.0);
+        assert!(soundness.fri_commit > 200.0);
+        assert!((soundness.total - 90.0).abs() < 1e-6);
+    }
+
+    #[test]
+    #[allow(clippy::cast_precision_loss)]
+    fn test_provable_is_weaker() {
+        let constraints = recurrance_constraints();
+        let conjectured = constraints.soundness(SecurityModel::Conjectured);
+        let provable = constraints.soundness(SecurityModel::Provable);
+        assert!(provable.proximity_parameter.is_some());
+        assert!(provable.composition < conjectured.composition);
+        assert!(provable.oods < conjectured.oods);
+        assert!(provable.fri_commit < conjectured.fri_commit);
+        assert!(provable.fri_query < conjectured.fri_query);
+        assert!(provable.total < conjectured.total);
+        // Each query adds slightly less than `log2(blowup) / 2` bits
+        let half = f64::from(constraints.blowup.trailing_zeros()) / 2.0;
+        let per_query = provable.fri_query / constraints.num_queries as f64;
+        assert!(per_query < half);
+        assert!(per_query > 0.8 * half);
+    }
+
+    #[test]
+    fn test_small_field() {
+        let constraints = Constraints::<Goldilocks>::from_expressions((1024, 1), vec![], vec![
+            (Trace(0, 1) - Trace(0, 0)) / (X.pow(1024) - Constant(1.into())),
+        ])
+        .unwrap();
+        let conjectured = constraints.soundness(SecurityModel::Conjectured);
+        let provable = constraints.soundness(SecurityModel::Provable);
+        // The field limits the security, not the queries
+        assert!(conjectured.oods < 64.0);
+        assert!(conjectured.total < conjectured.fri_query);
+        assert!(provable.fri_commit < 20.0);
+        assert!(provable.total <= provable.fri_commit);
+    }
+}
@@ -1,26 +1,50 @@
-use crate::polynomial::DensePolynomial;
+use crate::{field::StarkField, polynomial::DensePolynomial};
 use log::trace;
 use std::{
     ops::{Index, IndexMut},
+    path::Path,
     prelude::v1::*,
 };
 use zkp_mmap_vec::MmapVec;
-use zkp_primefield::{fft::permute, Fft, FieldElement, Root, Zero};
+use zkp_primefield::{fft::permute, Fft, FieldElement, RefFieldLike};
 
 #[derive(Clone, PartialEq)]
 #[cfg_attr(feature = "std", derive(Debug))]
-pub struct TraceTable {
+pub struct TraceTable<Field: StarkField = FieldElement> {
     trace_length: usize,
     num_columns:  usize,
-    values:       MmapVec<FieldElement>,
+    values:       MmapVec<Field>,
 }
 
-impl TraceTable {
+impl<Field> TraceTable<Field>
+where
+    Field: StarkField,
+    for<'a> &'a Field: RefFieldLike<Field>,
+{
     /// Constructs a zero-initialized trace table of the given size.
     pub fn new(trace_length: usize, num_columns: usize) -> Self {
-        let mut values: MmapVec<FieldElement> = MmapVec::with_capacity(trace_length * num_columns);
+        Self::from_values(
+            trace_length,
+            num_columns,
+            MmapVec::with_capacity(trace_length * num_columns),
+        )
+    }
+
+    /// Constructs a zero-initialized trace table of the given size, in a file
+    /// in `scratch` if it is large.
+    ///
+    /// See [`MmapVec::with_capacity_scratch`].
+    pub fn new_in(trace_length: usize, num_columns: usize, scratch: Option<&Path>) -> Self {
+        Self::from_values(
+            trace_length,
+            num_columns,
+            MmapVec::with_capacity_scratch(trace_length * num_columns, scratch),
+        )
+    }
+
+    fn from_values(trace_length: usize, num_columns: usize, mut values: MmapVec<Field>) -> Self {
         for _ in 0..(trace_length * num_columns) {
-            values.push(FieldElement::zero());
+            values.push(Field::zero());
         }
         Self {
             trace_length,
@@ -37,16 +61,16 @@ impl TraceTable {
         self.num_columns
     }
 
-    pub fn generator(&self) -> FieldElement {
-        FieldElement::root(self.trace_length).expect("No generator for trace table length.")
+    pub fn generator(&self) -> Field {
+        Field::root(self.trace_length).expect("No generator for trace table length.")
     }
 
-    pub fn iter_row(&self, i: usize) -> impl Iterator<Item = &FieldElement> {
+    pub fn iter_row(&self, i: usize) -> impl Iterator<Item = &Field> {
         // Delegate to Index<usize> which returns a row slice.
         self[i].iter()
     }
 
-    pub fn iter_column(&self, j: usize) -> impl Iterator<Item = &FieldElement> {
+    pub fn iter_column(&self, j: usize) -> impl Iterator<Item = &Field> {
         self.values[j..].iter().step_by(self.num_columns)
     }
 
@@ -57,15 +81,37 @@ impl TraceTable {
     /// not possible to get a slice of a column (since the representation is
     /// row first.)
     // TODO: Use strides
-    pub fn column_to_mmapvec(&self, j: usize) -> MmapVec<FieldElement> {
-        let mut result: MmapVec<FieldElement> = MmapVec::with_capacity(self.trace_length);
+    pub fn column_to_mmapvec(&self, j: usize) -> MmapVec<Field> {
+        let mut result: MmapVec<Field> = MmapVec::with_capacity(self.trace_length);
         for v in self.iter_column(j) {
             result.push(v.clone());
         }
         result
     }
 
-    pub fn interpolate(&self) -> Vec<DensePolynomial> {
+    /// Construct a new table with the columns of `other` appended.
+    pub fn concat_columns(&self, other: &Self) -> Self {
+        assert_eq!(self.num_rows(), other.num_rows());
+        let num_columns = self.num_columns + other.num_columns;
+        let mut values = MmapVec::with_capacity(self.trace_length * num_columns);
+        for i in 0..self.trace_length {
+            values.extend_from_slice(&self[i]);
+            values.extend_from_slice(&other[i]);
+        }
+        Self {
+            trace_length: self.trace_length,
+            num_columns,
+            values,
+        }
+    }
+
+    pub fn interpolate(&self) -> Vec<DensePolynomial<Field>> {
+        self.interpolate_in(None)
+    }
+
+    /// Like [`interpolate`](Self::interpolate), see
+    /// [`MmapVec::with_capacity_scratch`] for `scratch`.
+    pub(crate) fn interpolate_in(&self, scratch: Option<&Path>) -> Vec<DensePolynomial<Field>> {
         trace!("BEGIN Interpolate");
         let result = (0..self.num_columns())
             // OPT: Use and FFT that can transform the entire table in one pass,
@@ -74,7 +120,7 @@ impl TraceTable {
             // so it can be replaced by a matrix of coefficients.
             .map(|j| {
                 // Copy column to vec
-                let mut vec = MmapVec::with_capacity(self.num_rows());
+                let mut vec = MmapVec::with_capacity_scratch(self.num_rows(), scratch);
                 for v in self.iter_column(j) {
                     vec.push(v.clone());
                 }
@@ -84,15 +130,15 @@ impl TraceTable {
                 permute(&mut vec);
                 DensePolynomial::from_mmap_vec(vec)
             })
-            .collect::<Vec<DensePolynomial>>();
+            .collect::<Vec<_>>();
         trace!("END Interpolate");
         result
     }
 }
 
 /// Returns a field
-impl Index<(usize, usize)> for TraceTable {
-    type Output = FieldElement;
+impl<Field: StarkField> Index<(usize, usize)> for TraceTable<Field> {
+    type Output = Field;
 
     fn index(&self, (i, j): (usize, usize)) -> &Self::Output {
         assert!(i < self.trace_length);
@@ -102,7 +148,7 @@ impl Index<(usize, usize)> for TraceTable {
 }
 
 /// Returns a mutable field
-impl IndexMut<(usize, usize)> for TraceTable {
+impl<Field: StarkField> IndexMut<(usize, usize)> for TraceTable<Field> {
     fn index_mut(&mut self, (i, j): (usize, usize)) -> &mut Self::Output {
         assert!(i < self.trace_length);
         assert!(j < self.num_columns);
@@ -111,18 +157,18 @@ impl IndexMut<(usize, usize)> for TraceTable {
 }
 
 /// Returns a row as a slice
-impl Index<usize> for TraceTable {
-    type Output = [FieldElement];
+impl<Field: StarkField> Index<usize> for TraceTable<Field> {
+    type Output = [Field];
 
-    fn index(&self, i: usize) -> &[FieldElement] {
+    fn index(&self, i: usize) -> &[Field] {
         assert!(i < self.trace_length);
         &self.values[i * self.num_columns..(i + 1) * self.num_columns]
     }
 }
 
 /// Returns a mutable row as a slice
-impl IndexMut<usize> for TraceTable {
-    fn index_mut(&mut self, i: usize) -> &mut [FieldElement] {
+impl<Field: StarkField> IndexMut<usize> for TraceTable<Field> {
+    fn index_mut(&mut self, i: usize) -> &mut [Field] {
         assert!(i < self.trace_length);
         &mut self.values[i * self.num_columns..(i + 1) * self.num_columns]
     }
@@ -1,8 +1,12 @@
 #[cfg(feature = "prover")]
-use crate::constraint_check::{check_constraints, check_specific_constraint};
+use crate::constraint_check::{check_constraints, check_specific_constraint, complete_stages};
 #[cfg(feature = "prover")]
-use crate::{prove, ProverError, TraceTable};
+use crate::{prove_staged, ProverError, TraceTable};
 use crate::{verify, Constraints, Proof, VerifierError};
+#[cfg(feature = "prover")]
+use std::fmt;
+#[cfg(feature = "prover")]
+use zkp_primefield::FieldElement;
 
 pub trait Verifiable {
     fn constraints(&self) -> Constraints;
@@ -17,25 +21,92 @@ pub trait Verifiable {
 pub trait Provable<T>: Verifiable {
     fn trace(&self, witness: T) -> TraceTable;
 
+    /// Computes the columns of a later trace stage.
+    ///
+    /// Only required when the constraints have more than one trace stage. See
+    /// [`prove_staged`](crate::prove_staged) for the arguments. Returning
+    /// `None` makes proving fail with [`ProverError::InvalidTraceStage`] and
+    /// checking fail with [`CheckError::InvalidTraceStage`].
+    fn stage_trace(
+        &self,
+        _stage: usize,
+        _challenges: &[FieldElement],
+        _trace: &TraceTable,
+    ) -> Option<TraceTable> {
+        None
+    }
+
     fn prove(&self, witness: T) -> Result<Proof, ProverError> {
         let constraints = self.constraints();
         let trace = self.trace(witness);
-        prove(&constraints, &trace)
+        prove_staged(&constraints, &trace, |stage, challenges, trace| {
+            self.stage_trace(stage, challenges, trace)
+        })
     }
 
-    fn check(&self, witness: T) -> Result<(), (usize, usize)> {
-        let constraints = self.constraints();
-        let trace = self.trace(witness);
+    fn check(&self, witness: T) -> Result<(), CheckError> {
+        let mut constraints = self.constraints();
+        let trace = complete_stages(
+            &mut constraints,
+            self.trace(witness),
+            |stage, challenges, trace| self.stage_trace(stage, challenges, trace),
+        )
+        .ok_or(CheckError::InvalidTraceStage)?;
         check_constraints(&constraints, &trace)
+            .map_err(|(row, constraint)| CheckError::Constraint { row, constraint })
     }
 
-    fn check_specified(&self, witness: T, row: usize, which_constraint: usize) -> Result<(), ()> {
-        let constraints = self.constraints();
-        let trace = self.trace(witness);
+    fn check_specified(
+        &self,
+        witness: T,
+        row: usize,
+        which_constraint: usize,
+    ) -> Result<(), CheckError> {
+        let mut constraints = self.constraints();
+        let trace = complete_stages(
+            &mut constraints,
+            self.trace(witness),
+            |stage, challenges, trace| self.stage_trace(stage, challenges, trace),
+        )
+        .ok_or(CheckError::InvalidTraceStage)?;
         if check_specific_constraint(&constraints, &trace, row, which_constraint) {
             Ok(())
         } else {
-            Err(())
+            Err(CheckError::Constraint {
+                row,
+                constraint: which_constraint,
+            })
+        }
+    }
+}
+
+/// Reason [`Provable::check`] fails
+#[cfg(feature = "prover")]
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub enum CheckError {
+    /// A later trace stage is missing or has the wrong dimensions.
+    InvalidTraceStage,
+    /// The constraint does not hold on the row.
+    Constraint {
+        row:        usize,
+        constraint: usize,
+    },
+}
+
+#[cfg(feature = "prover")]
+impl fmt::Display for CheckError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use CheckError::*;
+        match *self {
+            InvalidTraceStage => {
+                write!(
+                    f,
+                    "A later trace stage is missing or has the wrong dimensions"
+                )
+            }
+            Constraint { row, constraint } => {
+                write!(f, "Constraint {} does not hold on row {}", constraint, row)
+            }
         }
     }
 }
@@ -46,7 +117,9 @@ pub(crate) mod tests {
     use crate::{polynomial::DensePolynomial, RationalExpression};
     use proptest::{collection::vec as prop_vec, prelude::*};
     use std::convert::TryInto;
+    use zkp_macros_decl::field_element;
     use zkp_primefield::{FieldElement, One, Pow, Root, Zero};
+    use zkp_u256::U256;
 
     // False positives on the Latex math.
     #[allow(clippy::doc_markdown)]
@@ -130,6 +203,17 @@ pub(crate) mod tests {
         }
     }
 
+    /// Constraints of a [`Recurrance`] claim, for tests that only need a
+    /// representative constraint system.
+    pub(crate) fn recurrance_constraints() -> Constraints {
+        let recurrance = Recurrance {
+            index:         1000,
+            initial_value: field_element!("cafebabe"),
+            exponent:      1,
+        };
+        recurrance.claim().constraints()
+    }
+
     impl Provable<&Witness> for Claim {
         fn trace(&self, witness: &Witness) -> TraceTable {
             let trace_length = (self.index + 1).next_power_of_two();
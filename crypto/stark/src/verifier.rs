@@ -1,18 +1,17 @@
 use crate::{
     channel::{RandomGenerator, Replayable, VerifierChannel},
     constraints::Constraints,
-    polynomial::DensePolynomial,
+    field::{pow_signed, StarkField},
+    fri::{Error as FriError, FriVerifier},
     proof_of_work, Proof,
 };
 use log::trace;
 #[cfg(feature = "std")]
 use std::error;
 use std::{collections::BTreeMap, fmt, prelude::v1::*};
-use zkp_hash::Hash;
+use zkp_hash::{Hash, Hasher, MaskedKeccak};
 use zkp_merkle_tree::{Commitment, Error as MerkleError, Proof as MerkleProof};
-use zkp_primefield::{
-    fft, geometric_series::root_series, FieldElement, One, Pow, Root, SquareInline, Zero,
-};
+use zkp_primefield::{fft, geometric_series::root_series, Pow, RefFieldLike};
 use zkp_u256::U256;
 
 type Result<T> = std::result::Result<T, Error>;
@@ -25,12 +24,17 @@ pub enum Error {
     InvalidPoW,
     InvalidLDECommitment,
     InvalidConstraintCommitment,
+    InvalidFriLayout,
     InvalidFriCommitment,
     HashMapFailure,
     ProofTooLong,
+    ProofTooShort,
+    InvalidEncoding,
     OodsCalculationFailure,
     OodsMismatch,
     FriCalculationFailure,
+    InvalidBatch,
+    UnsupportedVersion,
     Merkle(MerkleError),
 }
 
@@ -42,6 +46,7 @@ impl fmt::Display for Error {
             InvalidPoW => write!(f, "The suggested proof of work failed to verify"),
             InvalidLDECommitment => write!(f, "The LDE merkle proof is incorrect"),
             InvalidConstraintCommitment => write!(f, "The constraint merkle proof is incorrect"),
+            InvalidFriLayout => write!(f, "The FRI layout is not supported"),
             InvalidFriCommitment => write!(f, "A FRI layer commitment is incorrect"),
             HashMapFailure => {
                 write!(
@@ -50,6 +55,8 @@ impl fmt::Display for Error {
                 )
             }
             ProofTooLong => write!(f, "The proof length doesn't match the specification"),
+            ProofTooShort => write!(f, "The proof ended before all values were read"),
+            InvalidEncoding => write!(f, "A field element in the proof is out of range"),
             OodsCalculationFailure => {
                 write!(
                     f,
@@ -63,6 +70,8 @@ impl fmt::Display for Error {
                 )
             }
             OodsMismatch => write!(f, "Calculated oods value doesn't match the committed one"),
+            InvalidBatch => write!(f, "The statements can not be verified as a batch"),
+            UnsupportedVersion => write!(f, "The proof version is not supported"),
             // This is a wrapper, so defer to the underlying types' implementation of `fmt`.
             Merkle(ref e) => std::fmt::Display::fmt(e, f),
         }
@@ -75,6 +84,18 @@ impl From<MerkleError> for Error {
     }
 }
 
+impl From<FriError> for Error {
+    fn from(err: FriError) -> Self {
+        match err {
+            FriError::RootUnavailable => Self::RootUnavailable,
+            FriError::InvalidLayout => Self::InvalidFriLayout,
+            FriError::InvalidCommitment => Self::InvalidFriCommitment,
+            FriError::FinalLayerMismatch => Self::FriCalculationFailure,
+            FriError::Merkle(err) => Self::Merkle(err),
+        }
+    }
+}
+
 // False positives on the Latex math.
 #[allow(clippy::doc_markdown)]
 /// # Stark verify
@@ -90,6 +111,7 @@ impl From<MerkleError> for Error {
 /// ### Step 1: Read all commitments and draw random values
 ///
 /// * Read the trace polynomial commitment commitment.
+/// * For each later trace stage, draw its challenges and read its commitment.
 /// * Draw the constraint combination coefficients $\alpha_i$ and $\beta_i$.
 /// * Read the combined constraint polynomial commitment.
 /// * Draw the deep point $z$.
@@ -115,13 +137,16 @@ impl From<MerkleError> for Error {
 /// * Read evaluations of trace polynomial
 /// $T_0(x_0), T_1(x_0), \dots, T_0(x_1), T_1(x_1), \dots$
 /// * Read and verify merkle decommitments for trace polynomial
+/// * Repeat for each later trace stage
 /// * Read evaluations of the combined constraint polynomial
 /// $A_0(x_0), A_1(x_0), \dots, A_0(x_1), A_1(x_1), \dots$
 /// * Read and verify merkle decommitments for combined constraint polynomial
 ///
 /// ### Step 4: FRI decommitments and final layer verification
 ///
-/// <!-- TODO -->
+/// * Compute the first FRI layer values at the queries from the decommitted
+/// values and the deep values.
+/// * Read the FRI layer decommitments, see [`FriVerifier`].
 ///
 /// ### Step 5: Verify deep point evaluation
 ///
@@ -173,64 +198,93 @@ impl From<MerkleError> for Error {
 /// * Evaluate the final layer
 ///
 /// <!-- TODO: ellaborate FRI verification -->
+///
+/// ### Zero-knowledge
+///
+/// When `constraints.zero_knowledge` is set, the domains are sized for the
+/// masked trace polynomials, an additional masking polynomial value is read
+/// with the constraint values and every decommitted leaf has a salt appended.
+/// See [`prove`](crate::prove) for details.
+pub fn verify<Field>(constraints: &Constraints<Field>, proof: &Proof) -> Result<()>
+where
+    Field: StarkField,
+    for<'a> &'a Field: RefFieldLike<Field>,
+{
+    verify_with_hasher::<MaskedKeccak, _>(constraints, proof)
+}
+
+/// Verify a Stark proof created with hash function `H`.
+///
+/// See [`verify`] for details.
 // TODO: Refactor into smaller function
 #[allow(clippy::too_many_lines)]
-pub fn verify(constraints: &Constraints, proof: &Proof) -> Result<()> {
+pub fn verify_with_hasher<H, Field>(constraints: &Constraints<Field>, proof: &Proof) -> Result<()>
+where
+    H: Hasher,
+    Field: StarkField,
+    for<'a> &'a Field: RefFieldLike<Field>,
+{
     trace!("BEGIN Verify");
     let proof = proof.as_bytes();
     let trace_length = constraints.trace_nrows();
     let trace_cols = constraints.trace_ncolumns();
-    let eval_domain_size = trace_length * constraints.blowup;
-    let eval_x = root_series(eval_domain_size).collect::<Vec<_>>();
+    let eval_domain_size = constraints.eval_domain_size();
+    let eval_x = root_series::<Field>(eval_domain_size).collect::<Vec<_>>();
 
-    let mut channel = VerifierChannel::new(proof.to_vec());
-    channel.initialize(constraints.channel_seed());
+    let mut channel = VerifierChannel::<H>::new(proof.to_vec());
+    channel.initialize(&constraints.transcript_seed());
 
-    // Get the low degree root commitment, and constraint root commitment
+    // Get the low degree root commitments with the challenges for the later
+    // stages, and the constraint root commitment
     // TODO: Make it work as channel.read()
-    let low_degree_extension_root: Hash = channel.replay();
-    let lde_commitment = Commitment::from_size_hash(eval_domain_size, &low_degree_extension_root)?;
-    let constraint_coefficients = channel.get_coefficients(2 * constraints.len());
+    let mut lde_commitments = Vec::with_capacity(constraints.num_stages());
+    let mut challenges: Vec<Field> = Vec::with_capacity(constraints.num_challenges());
+    for stage in 0..constraints.num_stages() {
+        let stage_challenges: Vec<Field> =
+            channel.get_coefficients(constraints.stage_challenges(stage).len());
+        challenges.extend(stage_challenges);
+        let low_degree_extension_root: Hash = channel.replay();
+        lde_commitments.push(Commitment::from_size_hash(
+            eval_domain_size,
+            &low_degree_extension_root,
+        )?);
+    }
+    let constraint_coefficients: Vec<Field> = channel.get_coefficients(2 * constraints.len());
 
     let constraint_evaluated_root: Hash = channel.replay();
     let constraint_commitment =
         Commitment::from_size_hash(eval_domain_size, &constraint_evaluated_root)?;
 
     // Get the oods information from the proof and random
-    let oods_point: FieldElement = channel.get_random();
+    let oods_point: Field = channel.get_random();
 
     // This hack is annoying and should be removed
     let mut parseable_constraints = constraints.clone();
     parseable_constraints.substitute();
+    parseable_constraints.substitute_public_inputs();
+    parseable_constraints.substitute_challenges(&challenges);
     let trace_arguments = parseable_constraints.trace_arguments();
-    let trace_values: Vec<FieldElement> = channel.replay_many(trace_arguments.len());
-    let claimed_trace_map: BTreeMap<(usize, isize), FieldElement> = trace_arguments
+    let trace_values: Vec<Field> = channel.replay_many(trace_arguments.len());
+    let claimed_trace_map: BTreeMap<(usize, isize), Field> = trace_arguments
         .into_iter()
         .zip(trace_values.iter().cloned())
         .collect();
 
+    // In zero-knowledge mode there is an additional masking polynomial and all
+    // leaves have a salt appended.
+    let zk_columns = usize::from(constraints.zero_knowledge);
     let constraints_trace_degree = constraints.degree().next_power_of_two();
-    let claimed_constraint_values: Vec<FieldElement> =
-        channel.replay_many(constraints_trace_degree);
+    let claimed_constraint_values: Vec<Field> =
+        channel.replay_many(constraints_trace_degree + zk_columns);
 
-    let oods_coefficients =
+    let oods_coefficients: Vec<Field> =
         channel.get_coefficients(claimed_trace_map.len() + claimed_constraint_values.len());
 
-    let mut fri_commitments: Vec<Commitment> = Vec::with_capacity(constraints.fri_layout.len() + 1);
-    let mut eval_points: Vec<FieldElement> = Vec::with_capacity(constraints.fri_layout.len() + 1);
-    let mut fri_size = eval_domain_size;
-    // Get fri roots and eval points from the channel random
-    for &num_folds in &constraints.fri_layout {
-        fri_size >>= num_folds;
-        fri_commitments.push(Commitment::from_size_hash(fri_size, &channel.replay())?);
-        eval_points.push(channel.get_random());
-    }
-    // Gets the last layer coeffiencts
-    let last_layer_coefficients = channel.replay_fri_layer(fri_size / constraints.blowup);
+    let fri_verifier = FriVerifier::read_commitments(constraints.fri_parameters(), &mut channel)?;
 
     // Gets the proof of work from the proof.
     let pow_seed: proof_of_work::ChallengeSeed = channel.get_random();
-    let pow_challenge = pow_seed.with_difficulty(constraints.pow_bits);
+    let pow_challenge = pow_seed.with_difficulty::<H>(constraints.pow_bits);
     let pow_response: proof_of_work::Response = channel.replay();
     if !pow_challenge.verify(pow_response) {
         return Err(Error::InvalidPoW);
@@ -243,17 +297,28 @@ pub fn verify(constraints: &Constraints, proof: &Proof) -> Result<()> {
         &mut channel,
     );
 
-    // Get values and check decommitment of low degree extension
-    let lde_values: Vec<(usize, Vec<FieldElement>)> = queries
+    // Get values and check decommitment of low degree extension. The values
+    // of all stages are collected without the salts.
+    let mut lde_values: Vec<(usize, Vec<Field>)> = queries
         .iter()
-        .map(|&index| (index, channel.replay_fri_layer(trace_cols)))
+        .map(|&index| (index, Vec::with_capacity(trace_cols)))
         .collect();
-    let lde_proof_length = lde_commitment.proof_size(&queries)?;
-    let lde_hashes: Vec<Hash> = channel.replay_many(lde_proof_length);
-    let lde_proof = MerkleProof::from_hashes(&lde_commitment, &queries, &lde_hashes)?;
-    // Note - we could express this a merkle error instead but this adds specificity
-    if lde_proof.verify(&lde_values).is_err() {
-        return Err(Error::InvalidLDECommitment);
+    for (stage, lde_commitment) in lde_commitments.iter().enumerate() {
+        let stage_cols = constraints.stage_columns(stage).len();
+        let stage_values: Vec<(usize, Vec<Field>)> = queries
+            .iter()
+            .map(|&index| (index, channel.replay_fri_layer(stage_cols + zk_columns)))
+            .collect();
+        let lde_proof_length = lde_commitment.proof_size(&queries)?;
+        let lde_hashes: Vec<Hash> = channel.replay_many(lde_proof_length);
+        let lde_proof = MerkleProof::<H>::from_hashes_with(lde_commitment, &queries, &lde_hashes)?;
+        // Note - we could express this a merkle error instead but this adds specificity
+        if lde_proof.verify(&stage_values).is_err() {
+            return Err(Error::InvalidLDECommitment);
+        }
+        for ((_, values), (_, stage_values)) in lde_values.iter_mut().zip(stage_values) {
+            values.extend_from_slice(&stage_values[..stage_cols]);
+        }
     }
 
     // Gets the values and checks the constraint decommitment
@@ -261,146 +326,70 @@ pub fn verify(constraints: &Constraints, proof: &Proof) -> Result<()> {
     for query_index in &queries {
         constraint_values.push((
             *query_index,
-            channel.replay_fri_layer(constraints_trace_degree),
+            channel.replay_fri_layer(constraints_trace_degree + 2 * zk_columns),
         ));
     }
     let constraint_proof_length = constraint_commitment.proof_size(&queries)?;
     let constraint_hashes: Vec<Hash> = channel.replay_many(constraint_proof_length);
     let constraint_proof =
-        MerkleProof::from_hashes(&constraint_commitment, &queries, &constraint_hashes)?;
+        MerkleProof::<H>::from_hashes_with(&constraint_commitment, &queries, &constraint_hashes)?;
     // Note - we could express this a merkle error instead but this adds specificity
     if constraint_proof.verify(&constraint_values).is_err() {
         return Err(Error::InvalidConstraintCommitment);
     }
 
-    let coset_sizes = constraints
-        .fri_layout
-        .iter()
-        .map(|k| 1_usize << k)
-        .collect::<Vec<_>>();
-    let mut fri_indices: Vec<usize> = queries
-        .to_vec()
-        .iter()
-        .map(|x| x / coset_sizes[0])
-        .collect();
-
-    // Folded fri values from the previous layer
-    let mut fri_folds: BTreeMap<usize, FieldElement> = BTreeMap::new();
-
-    let mut previous_indices = queries.to_vec();
-    let mut step = 1;
-    let mut len = eval_domain_size;
-    for (k, commitment) in fri_commitments.iter().enumerate() {
-        let mut fri_layer_values = Vec::new();
-
-        fri_indices.dedup();
-        for i in &fri_indices {
-            let mut coset: Vec<FieldElement> = Vec::new();
-            for j in 0..coset_sizes[k] {
-                let n = i * coset_sizes[k] + j;
-                if let Ok(z) = previous_indices.binary_search(&n) {
-                    if k > 0 {
-                        coset.push(match fri_folds.get(&n) {
-                            Some(x) => x.clone(),
-                            None => return Err(Error::HashMapFailure),
-                        });
-                    } else {
-                        let z_reverse = fft::permute_index(eval_domain_size, queries[z]);
-                        coset.push(out_of_domain_element(
-                            &eval_x[z_reverse],
-                            &lde_values[z].1,
-                            &constraint_values[z].1,
-                            &oods_point,
-                            &claimed_trace_map,
-                            &claimed_constraint_values,
-                            &oods_coefficients,
-                            trace_length,
-                        )?);
-                    }
-                } else {
-                    coset.push(channel.replay());
-                }
-            }
-            fri_layer_values.push((*i, coset));
-        }
-        // Fold and record foldings
-        let mut layer_folds = BTreeMap::new();
-        for (i, coset) in &fri_layer_values {
-            let _old_value = layer_folds.insert(
-                *i,
-                fri_fold(
-                    coset.as_slice(),
-                    &eval_points[k],
-                    step,
-                    (coset_sizes[k] / 2) * i,
-                    len,
-                    eval_x.as_slice(),
-                ),
-            );
-        }
-
-        let merkle_proof_length = commitment.proof_size(&fri_indices)?;
-        let merkle_hashes: Vec<Hash> = channel.replay_many(merkle_proof_length);
-        let merkle_proof = MerkleProof::from_hashes(commitment, &fri_indices, &merkle_hashes)?;
-        fri_folds = layer_folds;
-
-        for _ in 0..constraints.fri_layout[k] {
-            step *= 2;
-        }
-        len /= coset_sizes[k];
-
-        // Note - we could express this a merkle error instead but this adds specificity
-        if merkle_proof.verify(&fri_layer_values).is_err() {
-            return Err(Error::InvalidFriCommitment);
-        };
-
-        previous_indices = fri_indices.clone();
-        if k + 1 < constraints.fri_layout.len() {
-            fri_indices = fri_indices
-                .iter()
-                .map(|ind| ind / coset_sizes[k + 1])
-                .collect();
-        }
+    // The first FRI layer is the DEEP composition of the decommitted values
+    let mut first_layer = Vec::with_capacity(queries.len());
+    for (z, &index) in queries.iter().enumerate() {
+        let z_reverse = fft::permute_index(eval_domain_size, index);
+        first_layer.push((
+            index,
+            out_of_domain_element(
+                &eval_x[z_reverse],
+                &lde_values[z].1,
+                &constraint_values[z].1,
+                &oods_point,
+                &claimed_trace_map,
+                &claimed_constraint_values,
+                &oods_coefficients,
+                trace_length,
+                constraints_trace_degree,
+            )?,
+        ));
     }
+    fri_verifier.verify::<H, _>(&first_layer, &mut channel)?;
     if !channel.at_end() {
         return Err(Error::ProofTooLong);
     }
-
-    // Checks that the calculated fri folded queries are the points interpolated by
-    // the decommited polynomial.
-    let interp_root = match FieldElement::root(len) {
-        Some(x) => x,
-        None => return Err(Error::RootUnavailable),
-    };
-    for key in &previous_indices {
-        let calculated = fri_folds[key].clone();
-        let x_pow = interp_root.pow(fft::permute_index(len, *key));
-        let committed = DensePolynomial::new(&last_layer_coefficients).evaluate(&x_pow);
-
-        if committed != calculated.clone() {
-            return Err(Error::OodsCalculationFailure);
-        }
+    if channel.has_invalid_encoding() {
+        return Err(Error::InvalidEncoding);
     }
 
     if oods_value_from_trace_values(
-        &constraints,
+        &parseable_constraints,
         &constraint_coefficients,
         &claimed_trace_map,
         &oods_point,
-    ) != oods_value_from_constraint_values(&claimed_constraint_values, &oods_point)
-    {
+    ) != oods_value_from_constraint_values(
+        &claimed_constraint_values[..constraints_trace_degree],
+        &oods_point,
+    ) {
         return Err(Error::OodsMismatch);
     }
     trace!("END Verify");
     Ok(())
 }
 
-fn oods_value_from_trace_values(
-    constraints: &Constraints,
-    coefficients: &[FieldElement],
-    trace_values: &BTreeMap<(usize, isize), FieldElement>,
-    oods_point: &FieldElement,
-) -> FieldElement {
+pub(crate) fn oods_value_from_trace_values<Field>(
+    constraints: &Constraints<Field>,
+    coefficients: &[Field],
+    trace_values: &BTreeMap<(usize, isize), Field>,
+    oods_point: &Field,
+) -> Field
+where
+    Field: StarkField,
+    for<'a> &'a Field: RefFieldLike<Field>,
+{
     let trace = |i: usize, j: isize| trace_values.get(&(i, j)).unwrap().clone();
     constraints
         .combine(coefficients)
@@ -408,12 +397,16 @@ fn oods_value_from_trace_values(
         .evaluate(oods_point, &trace)
 }
 
-fn oods_value_from_constraint_values(
-    constraint_values: &[FieldElement],
-    oods_point: &FieldElement,
-) -> FieldElement {
-    let mut result = FieldElement::zero();
-    let mut power = FieldElement::one();
+pub(crate) fn oods_value_from_constraint_values<Field>(
+    constraint_values: &[Field],
+    oods_point: &Field,
+) -> Field
+where
+    Field: StarkField,
+    for<'a> &'a Field: RefFieldLike<Field>,
+{
+    let mut result = Field::zero();
+    let mut power = Field::one();
     for value in constraint_values {
         result += value * &power;
         power *= oods_point;
@@ -423,7 +416,11 @@ fn oods_value_from_constraint_values(
 
 // TODO: Clean up
 #[allow(clippy::cast_possible_truncation)]
-fn get_indices(num: usize, bits: u32, proof: &mut VerifierChannel) -> Vec<usize> {
+pub(crate) fn get_indices<H: Hasher>(
+    num: usize,
+    bits: u32,
+    proof: &mut VerifierChannel<H>,
+) -> Vec<usize> {
     let mut query_indices = Vec::with_capacity(num + 3);
     while query_indices.len() < num {
         let val: U256 = proof.get_random();
@@ -440,54 +437,24 @@ fn get_indices(num: usize, bits: u32, proof: &mut VerifierChannel) -> Vec<usize>
     query_indices
 }
 
-fn fri_fold(
-    coset: &[FieldElement],
-    eval_point: &FieldElement,
-    mut step: usize,
-    mut index: usize,
-    mut len: usize,
-    eval_x: &[FieldElement],
-) -> FieldElement {
-    let mut mutable_eval_copy = eval_point.clone();
-    let mut coset_full: Vec<FieldElement> = coset.to_vec();
-    while coset_full.len() > 1 {
-        let mut next_coset = Vec::with_capacity(coset.len() / 2);
-
-        for (k, pair) in coset_full.chunks(2).enumerate() {
-            let x = &eval_x[fft::permute_index(len / 2, index + k) * step];
-            next_coset.push(fri_single_fold(&pair[0], &pair[1], x, &mutable_eval_copy));
-        }
-        len /= 2;
-        index /= 2;
-        step *= 2;
-        mutable_eval_copy = mutable_eval_copy.square();
-        coset_full = next_coset;
-    }
-    coset_full[0].clone()
-}
-
-fn fri_single_fold(
-    poly_at_x: &FieldElement,
-    poly_at_neg_x: &FieldElement,
-    x: &FieldElement,
-    eval_point: &FieldElement,
-) -> FieldElement {
-    (poly_at_x + poly_at_neg_x) + eval_point / x * (poly_at_x - poly_at_neg_x)
-}
-
 #[allow(clippy::too_many_arguments)]
-fn out_of_domain_element(
-    query_x: &FieldElement,
-    query_trace_values: &[FieldElement],
-    query_constraint_values: &[FieldElement],
-    oods_point: &FieldElement,
-    oods_trace_map: &BTreeMap<(usize, isize), FieldElement>,
-    oods_constraint_values: &[FieldElement],
-    oods_coefficients: &[FieldElement],
+pub(crate) fn out_of_domain_element<Field>(
+    query_x: &Field,
+    query_trace_values: &[Field],
+    query_constraint_values: &[Field],
+    oods_point: &Field,
+    oods_trace_map: &BTreeMap<(usize, isize), Field>,
+    oods_constraint_values: &[Field],
+    oods_coefficients: &[Field],
     trace_length: usize,
-) -> Result<FieldElement> {
-    let shifted_x = query_x * FieldElement::generator();
-    let trace_generator = match FieldElement::root(trace_length) {
+    constraints_trace_degree: usize,
+) -> Result<Field>
+where
+    Field: StarkField,
+    for<'a> &'a Field: RefFieldLike<Field>,
+{
+    let shifted_x = query_x * Field::generator();
+    let trace_generator = match Field::root(trace_length) {
         Some(x) => x,
         None => return Err(Error::RootUnavailable),
     };
@@ -496,10 +463,9 @@ fn out_of_domain_element(
         .iter()
         .map(|((column_index, offset), oods_value)| {
             (&query_trace_values[*column_index] - oods_value)
-                / (&shifted_x - trace_generator.pow(*offset).unwrap() * oods_point)
+                / (&shifted_x - pow_signed(&trace_generator, *offset).unwrap() * oods_point)
         });
 
-    let constraints_trace_degree = query_constraint_values.len();
     let combined_constraints_terms = query_constraint_values
         .iter()
         .zip(oods_constraint_values)
@@ -511,7 +477,7 @@ fn out_of_domain_element(
         .chain(combined_constraints_terms)
         .zip(oods_coefficients)
         .map(|(coeffient, term)| coeffient * term)
-        .sum())
+        .fold(Field::zero(), |sum, term| sum + term))
 }
 
 #[cfg(feature = "std")]
@@ -530,7 +496,7 @@ mod tests {
     use crate::{
         prove,
         traits::tests::{Recurrance, Recurrance2},
-        Provable, Verifiable,
+        Provable, ProverError, Verifiable,
     };
     use proptest::prelude::*;
 
@@ -556,5 +522,25 @@ mod tests {
 
             prop_assert!(verify(&constraints, &prove(&constraints, &trace).unwrap()).is_ok());
         }
+
+        #[test]
+        fn verify_recurrance_zero_knowledge(r: Recurrance) {
+            let public = r.claim();
+            let private = r.witness();
+
+            let mut constraints = public.constraints();
+            constraints.zero_knowledge = true;
+            let trace = public.trace(&private);
+            prop_assert!(matches!(
+                prove(&constraints, &trace),
+                Err(ProverError::TraceTooShort)
+            ));
+
+            // Use as many queries as the mask can hide
+            let num_oods = constraints.max_trace_openings() - constraints.num_queries;
+            prop_assume!(trace.num_rows() > num_oods);
+            constraints.num_queries = trace.num_rows() - num_oods;
+            prop_assert!(verify(&constraints, &prove(&constraints, &trace).unwrap()).is_ok());
+        }
     );
 }
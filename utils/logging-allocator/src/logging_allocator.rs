@@ -46,6 +46,12 @@ impl LoggingAllocator {
         self.peak_allocated.load(Relaxed)
     }
 
+    /// Reset the peak to the currently allocated memory and return the
+    /// previous peak.
+    pub fn reset_peak_allocated(&self) -> usize {
+        self.peak_allocated.swap(self.allocated(), Relaxed)
+    }
+
     pub fn total_allocated(&self) -> usize {
         self.total_allocated.load(Relaxed)
     }
@@ -58,3 +58,39 @@ pub use zkp_macros_impl::u256h;
 /// (Documentation goes here on the re-export, not in the other crate.)
 #[proc_macro_hack]
 pub use zkp_macros_impl::field_element;
+
+/// Algebraic intermediate representation of a state machine
+///
+/// Declares a struct with named trace columns, optional periodic columns and
+/// a transition rule for every column giving its value on the next row. The
+/// rules are expressions using `+`, `-`, `*`, integer literals and the names
+/// of the columns on the current row. Unknown names are a compile error.
+///
+/// This generates the struct `Name` with a constructor `Name::new(size)`, a
+/// struct `NameRow` holding the values of a row, the step function
+/// `Name::step(row, &current) -> NameRow` and an implementation of
+/// `zkp_stark::component::Component`. The witness is the first row and the
+/// claim the first and last rows.
+///
+/// Periodic columns are given by expressions that index to a slice of field
+/// elements, their length needs to be a power of two dividing the size.
+///
+/// ```ignore
+/// air! {
+///     /// MiMC-like round function
+///     pub struct Rounds {
+///         x, y,
+///     }
+///     periodic {
+///         k = &ROUND_CONSTANTS;
+///     }
+///     transition {
+///         x = x * x * x + k;
+///         y = x - y;
+///     }
+/// }
+///
+/// let component = Rounds::new(256);
+/// let proof = component.prove(&RoundsRow { x: 1.into(), y: 2.into() })?;
+/// ```
+pub use zkp_macros_impl::air;
@@ -58,3 +58,8 @@ pub fn u256h(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
 pub fn field_element(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     zkp_macros_lib::field_element(input.into()).into()
 }
+
+#[proc_macro]
+pub fn air(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    zkp_macros_lib::air(input.into()).into()
+}
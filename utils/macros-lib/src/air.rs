@@ -0,0 +1,475 @@
+use proc_macro2::TokenStream;
+use quote::{format_ident, quote, quote_spanned};
+use syn::{
+    braced,
+    parse::{Parse, ParseStream},
+    punctuated::Punctuated,
+    spanned::Spanned,
+    token, Attribute, BinOp, Expr, Ident, Lit, Token, UnOp, Visibility,
+};
+
+/// A `name = expression;` entry of a `periodic` or `transition` block
+struct Assignment {
+    name:       Ident,
+    expression: Expr,
+}
+
+impl Parse for Assignment {
+    fn parse(input: ParseStream<'_>) -> syn::Result<Self> {
+        let name = input.parse()?;
+        let _: Token![=] = input.parse()?;
+        let expression = input.parse()?;
+        let _: Token![;] = input.parse()?;
+        Ok(Self { name, expression })
+    }
+}
+
+/// Parse a block `keyword { name = expression; ... }`
+fn parse_block(input: ParseStream<'_>, keyword: &str) -> syn::Result<Vec<Assignment>> {
+    let ident: Ident = input.parse()?;
+    if ident != keyword {
+        return Err(syn::Error::new(
+            ident.span(),
+            format!("Expected `{}`", keyword),
+        ));
+    }
+    let content;
+    let _: token::Brace = braced!(content in input);
+    let mut assignments = Vec::new();
+    while !content.is_empty() {
+        assignments.push(content.parse()?);
+    }
+    Ok(assignments)
+}
+
+struct Air {
+    attributes:  Vec<Attribute>,
+    visibility:  Visibility,
+    name:        Ident,
+    columns:     Vec<Ident>,
+    periodic:    Vec<Assignment>,
+    transitions: Vec<Assignment>,
+}
+
+impl Parse for Air {
+    fn parse(input: ParseStream<'_>) -> syn::Result<Self> {
+        let attributes = input.call(Attribute::parse_outer)?;
+        let visibility = input.parse()?;
+        let _: Token![struct] = input.parse()?;
+        let name = input.parse()?;
+        let content;
+        let _: token::Brace = braced!(content in input);
+        let columns = Punctuated::<Ident, Token![,]>::parse_terminated(&content)?
+            .into_iter()
+            .collect();
+        let periodic = if input.peek(Ident) && input.fork().parse::<Ident>()? == "periodic" {
+            parse_block(input, "periodic")?
+        } else {
+            Vec::new()
+        };
+        let transitions = parse_block(input, "transition")?;
+        Ok(Self {
+            attributes,
+            visibility,
+            name,
+            columns,
+            periodic,
+            transitions,
+        })
+    }
+}
+
+impl Air {
+    /// Check names are unique and every column has exactly one transition.
+    fn validate(&self) -> syn::Result<()> {
+        let names = self
+            .columns
+            .iter()
+            .chain(self.periodic.iter().map(|periodic| &periodic.name));
+        for (i, name) in names.clone().enumerate() {
+            if names.clone().take(i).any(|other| other == name) {
+                return Err(syn::Error::new(
+                    name.span(),
+                    format!("Duplicate column `{}`", name),
+                ));
+            }
+        }
+        for (i, transition) in self.transitions.iter().enumerate() {
+            let _ = self.column_index(&transition.name)?;
+            if self.transitions[..i]
+                .iter()
+                .any(|other| other.name == transition.name)
+            {
+                return Err(syn::Error::new(
+                    transition.name.span(),
+                    format!("Duplicate transition for column `{}`", transition.name),
+                ));
+            }
+        }
+        for column in &self.columns {
+            if !self
+                .transitions
+                .iter()
+                .any(|transition| &transition.name == column)
+            {
+                return Err(syn::Error::new(
+                    column.span(),
+                    format!("Missing transition for column `{}`", column),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn column_index(&self, name: &Ident) -> syn::Result<usize> {
+        self.columns
+            .iter()
+            .position(|column| column == name)
+            .ok_or_else(|| syn::Error::new(name.span(), format!("Unknown column `{}`", name)))
+    }
+
+    fn is_periodic(&self, name: &Ident) -> bool {
+        self.periodic.iter().any(|periodic| &periodic.name == name)
+    }
+
+    /// Translate a transition expression using `leaf` for the names and
+    /// integer literals.
+    fn translate(
+        &self,
+        expression: &Expr,
+        leaf: &impl Fn(Leaf<'_>) -> syn::Result<TokenStream>,
+    ) -> syn::Result<TokenStream> {
+        match expression {
+            Expr::Paren(paren) => self.translate(&paren.expr, leaf),
+            Expr::Group(group) => self.translate(&group.expr, leaf),
+            Expr::Binary(binary) => {
+                let left = self.translate(&binary.left, leaf)?;
+                let right = self.translate(&binary.right, leaf)?;
+                match binary.op {
+                    BinOp::Add(_) => Ok(quote! { (#left + #right) }),
+                    BinOp::Sub(_) => Ok(quote! { (#left - #right) }),
+                    BinOp::Mul(_) => Ok(quote! { (#left * #right) }),
+                    _ => {
+                        Err(syn::Error::new(
+                            binary.op.span(),
+                            "Only `+`, `-` and `*` are supported",
+                        ))
+                    }
+                }
+            }
+            Expr::Unary(unary) => {
+                match unary.op {
+                    UnOp::Neg(_) => {
+                        let zero = leaf(Leaf::Integer(0))?;
+                        let operand = self.translate(&unary.expr, leaf)?;
+                        Ok(quote! { (#zero - #operand) })
+                    }
+                    _ => Err(syn::Error::new(unary.op.span(), "Only `-` is supported")),
+                }
+            }
+            Expr::Lit(literal) => {
+                match &literal.lit {
+                    Lit::Int(int) => leaf(Leaf::Integer(int.base10_parse()?)),
+                    other => Err(syn::Error::new(other.span(), "Expected integer literal")),
+                }
+            }
+            Expr::Path(path) => {
+                let name = path
+                    .path
+                    .get_ident()
+                    .ok_or_else(|| syn::Error::new(path.span(), "Expected column name"))?;
+                if self.is_periodic(name) {
+                    leaf(Leaf::Periodic(name))
+                } else {
+                    leaf(Leaf::Column(self.column_index(name)?, name))
+                }
+            }
+            other => Err(syn::Error::new(other.span(), "Unsupported expression")),
+        }
+    }
+}
+
+/// Leaves of the transition expressions
+enum Leaf<'a> {
+    Integer(u64),
+    Column(usize, &'a Ident),
+    Periodic(&'a Ident),
+}
+
+// TODO: Split up
+#[allow(clippy::too_many_lines)]
+fn generate(air: &Air) -> syn::Result<TokenStream> {
+    air.validate()?;
+    let Air {
+        attributes,
+        visibility,
+        name,
+        columns,
+        periodic,
+        ..
+    } = air;
+    let row_name = format_ident!("{}Row", name);
+    let num_columns = columns.len();
+    let column_names = columns.iter().map(Ident::to_string);
+    let periodic_names = periodic
+        .iter()
+        .map(|periodic| &periodic.name)
+        .collect::<Vec<_>>();
+    let periodic_values = periodic.iter().map(|periodic| &periodic.expression);
+    let column_indices = (0..num_columns).collect::<Vec<_>>();
+
+    // Transitions in column order
+    let transitions = columns
+        .iter()
+        .map(|column| {
+            air.transitions
+                .iter()
+                .find(|transition| &transition.name == column)
+                .unwrap()
+        })
+        .collect::<Vec<_>>();
+
+    // Step function computing the next row
+    let step_values = transitions
+        .iter()
+        .map(|transition| {
+            air.translate(&transition.expression, &|leaf| {
+                Ok(match leaf {
+                    Leaf::Integer(value) => quote! { FieldElement::from(#value) },
+                    Leaf::Column(_, name) => quote! { current.#name.clone() },
+                    Leaf::Periodic(name) => {
+                        quote_spanned! { name.span() =>
+                            self.#name[row % self.#name.len()].clone()
+                        }
+                    }
+                })
+            })
+        })
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    // Transition constraints
+    let constraint_values = transitions
+        .iter()
+        .map(|transition| {
+            air.translate(&transition.expression, &|leaf| {
+                Ok(match leaf {
+                    Leaf::Integer(value) => quote! { Constant(FieldElement::from(#value)) },
+                    Leaf::Column(index, _) => quote! { Trace(#index, 0) },
+                    Leaf::Periodic(name) => quote! { #name.clone() },
+                })
+            })
+        })
+        .collect::<syn::Result<Vec<_>>>()?;
+
+    Ok(quote! {
+        #(#attributes)*
+        #[derive(Clone, PartialEq, Eq, Debug)]
+        #visibility struct #name {
+            size: usize,
+            #(#periodic_names: Vec<::zkp_stark::primefield::FieldElement>,)*
+        }
+
+        /// The values of the columns on a row
+        #[derive(Clone, PartialEq, Eq, Debug)]
+        #visibility struct #row_name {
+            #(pub #columns: ::zkp_stark::primefield::FieldElement,)*
+        }
+
+        impl #name {
+            /// Names of the trace columns, in order.
+            pub const COLUMNS: [&'static str; #num_columns] = [#(#column_names),*];
+
+            /// Create the component for `size` rows.
+            ///
+            /// # Panics
+            ///
+            /// Panics if `size` is not a power of two or is not a multiple of
+            /// the length of a periodic column.
+            pub fn new(size: usize) -> Self {
+                use ::zkp_stark::primefield::FieldElement;
+                assert!(size.is_power_of_two());
+                #(
+                    let #periodic_names: Vec<FieldElement> = (#periodic_values)[..].to_vec();
+                    assert!(#periodic_names.len().is_power_of_two());
+                    assert!(#periodic_names.len() <= size);
+                )*
+                Self {
+                    size,
+                    #(#periodic_names,)*
+                }
+            }
+
+            /// Compute the values on row `row + 1` from those on `row`.
+            #[allow(unused_variables)]
+            pub fn step(&self, row: usize, current: &#row_name) -> #row_name {
+                use ::zkp_stark::primefield::FieldElement;
+                #row_name {
+                    #(#columns: #step_values,)*
+                }
+            }
+
+            /// Compute the values on all rows starting from `first`.
+            pub fn rows(&self, first: &#row_name) -> Vec<#row_name> {
+                let mut rows = Vec::with_capacity(self.size);
+                rows.push(first.clone());
+                for row in 1..self.size {
+                    let next = self.step(row - 1, &rows[row - 1]);
+                    rows.push(next);
+                }
+                rows
+            }
+        }
+
+        impl ::zkp_stark::component::Component for #name {
+            /// The values on the first and the last row.
+            type Claim = (#row_name, #row_name);
+            /// The values on the first row.
+            type Witness = #row_name;
+
+            fn claim(&self, witness: &Self::Witness) -> Self::Claim {
+                let last = self.rows(witness).pop().unwrap();
+                (witness.clone(), last)
+            }
+
+            fn num_polynomials(&self) -> usize {
+                #num_columns
+            }
+
+            fn polynomial_size(&self) -> usize {
+                self.size
+            }
+
+            fn constraints(&self, claim: &Self::Claim) -> Vec<::zkp_stark::RationalExpression> {
+                #[allow(unused_imports)]
+                use ::zkp_stark::{
+                    primefield::{FieldElement, Pow, Root},
+                    DensePolynomial, RationalExpression::*, TraceTable,
+                };
+                let size = self.size;
+                let omega = FieldElement::root(size).expect("Size not a power of two.");
+                let last_row = Constant(omega.pow(size - 1));
+                #(
+                    let #periodic_names = {
+                        let mut table = TraceTable::new(self.#periodic_names.len(), 1);
+                        for (row, value) in self.#periodic_names.iter().enumerate() {
+                            table[(row, 0)] = value.clone();
+                        }
+                        let polynomial: DensePolynomial = table.interpolate().pop().unwrap();
+                        Polynomial(
+                            polynomial,
+                            Box::new(X.pow(size / self.#periodic_names.len())),
+                        )
+                    };
+                )*
+                let mut constraints = Vec::new();
+                #(
+                    constraints.push(
+                        (Trace(#column_indices, 1) - #constraint_values)
+                            * (X - last_row.clone())
+                            / (X.pow(size) - 1),
+                    );
+                )*
+                let (first, last) = claim;
+                #(
+                    constraints.push(
+                        (Trace(#column_indices, 0) - Constant(first.#columns.clone())) / (X - 1),
+                    );
+                    constraints.push(
+                        (Trace(#column_indices, 0) - Constant(last.#columns.clone()))
+                            / (X - last_row.clone()),
+                    );
+                )*
+                constraints
+            }
+
+            fn trace<P: ::zkp_stark::component::PolynomialWriter>(
+                &self,
+                trace: &mut P,
+                witness: &Self::Witness,
+            ) {
+                for (row, values) in self.rows(witness).into_iter().enumerate() {
+                    #(
+                        trace.write(#column_indices, row, values.#columns);
+                    )*
+                }
+            }
+        }
+    })
+}
+
+#[must_use]
+pub fn air(input: TokenStream) -> TokenStream {
+    syn::parse2(input)
+        .and_then(|air| generate(&air))
+        .unwrap_or_else(|err: syn::Error| err.to_compile_error())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn error(output: &TokenStream) -> Option<String> {
+        let output = output.to_string();
+        if output.starts_with("compile_error") {
+            Some(output)
+        } else {
+            None
+        }
+    }
+
+    #[test]
+    fn air_positive() {
+        let output = air(quote! {
+            /// Fibonacci with an added round constant
+            pub struct Fibonacci {
+                a, b,
+            }
+            periodic {
+                k = &ROUND_CONSTANTS;
+            }
+            transition {
+                b = a + b * k;
+                a = b;
+            }
+        });
+        assert_eq!(error(&output), None);
+        let output = output.to_string();
+        assert!(output.contains("pub struct FibonacciRow"));
+        assert!(output.contains("Trace (1usize , 1)"));
+    }
+
+    #[test]
+    fn air_negative() {
+        let unknown = air(quote! {
+            struct Test { a }
+            transition { a = a + c; }
+        });
+        assert!(error(&unknown).unwrap().contains("Unknown column `c`"));
+        let unknown = air(quote! {
+            struct Test { a }
+            transition { a = a; c = a; }
+        });
+        assert!(error(&unknown).unwrap().contains("Unknown column `c`"));
+        let missing = air(quote! {
+            struct Test { a, b }
+            transition { a = b; }
+        });
+        assert!(error(&missing)
+            .unwrap()
+            .contains("Missing transition for column `b`"));
+        let duplicate = air(quote! {
+            struct Test { a }
+            transition { a = a; a = a + 1; }
+        });
+        assert!(error(&duplicate)
+            .unwrap()
+            .contains("Duplicate transition for column `a`"));
+        let unsupported = air(quote! {
+            struct Test { a }
+            transition { a = a / 2; }
+        });
+        assert!(error(&unsupported)
+            .unwrap()
+            .contains("Only `+`, `-` and `*` are supported"));
+    }
+}
@@ -45,7 +45,7 @@
 mod mmap_vec;
 
 #[cfg(feature = "std")]
-pub use crate::mmap_vec::MmapVec;
+pub use crate::mmap_vec::{mapped, peak_mapped, reset_peak_mapped, MmapVec, SCRATCH_THRESHOLD};
 
 // In no std mode, substitute no_std_compat
 #[cfg(not(feature = "std"))]
@@ -11,25 +11,81 @@ use log::trace;
 use memmap::{MmapMut, MmapOptions};
 use std::{
     cmp::max,
+    fs::{self, OpenOptions},
     marker::PhantomData,
     mem::size_of,
     ops::{Deref, DerefMut},
+    path::{Path, PathBuf},
+    process,
     ptr::drop_in_place,
     slice,
+    sync::atomic::{AtomicUsize, Ordering},
 };
 
-// TODO: Variant of MmapVec where it switched between Vec and Mmap after
-//       a treshold size.
+/// Allocations of at least this many bytes use the scratch directory when it
+/// is set, see [`MmapVec::with_capacity_scratch`].
+pub const SCRATCH_THRESHOLD: usize = 1 << 20;
+
+/// Counter to give scratch files unique names within the process.
+static SCRATCH_FILES: AtomicUsize = AtomicUsize::new(0);
+
+/// Bytes currently mapped by all vectors in the process.
+static MAPPED: AtomicUsize = AtomicUsize::new(0);
+
+/// Largest value of [`MAPPED`] since the last [`reset_peak_mapped`].
+static PEAK_MAPPED: AtomicUsize = AtomicUsize::new(0);
+
+/// Bytes currently mapped by all [`MmapVec`]s in the process, both anonymous
+/// and file-backed.
+pub fn mapped() -> usize {
+    MAPPED.load(Ordering::Relaxed)
+}
+
+/// Largest number of bytes mapped at once since the last
+/// [`reset_peak_mapped`].
+pub fn peak_mapped() -> usize {
+    PEAK_MAPPED.load(Ordering::Relaxed)
+}
+
+/// Reset the peak to the currently mapped bytes and return the previous peak.
+pub fn reset_peak_mapped() -> usize {
+    PEAK_MAPPED.swap(mapped(), Ordering::Relaxed)
+}
+
+fn add_mapped(size: usize) {
+    let mapped = MAPPED.fetch_add(size, Ordering::Relaxed) + size;
+    // TODO: Use `AtomicUsize::fetch_max` once it is stable
+    let mut peak = PEAK_MAPPED.load(Ordering::Relaxed);
+    while peak < mapped {
+        match PEAK_MAPPED.compare_exchange_weak(peak, mapped, Ordering::Relaxed, Ordering::Relaxed)
+        {
+            Ok(_) => break,
+            Err(next) => peak = next,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug)]
+enum Storage {
+    Anonymous,
+    /// Backed by a file that is already unlinked.
+    Unlinked,
+    /// Backed by a file that needs to be removed on drop.
+    File(PathBuf),
+}
 
 #[derive(Debug)] // TODO: Custom implementation
 pub struct MmapVec<T: Clone> {
     mmap:     MmapMut,
     length:   usize,
     capacity: usize,
+    storage:  Storage,
     _t:       PhantomData<T>,
 }
 
 impl<T: Clone> MmapVec<T> {
+    /// Allocate a vector with room for `capacity` elements in anonymous
+    /// memory.
     #[must_use]
     pub fn with_capacity(capacity: usize) -> Self {
         // TODO: Round up to nearest 4KB
@@ -40,14 +96,91 @@ impl<T: Clone> MmapVec<T> {
             .len(size)
             .map_anon()
             .expect("cannot access memory mapped file");
+        add_mapped(size);
+        Self {
+            mmap,
+            length: 0,
+            capacity,
+            storage: Storage::Anonymous,
+            _t: PhantomData,
+        }
+    }
+
+    /// Allocate a vector with room for `capacity` elements, in a file in
+    /// `scratch` if it is set and the vector takes at least
+    /// [`SCRATCH_THRESHOLD`] bytes.
+    ///
+    /// The pages of a file-backed vector can be written back to disk and
+    /// evicted by the operating system, so they do not count towards the
+    /// resident memory of the process. Small vectors use anonymous memory to
+    /// avoid creating many files.
+    ///
+    /// # Panics
+    ///
+    /// Panics when the file can not be created or mapped.
+    #[must_use]
+    pub fn with_capacity_scratch(capacity: usize, scratch: Option<&Path>) -> Self {
+        match scratch {
+            Some(directory) if capacity * size_of::<T>() >= SCRATCH_THRESHOLD => {
+                Self::with_capacity_in(capacity, directory)
+            }
+            _ => Self::with_capacity(capacity),
+        }
+    }
+
+    /// Allocate a vector with room for `capacity` elements in a new file in
+    /// `directory`.
+    ///
+    /// The file is removed when the vector is dropped. Where the platform
+    /// allows it, it is unlinked right away so it is also removed when the
+    /// process does not exit cleanly.
+    ///
+    /// # Panics
+    ///
+    /// Panics when the file can not be created or mapped.
+    #[must_use]
+    pub fn with_capacity_in(capacity: usize, directory: &Path) -> Self {
+        let size = max(1, capacity * size_of::<T>());
+        let path = directory.join(format!(
+            "zkp-mmap-vec-{}-{}",
+            process::id(),
+            SCRATCH_FILES.fetch_add(1, Ordering::Relaxed)
+        ));
+        trace!(
+            "Allocating {} MB in mmap file {}",
+            size / 1_000_000,
+            path.display()
+        );
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&path)
+            .expect("cannot create memory mapped file");
+        file.set_len(size as u64)
+            .expect("cannot allocate memory mapped file");
+        let mmap = unsafe { MmapOptions::new().len(size).map_mut(&file) }
+            .expect("cannot access memory mapped file");
+        add_mapped(size);
+        let storage = match fs::remove_file(&path) {
+            Ok(()) => Storage::Unlinked,
+            Err(_) => Storage::File(path),
+        };
         Self {
             mmap,
             length: 0,
             capacity,
+            storage,
             _t: PhantomData,
         }
     }
 
+    /// Returns `true` if the vector is backed by a file.
+    #[must_use]
+    pub fn is_file_backed(&self) -> bool {
+        self.storage != Storage::Anonymous
+    }
+
     /// # Safety
     /// This function returns an array of size `len` that is initialized
     /// with all bits set to zero. This is only safe if all-zeros is a valid
@@ -137,6 +270,16 @@ impl<T: Clone> MmapVec<T> {
     }
 }
 
+impl<T: Clone> Drop for MmapVec<T> {
+    fn drop(&mut self) {
+        let _ = MAPPED.fetch_sub(self.mmap.len(), Ordering::Relaxed);
+        if let Storage::File(path) = &self.storage {
+            // The map is still open, so this can only be best effort.
+            let _ = fs::remove_file(path);
+        }
+    }
+}
+
 impl<T: Clone + PartialEq> PartialEq for MmapVec<T> {
     fn eq(&self, other: &Self) -> bool {
         if self.len() != other.len() {
@@ -239,6 +382,57 @@ mod tests {
         }
     }
 
+    /// A new empty directory for a test, so concurrent tests do not see each
+    /// others files.
+    fn test_directory(name: &str) -> PathBuf {
+        let directory =
+            std::env::temp_dir().join(format!("zkp-mmap-vec-test-{}-{}", process::id(), name));
+        fs::create_dir_all(&directory).unwrap();
+        directory
+    }
+
+    #[test]
+    fn test_file_backed() {
+        let directory = test_directory("file-backed");
+        let mut m: MmapVec<u64> = MmapVec::with_capacity_in(1000, &directory);
+        assert!(m.is_file_backed());
+        m.extend(0..1000_u64);
+        assert_eq!(m.iter().sum::<u64>(), 999 * 1000 / 2);
+        assert!(m.clone() == m);
+        drop(m);
+        assert_eq!(fs::read_dir(&directory).unwrap().count(), 0);
+        fs::remove_dir(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_scratch_directory() {
+        let directory = test_directory("scratch");
+        let small: MmapVec<u8> =
+            MmapVec::with_capacity_scratch(SCRATCH_THRESHOLD - 1, Some(&directory));
+        let large: MmapVec<u8> =
+            MmapVec::with_capacity_scratch(SCRATCH_THRESHOLD, Some(&directory));
+        let anonymous: MmapVec<u8> = MmapVec::with_capacity_scratch(SCRATCH_THRESHOLD, None);
+        assert!(!small.is_file_backed());
+        assert!(large.is_file_backed());
+        assert!(!anonymous.is_file_backed());
+        drop(large);
+        fs::remove_dir(&directory).unwrap();
+    }
+
+    #[test]
+    fn test_peak_mapped() {
+        // Other tests map concurrently, so only lower bounds hold.
+        let _ = reset_peak_mapped();
+        let first: MmapVec<u64> = MmapVec::with_capacity(1000);
+        let second: MmapVec<u64> = MmapVec::with_capacity(1000);
+        assert!(mapped() >= 16000);
+        drop(first);
+        drop(second);
+        assert!(peak_mapped() >= 16000);
+        let _ = reset_peak_mapped();
+        assert!(peak_mapped() >= mapped());
+    }
+
     #[test]
     #[should_panic]
     fn test_cannot_index_beyond_end() {